@@ -0,0 +1,45 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::process::Command;
+use std::time::Duration;
+
+// The stash lives in the `me` binary, so it is asked to time the runs itself, which keeps its
+// startup and the stash generation out of the numbers.
+fn measure(op: &str, size: usize, runs: u64) -> Duration {
+    let output = Command::new(env!("CARGO_BIN_EXE_me"))
+        .args(["bench", "--nanos", "--op", op])
+        .args(["--size", &size.to_string(), "--runs", &runs.to_string()])
+        .output()
+        .expect("me could not be run");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let nanos = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .expect("me bench printed no time");
+
+    Duration::from_nanos(nanos)
+}
+
+fn stash(c: &mut Criterion) {
+    for op in ["load", "refresh", "search", "index"] {
+        let mut group = c.benchmark_group(op);
+
+        group.sample_size(10);
+
+        for size in [1_000, 10_000, 100_000] {
+            group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+                b.iter_custom(|runs| measure(op, size, runs))
+            });
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, stash);
+criterion_main!(benches);
@@ -0,0 +1,42 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// `me` is a binary only, so the parser's module is compiled in here as it is. All it takes from
+// the crate is the error type, for the ID generation that is not fuzzed.
+#[allow(dead_code)]
+#[path = "../../src/meta.rs"]
+mod meta;
+
+struct Error;
+
+impl Error {
+    fn with_cause<S, D>(_: S, _: D) -> Self {
+        Error
+    }
+}
+
+fuzz_target!(|content: &str| {
+    let (front, body) = meta::split(content);
+
+    assert!(content.ends_with(body));
+
+    let _ = (front.title(), front.tags(), front.id(), front.list("aliases"));
+
+    // What is written back reads back the same. Without fields nothing is written, and a body that
+    // opens with `---` of its own would be read as the front matter.
+    let rendered = front.render();
+
+    if !rendered.is_empty() {
+        let written = format!("{rendered}{body}");
+        let (again, rest) = meta::split(&written);
+
+        assert_eq!(again.render(), rendered);
+        assert_eq!(rest, body);
+    }
+
+    let _ = meta::wiki_links(content);
+    let _ = meta::urls(content);
+    let _ = meta::replace_wiki_links(content, |link| format!("[{}]({})", link.label, link.target));
+    let _ = meta::append_under_heading(content, "## Log", "- an entry");
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../src/fuzzy.rs"]
+mod fuzzy;
+
+// A search string typed into the palette against one of its entries, split at the first newline.
+fuzz_target!(|input: &str| {
+    let (query, text) = input.split_once('\n').unwrap_or((input, ""));
+
+    if let Some(score) = fuzzy::score(query, text) {
+        assert!(score >= query.chars().filter(|c| !c.is_whitespace()).count());
+    }
+
+    let _ = fuzzy::score(text, query);
+});
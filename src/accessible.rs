@@ -0,0 +1,80 @@
+use crate::{Error, cli, lock, read_secret};
+use std::io::{self, Write};
+
+const HELP: &str = "Type a command and press Enter. Every command of 'me' works here without the
+'me', for example:
+  list              the names of all memos
+  show <name>       a memo's content
+  edit <name>       a memo in the editor
+  new <name>        a new, empty memo
+  add <text>        a new memo from the text, named after it or by --name
+  rm <name>         a memo moved to the trash
+  day               the path of today's note, 'day --edit' to edit it
+Also: help, quit.";
+
+// Asks for the lock passphrase until it is the right one, as the locked TUI does; cancelling the
+// input gives up.
+pub fn unlock(hash: &str) -> Result<(), Error> {
+    while !lock::verify(&read_secret("Lock passphrase: ")?, hash)? {
+        println!("The passphrase is wrong.");
+    }
+
+    Ok(())
+}
+
+// A line-by-line stand-in for the TUI that a terminal screen reader can follow: no alternate
+// screen, no cursor movement, and every answer printed as plain lines after the command.
+pub fn run() -> Result<(), Error> {
+    println!("memoleak, accessible mode. Type help for the commands, quit to leave.");
+
+    loop {
+        print!("memoleak> ");
+
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+
+        let read = io::stdin()
+            .read_line(&mut line)
+            .map_err(|e| Error::with_cause("The command reading failed", e.kind()))?;
+
+        if read == 0 {
+            println!();
+
+            return Ok(());
+        }
+
+        let args = words(&line);
+
+        match args.first().map(String::as_str) {
+            None => {}
+            Some("quit" | "exit" | "q") => return Ok(()),
+            Some("help") => println!("{HELP}"),
+            Some(_) => match cli::run(&args) {
+                Ok(()) => println!("Done."),
+                Err(e) => println!("Failed: {e}"),
+            },
+        }
+    }
+}
+
+// Splits on whitespace, except inside double quotes, so `rm "shopping list"` names one memo.
+fn words(line: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = None::<String>;
+    let mut quoted = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_default();
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+
+    words.extend(word);
+
+    words
+}
@@ -0,0 +1,112 @@
+use crate::{
+    ARCHIVE_PATH, ATTACHMENT_PATH, Error, TRASH_PATH, memo_paths, private_file_options, sha256_hex,
+    storage,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// How memos refer to a blob; relative to MEMO_LIST_PATH, so the links work in other viewers too.
+const LINK_PREFIX: &str = "../attachments/";
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp"];
+
+// Copies a file into the store under its content hash unless the same content is there already.
+// Returns the blob name and whether it was newly stored.
+pub fn store(file: &Path) -> Result<(String, bool), Error> {
+    let content = fs::read(file).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", file.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+    let blob = match file.extension() {
+        Some(ext) => format!(
+            "{}.{}",
+            sha256_hex(&content),
+            ext.to_string_lossy().to_lowercase()
+        ),
+        None => sha256_hex(&content),
+    };
+    let path = ATTACHMENT_PATH.join(&blob);
+
+    if path.exists() {
+        return Ok((blob, false));
+    }
+
+    private_file_options()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(&content))
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' writing failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+
+    Ok((blob, true))
+}
+
+pub fn link(file: &Path, blob: &str) -> String {
+    let label = file
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| blob.to_string());
+    let image = file.extension().is_some_and(|ext| {
+        IMAGE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+    });
+
+    format!(
+        "{}[{label}]({LINK_PREFIX}{blob})",
+        if image { "!" } else { "" }
+    )
+}
+
+pub fn is_linked(content: &str) -> bool {
+    content.contains(LINK_PREFIX)
+}
+
+// Every blob in the store with the number of links to it from listed, archived and trashed memos,
+// leaving out the trashed ones in `purged` that gc is about to delete. The versions in the history
+// do not count: restoring one from before a gc may bring back links to blobs that are gone.
+pub fn references(purged: &[PathBuf]) -> Result<HashMap<PathBuf, usize>, Error> {
+    let mut counts = fs::read_dir(&*ATTACHMENT_PATH)
+        .map_err(|e| Error::with_cause("Attachments reading failed", e.kind()))?
+        .filter_map(|entry| entry.ok().map(|entry| (entry.file_name(), 0)))
+        .collect::<HashMap<_, _>>();
+
+    let archived = fs::read_dir(&*ARCHIVE_PATH)
+        .map_err(|e| Error::with_cause("Archive reading failed", e.kind()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()));
+
+    let trashed = fs::read_dir(&*TRASH_PATH)
+        .map_err(|e| Error::with_cause("Trash reading failed", e.kind()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| !purged.contains(path));
+
+    for path in memo_paths()?.into_iter().chain(archived).chain(trashed) {
+        let content = storage::read(&path).map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' reading failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+
+        for (at, _) in content.match_indices(LINK_PREFIX) {
+            let rest = &content[at + LINK_PREFIX.len()..];
+            let blob = &rest[..rest.find([')', ' ', '"', '\n']).unwrap_or(rest.len())];
+
+            if let Some(count) = counts.get_mut(std::ffi::OsStr::new(blob)) {
+                *count += 1;
+            }
+        }
+    }
+
+    Ok(counts
+        .into_iter()
+        .map(|(name, count)| (ATTACHMENT_PATH.join(name), count))
+        .collect())
+}
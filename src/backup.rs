@@ -0,0 +1,157 @@
+use crate::{
+    APP_DATA_PATH, ATTACHMENT_PATH, Error, MEMO_LIST_PATH, create_private_dir, memo_paths,
+    private_file_options,
+};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+pub static BACKUPS_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("backups"));
+
+const PREFIX: &str = "memoleak-";
+
+// Zips the memos and the attachments into a new `memoleak-<time>.zip` in `dir`, with the memos
+// under `memos/` and the attachments under `attachments/`.
+pub fn create(dir: &Path) -> Result<PathBuf, Error> {
+    create_private_dir(dir).map_err(|e| {
+        Error::with_cause(
+            format!("A directory '{}' creating failed", dir.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    let stamp = Local::now().format("%Y%m%d-%H%M%S");
+    let path = (1..)
+        .map(|n| match n {
+            1 => dir.join(format!("{PREFIX}{stamp}.zip")),
+            n => dir.join(format!("{PREFIX}{stamp}-{n}.zip")),
+        })
+        .find(|path| !path.exists())
+        .unwrap();
+
+    // Written aside and moved over once complete, so an interrupted backup never passes for one.
+    let partial = path.with_extension("zip.partial");
+    let res = write_zip(&partial).and_then(|_| {
+        fs::rename(&partial, &path).map_err(|e| {
+            Error::with_cause(
+                format!("A backup '{}' writing failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })
+    });
+
+    if res.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+
+    res.map(|_| path)
+}
+
+fn write_zip(path: &Path) -> Result<(), Error> {
+    let failed = |cause: String| {
+        Error::with_cause(
+            format!("A backup '{}' writing failed", path.to_string_lossy()),
+            cause,
+        )
+    };
+    let file = private_file_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|e| failed(e.kind().to_string()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut files = vec![];
+
+    for memo in memo_paths()? {
+        let name = memo.strip_prefix(&*MEMO_LIST_PATH).unwrap_or(&memo);
+
+        files.push((Path::new("memos").join(name), memo));
+    }
+
+    for entry in fs::read_dir(&*ATTACHMENT_PATH).map_err(|e| failed(e.kind().to_string()))? {
+        let attachment = entry.map_err(|e| failed(e.kind().to_string()))?.path();
+
+        if let Some(name) = attachment.file_name() {
+            files.push((Path::new("attachments").join(name), attachment));
+        }
+    }
+
+    for (name, from) in files {
+        let mut source = File::open(&from).map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' reading failed", from.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+
+        let options = match modified_time(&from) {
+            Some(time) => options.last_modified_time(time),
+            None => options,
+        };
+
+        zip.start_file(name.to_string_lossy().replace('\\', "/"), options)
+            .map_err(|e| failed(e.to_string()))?;
+        io::copy(&mut source, &mut zip).map_err(|e| failed(e.kind().to_string()))?;
+    }
+
+    zip.finish().map_err(|e| failed(e.to_string()))?;
+
+    Ok(())
+}
+
+fn modified_time(path: &Path) -> Option<zip::DateTime> {
+    let modified = DateTime::<Local>::from(fs::metadata(path).ok()?.modified().ok()?);
+
+    zip::DateTime::from_date_and_time(
+        modified.year().try_into().ok()?,
+        modified.month() as u8,
+        modified.day() as u8,
+        modified.hour() as u8,
+        modified.minute() as u8,
+        modified.second() as u8,
+    )
+    .ok()
+}
+
+// Removes all but the `keep` newest backups in `dir`, handing back the ones removed.
+pub fn rotate(dir: &Path, keep: usize) -> Result<Vec<PathBuf>, Error> {
+    let mut backups = fs::read_dir(dir)
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A directory '{}' reading failed", dir.to_string_lossy()),
+                e.kind(),
+            )
+        })?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name().is_some_and(|name| {
+                let name = name.to_string_lossy();
+
+                name.starts_with(PREFIX) && name.ends_with(".zip")
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // The time in the names sorts them oldest first.
+    backups.sort();
+
+    let old = backups.len().saturating_sub(keep);
+    let removed = backups.drain(..old).collect::<Vec<_>>();
+
+    for path in &removed {
+        fs::remove_file(path).map_err(|e| {
+            Error::with_cause(
+                format!("A backup '{}' removing failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+    }
+
+    Ok(removed)
+}
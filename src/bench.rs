@@ -0,0 +1,123 @@
+use crate::{Error, Stash, fill_stash_with, markdown_paths, reread_all};
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const OPS: [&str; 4] = ["load", "refresh", "search", "index"];
+pub const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+// A stash of `size` generated memos in the temp dir, written on first use and kept for the next
+// runs.
+pub fn stash(size: usize) -> Result<PathBuf, Error> {
+    let dir = env::temp_dir()
+        .join("memoleak-bench")
+        .join(size.to_string());
+    // Hidden, so it is not listed as a memo.
+    let complete = dir.join(".complete");
+
+    if complete.exists() {
+        return Ok(dir);
+    }
+
+    let failed = |e: std::io::Error| {
+        Error::with_cause(
+            format!("A bench stash '{}' writing failed", dir.to_string_lossy()),
+            e.kind(),
+        )
+    };
+
+    let _ = fs::remove_dir_all(&dir);
+
+    fs::create_dir_all(&dir).map_err(failed)?;
+
+    for i in 0..size {
+        let content = format!(
+            "# Memo {i}\n\nSome words to search through, about group {} and nothing much else.\n\nSee [[memo-{:06}]] and [[memo-{:06}]].\n",
+            i % 10,
+            (i * 7 + 1) % size,
+            (i * 13 + 5) % size,
+        );
+
+        fs::write(dir.join(format!("memo-{i:06}.md")), content).map_err(failed)?;
+    }
+
+    fs::write(&complete, "").map_err(failed)?;
+
+    Ok(dir)
+}
+
+// The total time of `runs` runs of `op` over the stash in `dir`.
+pub fn time(op: &str, dir: &Path, runs: u64) -> Result<Duration, Error> {
+    let load = || -> Result<Stash, Error> {
+        let mut stash = Stash::new();
+
+        fill_stash_with(&mut stash, markdown_paths(dir, true)?)?;
+
+        Ok(stash)
+    };
+
+    let mut total = Duration::ZERO;
+
+    if op == "load" {
+        for _ in 0..runs {
+            let started = Instant::now();
+
+            black_box(load()?);
+            total += started.elapsed();
+        }
+
+        return Ok(total);
+    }
+
+    let mut stash = load()?;
+
+    for _ in 0..runs {
+        match op {
+            "refresh" => {
+                let started = Instant::now();
+
+                let (changed, error) = reread_all(&stash.reread_jobs());
+
+                stash.apply_reread(changed);
+                total += started.elapsed();
+
+                if let Some(e) = error {
+                    return Err(Error::new(e));
+                }
+            }
+            "search" => {
+                let started = Instant::now();
+                let found = (0..stash.len())
+                    .filter_map(|idx| stash.get(idx))
+                    .filter(|memo| memo.matches("group 7"))
+                    .count();
+
+                black_box(found);
+                total += started.elapsed();
+            }
+            // A memo written outside the TUI, as the rescan tick picks it up.
+            "index" => {
+                let path = dir.join(".added.md");
+                let added = dir.join("memo-added.md");
+
+                fs::write(&path, "# Added\n")
+                    .and_then(|_| fs::rename(&path, &added))
+                    .map_err(|e| Error::with_cause("A bench memo writing failed", e.kind()))?;
+
+                let started = Instant::now();
+
+                stash.rescan(vec![(added.clone(), None)])?;
+                total += started.elapsed();
+
+                let _ = fs::remove_file(&added);
+
+                stash.rescan(vec![])?;
+            }
+            _ => return Err(Error::new(format!("Unknown benchmark '{op}'"))),
+        }
+    }
+
+    Ok(total)
+}
@@ -0,0 +1,77 @@
+use crate::Error;
+
+// `- [title](url)`, with the notes on the same line after a dash.
+pub fn entry(url: &str, title: Option<&str>, notes: &str) -> String {
+    let label = title
+        .filter(|title| !title.is_empty())
+        .unwrap_or(url)
+        .replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]");
+    let notes = notes.trim();
+
+    if notes.is_empty() {
+        format!("- [{label}]({url})")
+    } else {
+        format!("- [{label}]({url}) - {notes}")
+    }
+}
+
+pub fn validate_url(url: &str) -> Result<(), Error> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(())
+    } else {
+        Err(Error::with_cause(
+            format!("A URL '{url}' is invalid"),
+            "expected http:// or https://",
+        ))
+    }
+}
+
+// The page's `<title>`; only builds with the `net` feature can fetch one.
+#[cfg(feature = "net")]
+pub fn fetch_title(url: &str) -> Result<Option<String>, Error> {
+    use std::time::Duration;
+
+    let failed = |e: ureq::Error| Error::with_cause(format!("A page '{url}' fetching failed"), e);
+
+    let html = ureq::get(url)
+        .config()
+        .timeout_global(Some(Duration::from_secs(10)))
+        .build()
+        .call()
+        .map_err(failed)?
+        .body_mut()
+        .with_config()
+        .limit(1024 * 1024)
+        .read_to_string()
+        .map_err(failed)?;
+
+    Ok(title_of(&html))
+}
+
+#[cfg(not(feature = "net"))]
+pub fn fetch_title(_: &str) -> Result<Option<String>, Error> {
+    Ok(None)
+}
+
+#[cfg_attr(not(feature = "net"), allow(dead_code))]
+fn title_of(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+
+    let title = html[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&");
+
+    Some(title).filter(|title| !title.is_empty())
+}
@@ -0,0 +1,1751 @@
+use crate::bench;
+use crate::config::{self, HooksConfig};
+use crate::hooks::{self, Hook};
+use crate::periodic::Period;
+use crate::plan::{Action, Plan};
+use crate::task::Progress;
+use crate::{
+    Edited, Error, MEMO_LIST_PATH, Memo, OnCollision, PlainCopy, Stash, append_memo, capture_memo,
+    create_new_memo, edit_file, expired_trash, fill_stash_with_local, memo_id, memo_name_of,
+    memo_path, memo_paths, rename_memo, run_external, storage, trash_path, validate_memo_name,
+};
+use chrono::{Datelike, Days, Local, TimeDelta};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+pub fn run(args: &[String]) -> Result<(), Error> {
+    let (cmd, args) = args.split_first().unwrap();
+
+    match cmd.as_str() {
+        "new" => new(args),
+        "edit" => edit(args),
+        "rm" => rm(args),
+        "rename" => rename(args),
+        "gc" => gc(args),
+        "backup" => backup(args),
+        "history" => history(args),
+        "sed" => sed(args),
+        "import" => import(args),
+        "sync" => sync(args),
+        "add" => add(args),
+        "publish" => publish(args),
+        "export" => export(args),
+        "mail" => mail(args),
+        "list" => list(args),
+        "search" => search(args),
+        "clip" => clip(args),
+        "add-url" => add_url(args),
+        "day" | "week" | "month" => periodic(cmd, args),
+        "doctor" => doctor(args),
+        "attach" => attach(args),
+        "link" => link(args),
+        "lock" => lock(args),
+        "id" => id(args),
+        "show" => show(args),
+        "config" => config(args),
+        "bench" => bench(args),
+        "run" => run_over(args),
+        "links" => links(args),
+        "timesheet" => timesheet(args),
+        "review" => review(args),
+        "capture" => capture(args),
+        "watch-cmd" => watch_cmd(args),
+        _ => Err(Error::new(format!("Unknown command '{cmd}'"))),
+    }
+}
+
+struct Args {
+    positional: Vec<String>,
+    flags: HashSet<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    // `flags` are switches, `options` take the following argument as their value.
+    fn parse(args: &[String], flags: &[&str], options: &[&str]) -> Result<Self, Error> {
+        let mut parsed = Args {
+            positional: vec![],
+            flags: HashSet::new(),
+            options: HashMap::new(),
+        };
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--" => parsed.positional.extend(args.by_ref().cloned()),
+                flag if flags.contains(&flag) => {
+                    parsed.flags.insert(flag.to_string());
+                }
+                option if options.contains(&option) => {
+                    let value = args.next().ok_or_else(|| {
+                        Error::new(format!("The option '{option}' needs a value"))
+                    })?;
+
+                    parsed.options.insert(option.to_string(), value.clone());
+                }
+                flag if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(Error::new(format!("Unknown flag '{flag}'")));
+                }
+                arg => parsed.positional.push(arg.to_string()),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    fn has(&self, names: &[&str]) -> bool {
+        names.iter().any(|name| self.flags.contains(*name))
+    }
+}
+
+fn new(args: &[String]) -> Result<(), Error> {
+    let usage = "Usage: me new <name> [-f|--force] [-n|--numbered]";
+    let args = Args::parse(args, &["-f", "--force", "-n", "--numbered"], &[])?;
+
+    let on_collision = if args.has(&["-f", "--force"]) {
+        OnCollision::Overwrite
+    } else if args.has(&["-n", "--numbered"]) {
+        OnCollision::Numbered
+    } else {
+        OnCollision::Refuse
+    };
+
+    let [name] = args.positional.as_slice() else {
+        return Err(Error::new(usage));
+    };
+    let hooks = config::load()?.hooks;
+    let memo = create_new_memo(name, on_collision)?;
+
+    println!("{}", memo.original_path.to_string_lossy());
+
+    fire(&hooks, Hook::Create, &memo.original_path, "");
+
+    Ok(())
+}
+
+fn edit(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &[], &[])?;
+
+    let [name] = args.positional.as_slice() else {
+        return Err(Error::new("Usage: me edit <name>"));
+    };
+    let hooks = config::load()?.hooks;
+    let mut stash = Stash::new();
+
+    stash.push(Memo::with_content(existing_memo_path(name)?)?);
+    let name = stash.get(0).map(|memo| memo.name()).unwrap_or_default();
+
+    match stash.edit(&[0], None)? {
+        Edited::Saved => {}
+        Edited::Discarded => {
+            println!("Discarded the changes to '{name}'");
+
+            return Ok(());
+        }
+        Edited::Failed(how) => return Err(Error::new(format!("The editor {how}"))),
+    }
+
+    if stash.get(0).is_some_and(|memo| memo.eq_origin()) {
+        return Ok(());
+    }
+
+    stash.format(0)?;
+    stash.refresh(0)?;
+
+    if let Some(memo) = stash.get(0) {
+        fire(
+            &hooks,
+            Hook::Edit,
+            &memo.original_path,
+            &memo.content_buffer,
+        );
+    }
+
+    Ok(())
+}
+
+// A memo by its name, or else by one of the `aliases` in its front matter.
+fn existing_memo_path(name: &str) -> Result<PathBuf, Error> {
+    let path = memo_path(name);
+
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    for path in memo_paths()? {
+        let Ok(content) = storage::read(&path) else {
+            continue;
+        };
+
+        if crate::meta::split(&content)
+            .0
+            .aliases()
+            .iter()
+            .any(|alias| alias.eq_ignore_ascii_case(name))
+        {
+            return Ok(path);
+        }
+    }
+
+    Err(Error::new(format!("A memo '{name}' is not found")))
+}
+
+// Hooks run once the change is made, so a failing one is reported without failing the command.
+fn fire(hooks: &HooksConfig, hook: Hook, path: &Path, content: &str) {
+    if let Err(e) = hooks::fire(hooks, hook, path, content) {
+        eprintln!("[WARN] {e}");
+    }
+}
+
+// Runs a command over a memo, without a shell: `{file}` and `{name}` in its arguments are replaced
+// by the memo's path and name, e.g. `me run notes -- pandoc {file} -o notes.pdf`. A compressed
+// memo is handed over as a plain copy, and what the command changes in it is compressed back.
+fn run_over(args: &[String]) -> Result<(), Error> {
+    let usage = "Usage: me run <name> -- <command>...";
+
+    let Some(split) = args.iter().position(|arg| arg == "--") else {
+        return Err(Error::new(usage));
+    };
+    let ([name], [program, rest @ ..]) = (&args[..split], &args[split + 1..]) else {
+        return Err(Error::new(usage));
+    };
+    let path = existing_memo_path(name)?;
+    let copy = match rest.iter().any(|arg| arg.contains("{file}")) {
+        true => PlainCopy::of(&path)?,
+        false => None,
+    };
+    let file = copy
+        .as_ref()
+        .map_or(path.as_path(), PlainCopy::path)
+        .to_string_lossy()
+        .to_string();
+    let name = memo_name_of(&path);
+
+    run_external(
+        Command::new(program).args(
+            rest.iter()
+                .map(|arg| arg.replace("{file}", &file).replace("{name}", &name)),
+        ),
+    )?;
+
+    match copy {
+        Some(copy) => copy.write_back(),
+        None => Ok(()),
+    }
+}
+
+fn rm(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--dry-run", "--permanent", "--secure"], &[])?;
+
+    if args.positional.is_empty() {
+        return Err(Error::new(
+            "Usage: me rm <name>... [--permanent [--secure]] [--dry-run]",
+        ));
+    }
+
+    let config = config::load()?;
+    let secure = args.has(&["--secure"]) || config.delete.secure;
+
+    let mut plan = Plan::default();
+    let mut removed = vec![];
+    let mut seen = HashSet::new();
+
+    for name in &args.positional {
+        let path = existing_memo_path(name)?;
+
+        // A memo named twice, or by its name and an alias, is removed once.
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+
+        removed.push((path.clone(), storage::read(&path).unwrap_or_default()));
+
+        if args.has(&["--permanent"]) && secure {
+            plan.push(Action::Erase { path });
+        } else if args.has(&["--permanent"]) {
+            plan.push(Action::Delete { path });
+        } else {
+            plan.push(Action::Move {
+                to: trash_path(&path),
+                from: path,
+            });
+        }
+    }
+
+    plan.execute(args.has(&["--dry-run"]))?;
+
+    if !args.has(&["--dry-run"]) {
+        for (path, content) in removed {
+            fire(&config.hooks, Hook::Delete, &path, &content);
+        }
+    }
+
+    Ok(())
+}
+
+// Empties the trash, or with `delete.purge_after_days` only of what is older than that unless
+// `--all` is given, and removes the attachments no memo refers to.
+fn gc(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--dry-run", "--secure", "--all"], &[])?;
+    let config = config::load()?.delete;
+    let secure = args.has(&["--secure"]) || config.secure;
+    let days = config.purge_after_days.filter(|_| !args.has(&["--all"]));
+    let mut plan = Plan::default();
+
+    let trashed = expired_trash(days)?;
+
+    let mut unreferenced = crate::attachment::references(&trashed)?
+        .into_iter()
+        .filter(|(_, count)| *count == 0)
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+
+    unreferenced.sort();
+
+    let freed = trashed
+        .iter()
+        .chain(&unreferenced)
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum::<u64>();
+
+    for path in trashed.into_iter().chain(unreferenced) {
+        plan.push(match secure {
+            true => Action::Erase { path },
+            false => Action::Delete { path },
+        });
+    }
+
+    if plan.is_empty() {
+        match days {
+            Some(days) => println!(
+                "Nothing has been in the trash for {days} days and every attachment is in use"
+            ),
+            None => println!("The trash is already empty and every attachment is in use"),
+        }
+    } else if args.has(&["--dry-run"]) {
+        plan.execute(true)?;
+
+        println!("{} would be freed", format_size(freed));
+
+        return Ok(());
+    } else {
+        plan.execute(false)?;
+
+        println!("{} freed", format_size(freed));
+    }
+
+    let left = expired_trash(None)?
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .collect::<Vec<_>>();
+
+    if !left.is_empty() {
+        println!(
+            "{} files left in the trash, {}",
+            left.len(),
+            format_size(left.iter().sum())
+        );
+    }
+
+    Ok(())
+}
+
+// `512 B`, `3.4 KiB`, `12.0 MiB` and so on.
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 || unit == "GiB" {
+            return match unit {
+                "B" => format!("{bytes} B"),
+                unit => format!("{size:.1} {unit}"),
+            };
+        }
+
+        size /= 1024.0;
+    }
+
+    unreachable!()
+}
+
+fn history(args: &[String]) -> Result<(), Error> {
+    let usage = "Usage: me history (<name> [--show <n>]|gc [--keep-days <n>] [--dry-run])";
+    let args = Args::parse(args, &["--dry-run"], &["--show", "--keep-days"])?;
+    let number = |option: &str| {
+        args.options
+            .get(option)
+            .map(|value| value.parse::<usize>().map_err(|_| Error::new(usage)))
+            .transpose()
+    };
+
+    match args.positional.as_slice() {
+        [gc] if gc == "gc" && !args.options.contains_key("--show") => {
+            let keep_days = match number("--keep-days")? {
+                Some(days) => days as u64,
+                None => config::load()?.history.keep_days,
+            };
+            let dry_run = args.has(&["--dry-run"]);
+            let (versions, freed) = crate::history::gc(keep_days, dry_run)?;
+
+            match dry_run {
+                true => println!("{versions} versions, {} would be freed", format_size(freed)),
+                false => println!("{versions} versions, {} freed", format_size(freed)),
+            }
+
+            Ok(())
+        }
+        [name] if !args.options.contains_key("--keep-days") && !args.has(&["--dry-run"]) => {
+            let versions =
+                crate::history::versions(existing_memo_path(name).unwrap_or(memo_path(name)));
+
+            if versions.is_empty() {
+                return Err(Error::new(format!("A memo '{name}' has no history")));
+            }
+
+            match number("--show")? {
+                Some(n) => {
+                    let version = n
+                        .checked_sub(1)
+                        .and_then(|idx| versions.get(idx))
+                        .ok_or_else(|| Error::new(format!("A version '{n}' is not found")))?;
+
+                    print_piped(&crate::history::read(&version.hash)?)
+                }
+                None => {
+                    let out = versions
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, version)| {
+                            format!(
+                                "{}\t{}\n",
+                                idx + 1,
+                                version.time.format("%Y-%m-%d %H:%M:%S")
+                            )
+                        })
+                        .collect::<String>();
+
+                    print_piped(&out)
+                }
+            }
+        }
+        _ => Err(Error::new(usage)),
+    }
+}
+
+fn backup(args: &[String]) -> Result<(), Error> {
+    let usage = "Usage: me backup [--rotate <n>]";
+    let args = Args::parse(args, &[], &["--rotate"])?;
+
+    if !args.positional.is_empty() {
+        return Err(Error::new(usage));
+    }
+
+    let keep = match args.options.get("--rotate") {
+        Some(keep) => match keep.parse::<usize>() {
+            Ok(keep) if keep > 0 => Some(keep),
+            _ => return Err(Error::new(usage)),
+        },
+        None => None,
+    };
+    let dir = config::load()?
+        .backup
+        .dir()
+        .unwrap_or(crate::backup::BACKUPS_PATH.clone());
+    let path = crate::backup::create(&dir)?;
+
+    println!("{}", path.to_string_lossy());
+
+    if let Some(keep) = keep {
+        for removed in crate::backup::rotate(&dir, keep)? {
+            println!("removed {}", removed.to_string_lossy());
+        }
+    }
+
+    Ok(())
+}
+
+struct Substitution {
+    pattern: String,
+    replacement: String,
+    global: bool,
+}
+
+impl Substitution {
+    // Parses `s/pattern/replacement/[g]` with any delimiter; the pattern is matched literally.
+    fn parse(expr: &str) -> Result<Self, Error> {
+        let invalid = || {
+            Error::with_cause(
+                format!("An expression '{expr}' is invalid"),
+                "expected s/pattern/replacement/[g]",
+            )
+        };
+        let rest = expr.strip_prefix('s').ok_or_else(invalid)?;
+        let delim = rest.chars().next().ok_or_else(invalid)?;
+        let parts = rest[delim.len_utf8()..].split(delim).collect::<Vec<_>>();
+
+        let [pattern, replacement, flags] = parts.as_slice() else {
+            return Err(invalid());
+        };
+
+        if pattern.is_empty() || !flags.chars().all(|c| c == 'g') {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            global: flags.contains('g'),
+        })
+    }
+
+    // Returns the new content and how many lines changed.
+    fn apply(&self, content: &str) -> (String, usize) {
+        let mut changed = 0;
+        let mut out = String::with_capacity(content.len());
+
+        for line in content.split_inclusive('\n') {
+            let replaced = if self.global {
+                line.replace(&self.pattern, &self.replacement)
+            } else {
+                line.replacen(&self.pattern, &self.replacement, 1)
+            };
+
+            if replaced != line {
+                changed += 1;
+            }
+
+            out.push_str(&replaced);
+        }
+
+        (out, changed)
+    }
+}
+
+fn sed(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--dry-run"], &[])?;
+
+    let Some((expr, names)) = args.positional.split_first() else {
+        return Err(Error::new(
+            "Usage: me sed s/pattern/replacement/[g] [name]... [--dry-run]",
+        ));
+    };
+
+    let subst = Substitution::parse(expr)?;
+    let paths = if names.is_empty() {
+        memo_paths()?
+    } else {
+        names
+            .iter()
+            .map(|name| existing_memo_path(name))
+            .collect::<Result<_, _>>()?
+    };
+
+    let mut plan = Plan::default();
+
+    for path in paths {
+        let content = storage::read(&path).map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' reading failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+        let (content, lines) = subst.apply(&content);
+
+        if lines > 0 {
+            plan.push(Action::Modify {
+                path,
+                content,
+                lines,
+            });
+        }
+    }
+
+    if plan.is_empty() {
+        println!("No memo matched '{}'", subst.pattern);
+
+        return Ok(());
+    }
+
+    plan.execute(args.has(&["--dry-run"]))
+}
+
+// Renames a memo and, once the lines it would change are shown, rewrites the links to it. Asks
+// first when run in a terminal; `--dry-run` only shows them.
+fn rename(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--dry-run"], &[])?;
+
+    let [name, new_name] = args.positional.as_slice() else {
+        return Err(Error::new("Usage: me rename <name> <new-name> [--dry-run]"));
+    };
+
+    validate_memo_name(new_name)?;
+
+    let hooks = config::load()?.hooks;
+    let path = existing_memo_path(name)?;
+    let old_name = memo_name_of(&path);
+    let mut stash = Stash::new();
+
+    fill_stash_with_local(&mut stash)?;
+
+    let relinks = crate::links::relinks(&stash, &old_name, new_name);
+
+    for relink in &relinks {
+        for (line, old, new) in &relink.lines {
+            println!("{}:{line}:", relink.name);
+            println!("  - {}", old.trim());
+            println!("  + {}", new.trim());
+        }
+    }
+
+    if args.has(&["--dry-run"]) {
+        return Ok(());
+    }
+
+    let new_path = rename_memo(&Memo::new(&path), new_name)?;
+
+    println!("{}", new_path.to_string_lossy());
+
+    let question = format!("Rewrite the links in {} memos? [Y/n]: ", relinks.len());
+
+    if relinks.is_empty() || io::stdin().is_terminal() && !crate::onboard::confirm(&question)? {
+        return Ok(());
+    }
+
+    for relink in relinks {
+        // The memo may link to itself.
+        let target = match relink.path == path {
+            true => &new_path,
+            false => &relink.path,
+        };
+        let written = storage::write(target, &relink.content)?;
+
+        fire(&hooks, Hook::Edit, &written, &relink.content);
+    }
+
+    Ok(())
+}
+
+fn import(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--dry-run"], &["--from"])?;
+
+    if args.positional.is_empty() {
+        return Err(Error::new(
+            "Usage: me import [--from simplenote|jrnl|standardnotes] <file|dir>... [--dry-run]",
+        ));
+    }
+
+    let plan = crate::importer::plan(
+        &args.positional,
+        args.options.get("--from").map(String::as_str),
+    )?;
+
+    if plan.is_empty() {
+        println!("Nothing to import");
+
+        return Ok(());
+    }
+
+    plan.execute(args.has(&["--dry-run"]))
+}
+
+fn sync(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(
+        args,
+        &["--pull", "--encrypt", "--dry-run", "--prompt"],
+        &["--backend"],
+    )?;
+    let config = config::load()?;
+
+    let name = args
+        .options
+        .get("--backend")
+        .or(config.sync.backend.as_ref())
+        .ok_or_else(|| {
+            Error::new("Usage: me sync --backend <name> [target] [--pull] [--dry-run] [--prompt]")
+        })?;
+    let target = args.positional.first().map(String::as_str);
+    let mut backend = crate::sync::backend(name, target, &config)?;
+
+    if args.has(&["--encrypt"]) || config.sync.encryption.enabled {
+        backend = Box::new(crate::sync::Encrypted::new(
+            backend,
+            &config.sync.encryption,
+            args.has(&["--prompt"]),
+        ));
+    }
+
+    if args.has(&["--pull"]) {
+        backend.pull(&MEMO_LIST_PATH, args.has(&["--dry-run"]))?;
+    } else {
+        backend.push(&MEMO_LIST_PATH, args.has(&["--dry-run"]))?;
+    }
+
+    if args.has(&["--dry-run"]) {
+        return Ok(());
+    }
+
+    fire(&config.hooks, Hook::Sync, &MEMO_LIST_PATH, "");
+
+    Ok(())
+}
+
+fn add(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &[], &["--name"])?;
+
+    if args.positional.is_empty() {
+        return Err(Error::new("Usage: me add <text>... [--name <name>]"));
+    }
+
+    let text = args.positional.join(" ");
+    let name = args.options.get("--name").map(String::as_str);
+    let hooks = config::load()?.hooks;
+
+    #[cfg(unix)]
+    let sent = crate::ipc::send(name, &text)?;
+    #[cfg(not(unix))]
+    let sent = None;
+
+    let path = match sent {
+        Some(path) => path,
+        None => capture_memo(name, &text)?,
+    };
+
+    println!("{}", path.to_string_lossy());
+
+    fire(&hooks, Hook::Create, &path, &text);
+
+    Ok(())
+}
+
+// Links a note or a folder of notes kept elsewhere into the stash, where it is listed like any
+// other memo but stays in place.
+fn link(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &[], &["--name"])?;
+
+    let [target] = args.positional.as_slice() else {
+        return Err(Error::new("Usage: me link <path> [--name <name>]"));
+    };
+
+    let target = Path::new(target)
+        .canonicalize()
+        .map_err(|e| Error::with_cause(format!("A path '{target}' resolving failed"), e.kind()))?;
+    let name = match args.options.get("--name") {
+        Some(name) => name.clone(),
+        None if target.is_dir() => target
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        None => crate::memo_name_of(&target),
+    };
+
+    crate::validate_memo_name(&name)?;
+
+    let link = match target.is_dir() {
+        true => MEMO_LIST_PATH.join(&name),
+        false => MEMO_LIST_PATH.join(crate::memo_file_name(&name, &target)),
+    };
+
+    if link.symlink_metadata().is_ok() || memo_path(&name).exists() {
+        return Err(Error::with_cause(
+            format!("A link '{}' creating failed", link.to_string_lossy()),
+            "the name is taken",
+        ));
+    }
+
+    #[cfg(unix)]
+    let res = std::os::unix::fs::symlink(&target, &link);
+    #[cfg(windows)]
+    let res = match target.is_dir() {
+        true => std::os::windows::fs::symlink_dir(&target, &link),
+        false => std::os::windows::fs::symlink_file(&target, &link),
+    };
+
+    res.map_err(|e| {
+        Error::with_cause(
+            format!("A link '{}' creating failed", link.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    println!(
+        "linked {} -> {}",
+        link.to_string_lossy(),
+        target.to_string_lossy()
+    );
+
+    Ok(())
+}
+
+// Stores files in the attachment store and links them at the end of a memo.
+fn attach(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &[], &[])?;
+
+    let Some((name, files)) = args
+        .positional
+        .split_first()
+        .filter(|(_, files)| !files.is_empty())
+    else {
+        return Err(Error::new("Usage: me attach <name> <file>..."));
+    };
+
+    // The name may be an alias; the links go to the memo it resolves to.
+    let resolved = existing_memo_path(name)?;
+    let name = memo_name_of(&resolved);
+
+    if memo_path(&name) != resolved {
+        return Err(Error::with_cause(
+            format!("A memo '{name}' attaching failed"),
+            "it lies in a subdirectory, which appending by name cannot reach",
+        ));
+    }
+
+    let mut links = vec![];
+
+    for file in files.iter().map(Path::new) {
+        let (blob, stored) = crate::attachment::store(file)?;
+
+        match stored {
+            true => println!("stored {} as {blob}", file.to_string_lossy()),
+            false => println!("linked {} to the stored {blob}", file.to_string_lossy()),
+        }
+
+        links.push(crate::attachment::link(file, &blob));
+    }
+
+    let text = links.join("\n");
+    let hooks = config::load()?.hooks;
+
+    #[cfg(unix)]
+    let sent = crate::ipc::send_append(&name, &text)?;
+    #[cfg(not(unix))]
+    let sent = None;
+
+    let path = match sent {
+        Some(path) => path,
+        None => append_memo(&name, &text)?,
+    };
+
+    fire(
+        &hooks,
+        Hook::Edit,
+        &path,
+        &storage::read(&path).unwrap_or_default(),
+    );
+    Ok(())
+}
+
+// Captures the clipboard like `add`, or appends it to the inbox memo with `--inbox`.
+fn clip(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--inbox"], &[])?;
+    let inbox = args.has(&["--inbox"]);
+
+    if args.positional.len() > 1 || inbox && !args.positional.is_empty() {
+        return Err(Error::new("Usage: me clip [<name>|--inbox]"));
+    }
+
+    let config = config::load()?;
+    let text = crate::clipboard::read(config.clip.command.as_deref())?;
+
+    if text.trim().is_empty() {
+        return Err(Error::new("The clipboard is empty"));
+    }
+
+    let name = args.positional.first().map(String::as_str);
+
+    #[cfg(unix)]
+    let sent = if inbox {
+        crate::ipc::send_append(&config.clip.inbox, &text)?
+    } else {
+        crate::ipc::send(name, &text)?
+    };
+    #[cfg(not(unix))]
+    let sent = None;
+
+    let path = match sent {
+        Some(path) => path,
+        None if inbox => append_memo(&config.clip.inbox, &text)?,
+        None => capture_memo(name, &text)?,
+    };
+
+    println!("{}", path.to_string_lossy());
+
+    let hook = if inbox { Hook::Edit } else { Hook::Create };
+
+    fire(
+        &config.hooks,
+        hook,
+        &path,
+        &storage::read(&path).unwrap_or_default(),
+    );
+    Ok(())
+}
+
+// Runs a command and appends what it printed to a memo, in a code block under the time and the
+// command line.
+fn capture(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &[], &["--into"])?;
+
+    let (Some(name), false) = (args.options.get("--into"), args.positional.is_empty()) else {
+        return Err(Error::new(
+            "Usage: me capture --into <name> -- <command>...",
+        ));
+    };
+
+    let hooks = config::load()?.hooks;
+    let (output, status) = run_captured(&args.positional)?;
+
+    print!("{output}");
+
+    append_captured(&hooks, name, &args.positional, &output, status).map(|_| ())
+}
+
+// Runs a command every `--interval` seconds, 60 by default, and appends its output to a memo
+// like `capture` whenever it differs from the last run. Goes on until interrupted.
+fn watch_cmd(args: &[String]) -> Result<(), Error> {
+    let usage = "Usage: me watch-cmd --into <name> [--interval <secs>] -- <command>...";
+    let args = Args::parse(args, &[], &["--into", "--interval"])?;
+
+    let (Some(name), false) = (args.options.get("--into"), args.positional.is_empty()) else {
+        return Err(Error::new(usage));
+    };
+
+    let interval = match args.options.get("--interval") {
+        Some(secs) => secs
+            .parse()
+            .ok()
+            .filter(|&secs| secs > 0)
+            .ok_or_else(|| Error::new(usage))?,
+        None => 60,
+    };
+    let hooks = config::load()?.hooks;
+    let mut last = None;
+
+    loop {
+        let (output, status) = run_captured(&args.positional)?;
+        let mut hasher = DefaultHasher::new();
+
+        (&output, status).hash(&mut hasher);
+
+        let hash = hasher.finish();
+
+        if last != Some(hash) {
+            let path = append_captured(&hooks, name, &args.positional, &output, status)?;
+
+            println!(
+                "{} changed, appended to {}",
+                Local::now().format("%H:%M:%S"),
+                path.to_string_lossy()
+            );
+
+            last = Some(hash);
+        }
+
+        std::thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+// Appends a code block of the output under the time and the command line, with the exit code
+// after it when the command failed.
+fn append_captured(
+    hooks: &HooksConfig,
+    name: &str,
+    command: &[String],
+    output: &str,
+    status: Option<i32>,
+) -> Result<PathBuf, Error> {
+    let mut text = format!(
+        "## {} `{}`\n\n{}",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        command.join(" "),
+        fenced(output)
+    );
+
+    if let Some(code) = status {
+        text.push_str(&format!("\nExit status {code}\n"));
+    }
+
+    #[cfg(unix)]
+    let sent = crate::ipc::send_append(name, &text)?;
+    #[cfg(not(unix))]
+    let sent = None;
+
+    let path = match sent {
+        Some(path) => path,
+        None => append_memo(name, &text)?,
+    };
+
+    fire(
+        hooks,
+        Hook::Edit,
+        &path,
+        &storage::read(&path).unwrap_or_default(),
+    );
+
+    Ok(path)
+}
+
+// What the command printed, stdout then stderr, and its exit code when it failed.
+fn run_captured(command: &[String]) -> Result<(String, Option<i32>), Error> {
+    let (program, args) = command.split_first().unwrap();
+    let failed = |cause: String| {
+        Error::with_cause(format!("The command '{program}' executing failed"), cause)
+    };
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                failed(String::from("it is not installed or not in PATH"))
+            }
+            kind => failed(kind.to_string()),
+        })?;
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+
+    let code = match output.status.success() {
+        true => None,
+        false => Some(output.status.code().unwrap_or(-1)),
+    };
+
+    Ok((text, code))
+}
+
+// A code block around `text`, fenced with more backticks than any run inside it.
+fn fenced(text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat((longest + 1).max(3));
+    let newline = if text.is_empty() || text.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+
+    format!("{fence}text\n{text}{newline}{fence}\n")
+}
+
+// Appends a link to the bookmarks memo, titled after the page when the build can fetch it.
+fn add_url(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &[], &["--title"])?;
+
+    let Some((url, notes)) = args.positional.split_first() else {
+        return Err(Error::new(
+            "Usage: me add-url <url> [<note>...] [--title <title>]",
+        ));
+    };
+
+    crate::bookmark::validate_url(url)?;
+
+    let title = match args.options.get("--title") {
+        Some(title) => Some(title.clone()),
+        // The link matters more than its title, so a page that cannot be fetched is still saved.
+        None => crate::bookmark::fetch_title(url).unwrap_or_else(|e| {
+            eprintln!("[ERR] {e}");
+            None
+        }),
+    };
+    let entry = crate::bookmark::entry(url, title.as_deref(), &notes.join(" "));
+    let config = config::load()?;
+    let memo = config.bookmarks.memo;
+
+    #[cfg(unix)]
+    let sent = crate::ipc::send_append(&memo, &entry)?;
+    #[cfg(not(unix))]
+    let sent = None;
+
+    let path = match sent {
+        Some(path) => path,
+        None => append_memo(&memo, &entry)?,
+    };
+
+    println!("{entry}");
+
+    fire(
+        &config.hooks,
+        Hook::Edit,
+        &path,
+        &storage::read(&path).unwrap_or_default(),
+    );
+    Ok(())
+}
+
+fn publish(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--no-feed"], &["--out", "--tag", "--feed-tag"])?;
+    let mut config = config::load()?.publish;
+
+    let (Some(out), true) = (args.options.get("--out"), args.positional.is_empty()) else {
+        return Err(Error::new(
+            "Usage: me publish --out <dir> [--tag <tag>] [--feed-tag <tag>] [--no-feed]",
+        ));
+    };
+
+    if let Some(tag) = args.options.get("--feed-tag") {
+        config.feed.tag = Some(tag.clone());
+    }
+
+    if args.has(&["--no-feed"]) {
+        config.feed.enabled = false;
+    }
+
+    let tag = args.options.get("--tag").map(String::as_str);
+    let count = crate::publish::publish(&memo_paths()?, Path::new(out), tag, &config)?;
+
+    println!("{count} memos published to '{out}'");
+
+    Ok(())
+}
+
+fn export(args: &[String]) -> Result<(), Error> {
+    let usage =
+        "Usage: me export --format pdf|docx|md (<name> [--out <file>]|--query <query> --out <dir>)";
+    let args = Args::parse(args, &[], &["--format", "--out", "--query"])?;
+
+    if let Some(query) = args.options.get("--query") {
+        let (Some(format), Some(out), []) = (
+            args.options.get("--format"),
+            args.options.get("--out"),
+            args.positional.as_slice(),
+        ) else {
+            return Err(Error::new(usage));
+        };
+
+        return export_query(query, crate::export::Format::parse(format)?, Path::new(out));
+    }
+
+    let (Some(format), [name]) = (args.options.get("--format"), args.positional.as_slice()) else {
+        return Err(Error::new(usage));
+    };
+
+    let format = crate::export::Format::parse(format)?;
+    let path = existing_memo_path(name)?;
+    let out = args
+        .options
+        .get("--out")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("{name}.{}", format.extension())));
+
+    crate::export::export(&path, format, &out, &config::load()?.export, None)?;
+
+    println!("{}", out.to_string_lossy());
+
+    Ok(())
+}
+
+// Exports every memo matching `query` into the directory `out`, one file each.
+fn export_query(query: &str, format: crate::export::Format, out: &Path) -> Result<(), Error> {
+    let mut stash = Stash::new();
+
+    fill_stash_with_local(&mut stash)?;
+
+    let paths = stash
+        .stash
+        .iter()
+        .filter(|memo| memo.matches_all(query))
+        .map(|memo| memo.original_path.clone())
+        .collect::<Vec<_>>();
+
+    if paths.is_empty() {
+        return Err(Error::new(format!("No memo matches '{query}'")));
+    }
+
+    crate::export::export_all(&paths, format, out, &config::load()?.export, None)?;
+
+    println!(
+        "{} memos exported to '{}'",
+        paths.len(),
+        out.to_string_lossy()
+    );
+
+    Ok(())
+}
+
+fn mail(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &[], &["--to"])?;
+
+    let (Some(to), [name]) = (args.options.get("--to"), args.positional.as_slice()) else {
+        return Err(Error::new("Usage: me mail <name> --to <addr>[,<addr>...]"));
+    };
+
+    let to = to
+        .split(',')
+        .map(|addr| addr.trim().to_string())
+        .filter(|addr| !addr.is_empty())
+        .collect::<Vec<_>>();
+
+    crate::mail::send(&existing_memo_path(name)?, &to, &config::load()?.mail)?;
+
+    println!("Sent '{name}' to {}", to.join(", "));
+
+    Ok(())
+}
+
+// `memo:line: [[target]]` for each wiki link that leads to no memo.
+fn links(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--broken"], &[])?;
+
+    if !args.positional.is_empty() || !args.has(&["--broken"]) {
+        return Err(Error::new("Usage: me links --broken"));
+    }
+
+    let mut stash = Stash::new();
+
+    fill_stash_with_local(&mut stash)?;
+
+    let mut out = String::new();
+
+    for link in crate::links::broken(&stash) {
+        out.push_str(&format!(
+            "{}:{}: [[{}]]\n",
+            link.name, link.line, link.target
+        ));
+    }
+
+    print_piped(&out)
+}
+
+// Memos gone longest without a look, drawn at random and counted as reviewed from then on. With
+// `--stale`, every memo untouched for `review.stale_days` instead, leaving them as they are.
+fn review(args: &[String]) -> Result<(), Error> {
+    let usage = "Usage: me review [--count <n>|--stale]";
+    let args = Args::parse(args, &["--stale"], &["--count"])?;
+    let stale = args.has(&["--stale"]);
+
+    if !args.positional.is_empty() || stale && args.options.contains_key("--count") {
+        return Err(Error::new(usage));
+    }
+
+    let count = match args.options.get("--count") {
+        Some(count) => count.parse().map_err(|_| Error::new(usage))?,
+        None => crate::review::COUNT,
+    };
+    let mut stash = Stash::new();
+
+    fill_stash_with_local(&mut stash)?;
+
+    let candidates = stash
+        .stash
+        .iter()
+        .map(|memo| {
+            let modified = fs::metadata(memo.current_path()).and_then(|meta| meta.modified());
+
+            (memo.name(), modified.ok())
+        })
+        .collect::<Vec<_>>();
+    let picked = match stale {
+        true => crate::review::stale(&candidates, config::load()?.review.stale_days),
+        false => crate::review::pick(&candidates, count),
+    };
+    let mut names = vec![];
+    let mut out = String::new();
+
+    for idx in picked {
+        let memo = &stash.stash[idx];
+        let (front, body) = crate::meta::split(&memo.content_buffer);
+        let first = body.lines().map(str::trim).find(|line| !line.is_empty());
+
+        match front.title().or(first) {
+            Some(line) => out.push_str(&format!("{}\t{line}\n", memo.name())),
+            None => out.push_str(&format!("{}\n", memo.name())),
+        }
+
+        names.push(memo.name());
+    }
+
+    if !stale {
+        crate::review::mark(&names)?;
+    }
+
+    print_piped(&out)
+}
+
+// Time clocked per memo, or per tag with `--tags`, over this week, today or all time.
+fn timesheet(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--week", "--today", "--tags"], &[])?;
+
+    if !args.positional.is_empty() || args.has(&["--week"]) && args.has(&["--today"]) {
+        return Err(Error::new("Usage: me timesheet [--week|--today] [--tags]"));
+    }
+
+    let today = Local::now().date_naive();
+    let since = if args.has(&["--today"]) {
+        Some(today)
+    } else if args.has(&["--week"]) {
+        Some(today - Days::new(today.weekday().num_days_from_monday() as u64))
+    } else {
+        None
+    };
+    let since = since
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .and_then(|time| time.and_local_timezone(Local).earliest());
+    let mut tags = HashMap::<String, Vec<String>>::new();
+    let mut totals = HashMap::<String, TimeDelta>::new();
+    let mut total = TimeDelta::zero();
+
+    for interval in crate::timesheet::intervals()? {
+        let start = since.map_or(interval.start, |since| interval.start.max(since));
+
+        if interval.end <= start {
+            continue;
+        }
+
+        let spent = interval.end - start;
+        let keys = match args.has(&["--tags"]) {
+            true => tags
+                .entry(interval.memo.clone())
+                .or_insert_with(|| {
+                    let content = existing_memo_path(&interval.memo)
+                        .ok()
+                        .and_then(|path| storage::read(path).ok())
+                        .unwrap_or_default();
+                    let (front, _) = crate::meta::split(&content);
+                    let tags = front.tags();
+
+                    match tags.is_empty() {
+                        true => vec![String::from("(untagged)")],
+                        false => tags.into_iter().map(String::from).collect(),
+                    }
+                })
+                .clone(),
+            false => vec![interval.memo],
+        };
+
+        for key in keys {
+            *totals.entry(key).or_insert(TimeDelta::zero()) += spent;
+        }
+
+        total += spent;
+    }
+
+    let mut totals = totals.into_iter().collect::<Vec<_>>();
+
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut out = String::new();
+
+    for (key, spent) in totals {
+        out.push_str(&format!(
+            "{:>8}  {key}\n",
+            crate::timesheet::format_duration(spent)
+        ));
+    }
+
+    out.push_str(&format!(
+        "{:>8}  total\n",
+        crate::timesheet::format_duration(total)
+    ));
+
+    print_piped(&out)
+}
+
+fn search(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--hidden"], &[])?;
+
+    if args.positional.is_empty() {
+        return Err(Error::new("Usage: me search [--hidden] <query>..."));
+    }
+
+    let mut found = storage::search(
+        &memo_paths()?,
+        &args.positional.join(" "),
+        &Progress::default(),
+    )?;
+
+    // Hidden memos are left out as `me list` leaves them out.
+    if !args.has(&["--hidden"]) {
+        found.retain(|path| {
+            !Memo::with_head(path)
+                .unwrap_or_else(|_| Memo::new(path))
+                .is_hidden()
+        });
+    }
+    let out = found
+        .iter()
+        .map(|path| format!("{}\n", memo_name_of(path)))
+        .collect::<String>();
+
+    print_piped(&out)
+}
+
+fn list(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &["--csv", "--favorites", "--hidden"], &[])?;
+
+    if !args.positional.is_empty() {
+        return Err(Error::new(
+            "Usage: me list [--csv] [--favorites] [--hidden]",
+        ));
+    }
+
+    let favorites = args.has(&["--favorites"]);
+    let hidden = args.has(&["--hidden"]);
+    let mut paths = memo_paths()?;
+
+    // Both flags go by the front matter, of which only the head is read, and only when one of them
+    // could drop the memo.
+    paths.retain(|path| {
+        if hidden && !favorites {
+            return true;
+        }
+
+        let memo = Memo::with_head(path).unwrap_or_else(|_| Memo::new(path));
+
+        (hidden || !memo.is_hidden()) && (!favorites || memo.is_favorite())
+    });
+
+    let mut out = String::new();
+
+    if !args.has(&["--csv"]) {
+        for path in paths {
+            out.push_str(&format!("{}\n", memo_name_of(&path)));
+        }
+
+        return print_piped(&out);
+    }
+
+    out.push_str("name,title,tags,created,modified,words,path\n");
+
+    for path in paths {
+        let content = storage::read(&path).map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' reading failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+        let metadata = fs::metadata(&path).ok();
+        let name = memo_name_of(&path);
+        let (front, body) = crate::meta::split(&content);
+
+        // Front matter wins for the creation date since copies and syncs reset the file's own.
+        let created = front.get("created").map(String::from).or_else(|| {
+            metadata
+                .as_ref()
+                .and_then(|meta| meta.created().ok())
+                .map(rfc3339)
+        });
+        let modified = metadata
+            .as_ref()
+            .and_then(|meta| meta.modified().ok())
+            .map(rfc3339);
+
+        let row = [
+            name.clone(),
+            front.title().unwrap_or(&name).to_string(),
+            front.tags().join(" "),
+            created.unwrap_or_default(),
+            modified.unwrap_or_default(),
+            body.split_whitespace().count().to_string(),
+            path.to_string_lossy().to_string(),
+        ];
+
+        let row = row.iter().map(|field| csv_field(field)).collect::<Vec<_>>();
+
+        out.push_str(&format!("{}\n", row.join(",")));
+    }
+
+    print_piped(&out)
+}
+
+// Prints a memo's ID for referring to it by something renames do not break.
+fn id(args: &[String]) -> Result<(), Error> {
+    let [name] = args else {
+        return Err(Error::new("Usage: me id <name>"));
+    };
+
+    println!("{}", memo_id(&existing_memo_path(name)?)?);
+
+    Ok(())
+}
+
+fn show(args: &[String]) -> Result<(), Error> {
+    let usage = "Usage: me show <name> | me show --id <id>";
+    let args = Args::parse(args, &[], &["--id"])?;
+
+    let path = match (args.options.get("--id"), args.positional.as_slice()) {
+        (Some(id), []) => memo_path_by_id(id)?,
+        (None, [name]) => existing_memo_path(name)?,
+        _ => return Err(Error::new(usage)),
+    };
+    let content = storage::read(&path).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    print_piped(&content)
+}
+
+fn memo_path_by_id(id: &str) -> Result<PathBuf, Error> {
+    for path in memo_paths()? {
+        let Ok(content) = storage::read(&path) else {
+            continue;
+        };
+
+        if crate::meta::split(&content)
+            .0
+            .id()
+            .is_some_and(|found| found.eq_ignore_ascii_case(id))
+        {
+            return Ok(path);
+        }
+    }
+
+    Err(Error::new(format!(
+        "A memo with the ID '{id}' is not found"
+    )))
+}
+
+// Prints (creating it from the template if needed) the current day's, week's or month's memo.
+fn periodic(cmd: &str, args: &[String]) -> Result<(), Error> {
+    let usage = format!("Usage: me {cmd} [--prev|--next] [-e|--edit]");
+    let args = Args::parse(args, &["--prev", "--next", "-e", "--edit"], &[])?;
+
+    if !args.positional.is_empty() || args.has(&["--prev"]) && args.has(&["--next"]) {
+        return Err(Error::new(usage));
+    }
+
+    let config = config::load()?;
+    let period = Period::parse(cmd)?;
+    let step = match (args.has(&["--prev"]), args.has(&["--next"])) {
+        (true, _) => -1,
+        (_, true) => 1,
+        _ => 0,
+    };
+    let start = period.step(period.start(chrono::Local::now().date_naive()), step);
+
+    let (path, created) = crate::periodic::ensure(period, start, &config.periodic)?;
+
+    if created {
+        fire(
+            &config.hooks,
+            Hook::Create,
+            &path,
+            &storage::read(&path).unwrap_or_default(),
+        );
+    }
+
+    if args.has(&["-e", "--edit"]) {
+        edit_file(&path)?;
+    } else {
+        println!("{}", path.to_string_lossy());
+    }
+
+    Ok(())
+}
+
+// Prints the config lines that turn on the TUI lock for a new passphrase.
+fn lock(args: &[String]) -> Result<(), Error> {
+    Args::parse(args, &[], &[])?;
+
+    let passphrase = crate::read_secret("New lock passphrase: ")?;
+
+    if passphrase.is_empty() {
+        return Err(Error::new("The passphrase is empty"));
+    }
+
+    if crate::read_secret("Again: ")? != passphrase {
+        return Err(Error::new("The passphrases do not match"));
+    }
+
+    println!("[lock]");
+    println!("passphrase_hash = \"{}\"", crate::lock::hash(&passphrase)?);
+
+    Ok(())
+}
+
+fn config(args: &[String]) -> Result<(), Error> {
+    let args = Args::parse(args, &[], &[])?;
+
+    let ["check"] = args
+        .positional
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    else {
+        return Err(Error::new("Usage: me config check"));
+    };
+
+    if !config::CONFIG_PATH.exists() {
+        println!("No config file, every setting is at its default");
+
+        return Ok(());
+    }
+
+    let problems = config::check()?;
+
+    for problem in &problems {
+        match problem.line {
+            Some(line) => println!("[WARN] line {line}: {}", problem.message),
+            None => println!("[WARN] {}", problem.message),
+        }
+    }
+
+    match problems.len() {
+        0 => {
+            println!("No problems found");
+
+            Ok(())
+        }
+        n => Err(Error::new(format!("The config has {n} problem(s)"))),
+    }
+}
+
+// Warns about parts of the stash that other users can get at.
+fn doctor(args: &[String]) -> Result<(), Error> {
+    Args::parse(args, &[], &[])?;
+
+    #[cfg(unix)]
+    {
+        let exposed = exposed_paths(&crate::APP_DATA_PATH)?;
+
+        for (path, mode) in &exposed {
+            println!(
+                "[WARN] '{}' is accessible by group or others (mode {:o})",
+                path.to_string_lossy(),
+                mode & 0o777
+            );
+        }
+
+        if exposed.is_empty() {
+            println!("No problems found");
+        } else {
+            println!(
+                "Run `chmod -R go-rwx {}` to make the stash private",
+                crate::shell_quote(crate::APP_DATA_PATH.to_string_lossy())
+            );
+        }
+    }
+
+    #[cfg(not(unix))]
+    println!("Permission checks are only done on Unix");
+
+    Ok(())
+}
+
+// Everything under `dir` (and itself) with any group or other permission bit set.
+#[cfg(unix)]
+fn exposed_paths(dir: &Path) -> Result<Vec<(PathBuf, u32)>, Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let failed = |path: &Path, e: std::io::Error| {
+        Error::with_cause(
+            format!("A file '{}' checking failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    };
+
+    let mut exposed = vec![];
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(path) = pending.pop() {
+        // Links are reported, not followed; their own mode is meaningless.
+        let meta = fs::symlink_metadata(&path).map_err(|e| failed(&path, e))?;
+        let mode = meta.permissions().mode();
+
+        if meta.is_dir() {
+            let entries = path.read_dir().map_err(|e| failed(&path, e))?;
+
+            pending.extend(entries.filter_map(|entry| entry.ok().map(|entry| entry.path())));
+        }
+
+        if !meta.is_symlink() && mode & 0o077 != 0 {
+            exposed.push((path, mode));
+        }
+    }
+
+    exposed.sort();
+
+    Ok(exposed)
+}
+
+// Output meant for pipes; a reader that quits early (e.g. `head`) is not an error.
+fn print_piped(out: &str) -> Result<(), Error> {
+    use std::io::Write;
+
+    match std::io::stdout().lock().write_all(out.as_bytes()) {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+            Err(Error::with_cause("The output writing failed", e.kind()))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn rfc3339(time: std::time::SystemTime) -> String {
+    chrono::DateTime::<chrono::Local>::from(time)
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// Left out of the docs: times stash operations over generated stashes, for catching performance
+// regressions. With `--nanos` only the total of the runs is printed, which the criterion benches
+// read.
+fn bench(args: &[String]) -> Result<(), Error> {
+    let usage = "Usage: me bench [--op <op>] [--size <memos>] [--runs <n>] [--nanos]";
+    let args = Args::parse(args, &["--nanos"], &["--op", "--size", "--runs"])?;
+
+    if !args.positional.is_empty() {
+        return Err(Error::new(usage));
+    }
+
+    let ops = match args.options.get("--op") {
+        Some(op) if bench::OPS.contains(&op.as_str()) => vec![op.as_str()],
+        Some(op) => {
+            return Err(Error::new(format!(
+                "Unknown benchmark '{op}', expected one of {}",
+                bench::OPS.join(", ")
+            )));
+        }
+        None => bench::OPS.to_vec(),
+    };
+    let sizes = match args.options.get("--size") {
+        Some(size) => vec![
+            size.parse()
+                .map_err(|_| Error::new(format!("Not a memo count: '{size}'")))?,
+        ],
+        None => bench::SIZES.to_vec(),
+    };
+    let runs = match args.options.get("--runs") {
+        Some(runs) => runs
+            .parse()
+            .ok()
+            .filter(|&runs| runs > 0)
+            .ok_or_else(|| Error::new(format!("Not a run count: '{runs}'")))?,
+        None => 5,
+    };
+
+    for size in sizes {
+        let dir = bench::stash(size)?;
+
+        for op in &ops {
+            let total = bench::time(op, &dir, runs)?;
+
+            if args.has(&["--nanos"]) {
+                println!("{}", total.as_nanos());
+            } else {
+                let each = total.as_secs_f64() * 1000.0 / runs as f64;
+
+                println!("{op:<8} {size:>7} memos {each:>10.3}ms");
+            }
+        }
+    }
+
+    Ok(())
+}
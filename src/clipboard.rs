@@ -0,0 +1,136 @@
+use crate::{Error, shell_command};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus, Output, Stdio};
+
+// Reads the clipboard with `command` when one is configured, otherwise with the first
+// clipboard tool that is installed.
+pub fn read(command: Option<&str>) -> Result<String, Error> {
+    if let Some(line) = command {
+        return text(run(&mut shell_command(line)), line);
+    }
+
+    let candidates: &[&[&str]] = if cfg!(target_os = "macos") {
+        &[&["pbpaste"]]
+    } else if cfg!(windows) {
+        &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard -Raw"]]
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[
+            &["wl-paste", "--no-newline"],
+            &["xclip", "-selection", "clipboard", "-o"],
+            &["xsel", "--clipboard", "--output"],
+        ]
+    } else {
+        &[
+            &["xclip", "-selection", "clipboard", "-o"],
+            &["xsel", "--clipboard", "--output"],
+            &["wl-paste", "--no-newline"],
+        ]
+    };
+
+    for argv in candidates {
+        match run(Command::new(argv[0]).args(&argv[1..])) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            res => return text(res, argv[0]),
+        }
+    }
+
+    Err(Error::with_cause(
+        "The clipboard reading failed",
+        "no clipboard tool is found, install wl-clipboard, xclip or xsel or set clip.command",
+    ))
+}
+
+// Puts `text` on the clipboard with `command` when one is configured, otherwise with the first
+// clipboard tool that is installed, or else asks the terminal to with an OSC 52 sequence.
+pub fn write(text: &str, command: Option<&str>) -> Result<(), Error> {
+    if let Some(line) = command {
+        return copied(feed(&mut shell_command(line), text), line);
+    }
+
+    let candidates: &[&[&str]] = if cfg!(target_os = "macos") {
+        &[&["pbcopy"]]
+    } else if cfg!(windows) {
+        &[&["clip"]]
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        &[
+            &["wl-copy"],
+            &["xclip", "-selection", "clipboard"],
+            &["xsel", "--clipboard", "--input"],
+        ]
+    } else {
+        &[
+            &["xclip", "-selection", "clipboard"],
+            &["xsel", "--clipboard", "--input"],
+            &["wl-copy"],
+        ]
+    };
+
+    for argv in candidates {
+        match feed(Command::new(argv[0]).args(&argv[1..]), text) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            res => return copied(res, argv[0]),
+        }
+    }
+
+    let mut out = io::stdout();
+
+    write!(out, "\x1b]52;c;{}\x07", STANDARD.encode(text))
+        .and_then(|_| out.flush())
+        .map_err(|e| Error::with_cause("The clipboard writing failed", e.kind()))
+}
+
+// The tools that keep serving the clipboard in the background must not hold on to our output.
+fn feed(cmd: &mut Command, text: &str) -> io::Result<ExitStatus> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    child.wait()
+}
+
+fn copied(res: io::Result<ExitStatus>, program: &str) -> Result<(), Error> {
+    let status = res.map_err(|e| {
+        Error::with_cause(
+            format!("The command '{program}' executing failed"),
+            e.kind(),
+        )
+    })?;
+
+    match status.success() {
+        true => Ok(()),
+        false => Err(Error::with_cause(
+            format!("The command '{program}' failed"),
+            status,
+        )),
+    }
+}
+
+fn run(cmd: &mut Command) -> io::Result<Output> {
+    cmd.stdin(Stdio::null()).stderr(Stdio::null()).output()
+}
+
+fn text(res: io::Result<Output>, program: &str) -> Result<String, Error> {
+    let out = res.map_err(|e| {
+        Error::with_cause(
+            format!("The command '{program}' executing failed"),
+            e.kind(),
+        )
+    })?;
+
+    if !out.status.success() {
+        return Err(Error::with_cause(
+            format!("The command '{program}' failed"),
+            out.status,
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&out.stdout).to_string())
+}
@@ -0,0 +1,959 @@
+use crate::keymap::{Context, canonical_keymap};
+use crate::{Error, fatal_err};
+use crossterm::style::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, SystemTime};
+use toml::de::{DeTable, DeValue};
+use viks::Keymap;
+
+pub static CONFIG_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+    dirs::config_dir()
+        .unwrap_or_else(|| {
+            fatal_err(
+                "CONFIG_PATH loading failed",
+                Error::new("A config dir is not found"),
+            )
+        })
+        .join("memoleak")
+        .join("config.toml")
+});
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    pub sync: SyncConfig,
+    pub publish: PublishConfig,
+    pub export: ExportConfig,
+    pub mail: MailConfig,
+    pub hooks: HooksConfig,
+    // Keymaps bound to shell command lines, e.g. `"gp" = { shell = "git -C {stash} push" }`.
+    pub keys: BTreeMap<String, ShellKey>,
+    pub scratch: ScratchConfig,
+    pub log: LogConfig,
+    pub focus: FocusConfig,
+    pub review: ReviewConfig,
+    pub periodic: PeriodicConfig,
+    pub board: BoardConfig,
+    pub clip: ClipConfig,
+    pub bookmarks: BookmarksConfig,
+    pub spell: SpellConfig,
+    pub lint: LintConfig,
+    pub preview: PreviewConfig,
+    pub display: DisplayConfig,
+    pub delete: DeleteConfig,
+    pub backup: BackupConfig,
+    pub history: HistoryConfig,
+    pub lock: LockConfig,
+    pub storage: StorageConfig,
+    // Note folders kept elsewhere whose markdown files are listed and edited in place, e.g.
+    // `sources = [{ path = "~/Documents/notes", name = "docs" }]`.
+    pub sources: Vec<SourceConfig>,
+    // Snippet name to text, expanded from `;name` with TAB in prompts.
+    pub snippets: BTreeMap<String, String>,
+    pub startup: Startup,
+    pub editor: EditorConfig,
+    pub input: InputConfig,
+    // Search name to query, for `startup` and `:search <name>`.
+    pub searches: BTreeMap<String, String>,
+    // Tag to how memos carrying it are listed, e.g. `[tags.urgent] color = "red", icon = "!"`.
+    pub tags: BTreeMap<String, TagStyle>,
+    // Where the stash and everything else memoleak keeps are; the platform's data dir by default.
+    pub data_dir: Option<PathBuf>,
+    // Starts a line-based prompt instead of the full-screen TUI, for screen readers; the same as
+    // `me --accessible`.
+    pub accessible: bool,
+    // Settings laid over the others by `me --profile <name>`, e.g. a `[profiles.work]` table with
+    // its own `data_dir` and `[profiles.work.editor]`.
+    pub profiles: BTreeMap<String, toml::Table>,
+}
+
+impl Config {
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        self.data_dir.as_deref().map(expand_home)
+    }
+}
+
+pub fn expand_home(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+// Set once at startup, before anything loads the config.
+pub fn select_profile(name: String) {
+    let _ = PROFILE.set(name);
+}
+
+pub fn profile() -> Option<&'static str> {
+    PROFILE.get().map(String::as_str)
+}
+
+// What the TUI opens on: `"list"`, `"last"` for the memo last opened in the editor, `"today"` for
+// the daily note, or `{ search = "<name>" }` to start filtered by one of `searches`.
+#[derive(Deserialize, Default, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum Startup {
+    #[default]
+    List,
+    Last,
+    Today,
+    Search(String),
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct SyncConfig {
+    pub backend: Option<String>,
+    pub s3: S3Config,
+    pub ssh: SshConfig,
+    pub encryption: EncryptionConfig,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    pub enabled: bool,
+    // An age X25519 identity file (as made by age-keygen); takes precedence over a passphrase.
+    pub identity: Option<PathBuf>,
+    // Prompted for when neither this nor an identity is set.
+    pub passphrase: Option<String>,
+    // Keeps a prompted passphrase in the OS keyring so later syncs do not ask again;
+    // `me sync --prompt` asks anyway and replaces it.
+    pub keyring: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct S3Config {
+    pub bucket: Option<String>,
+    pub prefix: Option<String>,
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct SshConfig {
+    pub target: Option<String>,
+    pub port: Option<u16>,
+    pub identity: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct PublishConfig {
+    pub title: Option<String>,
+    // The site's public URL; feed links are absolute only when it is set.
+    pub base_url: Option<String>,
+    pub feed: FeedConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct FeedConfig {
+    pub enabled: bool,
+    pub tag: Option<String>,
+    pub limit: usize,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tag: None,
+            limit: 20,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ExportConfig {
+    // Defaults to `pandoc` from PATH.
+    pub pandoc: Option<PathBuf>,
+    pub pdf_engine: Option<String>,
+    pub template: Option<PathBuf>,
+    pub css: Option<PathBuf>,
+    // Styles DOCX output, which ignores templates.
+    pub reference_doc: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct MailConfig {
+    pub from: Option<String>,
+    // A sendmail-compatible command line; used when no SMTP host is set.
+    pub sendmail: Option<String>,
+    pub smtp: SmtpConfig,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct SmtpConfig {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    // Prompted for when a username is set without it.
+    pub password: Option<String>,
+}
+
+// Shell command lines run after memo lifecycle events; see hooks.rs for the environment they get.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub on_create: Option<String>,
+    pub on_edit: Option<String>,
+    pub on_delete: Option<String>,
+    pub on_sync: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ScratchConfig {
+    // Empties the scratch memo when the TUI quits.
+    pub clear_on_exit: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct FocusConfig {
+    // How long a focus session lasts.
+    pub minutes: u64,
+    // Where finished sessions are written down: `file` for the focus log in the data dir, or
+    // `memo` for a `## Focus` section in the memo itself.
+    pub log: FocusLog,
+}
+
+impl Default for FocusConfig {
+    fn default() -> Self {
+        Self {
+            minutes: 25,
+            log: FocusLog::File,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FocusLog {
+    File,
+    Memo,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ReviewConfig {
+    // How many days a memo goes unchanged and unreviewed before `:stale` brings it up.
+    pub stale_days: u64,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self { stale_days: 90 }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    // The memo that timestamped log entries are appended to.
+    pub memo: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            memo: String::from("log"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ClipConfig {
+    // A command line printing the clipboard; found automatically when unset.
+    pub command: Option<String>,
+    // A command line putting its input on the clipboard; found automatically when unset.
+    pub copy_command: Option<String>,
+    // The memo that `me clip --inbox` appends to.
+    pub inbox: String,
+}
+
+impl Default for ClipConfig {
+    fn default() -> Self {
+        Self {
+            command: None,
+            copy_command: None,
+            inbox: String::from("inbox"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct BookmarksConfig {
+    // The memo that `me add-url` appends links to.
+    pub memo: String,
+}
+
+impl Default for BookmarksConfig {
+    fn default() -> Self {
+        Self {
+            memo: String::from("bookmarks"),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct LintConfig {
+    // A linter run as `command <file>` in place of the built-in rules, e.g. `markdownlint`; the
+    // `file:line[:column] message` lines it prints are listed.
+    pub command: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct SpellConfig {
+    // Checks the preview from startup; `zs` toggles it either way.
+    pub enabled: bool,
+    // A hunspell dictionary name.
+    pub language: String,
+    // Reads text on stdin and prints one misspelled word per line; `hunspell -l` when unset.
+    pub command: Option<String>,
+}
+
+impl Default for SpellConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            language: String::from("en_US"),
+            command: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SourceConfig {
+    pub path: PathBuf,
+    // The badge its memos are shown with; the folder name by default.
+    pub name: Option<String>,
+}
+
+impl SourceConfig {
+    pub fn dir(&self) -> PathBuf {
+        expand_home(&self.path)
+    }
+
+    pub fn badge(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.dir()
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    // Milliseconds to wait for the next key of a longer binding, e.g. after the `g` of `gx`.
+    pub timeout: u64,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self { timeout: 1000 }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct EditorConfig {
+    // A command line that the memo path is appended to; $EDITOR at build time, or vim, when unset.
+    pub command: Option<String>,
+    pub open_in: OpenIn,
+    // Exit codes meaning "throw my changes away", e.g. `[1]` for vim's `:cq`; the memo is put back
+    // as it was before the editor opened.
+    pub discard_codes: Vec<i32>,
+    // A command line the memo is piped through once the editor saved a change, e.g.
+    // `prettier --parser markdown`; what it prints replaces the memo.
+    pub format: Option<String>,
+}
+
+// Where the editor opens. Anything but `terminal` leaves the TUI running beside it in a new tmux
+// or WezTerm pane, except for compressed memos, which are still edited in place.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OpenIn {
+    #[default]
+    Terminal,
+    TmuxSplit,
+    TmuxWindow,
+    WeztermSplit,
+    WeztermTab,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct StorageConfig {
+    // Memos larger than this many bytes are kept gzip-compressed as `name.md.gz`, which keeps
+    // synced stashes small. They are still edited as plain markdown.
+    pub compress_above: Option<u64>,
+    // `"sqlite"` also keeps each memo's metadata and a full-text index of it in one SQLite file,
+    // which `me search` queries; builds need the `sqlite` feature. The memo files stay the copies
+    // that are edited. Its search matches terms at the start of words, where the files alone match
+    // them anywhere in the text.
+    pub backend: Backend,
+    // Keeps the memos' text in the SQLite file as well, not only the index built from it.
+    pub store_content: bool,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Files,
+    Sqlite,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct LockConfig {
+    // An argon2 hash as printed by `me lock`. When set, the TUI starts locked and <c-l> locks it
+    // again; this keeps out someone at the keyboard, the memos stay readable on disk.
+    pub passphrase_hash: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct DeleteConfig {
+    // Overwrites memos with zeros before unlinking them on permanent deletes and trash purges.
+    // Journaling and copy-on-write filesystems or SSDs may still keep the old blocks.
+    pub secure: bool,
+    // Days a memo stays in the trash. Older ones are purged when the TUI starts and by `me gc`,
+    // which otherwise empties the whole trash.
+    pub purge_after_days: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    // Keeps each version of a memo as it is written or edited, for `me history`.
+    pub enabled: bool,
+    // How many days of versions `me history gc` leaves, besides each memo's latest one.
+    pub keep_days: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            keep_days: 90,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    // Zips the stash into `dir` each time the TUI quits, as `me backup` does.
+    pub on_exit: bool,
+    // How many of the newest archives the backup on exit leaves in `dir`; at least one, as the
+    // backup just made would otherwise go right away.
+    pub keep: NonZeroUsize,
+    // Where the archives go; `backups` in the data dir by default.
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            on_exit: false,
+            keep: NonZeroUsize::new(7).unwrap(),
+            dir: None,
+        }
+    }
+}
+
+impl BackupConfig {
+    pub fn dir(&self) -> Option<PathBuf> {
+        self.dir.as_deref().map(expand_home)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct PreviewConfig {
+    // The wrap mode at startup; `zw` cycles through them.
+    pub wrap: Wrap,
+    // Where `column` wraps, or the pane edge if that comes first.
+    pub wrap_column: usize,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            wrap: Wrap::Off,
+            wrap_column: 80,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    // ASCII table borders and white on black, with the cursor row and marks in reverse video, for
+    // serial consoles and old terminals. Guessed from `TERM` when unset.
+    pub basic: Option<bool>,
+    // Redraws per second at most, however fast keys come in; 0 for no limit.
+    pub max_fps: u32,
+    // A column of icons before memo names: file type, favorite, attachments and open tasks.
+    pub icons: bool,
+    // Whether the terminal font has the nerd-font glyphs the icons use; ASCII markers otherwise.
+    pub nerd_font: bool,
+    // The list columns in order, e.g. `[{ show = "title" }, { show = "modified", width = 10 }]`.
+    // Empty for the name followed by its badges.
+    pub columns: Vec<ListColumn>,
+    // The list's share of the screen width in percent; a third, up to 40 cells, when unset.
+    pub list_width: Option<usize>,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            basic: None,
+            max_fps: 60,
+            icons: false,
+            nerd_font: true,
+            columns: vec![],
+            list_width: None,
+        }
+    }
+}
+
+impl DisplayConfig {
+    pub fn frame_interval(&self) -> Duration {
+        match self.max_fps {
+            0 => Duration::ZERO,
+            fps => Duration::from_secs(1) / fps,
+        }
+    }
+
+    pub fn basic(&self) -> bool {
+        self.basic
+            .unwrap_or_else(|| limited_term(env::var("TERM").ok().as_deref()))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListColumn {
+    pub show: ColumnKind,
+    // Cells the column takes. Without it a title takes the room the other columns leave and the
+    // rest are as wide as their widest row on screen.
+    pub width: Option<usize>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnKind {
+    Icon,
+    // The name with its badges.
+    Title,
+    Tags,
+    Modified,
+    Words,
+    // The directory the memo file is in, blank for the stash directory.
+    Folder,
+}
+
+// Terminals without line-drawing glyphs or colors, going by their terminfo names.
+fn limited_term(term: Option<&str>) -> bool {
+    match term {
+        None | Some("" | "dumb" | "ansi") => true,
+        Some(term) => {
+            term.strip_prefix("vt")
+                .is_some_and(|model| model.starts_with(|c: char| c.is_ascii_digit()))
+                || term.ends_with("-mono")
+                || term.ends_with("-m")
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Wrap {
+    // Long lines are cut at the pane edge and scrolled sideways with zh / zl.
+    Off,
+    Window,
+    Column,
+}
+
+// A memo's column is its front matter `status`, or else the first of its tags naming a column.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct BoardConfig {
+    pub columns: Vec<String>,
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        Self {
+            columns: ["todo", "doing", "done"].map(String::from).to_vec(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct PeriodicConfig {
+    pub day: PeriodConfig,
+    pub week: PeriodConfig,
+    pub month: PeriodConfig,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct PeriodConfig {
+    // A chrono strftime format for the memo name; `%Y-%m-%d` for days, `%G-W%V` for weeks and
+    // `%Y-%m` for months.
+    pub format: Option<String>,
+    pub template: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct TagStyle {
+    // A color name such as `dark_green`, `#rrggbb` or an ANSI value from 0 to 255.
+    pub color: Option<String>,
+    // Put before the name, e.g. a nerd-font glyph.
+    pub icon: Option<String>,
+}
+
+impl TagStyle {
+    pub fn color(&self) -> Option<Color> {
+        let color = self.color.as_deref()?.trim();
+
+        if let Some(hex) = color.strip_prefix('#') {
+            let value = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)?;
+
+            return Some(Color::Rgb {
+                r: (value >> 16) as u8,
+                g: (value >> 8) as u8,
+                b: value as u8,
+            });
+        }
+
+        match color.parse() {
+            Ok(value) => Some(Color::AnsiValue(value)),
+            Err(_) => Color::try_from(color).ok(),
+        }
+    }
+}
+
+// `{file}`, `{name}` and `{stash}` in `shell` become the selected memo's path and name and the
+// stash directory, each shell-quoted.
+#[derive(Deserialize)]
+pub struct ShellKey {
+    pub shell: String,
+    #[serde(default)]
+    pub output: ShellOutput,
+    // Where the keys are bound: `list`, `pager`, `dialog`, `prompt` or `global` for all of them.
+    #[serde(default)]
+    pub context: Context,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ShellOutput {
+    // The last line of output, in the status line.
+    #[default]
+    Statusbar,
+    // All of the output, in a scrollable view.
+    Scratch,
+    // Hands the terminal to the command, for interactive programs.
+    Terminal,
+    None,
+}
+
+// When the config file was last changed, if it exists.
+pub fn modified() -> Option<SystemTime> {
+    fs::metadata(&*CONFIG_PATH)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// A missing config file is not an error; every setting has a default.
+pub fn load() -> Result<Config, Error> {
+    let Ok(content) = fs::read_to_string(&*CONFIG_PATH) else {
+        return match PROFILE.get() {
+            Some(name) => Err(Error::new(format!(
+                "A profile '{name}' needs a config, but '{}' is not found",
+                CONFIG_PATH.to_string_lossy()
+            ))),
+            None => Ok(Config::default()),
+        };
+    };
+
+    let config = toml::from_str(&content).map_err(|e| parse_error(&content, e))?;
+
+    match PROFILE.get() {
+        Some(name) => with_profile(&content, name),
+        None => Ok(config),
+    }
+}
+
+// Reads the config again with the profile's table merged over the top level.
+fn with_profile(content: &str, name: &str) -> Result<Config, Error> {
+    let mut table = toml::from_str::<toml::Table>(content).map_err(|e| parse_error(content, e))?;
+    let profile = table
+        .get("profiles")
+        .and_then(|profiles| profiles.get(name))
+        .and_then(toml::Value::as_table)
+        .cloned()
+        .ok_or_else(|| Error::new(format!("A profile '{name}' is not in the config")))?;
+
+    merge(&mut table, profile);
+
+    toml::Value::Table(table).try_into().map_err(|e| {
+        Error::with_cause(
+            format!("A profile '{name}' of the config parsing failed"),
+            e.message(),
+        )
+    })
+}
+
+// Tables are merged key by key; anything else in `over` replaces what it is laid over.
+fn merge(under: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (under.get_mut(&key), value) {
+            (Some(toml::Value::Table(under)), toml::Value::Table(over)) => merge(under, over),
+            (_, value) => {
+                under.insert(key, value);
+            }
+        }
+    }
+}
+
+fn parse_error(content: &str, e: toml::de::Error) -> Error {
+    let line = e
+        .span()
+        .map(|span| line_of(content, span.start))
+        .unwrap_or(1);
+
+    Error::with_cause(
+        format!(
+            "A config '{}' parsing failed at line {line}",
+            CONFIG_PATH.to_string_lossy()
+        ),
+        e.message(),
+    )
+}
+
+fn line_of(content: &str, offset: usize) -> usize {
+    content[..offset].matches('\n').count() + 1
+}
+
+// Something `load` lets through that is most likely a mistake.
+pub struct Problem {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+// Looks for settings `load` ignores, like misspelled keys, and for ones that only fail once used.
+pub fn check() -> Result<Vec<Problem>, Error> {
+    let Ok(content) = fs::read_to_string(&*CONFIG_PATH) else {
+        return Ok(vec![]);
+    };
+    let mut unknown = vec![];
+    let config: Config = toml::de::Deserializer::parse(&content)
+        .and_then(|de| serde_ignored::deserialize(de, |path| unknown.push(segments(&path))))
+        .map_err(|e| parse_error(&content, e))?;
+
+    let mut broken = vec![];
+
+    for (name, profile) in &config.profiles {
+        let res: Result<Config, _> =
+            serde_ignored::deserialize(toml::Value::Table(profile.clone()), |path| {
+                let mut path = segments(&path);
+
+                path.splice(0..0, [String::from("profiles"), name.clone()]);
+                unknown.push(path);
+            });
+
+        if let Err(e) = res {
+            broken.push((name.clone(), e.message().to_string()));
+        }
+    }
+
+    // The rest is checked as the selected profile sees it.
+    let config = match PROFILE.get() {
+        Some(name) => with_profile(&content, name)?,
+        None => config,
+    };
+    let document = DeTable::parse(&content).map_err(|e| parse_error(&content, e))?;
+    let line = |path: &[&str]| {
+        let in_profile = PROFILE
+            .get()
+            .and_then(|name| key_span(document.get_ref(), &[&["profiles", name], path].concat()));
+
+        in_profile
+            .or_else(|| key_span(document.get_ref(), path))
+            .map(|span| line_of(&content, span.start))
+    };
+    let mut problems = vec![];
+
+    for (name, message) in broken {
+        problems.push(Problem {
+            line: line(&["profiles", &name]),
+            message: format!("The profile '{name}' is invalid: {message}"),
+        });
+    }
+
+    for path in &unknown {
+        let path = path.iter().map(String::as_str).collect::<Vec<_>>();
+
+        problems.push(Problem {
+            line: line(&path),
+            message: format!("'{}' is not a setting", path.join(".")),
+        });
+    }
+
+    for keys in config.keys.keys() {
+        if Keymap::new(&canonical_keymap(keys)).is_err() {
+            problems.push(Problem {
+                line: line(&["keys", keys]),
+                message: format!("The keymap '{keys}' is invalid"),
+            });
+        }
+    }
+
+    let periods = [
+        ("day", &config.periodic.day),
+        ("week", &config.periodic.week),
+        ("month", &config.periodic.month),
+    ];
+    let files = periods
+        .iter()
+        .map(|(name, period)| (vec!["periodic", name, "template"], &period.template))
+        .chain([
+            (vec!["export", "pandoc"], &config.export.pandoc),
+            (vec!["export", "template"], &config.export.template),
+            (vec!["export", "css"], &config.export.css),
+            (
+                vec!["export", "reference_doc"],
+                &config.export.reference_doc,
+            ),
+            (vec!["sync", "ssh", "identity"], &config.sync.ssh.identity),
+            (
+                vec!["sync", "encryption", "identity"],
+                &config.sync.encryption.identity,
+            ),
+        ]);
+
+    for (path, file) in files {
+        if let Some(file) = file
+            && !file.exists()
+        {
+            problems.push(Problem {
+                line: line(&path),
+                message: format!(
+                    "A file '{}' of '{}' does not exist",
+                    file.to_string_lossy(),
+                    path.join(".")
+                ),
+            });
+        }
+    }
+
+    for (idx, source) in config.sources.iter().enumerate() {
+        if !source.dir().is_dir() {
+            problems.push(Problem {
+                line: line(&["sources", &idx.to_string()]),
+                message: format!(
+                    "A source folder '{}' does not exist",
+                    source.path.to_string_lossy()
+                ),
+            });
+        }
+    }
+
+    if let Startup::Search(name) = &config.startup
+        && !config.searches.contains_key(name)
+    {
+        problems.push(Problem {
+            line: line(&["startup"]),
+            message: format!("No search is named '{name}' in 'searches'"),
+        });
+    }
+
+    problems.sort_by_key(|problem| problem.line);
+
+    Ok(problems)
+}
+
+fn segments(path: &serde_ignored::Path) -> Vec<String> {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => vec![],
+        Path::Seq { parent, index } => {
+            let mut segments = segments(parent);
+
+            segments.push(index.to_string());
+            segments
+        }
+        Path::Map { parent, key } => {
+            let mut segments = segments(parent);
+
+            segments.push(key.clone());
+            segments
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => segments(parent),
+    }
+}
+
+// Where the last key of `path` is written, following tables by key and arrays by index.
+fn key_span(table: &DeTable, path: &[&str]) -> Option<Range<usize>> {
+    let (first, rest) = path.split_first()?;
+    let (key, value) = table.iter().find(|(key, _)| key.get_ref() == first)?;
+
+    if rest.is_empty() {
+        return Some(key.span());
+    }
+
+    value_span(value.get_ref(), rest)
+}
+
+fn value_span(value: &DeValue, path: &[&str]) -> Option<Range<usize>> {
+    match value {
+        DeValue::Table(table) => key_span(table, path),
+        DeValue::Array(array) => {
+            let (first, rest) = path.split_first()?;
+            let item = array.get(first.parse::<usize>().ok()?)?;
+
+            match rest.is_empty() {
+                true => Some(item.span()),
+                false => value_span(item.get_ref(), rest),
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,543 @@
+use crate::fit_width;
+use crate::fuzzy;
+use crate::prompt::LineEditor;
+use chrono::{Datelike, Days, Months, NaiveDate};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+pub enum Picked<T> {
+    Pending,
+    Cancelled,
+    Chosen(T),
+    // One of the picker's action keys was pressed on a row.
+    Action(char, T),
+}
+
+// A full-screen list that hands back the value of the chosen row.
+pub struct Picker<T> {
+    pub title: String,
+    items: Vec<(String, T)>,
+    cursor: usize,
+    actions: Vec<char>,
+}
+
+impl<T> Picker<T> {
+    pub fn new<S: Into<String>>(title: S, items: Vec<(String, T)>) -> Self {
+        Self {
+            title: title.into(),
+            items,
+            cursor: 0,
+            actions: vec![],
+        }
+    }
+
+    // Keys that hand back the row under the cursor along with the key, besides Enter.
+    pub fn with_actions(mut self, keys: &[char]) -> Self {
+        self.actions = keys.to_vec();
+        self
+    }
+
+    pub fn handle(&mut self, key: KeyEvent) -> Picked<T> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Picked::Cancelled,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.cursor = (self.cursor + 1).min(self.items.len().saturating_sub(1))
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Home | KeyCode::Char('g') => self.cursor = 0,
+            KeyCode::End | KeyCode::Char('G') => self.cursor = self.items.len().saturating_sub(1),
+            KeyCode::Enter if self.cursor < self.items.len() => {
+                return Picked::Chosen(self.items.swap_remove(self.cursor).1);
+            }
+            KeyCode::Char(c) if self.actions.contains(&c) && self.cursor < self.items.len() => {
+                return Picked::Action(c, self.items.swap_remove(self.cursor).1);
+            }
+            _ => {}
+        }
+
+        Picked::Pending
+    }
+
+    pub fn lines(&self, height: usize) -> Vec<String> {
+        let scroll = self.cursor.saturating_sub(height.saturating_sub(2));
+        let mut lines = vec![self.title.clone()];
+
+        lines.extend(
+            self.items
+                .iter()
+                .enumerate()
+                .skip(scroll)
+                .map(|(i, (label, _))| {
+                    let marker = if i == self.cursor { ">" } else { " " };
+
+                    format!("{marker} {label}")
+                }),
+        );
+
+        lines
+    }
+}
+
+// Read-only text shown over the list. A fixed overlay (e.g. a QR code) must be shown whole and
+// closes on any key; otherwise it scrolls and closes on q, Esc or Enter.
+pub struct Overlay {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub fixed: bool,
+    scroll: usize,
+}
+
+impl Overlay {
+    pub fn fixed<S: Into<String>>(title: S, lines: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+            fixed: true,
+            scroll: 0,
+        }
+    }
+
+    pub fn text<S: Into<String>>(title: S, text: &str) -> Self {
+        Self {
+            title: title.into(),
+            lines: text
+                .lines()
+                .map(|line| line.replace('\t', "    "))
+                .collect(),
+            fixed: false,
+            scroll: 0,
+        }
+    }
+
+    // Returns false once the overlay should close.
+    pub fn handle(&mut self, key: KeyEvent) -> bool {
+        if self.fixed {
+            return false;
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll += 10,
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll = 0,
+            KeyCode::End | KeyCode::Char('G') => self.scroll = self.lines.len(),
+            _ => {}
+        }
+
+        self.scroll = self.scroll.min(self.lines.len().saturating_sub(1));
+
+        true
+    }
+
+    pub fn scroll_by(&mut self, lines: i32) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(lines as isize)
+            .min(self.lines.len().saturating_sub(1));
+    }
+
+    pub fn visible(&self, height: usize) -> Vec<String> {
+        let mut lines = vec![self.title.clone()];
+
+        lines.extend(
+            self.lines
+                .iter()
+                .skip(self.scroll)
+                .take(height.saturating_sub(1))
+                .cloned(),
+        );
+
+        lines
+    }
+}
+
+// A month grid; days that have memos are starred and the memos of the day under the cursor are
+// listed below it.
+pub struct Calendar {
+    cursor: NaiveDate,
+    days: BTreeMap<NaiveDate, Vec<String>>,
+}
+
+impl Calendar {
+    pub fn new(today: NaiveDate, days: BTreeMap<NaiveDate, Vec<String>>) -> Self {
+        Self {
+            cursor: today,
+            days,
+        }
+    }
+
+    pub fn handle(&mut self, key: KeyEvent) -> Picked<NaiveDate> {
+        let cursor = match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return Picked::Cancelled,
+            KeyCode::Enter => return Picked::Chosen(self.cursor),
+            KeyCode::Left | KeyCode::Char('h') => self.cursor.checked_sub_days(Days::new(1)),
+            KeyCode::Right | KeyCode::Char('l') => self.cursor.checked_add_days(Days::new(1)),
+            KeyCode::Up | KeyCode::Char('k') => self.cursor.checked_sub_days(Days::new(7)),
+            KeyCode::Down | KeyCode::Char('j') => self.cursor.checked_add_days(Days::new(7)),
+            KeyCode::PageUp | KeyCode::Char('<') => self.cursor.checked_sub_months(Months::new(1)),
+            KeyCode::PageDown | KeyCode::Char('>') => {
+                self.cursor.checked_add_months(Months::new(1))
+            }
+            _ => None,
+        };
+
+        if let Some(cursor) = cursor {
+            self.cursor = cursor;
+        }
+
+        Picked::Pending
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let first = self.cursor.with_day(1).unwrap_or(self.cursor);
+        let offset = first.weekday().num_days_from_monday() as usize;
+        let mut lines = vec![
+            format!(
+                "{}  (h/j/k/l: move, </>: month, Enter: open or create the day's note)",
+                first.format("%B %Y")
+            ),
+            String::new(),
+            String::from(" Mo  Tu  We  Th  Fr  Sa  Su"),
+        ];
+        let mut row = "    ".repeat(offset);
+        let mut day = first;
+
+        while day.month() == first.month() {
+            let (open, close) = if day == self.cursor {
+                ('[', ']')
+            } else if self.days.contains_key(&day) {
+                (' ', '*')
+            } else {
+                (' ', ' ')
+            };
+
+            row.push_str(&format!("{open}{:>2}{close}", day.day()));
+
+            if day.weekday().num_days_from_monday() == 6 {
+                lines.push(std::mem::take(&mut row));
+            }
+
+            match day.succ_opt() {
+                Some(next) => day = next,
+                None => break,
+            }
+        }
+
+        if !row.is_empty() {
+            lines.push(row);
+        }
+
+        lines.push(String::new());
+        lines.push(self.cursor.format("%A, %Y-%m-%d").to_string());
+        lines.extend(
+            self.days
+                .get(&self.cursor)
+                .into_iter()
+                .flatten()
+                .map(|name| format!("  {name}")),
+        );
+
+        lines
+    }
+}
+
+pub enum BoardEvent {
+    Pending,
+    Closed,
+    Open(PathBuf),
+    // A card moved to the named column; the board already shows it there.
+    Moved(PathBuf, String),
+}
+
+// Memos as cards in side-by-side columns.
+pub struct Board {
+    columns: Vec<(String, Vec<(String, PathBuf)>)>,
+    column: usize,
+    row: usize,
+}
+
+impl Board {
+    pub fn new(columns: Vec<(String, Vec<(String, PathBuf)>)>) -> Self {
+        Self {
+            columns,
+            column: 0,
+            row: 0,
+        }
+    }
+
+    fn cards(&self) -> &[(String, PathBuf)] {
+        self.columns
+            .get(self.column)
+            .map(|(_, cards)| cards.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn handle(&mut self, key: KeyEvent) -> BoardEvent {
+        let last = self.columns.len().saturating_sub(1);
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return BoardEvent::Closed,
+            KeyCode::Enter => {
+                if let Some((_, path)) = self.cards().get(self.row) {
+                    return BoardEvent::Open(path.clone());
+                }
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.column = self.column.saturating_sub(1),
+            KeyCode::Right | KeyCode::Char('l') => self.column = (self.column + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.row = self.row.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.row += 1,
+            KeyCode::Char('H') if self.column > 0 => return self.shift(self.column - 1),
+            KeyCode::Char('L') if self.column < last => return self.shift(self.column + 1),
+            _ => {}
+        }
+
+        self.row = self.row.min(self.cards().len().saturating_sub(1));
+
+        BoardEvent::Pending
+    }
+
+    // Moves the selected card and follows it.
+    fn shift(&mut self, to: usize) -> BoardEvent {
+        if self.row >= self.cards().len() {
+            return BoardEvent::Pending;
+        }
+
+        let card = self.columns[self.column].1.remove(self.row);
+        let path = card.1.clone();
+
+        self.columns[to].1.push(card);
+        self.columns[to].1.sort();
+        self.row = self.columns[to]
+            .1
+            .iter()
+            .position(|(_, p)| *p == path)
+            .unwrap_or_default();
+        self.column = to;
+
+        BoardEvent::Moved(path, self.columns[to].0.clone())
+    }
+
+    pub fn lines(&self, width: usize, height: usize) -> Vec<String> {
+        let cell = width / self.columns.len().max(1);
+        let row = |cells: Vec<String>| {
+            cells
+                .iter()
+                .map(|text| fit_width(text, cell))
+                .collect::<String>()
+        };
+
+        let mut lines = vec![
+            String::from("Board  (h/l, j/k: move, H/L: move the card, Enter: select, q: close)"),
+            row(self
+                .columns
+                .iter()
+                .map(|(name, cards)| format!("{name} ({})", cards.len()))
+                .collect()),
+            row(vec!["-".repeat(cell.saturating_sub(1)); self.columns.len()]),
+        ];
+
+        let rows = self.columns.iter().map(|(_, cards)| cards.len()).max();
+        let scroll = self.row.saturating_sub(height.saturating_sub(4));
+
+        for i in (0..rows.unwrap_or_default()).skip(scroll) {
+            lines.push(row(self
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(c, (_, cards))| match cards.get(i) {
+                    Some((name, _)) if c == self.column && i == self.row => format!("> {name}"),
+                    Some((name, _)) => format!("  {name}"),
+                    None => String::new(),
+                })
+                .collect()));
+        }
+
+        lines
+    }
+}
+
+// Cells a card needs at least; the grid has as many columns as fit, from 2 to 4.
+const CARD_WIDTH: usize = 30;
+// Lines of the memo on a card, under its name.
+const CARD_LINES: usize = 4;
+
+pub struct Card {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+pub fn grid_columns(width: usize) -> usize {
+    (width / CARD_WIDTH).clamp(2, 4)
+}
+
+// `count` cards made by `card` on demand, in rows that scroll to keep the cursor card in sight.
+// The cursor card has a double border, or `=` and `#` in basic mode.
+pub fn grid<F: Fn(usize) -> Card>(
+    count: usize,
+    cursor: usize,
+    card: F,
+    (width, height): (usize, usize),
+    basic: bool,
+) -> Vec<String> {
+    let columns = grid_columns(width);
+    let card_width = ((width + 1) / columns).saturating_sub(1).max(2);
+    let rows = (height / (CARD_LINES + 3)).max(1);
+    let scroll = (cursor / columns).saturating_sub(rows - 1);
+    let mut lines = vec![];
+
+    for row in scroll..scroll + rows {
+        let boxes = (row * columns..(row + 1) * columns)
+            .filter(|&idx| idx < count)
+            .map(|idx| boxed(&card(idx), card_width, idx == cursor, basic))
+            .collect::<Vec<_>>();
+
+        if boxes.is_empty() {
+            break;
+        }
+
+        for i in 0..CARD_LINES + 3 {
+            lines.push(
+                boxes
+                    .iter()
+                    .map(|lines| lines[i].as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        }
+    }
+
+    lines
+}
+
+fn boxed(card: &Card, width: usize, selected: bool, basic: bool) -> Vec<String> {
+    let [h, v, tl, tr, bl, br] = match (basic, selected) {
+        (false, false) => ["─", "│", "┌", "┐", "└", "┘"],
+        (false, true) => ["═", "║", "╔", "╗", "╚", "╝"],
+        (true, false) => ["-", "|", "+", "+", "+", "+"],
+        (true, true) => ["=", "#", "#", "#", "#", "#"],
+    };
+    let inner = width - 2;
+    let mut lines = vec![format!("{tl}{}{tr}", h.repeat(inner))];
+
+    lines.push(format!(
+        "{v}{}{v}",
+        fit_width(&format!(" {}", card.title), inner)
+    ));
+
+    for i in 0..CARD_LINES {
+        let text = card.lines.get(i).map(String::as_str).unwrap_or_default();
+
+        lines.push(format!("{v}{}{v}", fit_width(&format!(" {text}"), inner)));
+    }
+
+    lines.push(format!("{bl}{}{br}", h.repeat(inner)));
+
+    lines
+}
+
+// Named actions with their keys, narrowed down by a fuzzy query as it is typed.
+pub struct Palette<T> {
+    pub editor: LineEditor,
+    items: Vec<(String, String, T)>,
+    // Indices into items, best match first.
+    matches: Vec<usize>,
+    cursor: usize,
+}
+
+impl<T> Palette<T> {
+    pub fn new(items: Vec<(String, String, T)>) -> Self {
+        Self {
+            editor: LineEditor::new(vec![]),
+            matches: (0..items.len()).collect(),
+            items,
+            cursor: 0,
+        }
+    }
+
+    pub fn handle(&mut self, key: KeyEvent) -> Picked<T> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        match key.code {
+            KeyCode::Esc => return Picked::Cancelled,
+            KeyCode::Enter => {
+                if let Some(&idx) = self.matches.get(self.cursor) {
+                    return Picked::Chosen(self.items.swap_remove(idx).2);
+                }
+            }
+            KeyCode::Down | KeyCode::Tab => self.step(1),
+            KeyCode::Char('n') if ctrl => self.step(1),
+            KeyCode::Up | KeyCode::BackTab => self.step(-1),
+            KeyCode::Char('p') if ctrl => self.step(-1),
+            _ => {
+                if self.editor.handle(key) {
+                    self.narrow();
+                }
+            }
+        }
+
+        Picked::Pending
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        self.editor.paste(text);
+        self.narrow();
+    }
+
+    fn step(&mut self, by: isize) {
+        self.cursor = self
+            .cursor
+            .saturating_add_signed(by)
+            .min(self.matches.len().saturating_sub(1));
+    }
+
+    fn narrow(&mut self) {
+        let query = self.editor.text();
+        let mut scored = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, (name, _, _))| Some((fuzzy::score(query, name)?, idx)))
+            .collect::<Vec<_>>();
+
+        // Ties keep the order the items came in.
+        scored.sort_by_key(|&(score, idx)| (std::cmp::Reverse(score), idx));
+
+        self.matches = scored.into_iter().map(|(_, idx)| idx).collect();
+        self.cursor = 0;
+    }
+
+    pub fn lines(&self, width: usize, height: usize) -> Vec<String> {
+        let keys_width = self
+            .items
+            .iter()
+            .map(|(_, keys, _)| keys.len())
+            .max()
+            .unwrap_or(0);
+        let name_width = width.saturating_sub(keys_width + 4);
+        let scroll = self.cursor.saturating_sub(height.saturating_sub(2));
+        let mut lines = vec![format!("> {}", self.editor.text())];
+
+        lines.extend(
+            self.matches
+                .iter()
+                .enumerate()
+                .skip(scroll)
+                .map(|(i, &idx)| {
+                    let (name, keys, _) = &self.items[idx];
+                    let marker = if i == self.cursor { ">" } else { " " };
+
+                    format!("{marker} {}{keys}", fit_width(name, name_width))
+                }),
+        );
+
+        lines
+    }
+
+    // Terminal column of the cursor within the query line.
+    pub fn cursor_column(&self) -> usize {
+        2 + self.editor.cursor_width()
+    }
+}
@@ -0,0 +1,275 @@
+use crate::config::ExportConfig;
+use crate::meta::{self, WikiLink};
+use crate::publish::href;
+use crate::task::Progress;
+use crate::{Error, MEMO_LIST_PATH, TempFile, memo_name_of, run_detached, run_external, storage};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Pdf,
+    Docx,
+    Markdown,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, Error> {
+        match name {
+            "pdf" => Ok(Format::Pdf),
+            "docx" => Ok(Format::Docx),
+            "md" | "markdown" => Ok(Format::Markdown),
+            _ => Err(Error::with_cause(
+                format!("A format '{name}' is not supported"),
+                "expected pdf, docx or md",
+            )),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Pdf => "pdf",
+            Format::Docx => "docx",
+            Format::Markdown => "md",
+        }
+    }
+}
+
+fn pandoc_program(config: &ExportConfig) -> Result<PathBuf, Error> {
+    let program = config
+        .pandoc
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("pandoc"));
+
+    match Command::new(&program)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+    {
+        Ok(_) => Ok(program),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(Error::with_cause(
+            format!("A pandoc '{}' is not found", program.to_string_lossy()),
+            "install it from https://pandoc.org/installing.html or set export.pandoc in the config",
+        )),
+        Err(e) => Err(Error::with_cause(
+            format!("A pandoc '{}' executing failed", program.to_string_lossy()),
+            e.kind(),
+        )),
+    }
+}
+
+// Converts a memo with pandoc. Front matter is turned into pandoc metadata and wiki-links into
+// plain text.
+// Pandoc runs in the foreground for the CLI, and with a `progress` detached from the terminal for
+// a TUI task, which it cannot draw over and which can cancel it.
+pub fn export(
+    path: &Path,
+    format: Format,
+    out: &Path,
+    config: &ExportConfig,
+    progress: Option<&Progress>,
+) -> Result<(), Error> {
+    let content = read(path)?;
+
+    if let Format::Markdown = format {
+        return write_markdown(&content, &HashMap::new(), out);
+    }
+
+    let pandoc = pandoc_program(config)?;
+    let name = memo_name_of(path);
+
+    let (front, body) = meta::split(&content);
+    let body = meta::replace_wiki_links(body, |link| link.label.to_string());
+
+    let input = TempFile::write(format!("export-{name}.md"), body)?;
+    let mut cmd = Command::new(pandoc);
+
+    cmd.arg(input.path())
+        .args(["--from", "markdown", "--standalone", "--output"])
+        .arg(out)
+        .arg("--resource-path")
+        .arg(&*MEMO_LIST_PATH)
+        .arg("--metadata")
+        .arg(format!("title={}", front.title().unwrap_or(&name)));
+
+    // A field given more than once becomes a list in pandoc, as `tags` and `aliases` are.
+    for (key, _) in front
+        .entries()
+        .into_iter()
+        .filter(|(key, _)| *key != "title")
+    {
+        for value in front.list(key) {
+            cmd.arg("--metadata").arg(format!("{key}={value}"));
+        }
+    }
+
+    match format {
+        Format::Pdf => {
+            if let Some(engine) = &config.pdf_engine {
+                cmd.arg("--pdf-engine").arg(engine);
+            }
+
+            if let Some(template) = &config.template {
+                cmd.arg("--template").arg(template);
+            }
+
+            if let Some(css) = &config.css {
+                cmd.arg("--css").arg(css);
+            }
+        }
+        Format::Docx => {
+            if let Some(reference) = &config.reference_doc {
+                cmd.arg("--reference-doc").arg(reference);
+            }
+        }
+        Format::Markdown => {}
+    }
+
+    match progress {
+        Some(progress) => run_detached(&mut cmd, progress),
+        None => run_external(&mut cmd),
+    }
+}
+
+// Exports the memos at `paths` into the directory `out`, one file each, stopping at the next one
+// once `progress` is cancelled. Without a `progress`, as from the CLI, pandoc runs in the
+// foreground.
+pub fn export_all(
+    paths: &[PathBuf],
+    format: Format,
+    out: &Path,
+    config: &ExportConfig,
+    progress: Option<&Progress>,
+) -> Result<(), Error> {
+    let counted = progress.cloned().unwrap_or_default();
+
+    if let Format::Markdown = format {
+        return export_markdown(paths, out, &counted);
+    }
+
+    fs::create_dir_all(out).map_err(|e| {
+        Error::with_cause(
+            format!("A directory '{}' creating failed", out.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    counted.start(paths.len());
+
+    for path in paths {
+        counted.step()?;
+
+        let file = format!("{}.{}", memo_name_of(path), format.extension());
+
+        export(path, format, &out.join(file), config, progress)?;
+    }
+
+    Ok(())
+}
+
+// Copies the memos at `paths` into the directory `out` as they are, except that wiki links among
+// them become relative links and the ones leading elsewhere plain text.
+fn export_markdown(paths: &[PathBuf], out: &Path, progress: &Progress) -> Result<(), Error> {
+    let memos = paths
+        .iter()
+        .map(|path| Ok((memo_name_of(path), read(path)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // Link targets to file names, matching names case-insensitively, IDs and aliases as well.
+    let mut by_name = memos
+        .iter()
+        .map(|(name, _)| (name.to_lowercase(), format!("{name}.md")))
+        .collect::<HashMap<_, _>>();
+
+    for (name, content) in &memos {
+        let (front, _) = meta::split(content);
+
+        if let Some(id) = front.id() {
+            by_name.insert(id.to_lowercase(), format!("{name}.md"));
+        }
+
+        for alias in front.aliases() {
+            by_name
+                .entry(alias.to_lowercase())
+                .or_insert_with(|| format!("{name}.md"));
+        }
+    }
+
+    fs::create_dir_all(out).map_err(|e| {
+        Error::with_cause(
+            format!("A directory '{}' creating failed", out.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    progress.start(memos.len());
+
+    for (name, content) in &memos {
+        progress.step()?;
+
+        write_markdown(content, &by_name, &out.join(format!("{name}.md")))?;
+    }
+
+    Ok(())
+}
+
+fn write_markdown(
+    content: &str,
+    by_name: &HashMap<String, String>,
+    out: &Path,
+) -> Result<(), Error> {
+    let (_, body) = meta::split(content);
+    let front = &content[..content.len() - body.len()];
+    let body = meta::replace_wiki_links(body, |link| markdown_link(&link, by_name));
+
+    fs::write(out, format!("{front}{body}")).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' writing failed", out.to_string_lossy()),
+            e.kind(),
+        )
+    })
+}
+
+fn markdown_link(link: &WikiLink, by_name: &HashMap<String, String>) -> String {
+    let label = link.label.replace(['[', ']'], "");
+    let (to, heading) = link.target.split_once('#').unwrap_or((link.target, ""));
+    let anchor = match heading.trim() {
+        "" => String::new(),
+        heading => format!("#{}", anchor(heading)),
+    };
+
+    match to.trim() {
+        "" if !anchor.is_empty() => format!("[{label}]({anchor})"),
+        to => match by_name.get(&to.to_lowercase()) {
+            Some(file) => format!("[{label}]({}{anchor})", href(file)),
+            None => label,
+        },
+    }
+}
+
+// The id a heading gets on GitHub and most other renderers: lowercased, spaces turned into
+// hyphens and other punctuation dropped.
+fn anchor(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    storage::read(path).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })
+}
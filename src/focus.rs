@@ -0,0 +1,80 @@
+use crate::{APP_DATA_PATH, Error, private_file_options};
+use chrono::{DateTime, Local};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+// Finished sessions, one `start<TAB>minutes<TAB>memo` per line with the start in RFC 3339.
+static LOG_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("focus_log"));
+
+// A timed stretch of work on one memo.
+pub struct Session {
+    pub path: PathBuf,
+    pub name: String,
+    pub started: DateTime<Local>,
+    since: Instant,
+    length: Duration,
+}
+
+impl Session {
+    pub fn new(path: PathBuf, name: String, minutes: u64) -> Self {
+        Self {
+            path,
+            name,
+            started: Local::now(),
+            since: Instant::now(),
+            length: Duration::from_secs(minutes * 60),
+        }
+    }
+
+    pub fn minutes(&self) -> u64 {
+        self.length.as_secs() / 60
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.length.saturating_sub(self.since.elapsed())
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    // `mm:ss` left, rounded up so that 00:00 only shows once the session is over.
+    pub fn countdown(&self) -> String {
+        let left = self.remaining();
+        let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+
+    // The line a finished session leaves in its memo.
+    pub fn entry(&self) -> String {
+        format!(
+            "- {} {} min",
+            self.started.format("%Y-%m-%d %H:%M"),
+            self.minutes()
+        )
+    }
+}
+
+pub fn log(session: &Session) -> Result<(), Error> {
+    let line = format!(
+        "{}\t{}\t{}\n",
+        session.started.to_rfc3339(),
+        session.minutes(),
+        session.name
+    );
+
+    private_file_options()
+        .create(true)
+        .append(true)
+        .open(&*LOG_PATH)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' writing failed", LOG_PATH.to_string_lossy()),
+                e.kind(),
+            )
+        })
+}
@@ -0,0 +1,27 @@
+// Scores `text` for holding the characters of `query` in order, ignoring case; matches at the
+// start of words and runs of adjacent characters score higher. None when it does not hold them.
+pub fn score(query: &str, text: &str) -> Option<usize> {
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut from = 0;
+    let mut last = None;
+
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let at = from + text[from..].iter().position(|&t| t == c)?;
+
+        score += 1;
+
+        if at == 0 || !text[at - 1].is_alphanumeric() {
+            score += 4;
+        }
+
+        if last.is_some_and(|last| last + 1 == at) {
+            score += 2;
+        }
+
+        last = Some(at);
+        from = at + 1;
+    }
+
+    Some(score)
+}
@@ -0,0 +1,152 @@
+use crate::meta;
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use std::collections::BTreeSet;
+
+// Weeks of days in a habit's grid, the current one last.
+pub const WEEKS: usize = 12;
+
+const DONE_HEADING: &str = "## Done";
+
+// A memo with `habit: <name>` in its front matter, and optionally `schedule: daily`, `weekdays`
+// or a list of days such as `[mon, wed, fri]`. Each `- [x] <date>` line in it is a day done.
+pub struct Habit {
+    pub name: String,
+    days: Vec<Weekday>,
+    done: BTreeSet<NaiveDate>,
+}
+
+impl Habit {
+    pub fn parse(content: &str) -> Option<Self> {
+        let (front, body) = meta::split(content);
+        let name = front.get("habit").filter(|name| !name.is_empty())?;
+        let schedule = front.list("schedule");
+        let every = [
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ];
+        let days = match schedule.as_slice() {
+            [] | ["daily"] => every.to_vec(),
+            ["weekdays"] => every[..5].to_vec(),
+            days => days.iter().filter_map(|day| day.parse().ok()).collect(),
+        };
+        let done = body
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("- [x] "))
+            .filter_map(|date| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok())
+            .collect();
+
+        Some(Self {
+            name: name.to_string(),
+            days,
+            done,
+        })
+    }
+
+    pub fn is_due(&self, day: NaiveDate) -> bool {
+        self.days.contains(&day.weekday())
+    }
+
+    pub fn is_done(&self, day: NaiveDate) -> bool {
+        self.done.contains(&day)
+    }
+
+    // Due days done in a row up to today. Today only counts once it is done, as the day is not
+    // over before that.
+    pub fn streak(&self, today: NaiveDate) -> usize {
+        let Some(first) = self.first() else {
+            return 0;
+        };
+        let mut streak = 0;
+
+        for day in today.iter_days().rev().take_while(|&day| day >= first) {
+            match (self.is_due(day), self.is_done(day)) {
+                (_, true) => streak += 1,
+                (true, false) if day == today => {}
+                (true, false) => break,
+                (false, false) => {}
+            }
+        }
+
+        streak
+    }
+
+    pub fn best(&self, today: NaiveDate) -> usize {
+        let Some(first) = self.first() else {
+            return 0;
+        };
+        let (mut best, mut run) = (0, 0);
+
+        for day in first.iter_days().take_while(|&day| day <= today) {
+            match (self.is_due(day), self.is_done(day)) {
+                (_, true) => run += 1,
+                (true, false) if day == today => {}
+                (true, false) => run = 0,
+                (false, false) => {}
+            }
+
+            best = best.max(run);
+        }
+
+        best
+    }
+
+    // One row per weekday and one column per week: `■` done, `·` missed, `□` due today and
+    // blank for days off, days to come and days before the first one done. Basic mode has `#`, `.`
+    // and `o` instead.
+    pub fn grid(&self, today: NaiveDate, basic: bool) -> Vec<String> {
+        let [done, missed, pending] = match basic {
+            true => ["#", ".", "o"],
+            false => ["■", "·", "□"],
+        };
+        let monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+        let first = monday - Days::new(7 * (WEEKS as u64 - 1));
+
+        (0..7)
+            .map(|weekday| {
+                let cells = (0..WEEKS)
+                    .map(|week| {
+                        let day = first + Days::new((week * 7 + weekday) as u64);
+
+                        if self.is_done(day) {
+                            done
+                        } else if day > today || !self.is_due(day) {
+                            " "
+                        } else if day == today {
+                            pending
+                        } else if self.first().is_none_or(|first| day < first) {
+                            " "
+                        } else {
+                            missed
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let label = (first + Days::new(weekday as u64)).format("%a");
+
+                format!("{label} {}", cells.join(" "))
+            })
+            .collect()
+    }
+
+    fn first(&self) -> Option<NaiveDate> {
+        self.done.first().copied()
+    }
+}
+
+// Checks `day` off in the memo, or takes the check back when it is there already.
+pub fn toggle(content: &str, day: NaiveDate) -> String {
+    let line = format!("- [x] {}", day.format("%Y-%m-%d"));
+
+    match content.lines().any(|l| l.trim() == line) {
+        true => content
+            .lines()
+            .filter(|l| l.trim() != line)
+            .map(|l| format!("{l}\n"))
+            .collect(),
+        false => meta::append_under_heading(content, DONE_HEADING, &line),
+    }
+}
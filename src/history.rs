@@ -0,0 +1,476 @@
+use crate::{APP_DATA_PATH, Error, config, create_private_dir, private_file_options, sha256_hex};
+use crate::{MEMO_LIST_PATH, TRASH_PATH, meta, storage, stored_id};
+use chrono::{DateTime, Local, TimeDelta};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+// Versions of memos, content-addressed: `objects/<sha256>` holds one version's text and
+// `ids/<id>` the versions of the memo with that `id`, one `time<TAB>hash` per line with the time
+// in RFC 3339, which renames and moves leave where it is. A memo without an id is logged in
+// `logs/<path>` by its path in the list instead, and that log moves along to `trashed/<file>`.
+static SNAPSHOT_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("snapshots"));
+
+static ENABLED: LazyLock<bool> =
+    LazyLock::new(|| config::load().is_ok_and(|config| config.history.enabled));
+
+// A version is stored as a zstd delta, compressed with the version before it as the dictionary,
+// unless that makes this many deltas in a row; then it is stored whole, which bounds the work to
+// read one back.
+const CHAIN_LIMIT: u32 = 16;
+
+// Deltas shrink a lot up to about here, where compressing still keeps up with every save.
+const LEVEL: i32 = 9;
+
+pub struct Version {
+    pub time: DateTime<Local>,
+    pub hash: String,
+}
+
+// The first line of an object: `full <len>` or `delta <depth> <len> <base>`, with `len` the size
+// of the text and `depth` how many deltas lead back to a whole one.
+enum Header {
+    Full {
+        len: usize,
+    },
+    Delta {
+        depth: u32,
+        len: usize,
+        base: String,
+    },
+}
+
+impl Header {
+    fn parse(line: &str) -> Option<Self> {
+        let fields = line.split(' ').collect::<Vec<_>>();
+
+        match fields.as_slice() {
+            ["full", len] => Some(Header::Full {
+                len: len.parse().ok()?,
+            }),
+            ["delta", depth, len, base] => Some(Header::Delta {
+                depth: depth.parse().ok()?,
+                len: len.parse().ok()?,
+                base: base.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            Header::Full { len } => format!("full {len}"),
+            Header::Delta { depth, len, base } => format!("delta {depth} {len} {base}"),
+        }
+    }
+
+    fn depth(&self) -> u32 {
+        match self {
+            Header::Full { .. } => 0,
+            Header::Delta { depth, .. } => *depth,
+        }
+    }
+}
+
+// Adds `content` as the memo's latest version, unless it is that already. Does nothing unless
+// `history.enabled` is set.
+pub fn record<P: AsRef<Path>>(path: P, content: &str) -> Result<(), Error> {
+    if !*ENABLED {
+        return Ok(());
+    }
+
+    let Some(log) = log_path(path, meta::split(content).0.id()) else {
+        return Ok(());
+    };
+    let hash = sha256_hex(content);
+    let last = read_log(&log).pop();
+
+    if last.as_ref().is_some_and(|last| last.hash == hash) {
+        return Ok(());
+    }
+
+    store(&hash, content, last.map(|last| last.hash).as_deref())?;
+    create_dir(log.parent().unwrap_or(&log))?;
+
+    private_file_options()
+        .append(true)
+        .create(true)
+        .open(&log)
+        .and_then(|mut file| writeln!(file, "{}\t{hash}", Local::now().to_rfc3339()))
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' writing failed", log.to_string_lossy()),
+                e.kind(),
+            )
+        })
+}
+
+// Records the memo as it is on disk, in either storage form, before something replaces it.
+pub fn record_current<P: AsRef<Path>>(path: P) -> Result<(), Error> {
+    if !*ENABLED {
+        return Ok(());
+    }
+
+    let path = path.as_ref();
+    let Some(path) = [path.to_path_buf(), storage::other_path(path)]
+        .into_iter()
+        .find(|path| path.exists())
+    else {
+        return Ok(());
+    };
+    let content = storage::read(&path).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    record(&path, &content)
+}
+
+// The versions of the memo at `path`, the oldest first.
+pub fn versions<P: AsRef<Path>>(path: P) -> Vec<Version> {
+    let id = stored_id(&path);
+
+    log_path(path, id.as_deref()).map_or(vec![], |log| read_log(&log))
+}
+
+// Carries the versions logged by path over to where the memo was moved, in the list or to the
+// trash, or to its id if it has one by now. Elsewhere, such as in the archive, they stay under the
+// old path.
+pub fn moved<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<(), Error> {
+    let id = stored_id(&to);
+    let (Some(from), Some(to)) = (path_log(from), log_path(to, id.as_deref())) else {
+        return Ok(());
+    };
+
+    // Versions already logged by id are the memo's own; a log left by path is stale next to them.
+    if from == to || !from.exists() || id.is_some() && to.exists() {
+        return Ok(());
+    }
+
+    create_dir(to.parent().unwrap_or(&to))?;
+
+    fs::rename(&from, &to).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' moving failed", from.to_string_lossy()),
+            e.kind(),
+        )
+    })
+}
+
+// Drops the versions of the memo at `path`, with the `id` it had, along with the objects no other
+// memo's versions need, for a memo that is erased. Works whether or not `history.enabled` is
+// still set.
+pub fn forget<P: AsRef<Path>>(path: P, id: Option<&str>) -> Result<(), Error> {
+    let Some(log) = log_path(path, id) else {
+        return Ok(());
+    };
+
+    match fs::remove_file(&log) {
+        Ok(_) => prune(None, false).map(|_| ()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(Error::with_cause(
+            format!("A file '{}' removing failed", log.to_string_lossy()),
+            e.kind(),
+        )),
+    }
+}
+
+// Where the versions of the memo at `path` with the `id` it has are logged. Files that are neither
+// listed nor trashed memos have no history. A memo that got its id after versions were logged by
+// its path takes them along to the id.
+fn log_path<P: AsRef<Path>>(path: P, id: Option<&str>) -> Option<PathBuf> {
+    let by_path = path_log(path)?;
+    // Ids are written by hand at times; one that cannot be a file name is left out.
+    let Some(id) = id.filter(|id| id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')) else {
+        return Some(by_path);
+    };
+    let by_id = SNAPSHOT_PATH.join("ids").join(id.to_lowercase());
+
+    if !by_id.exists() && by_path.exists() {
+        let moved = create_dir(by_id.parent().unwrap_or(&by_id)).and_then(|_| {
+            fs::rename(&by_path, &by_id).map_err(|e| {
+                Error::with_cause(
+                    format!("A file '{}' moving failed", by_path.to_string_lossy()),
+                    e.kind(),
+                )
+            })
+        });
+
+        if let Err(e) = moved {
+            crate::log_err(&e);
+
+            return Some(by_path);
+        }
+    }
+
+    Some(by_id)
+}
+
+// Where the versions of the memo at `path` are logged by path, the same for either storage form.
+fn path_log<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
+    let plain = storage::plain_path(path);
+    let (dir, relative) = match plain.strip_prefix(&*MEMO_LIST_PATH) {
+        Ok(relative) => ("logs", relative),
+        Err(_) => ("trashed", plain.strip_prefix(&*TRASH_PATH).ok()?),
+    };
+
+    (!relative.as_os_str().is_empty()).then(|| SNAPSHOT_PATH.join(dir).join(relative))
+}
+
+fn read_log(log: &Path) -> Vec<Version> {
+    fs::read_to_string(log)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (time, hash) = line.split_once('\t')?;
+            let time = DateTime::parse_from_rfc3339(time).ok()?;
+
+            Some(Version {
+                time: time.with_timezone(&Local),
+                hash: hash.to_string(),
+            })
+        })
+        .collect()
+}
+
+// The text of a version.
+pub fn read(hash: &str) -> Result<String, Error> {
+    let path = object_path(hash);
+    let failed = |cause: String| {
+        Error::with_cause(
+            format!("A snapshot '{}' reading failed", path.to_string_lossy()),
+            cause,
+        )
+    };
+    let (header, data) = read_object(hash).map_err(|e| failed(e.to_string()))?;
+    let bytes = match header {
+        Header::Full { len } => zstd::bulk::decompress(&data, len),
+        Header::Delta { len, base, .. } => {
+            let base = read(&base)?;
+
+            zstd::bulk::Decompressor::with_dictionary(base.as_bytes())
+                .and_then(|mut decompressor| decompressor.decompress(&data, len))
+        }
+    }
+    .map_err(|e| failed(e.to_string()))?;
+
+    String::from_utf8(bytes).map_err(|e| failed(e.to_string()))
+}
+
+// Drops the versions older than `keep_days`, each memo's latest one aside, and the objects no
+// version needs any more. Hands back how many versions went and how many bytes that frees.
+pub fn gc(keep_days: u64, dry_run: bool) -> Result<(usize, u64), Error> {
+    // Beyond what a date can go back to, every version is kept.
+    let since = i64::try_from(keep_days)
+        .ok()
+        .and_then(TimeDelta::try_days)
+        .and_then(|days| Local::now().checked_sub_signed(days));
+
+    prune(since, dry_run)
+}
+
+// `gc` with the versions from before `since` dropped, or none of them without it.
+fn prune(since: Option<DateTime<Local>>, dry_run: bool) -> Result<(usize, u64), Error> {
+    let mut live = HashSet::new();
+    let mut dropped = 0;
+    let mut logs = vec![];
+
+    for dir in ["ids", "logs", "trashed"] {
+        list_logs(&SNAPSHOT_PATH.join(dir), &mut logs)?;
+    }
+
+    for log in logs {
+        let versions = read_log(&log);
+
+        if versions.is_empty() {
+            continue;
+        }
+
+        let last = versions.len() - 1;
+        let kept = versions
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, version)| {
+                *idx == last || since.is_none_or(|since| version.time >= since)
+            })
+            .map(|(_, version)| version)
+            .collect::<Vec<_>>();
+
+        dropped += last + 1 - kept.len();
+
+        if !dry_run && last + 1 != kept.len() {
+            let lines = kept
+                .iter()
+                .map(|version| format!("{}\t{}\n", version.time.to_rfc3339(), version.hash))
+                .collect::<String>();
+
+            fs::write(&log, lines).map_err(|e| {
+                Error::with_cause(
+                    format!("A file '{}' writing failed", log.to_string_lossy()),
+                    e.kind(),
+                )
+            })?;
+        }
+
+        live.extend(kept.into_iter().map(|version| version.hash));
+    }
+
+    // A kept delta would lose its base along with the dropped versions, so it is stored whole.
+    if !dry_run {
+        for hash in &live {
+            let base = match read_object(hash) {
+                Ok((Header::Delta { base, .. }, _)) => base,
+                _ => continue,
+            };
+
+            if !live.contains(&base) {
+                let content = read(hash)?;
+
+                write_object(
+                    hash,
+                    &Header::Full { len: content.len() },
+                    &compress(&content, None)?,
+                )?;
+            }
+        }
+    }
+
+    let mut freed = 0;
+
+    for object in list(&SNAPSHOT_PATH.join("objects"))? {
+        if live.contains(
+            object
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .as_ref(),
+        ) {
+            continue;
+        }
+
+        freed += fs::metadata(&object).map_or(0, |meta| meta.len());
+
+        if !dry_run {
+            fs::remove_file(&object).map_err(|e| {
+                Error::with_cause(
+                    format!("A snapshot '{}' removing failed", object.to_string_lossy()),
+                    e.kind(),
+                )
+            })?;
+        }
+    }
+
+    Ok((dropped, freed))
+}
+
+fn store(hash: &str, content: &str, base: Option<&str>) -> Result<(), Error> {
+    create_dir(&SNAPSHOT_PATH.join("objects"))?;
+
+    if object_path(hash).exists() {
+        return Ok(());
+    }
+
+    let len = content.len();
+    let base = base.and_then(|base| {
+        let depth = read_object(base).ok()?.0.depth() + 1;
+
+        // An unreadable base only costs storing this one whole.
+        (depth < CHAIN_LIMIT).then_some((base, depth, read(base).ok()?))
+    });
+    let (header, data) = match base {
+        Some((base, depth, text)) => (
+            Header::Delta {
+                depth,
+                len,
+                base: base.to_string(),
+            },
+            compress(content, Some(&text))?,
+        ),
+        None => (Header::Full { len }, compress(content, None)?),
+    };
+
+    write_object(hash, &header, &data)
+}
+
+fn compress(content: &str, dictionary: Option<&str>) -> Result<Vec<u8>, Error> {
+    match dictionary {
+        Some(dictionary) => zstd::bulk::Compressor::with_dictionary(LEVEL, dictionary.as_bytes())
+            .and_then(|mut compressor| compressor.compress(content.as_bytes())),
+        None => zstd::bulk::compress(content.as_bytes(), LEVEL),
+    }
+    .map_err(|e| Error::with_cause("A snapshot compressing failed", e))
+}
+
+fn object_path(hash: &str) -> PathBuf {
+    SNAPSHOT_PATH.join("objects").join(hash)
+}
+
+fn read_object(hash: &str) -> io::Result<(Header, Vec<u8>)> {
+    let bytes = fs::read(object_path(hash))?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "the header is broken");
+    let end = bytes.iter().position(|&b| b == b'\n').ok_or_else(invalid)?;
+    let header = Header::parse(&String::from_utf8_lossy(&bytes[..end])).ok_or_else(invalid)?;
+
+    Ok((header, bytes[end + 1..].to_vec()))
+}
+
+// Written aside and moved over, as a half-written object would stand for its hash for good.
+fn write_object(hash: &str, header: &Header, data: &[u8]) -> Result<(), Error> {
+    let path = object_path(hash);
+    let partial = path.with_extension("partial");
+
+    private_file_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&partial)
+        .and_then(|mut file| {
+            file.write_all(format!("{}\n", header.render()).as_bytes())?;
+            file.write_all(data)
+        })
+        .and_then(|_| fs::rename(&partial, &path))
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A snapshot '{}' writing failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })
+}
+
+fn create_dir(dir: &Path) -> Result<(), Error> {
+    create_private_dir(dir).map_err(|e| {
+        Error::with_cause(
+            format!("A directory '{}' creating failed", dir.to_string_lossy()),
+            e.kind(),
+        )
+    })
+}
+
+// The logs under `dir`, which nest as the memos do.
+fn list_logs(dir: &Path, logs: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for path in list(dir)? {
+        match path.is_dir() {
+            true => list_logs(&path, logs)?,
+            false => logs.push(path),
+        }
+    }
+
+    Ok(())
+}
+
+fn list(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Ok(entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(Error::with_cause(
+            format!("A directory '{}' reading failed", dir.to_string_lossy()),
+            e.kind(),
+        )),
+    }
+}
@@ -0,0 +1,70 @@
+use crate::config::HooksConfig;
+use crate::{Error, memo_name_of, meta, shell_command};
+use std::path::Path;
+use std::process::Stdio;
+use std::thread;
+
+#[derive(Clone, Copy)]
+pub enum Hook {
+    Create,
+    Edit,
+    Delete,
+    Sync,
+}
+
+impl Hook {
+    fn name(&self) -> &'static str {
+        match self {
+            Hook::Create => "create",
+            Hook::Edit => "edit",
+            Hook::Delete => "delete",
+            Hook::Sync => "sync",
+        }
+    }
+
+    fn command<'a>(&self, config: &'a HooksConfig) -> Option<&'a str> {
+        match self {
+            Hook::Create => config.on_create.as_deref(),
+            Hook::Edit => config.on_edit.as_deref(),
+            Hook::Delete => config.on_delete.as_deref(),
+            Hook::Sync => config.on_sync.as_deref(),
+        }
+    }
+}
+
+// Runs the configured hook in the background with the memo described in MEMOLEAK_* variables.
+// `content` is passed in rather than read so a delete hook still sees the memo's title and tags.
+pub fn fire(config: &HooksConfig, hook: Hook, path: &Path, content: &str) -> Result<(), Error> {
+    let Some(line) = hook.command(config) else {
+        return Ok(());
+    };
+
+    let (front, body) = meta::split(content);
+    let name = memo_name_of(path);
+
+    let mut cmd = shell_command(line);
+
+    cmd.env("MEMOLEAK_EVENT", hook.name())
+        .env("MEMOLEAK_PATH", path)
+        .env("MEMOLEAK_NAME", &name)
+        .env("MEMOLEAK_TITLE", front.title().unwrap_or(&name))
+        .env("MEMOLEAK_TAGS", front.tags().join(" "))
+        .env(
+            "MEMOLEAK_WORDS",
+            body.split_whitespace().count().to_string(),
+        )
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    let mut child = cmd.spawn().map_err(|e| {
+        Error::with_cause(
+            format!("The {} hook executing failed", hook.name()),
+            e.kind(),
+        )
+    })?;
+
+    thread::spawn(move || child.wait());
+
+    Ok(())
+}
@@ -0,0 +1,325 @@
+use crate::meta::{self, FrontMatter, Value};
+use crate::plan::{Action, Plan};
+use crate::{Error, MEMO_EXTENSION, memo_path, sanitize_memo_name};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, SecondsFormat, TimeZone};
+use serde_json::Value as Json;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// A note read from another app's export, before it is named and written as a memo.
+pub struct Note {
+    pub title: String,
+    pub body: String,
+    pub tags: Vec<String>,
+    pub created: Option<DateTime<Local>>,
+    pub modified: Option<DateTime<Local>>,
+}
+
+impl Note {
+    // Tags and the creation date move into front matter; the title stays in the body as written.
+    pub fn content(&self) -> String {
+        let mut front = FrontMatter::default();
+
+        if !self.tags.is_empty() {
+            front.set("tags", Value::List(self.tags.clone()));
+        }
+
+        if let Some(created) = self.created {
+            front.set(
+                "created",
+                Value::Text(created.to_rfc3339_opts(SecondsFormat::Secs, false)),
+            );
+        }
+
+        let newline = if self.body.ends_with('\n') { "" } else { "\n" };
+
+        format!("{}{}{newline}", front.render(), self.body)
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified.or(self.created).map(SystemTime::from)
+    }
+}
+
+pub fn read(format: &str, path: &Path) -> Result<Vec<Note>, Error> {
+    match format {
+        "simplenote" => simplenote(&parse(&read_export(path, "notes.json")?, path)?),
+        "jrnl" => jrnl(&parse(&read_export(path, ".json")?, path)?),
+        "standardnotes" => standard_notes(&parse(&read_export(path, ".json")?, path)?),
+        _ => Err(Error::with_cause(
+            format!("An import format '{format}' is not supported"),
+            "expected simplenote, jrnl or standardnotes",
+        )),
+    }
+}
+
+// Plans memos for the files at `sources`, or for the notes in the exports there when `format`
+// names the app they came from.
+pub fn plan<P: AsRef<Path>>(sources: &[P], format: Option<&str>) -> Result<Plan, Error> {
+    let mut plan = Plan::default();
+    let mut taken = HashSet::new();
+
+    for source in sources {
+        if let Some(format) = format {
+            for note in read(format, source.as_ref())? {
+                let title = match note.title.trim() {
+                    "" => note.body.lines().find(|line| !line.trim().is_empty()),
+                    title => Some(title),
+                };
+                let name = unique_memo_name(title.unwrap_or("imported"), &mut taken);
+
+                plan.push(Action::Create {
+                    path: memo_path(&name),
+                    content: meta::with_id(&note.content())?,
+                    modified: note.modified(),
+                });
+            }
+
+            continue;
+        }
+
+        for from in importable_files(source.as_ref())? {
+            let stem = from
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let name = unique_memo_name(&stem, &mut taken);
+
+            plan.push(Action::Copy {
+                from,
+                to: memo_path(&name),
+            });
+        }
+    }
+
+    Ok(plan)
+}
+
+fn importable_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = path
+        .read_dir()
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A directory '{}' reading failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == MEMO_EXTENSION || ext == "markdown" || ext == "txt")
+        })
+        .collect::<Vec<_>>();
+
+    files.sort();
+
+    Ok(files)
+}
+
+// A sanitized, numbered name that neither exists nor was handed out earlier in the same run.
+fn unique_memo_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let base = sanitize_memo_name(name);
+
+    let name = (1..)
+        .map(|n| {
+            if n == 1 {
+                base.clone()
+            } else {
+                format!("{base}-{n}")
+            }
+        })
+        .find(|name| !taken.contains(name) && !memo_path(name).exists())
+        .unwrap();
+
+    taken.insert(name.clone());
+
+    name
+}
+
+// Reads a JSON export either directly or as the first zip entry whose name ends with `suffix`.
+fn read_export(path: &Path, suffix: &str) -> Result<String, Error> {
+    let failed = |cause: String| {
+        Error::with_cause(
+            format!("An export '{}' reading failed", path.to_string_lossy()),
+            cause,
+        )
+    };
+
+    if path.extension().is_none_or(|ext| ext != "zip") {
+        return fs::read_to_string(path).map_err(|e| failed(e.kind().to_string()));
+    }
+
+    let file = fs::File::open(path).map_err(|e| failed(e.kind().to_string()))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| failed(e.to_string()))?;
+
+    let name = archive
+        .file_names()
+        .filter(|name| name.ends_with(suffix))
+        .min_by_key(|name| name.len())
+        .map(String::from)
+        .ok_or_else(|| failed(format!("no '*{suffix}' in the archive")))?;
+
+    let mut content = String::new();
+
+    archive
+        .by_name(&name)
+        .map_err(|e| failed(e.to_string()))?
+        .read_to_string(&mut content)
+        .map_err(|e| failed(e.kind().to_string()))?;
+
+    Ok(content)
+}
+
+fn parse(content: &str, path: &Path) -> Result<Json, Error> {
+    serde_json::from_str(content).map_err(|e| {
+        Error::with_cause(
+            format!("An export '{}' parsing failed", path.to_string_lossy()),
+            e,
+        )
+    })
+}
+
+fn text(json: &Json, key: &str) -> String {
+    json[key].as_str().unwrap_or_default().to_string()
+}
+
+fn strings(json: &Json) -> Vec<String> {
+    json.as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn timestamp(json: &Json) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(json.as_str()?)
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
+
+fn invalid(format: &str, cause: &str) -> Error {
+    Error::with_cause(format!("A {format} export is invalid"), cause)
+}
+
+// `source/notes.json` from the Simplenote export zip; the first line of a note is its title.
+fn simplenote(json: &Json) -> Result<Vec<Note>, Error> {
+    let notes = json["activeNotes"]
+        .as_array()
+        .ok_or_else(|| invalid("Simplenote", "no 'activeNotes' list"))?;
+
+    Ok(notes
+        .iter()
+        .map(|note| {
+            let body = text(note, "content");
+
+            Note {
+                title: body.lines().next().unwrap_or_default().to_string(),
+                tags: strings(&note["tags"]),
+                created: timestamp(&note["creationDate"]),
+                modified: timestamp(&note["lastModified"]),
+                body,
+            }
+        })
+        .collect())
+}
+
+// `jrnl --export json`; dates are local and tags keep jrnl's `@` prefix, which is dropped.
+fn jrnl(json: &Json) -> Result<Vec<Note>, Error> {
+    let entries = json["entries"]
+        .as_array()
+        .ok_or_else(|| invalid("jrnl", "no 'entries' list"))?;
+
+    Ok(entries
+        .iter()
+        .map(|entry| {
+            let title = text(entry, "title");
+            let body = text(entry, "body");
+            let date = NaiveDate::parse_from_str(&text(entry, "date"), "%Y-%m-%d").ok();
+            let time = NaiveTime::parse_from_str(&text(entry, "time"), "%H:%M").unwrap_or_default();
+            let created =
+                date.and_then(|date| Local.from_local_datetime(&date.and_time(time)).earliest());
+
+            Note {
+                body: format!("{title}\n\n{}", body.trim_start()),
+                tags: strings(&entry["tags"])
+                    .into_iter()
+                    .map(|tag| tag.trim_start_matches(['@', '#']).to_string())
+                    .collect(),
+                title,
+                created,
+                modified: created,
+            }
+        })
+        .collect())
+}
+
+// A decrypted Standard Notes backup; tags are separate items that reference their notes.
+fn standard_notes(json: &Json) -> Result<Vec<Note>, Error> {
+    let items = json["items"]
+        .as_array()
+        .ok_or_else(|| invalid("Standard Notes", "no 'items' list"))?;
+
+    let mut tags = HashMap::<&str, Vec<String>>::new();
+
+    for tag in items.iter().filter(|item| item["content_type"] == "Tag") {
+        let name = text(&tag["content"], "title");
+
+        for reference in tag["content"]["references"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            if let Some(uuid) = reference["uuid"].as_str() {
+                tags.entry(uuid).or_default().push(name.clone());
+            }
+        }
+    }
+
+    let mut notes = vec![];
+
+    for item in items.iter().filter(|item| item["content_type"] == "Note") {
+        let content = &item["content"];
+
+        if content.is_string() {
+            return Err(invalid(
+                "Standard Notes",
+                "the backup is encrypted, export a decrypted one instead",
+            ));
+        }
+
+        if content["trashed"].as_bool() == Some(true) {
+            continue;
+        }
+
+        let title = text(content, "title");
+        let body = text(content, "text");
+
+        notes.push(Note {
+            body: if title.is_empty() {
+                body
+            } else {
+                format!("# {title}\n\n{body}")
+            },
+            tags: tags
+                .remove(item["uuid"].as_str().unwrap_or_default())
+                .unwrap_or_default(),
+            title,
+            created: timestamp(&item["created_at"]),
+            modified: timestamp(&item["updated_at"]),
+        });
+    }
+
+    Ok(notes)
+}
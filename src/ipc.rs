@@ -0,0 +1,116 @@
+use crate::{APP_DATA_PATH, Error, Order, append_memo, capture_memo};
+use std::io::{Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
+use std::{fs, thread};
+
+static SOCKET_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("memoleak.sock"));
+
+// How long a client gets to send its request or take the reply, so one that stalls cannot hold up
+// the captures after it.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Removes the socket when the TUI goes away so the next `me add` writes to disk directly.
+pub struct Listener;
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&*SOCKET_PATH);
+    }
+}
+
+// Whether another TUI is listening.
+pub fn running() -> bool {
+    UnixStream::connect(&*SOCKET_PATH).is_ok()
+}
+
+// Accepts quick captures from `me add` while the TUI runs. The memo is written here and the UI
+// thread is only told to pick it up, so a capture never waits on rendering.
+pub fn listen(orders: Arc<RwLock<Vec<Order>>>) -> Result<Listener, Error> {
+    if running() {
+        return Err(Error::new("Another instance is already listening"));
+    }
+
+    let _ = fs::remove_file(&*SOCKET_PATH);
+
+    let listener = UnixListener::bind(&*SOCKET_PATH)
+        .map_err(|e| Error::with_cause("The capture socket binding failed", e.kind()))?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if stream
+                .set_read_timeout(Some(TIMEOUT))
+                .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+                .is_err()
+            {
+                continue;
+            }
+
+            let reply = match handle(&stream) {
+                Ok(path) => {
+                    orders.write().unwrap().push(Order::Insert(path.clone()));
+
+                    format!("ok {}", path.to_string_lossy())
+                }
+                Err(e) => format!("err {e}"),
+            };
+
+            let _ = (&stream).write_all(reply.as_bytes());
+        }
+    });
+
+    Ok(Listener)
+}
+
+// A request is the memo name on the first line (possibly empty) followed by the text. A name
+// prefixed with `>>`, which memo names cannot contain, appends to that memo instead.
+fn handle(mut stream: &UnixStream) -> Result<PathBuf, Error> {
+    let mut request = String::new();
+
+    stream
+        .read_to_string(&mut request)
+        .map_err(|e| Error::with_cause("A capture request reading failed", e.kind()))?;
+
+    let (name, text) = request.split_once('\n').unwrap_or((&request, ""));
+
+    match name.strip_prefix(">>") {
+        Some(name) => append_memo(name, text),
+        None => capture_memo(Some(name).filter(|name| !name.is_empty()), text),
+    }
+}
+
+// Hands a capture to the running TUI. Returns None when no instance is listening.
+pub fn send(name: Option<&str>, text: &str) -> Result<Option<PathBuf>, Error> {
+    request(name.unwrap_or_default(), text)
+}
+
+pub fn send_append(name: &str, text: &str) -> Result<Option<PathBuf>, Error> {
+    request(&format!(">>{name}"), text)
+}
+
+fn request(head: &str, text: &str) -> Result<Option<PathBuf>, Error> {
+    let Ok(mut stream) = UnixStream::connect(&*SOCKET_PATH) else {
+        return Ok(None);
+    };
+
+    let request = format!("{head}\n{text}");
+
+    stream
+        .write_all(request.as_bytes())
+        .and_then(|_| stream.shutdown(std::net::Shutdown::Write))
+        .map_err(|e| Error::with_cause("A capture request sending failed", e.kind()))?;
+
+    let mut reply = String::new();
+
+    stream
+        .read_to_string(&mut reply)
+        .map_err(|e| Error::with_cause("A capture reply reading failed", e.kind()))?;
+
+    match reply.split_once(' ') {
+        Some(("ok", path)) => Ok(Some(PathBuf::from(path))),
+        Some(("err", e)) => Err(Error::new(e)),
+        _ => Err(Error::new("The running instance sent a broken reply")),
+    }
+}
@@ -0,0 +1,104 @@
+use crate::schema::{self, Schema};
+use crate::{APP_DATA_PATH, Error, memo_name_of, private_file_options};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+pub static JOURNAL_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("journal.json"));
+
+static SCHEMA: Schema<Json> = Schema {
+    name: "journal",
+    steps: &[schema::unchanged],
+};
+
+// What the TUI holds that is not on disk yet: contents staged for the next autosave, and a log
+// entry being typed. The file only exists while there is something in it, so one found at startup
+// was left behind by a crash, a kill or a save that failed.
+#[derive(Serialize, Deserialize, Default, PartialEq, Clone)]
+#[serde(default)]
+pub struct Journal {
+    pub staged: Vec<(PathBuf, String)>,
+    pub log_entry: Option<String>,
+}
+
+impl Journal {
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty() && self.log_entry.is_none()
+    }
+
+    // One line per change, for asking about them.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = self
+            .staged
+            .iter()
+            .map(|(path, _)| format!("changes to '{}'", memo_name_of(path)))
+            .collect::<Vec<_>>();
+
+        // Only that there is one; the text may be read later in the prompt it comes back in.
+        if self.log_entry.is_some() {
+            lines.push(String::from("a log entry"));
+        }
+
+        lines
+    }
+}
+
+// The journal left from an earlier run, if there is one. One that cannot be read, e.g. as a newer
+// memoleak wrote it, is an error rather than nothing, so its changes are not written over.
+pub fn read() -> Result<Option<Journal>, Error> {
+    let content = match fs::read_to_string(&*JOURNAL_PATH) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(Error::with_cause(
+                format!(
+                    "A journal '{}' reading failed",
+                    JOURNAL_PATH.to_string_lossy()
+                ),
+                e.kind(),
+            ));
+        }
+    };
+
+    schema::read_json(&SCHEMA, &content)
+        .map(|journal: Journal| Some(journal).filter(|journal| !journal.is_empty()))
+}
+
+pub fn write(journal: &Journal) -> Result<(), Error> {
+    if journal.is_empty() {
+        return clear();
+    }
+
+    let content = schema::write_json(&SCHEMA, journal)?;
+    // Written aside and moved over, so a crash halfway through keeps the previous journal.
+    let partial = JOURNAL_PATH.with_extension("json.partial");
+
+    private_file_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&partial)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .and_then(|_| fs::rename(&partial, &*JOURNAL_PATH))
+        .map_err(failed)
+}
+
+pub fn clear() -> Result<(), Error> {
+    match fs::remove_file(&*JOURNAL_PATH) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(failed(e)),
+        _ => Ok(()),
+    }
+}
+
+fn failed(e: io::Error) -> Error {
+    Error::with_cause(
+        format!(
+            "A journal '{}' writing failed",
+            JOURNAL_PATH.to_string_lossy()
+        ),
+        e.kind(),
+    )
+}
@@ -0,0 +1,672 @@
+use crate::periodic::Period;
+use crate::{Error, Order};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use viks::{Key, Keymap};
+
+// Where keys are read. Each context has its own bindings and falls back to the ones of the
+// contexts after it, so `d` can scroll the pager while `dd` still deletes in the list.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Context {
+    // Bound everywhere a context does not bind the same keys itself.
+    Global,
+    #[default]
+    List,
+    // A scrollable text overlay, like shell output.
+    Pager,
+    // Pickers, the calendar, the board and fixed overlays.
+    Dialog,
+    Prompt,
+    // The lock screen, where every key is part of the passphrase.
+    #[serde(skip)]
+    Locked,
+}
+
+impl Context {
+    fn chain(self) -> &'static [Context] {
+        match self {
+            Context::Global => &[Context::Global],
+            Context::List => &[Context::List, Context::Global],
+            Context::Pager => &[Context::Pager, Context::Dialog, Context::Global],
+            Context::Dialog => &[Context::Dialog, Context::Global],
+            Context::Prompt => &[Context::Prompt, Context::Global],
+            Context::Locked => &[],
+        }
+    }
+
+    // Whether keys bound nowhere in the context still reach the view as raw input.
+    pub fn takes_input(self) -> bool {
+        !matches!(self, Context::Global | Context::List)
+    }
+}
+
+// Bindings by context, each kept with the keys as written for showing them.
+pub struct Keymaps {
+    tables: HashMap<Context, HashMap<Keymap, (String, Order)>>,
+    // How long to wait for the next key of a longer binding.
+    pub timeout: Duration,
+}
+
+impl Keymaps {
+    pub fn bind(&mut self, context: Context, keys: &str, order: Order) -> Result<(), Error> {
+        let keymap =
+            Keymap::new(keys).map_err(|_| Error::new(format!("A keymap '{keys}' is invalid")))?;
+
+        self.tables
+            .entry(context)
+            .or_default()
+            .insert(keymap, (keys.to_string(), order));
+
+        Ok(())
+    }
+
+    // The order bound to exactly these keys, from the first context in the chain binding them.
+    pub fn get(&self, context: Context, keys: &[Key]) -> Option<&Order> {
+        let keymap = Keymap::from(keys.to_vec());
+
+        context
+            .chain()
+            .iter()
+            .find_map(|context| self.tables.get(context)?.get(&keymap))
+            .map(|(_, order)| order)
+    }
+
+    // The keys bound to the order in the context, shortest first.
+    pub fn keys_for(&self, context: Context, order: &Order) -> Vec<&str> {
+        let mut keys = context
+            .chain()
+            .iter()
+            .filter_map(|context| self.tables.get(context))
+            .flat_map(|table| table.values())
+            .filter(|(_, bound)| bound == order)
+            .map(|(keys, _)| keys.as_str())
+            .collect::<Vec<_>>();
+
+        keys.sort_by_key(|keys| (keys.len(), *keys));
+
+        keys
+    }
+
+    // Whether some binding in the chain starts with these keys and goes on.
+    pub fn is_prefix(&self, context: Context, keys: &[Key]) -> bool {
+        context
+            .chain()
+            .iter()
+            .filter_map(|context| self.tables.get(context))
+            .flat_map(|table| table.keys())
+            .any(|keymap| {
+                let bound = keymap.as_vec();
+
+                bound.len() > keys.len() && bound[..keys.len()] == *keys
+            })
+    }
+}
+
+// Keys read but not resolved yet, while they may still be the start of a longer binding.
+#[derive(Default)]
+pub struct Pool {
+    keys: Vec<(Key, KeyEvent)>,
+    // When the last key came.
+    pooled: Option<Instant>,
+}
+
+impl Pool {
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn waited(&self) -> Duration {
+        self.pooled.map(|at| at.elapsed()).unwrap_or_default()
+    }
+
+    // Adds a key, returning what the pool resolves to unless it may still grow into a binding.
+    pub fn push(
+        &mut self,
+        keymaps: &Keymaps,
+        context: Context,
+        key: Key,
+        ev: KeyEvent,
+    ) -> Vec<Order> {
+        self.keys.push((key, ev));
+        self.pooled = Some(Instant::now());
+
+        self.resolve(keymaps, context, true)
+    }
+
+    // Resolves everything pooled, for when no more keys are coming.
+    pub fn flush(&mut self, keymaps: &Keymaps, context: Context) -> Vec<Order> {
+        self.resolve(keymaps, context, false)
+    }
+
+    // The longest run of keys from the start that binds something runs. A first key that starts
+    // nothing goes on as input where the context takes it, or is dropped, and the keys after it are
+    // tried again, so a stray key does not take a binding typed right after it down with it.
+    fn resolve(&mut self, keymaps: &Keymaps, context: Context, wait: bool) -> Vec<Order> {
+        let mut orders = vec![];
+
+        while !self.keys.is_empty() {
+            let keys = self
+                .keys
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+
+            if wait && keymaps.is_prefix(context, &keys) {
+                break;
+            }
+
+            let bound = (1..=keys.len())
+                .rev()
+                .find_map(|len| Some((len, keymaps.get(context, &keys[..len])?)));
+
+            match bound {
+                Some((len, order)) => {
+                    orders.push(order.clone());
+                    self.keys.drain(..len);
+                }
+                None => {
+                    let (_, ev) = self.keys.remove(0);
+
+                    if context.takes_input() {
+                        orders.push(Order::Input(ev));
+                    }
+                }
+            }
+        }
+
+        orders
+    }
+}
+
+impl Default for Keymaps {
+    fn default() -> Self {
+        let mut keymaps = Self {
+            tables: HashMap::new(),
+            timeout: Duration::from_secs(1),
+        };
+        // These are all valid.
+        let mut bind = |context, keys: &str, order| {
+            let _ = keymaps.bind(context, keys, order);
+        };
+
+        bind(Context::Global, "<c-l>", Order::Lock);
+
+        bind(Context::List, "ZZ", Order::Exit);
+        bind(Context::List, "j", Order::Down);
+        bind(Context::List, "k", Order::Up);
+        bind(Context::List, "h", Order::Left);
+        bind(Context::List, "l", Order::Right);
+        bind(Context::List, "<DOWN>", Order::Down);
+        bind(Context::List, "<UP>", Order::Up);
+        bind(Context::List, "<ENTER>", Order::Edit);
+        bind(Context::List, "v", Order::ToggleMark);
+        bind(Context::List, "E", Order::EditSelection);
+        bind(Context::List, "f", Order::ToggleFavorite);
+        bind(Context::List, "F", Order::Favorites);
+        bind(Context::List, "H", Order::ToggleHidden);
+        bind(Context::List, "a", Order::Create);
+        bind(Context::List, "dd", Order::Delete);
+        bind(Context::List, "r", Order::Rename);
+        bind(Context::List, "A", Order::Archive);
+        bind(Context::List, "u", Order::Undo);
+        bind(Context::List, "<c-r>", Order::Redo);
+        bind(Context::List, "/", Order::Search);
+        bind(Context::List, ":", Order::Command);
+        bind(Context::List, "Q", Order::Qr);
+        bind(Context::List, "L", Order::Graph);
+        bind(Context::List, "S", Order::Scratch);
+        bind(Context::List, "O", Order::LogEntry);
+        bind(Context::List, "i", Order::QuickEdit);
+        bind(Context::List, "T", Order::Timer);
+        bind(Context::List, "ci", Order::ClockIn);
+        bind(Context::List, "co", Order::ClockOut);
+        bind(Context::List, "gh", Order::Habits);
+        bind(Context::List, "gc", Order::CheckHabit);
+        bind(Context::List, "yf", Order::CopyField);
+        bind(Context::List, "gr", Order::Random);
+        bind(Context::List, "gR", Order::Review);
+        bind(Context::List, "gs", Order::Stale);
+        bind(Context::List, "W", Order::Periodic(Period::Week));
+        bind(Context::List, "M", Order::Periodic(Period::Month));
+        bind(Context::List, "[", Order::StepPeriod(-1));
+        bind(Context::List, "]", Order::StepPeriod(1));
+        bind(Context::List, "C", Order::Calendar);
+        bind(Context::List, "B", Order::Board);
+        bind(Context::List, "gx", Order::OpenLink);
+        bind(Context::List, "zs", Order::ToggleSpell);
+        bind(Context::List, "gl", Order::Lint);
+        bind(Context::List, "gb", Order::BrokenLinks);
+        bind(Context::List, "zw", Order::CycleWrap);
+        bind(Context::List, "zg", Order::ToggleGrid);
+        bind(Context::List, "gt", Order::StepTab(1));
+        bind(Context::List, "gT", Order::StepTab(-1));
+        bind(Context::List, "<c-w>t", Order::NewTab);
+        bind(Context::List, "<c-w>c", Order::CloseTab);
+        bind(Context::List, "<c-w>v", Order::Split);
+        bind(Context::List, "<c-w>w", Order::OtherPane);
+        bind(Context::List, "<c-w>o", Order::Unsplit);
+        bind(Context::List, "zh", Order::ScrollLeft);
+        bind(Context::List, "zl", Order::ScrollRight);
+        bind(Context::List, "zd", Order::DebugHud);
+
+        bind(Context::List, "<c-p>", Order::Palette);
+        bind(Context::List, "<ESC>", Order::Cancel);
+        bind(Context::List, "<c-c>", Order::Cancel);
+
+        bind(Context::Pager, "d", Order::HalfPage(1));
+        bind(Context::Pager, "u", Order::HalfPage(-1));
+        bind(Context::Pager, "<c-d>", Order::HalfPage(1));
+        bind(Context::Pager, "<c-u>", Order::HalfPage(-1));
+
+        keymaps
+    }
+}
+
+pub fn translate_to_key(key: KeyEvent) -> Option<Key> {
+    let mut key_str = match key.code {
+        KeyCode::Backspace => "BS",
+        KeyCode::Tab => "TAB",
+        KeyCode::Enter => "ENTER",
+        KeyCode::Esc => "ESC",
+        KeyCode::Char(' ') => "SPACE",
+        KeyCode::Char('!') => "!",
+        KeyCode::Char('"') => "\"",
+        KeyCode::Char('#') => "#",
+        KeyCode::Char('$') => "$",
+        KeyCode::Char('%') => "%",
+        KeyCode::Char('&') => "&",
+        KeyCode::Char('\'') => "'",
+        KeyCode::Char('(') => "(",
+        KeyCode::Char(')') => ")",
+        KeyCode::Char('*') => "*",
+        KeyCode::Char('+') => "+",
+        KeyCode::Char(',') => ",",
+        KeyCode::Char('-') => "-",
+        KeyCode::Char('.') => ".",
+        KeyCode::Char('/') => "/",
+        KeyCode::Char('0') => "0",
+        KeyCode::Char('1') => "1",
+        KeyCode::Char('2') => "2",
+        KeyCode::Char('3') => "3",
+        KeyCode::Char('4') => "4",
+        KeyCode::Char('5') => "5",
+        KeyCode::Char('6') => "6",
+        KeyCode::Char('7') => "7",
+        KeyCode::Char('8') => "8",
+        KeyCode::Char('9') => "9",
+        KeyCode::Char(':') => ":",
+        KeyCode::Char(';') => ";",
+        KeyCode::Char('<') => "lt",
+        KeyCode::Char('=') => "=",
+        KeyCode::Char('>') => ">",
+        KeyCode::Char('?') => "?",
+        KeyCode::Char('@') => "@",
+        KeyCode::Char('a') => "a",
+        KeyCode::Char('b') => "b",
+        KeyCode::Char('c') => "c",
+        KeyCode::Char('d') => "d",
+        KeyCode::Char('e') => "e",
+        KeyCode::Char('f') => "f",
+        KeyCode::Char('g') => "g",
+        KeyCode::Char('h') => "h",
+        KeyCode::Char('i') => "i",
+        KeyCode::Char('j') => "j",
+        KeyCode::Char('k') => "k",
+        KeyCode::Char('l') => "l",
+        KeyCode::Char('m') => "m",
+        KeyCode::Char('n') => "n",
+        KeyCode::Char('o') => "o",
+        KeyCode::Char('p') => "p",
+        KeyCode::Char('q') => "q",
+        KeyCode::Char('r') => "r",
+        KeyCode::Char('s') => "s",
+        KeyCode::Char('t') => "t",
+        KeyCode::Char('u') => "u",
+        KeyCode::Char('v') => "v",
+        KeyCode::Char('w') => "w",
+        KeyCode::Char('x') => "x",
+        KeyCode::Char('y') => "y",
+        KeyCode::Char('z') => "z",
+        KeyCode::Char('A') => "A",
+        KeyCode::Char('B') => "B",
+        KeyCode::Char('C') => "C",
+        KeyCode::Char('D') => "D",
+        KeyCode::Char('E') => "E",
+        KeyCode::Char('F') => "F",
+        KeyCode::Char('G') => "G",
+        KeyCode::Char('H') => "H",
+        KeyCode::Char('I') => "I",
+        KeyCode::Char('J') => "J",
+        KeyCode::Char('K') => "K",
+        KeyCode::Char('L') => "L",
+        KeyCode::Char('M') => "M",
+        KeyCode::Char('N') => "N",
+        KeyCode::Char('O') => "O",
+        KeyCode::Char('P') => "P",
+        KeyCode::Char('Q') => "Q",
+        KeyCode::Char('R') => "R",
+        KeyCode::Char('S') => "S",
+        KeyCode::Char('T') => "T",
+        KeyCode::Char('U') => "U",
+        KeyCode::Char('V') => "V",
+        KeyCode::Char('W') => "W",
+        KeyCode::Char('X') => "X",
+        KeyCode::Char('Y') => "Y",
+        KeyCode::Char('Z') => "Z",
+        KeyCode::Char('[') => "[",
+        KeyCode::Char('\\') => "\\",
+        KeyCode::Char(']') => "]",
+        KeyCode::Char('^') => "^",
+        KeyCode::Char('_') => "_",
+        KeyCode::Char('`') => "`",
+        KeyCode::Char('{') => "{",
+        KeyCode::Char('|') => "|",
+        KeyCode::Char('}') => "}",
+        KeyCode::Char('~') => "~",
+        KeyCode::Delete => "DEL",
+        KeyCode::Up => "UP",
+        KeyCode::Down => "DOWN",
+        KeyCode::Left => "LEFT",
+        KeyCode::Right => "RIGHT",
+        KeyCode::Home => "HOME",
+        KeyCode::End => "END",
+        KeyCode::PageUp => "PAGEUP",
+        KeyCode::PageDown => "PAGEDOWN",
+        _ => return None,
+    }
+    .to_string();
+
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let mut shift = key.modifiers.contains(KeyModifiers::SHIFT);
+
+    match key.code {
+        // A letter carries its shift as its case, unless other modifiers come with it: `<c-s-p>`.
+        KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+            shift |= c.is_ascii_uppercase();
+
+            if ctrl || alt {
+                key_str.make_ascii_lowercase();
+            } else if shift {
+                shift = false;
+                key_str.make_ascii_uppercase();
+            }
+        }
+        // Other characters come shifted already, as `!` rather than `<s-1>`.
+        KeyCode::Char(c) if c.is_ascii_graphic() => shift = false,
+        _ => {}
+    }
+
+    Key::new(&key_notation(ctrl, alt, shift, &key_str)).ok()
+}
+
+// Modifiers always come in the order c, a, s so that every combination has one spelling.
+fn key_notation(ctrl: bool, alt: bool, shift: bool, name: &str) -> String {
+    let prefix = [(ctrl, "c-"), (alt, "a-"), (shift, "s-")]
+        .into_iter()
+        .filter_map(|(on, prefix)| on.then_some(prefix))
+        .collect::<String>();
+    let key = format!("{prefix}{name}");
+
+    if key.len() > 1 {
+        format!("<{key}>")
+    } else {
+        key
+    }
+}
+
+// Rewrites the `<...>` keys of a user keymap into the spelling translate_to_key produces, so that
+// `<s-c-p>`, `<C-S-p>` and `<c-P>` all bind ctrl+shift+p and `<a-enter>` binds alt+ENTER.
+pub fn canonical_keymap(keymap: &str) -> String {
+    const NAMES: [&str; 15] = [
+        "BS", "TAB", "ENTER", "ESC", "SPACE", "DEL", "UP", "DOWN", "LEFT", "RIGHT", "HOME", "END",
+        "PAGEUP", "PAGEDOWN", "lt",
+    ];
+
+    let mut out = String::new();
+    let mut rest = keymap;
+
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+
+        out.push_str(&rest[..start]);
+
+        let mut name = &rest[start + 1..start + len];
+        let (mut ctrl, mut alt, mut shift) = (false, false, false);
+
+        // The last part is the key itself, so `<c-->` is ctrl and a dash.
+        while name.len() > 2 && name.as_bytes()[1] == b'-' {
+            match name.as_bytes()[0].to_ascii_lowercase() {
+                b'c' => ctrl = true,
+                b'a' => alt = true,
+                b's' => shift = true,
+                _ => break,
+            }
+
+            name = &name[2..];
+        }
+
+        let mut name = NAMES
+            .iter()
+            .find(|known| known.eq_ignore_ascii_case(name))
+            .map_or_else(|| name.to_string(), |known| known.to_string());
+
+        if name.len() == 1 && name.chars().all(|c| c.is_ascii_uppercase()) && (ctrl || alt) {
+            shift = true;
+        }
+
+        if shift && name.len() == 1 && name.chars().all(|c| c.is_ascii_alphabetic()) {
+            name.make_ascii_lowercase();
+
+            // A shifted letter on its own is just the capital.
+            if !ctrl && !alt {
+                shift = false;
+                name.make_ascii_uppercase();
+            }
+        } else if name == "lt" || name.len() == 1 && name.chars().all(|c| c.is_ascii_graphic()) {
+            shift = false;
+        }
+
+        out.push_str(&key_notation(ctrl, alt, shift, &name));
+        rest = &rest[start + len + 1..];
+    }
+
+    out.push_str(rest);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    const NAMED: [(KeyCode, &str); 13] = [
+        (KeyCode::Backspace, "BS"),
+        (KeyCode::Tab, "TAB"),
+        (KeyCode::Enter, "ENTER"),
+        (KeyCode::Esc, "ESC"),
+        (KeyCode::Delete, "DEL"),
+        (KeyCode::Up, "UP"),
+        (KeyCode::Down, "DOWN"),
+        (KeyCode::Left, "LEFT"),
+        (KeyCode::Right, "RIGHT"),
+        (KeyCode::Home, "HOME"),
+        (KeyCode::End, "END"),
+        (KeyCode::PageUp, "PAGEUP"),
+        (KeyCode::PageDown, "PAGEDOWN"),
+    ];
+
+    fn code() -> impl Strategy<Value = KeyCode> {
+        prop_oneof![
+            (b' '..=b'~').prop_map(|c| KeyCode::Char(c as char)),
+            select(NAMED.map(|(code, _)| code).to_vec()),
+        ]
+    }
+
+    // How someone might write the key in a keymap: the modifiers in `order`, and `upper` picking
+    // between the spellings a key has and the case of the names.
+    fn written(code: KeyCode, mods: [bool; 3], order: [usize; 3], upper: bool) -> String {
+        let [ctrl, alt, mut shift] = mods;
+        let name = match code {
+            KeyCode::Char(' ') => String::from("space"),
+            KeyCode::Char('<') => String::from("lt"),
+            KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+                shift |= c.is_ascii_uppercase();
+
+                // `P` or `<s-p>`, and `<c-s-p>` or `<c-P>`.
+                if shift && upper {
+                    shift = false;
+                    c.to_ascii_uppercase().to_string()
+                } else {
+                    c.to_ascii_lowercase().to_string()
+                }
+            }
+            KeyCode::Char(c) => c.to_string(),
+            code => NAMED
+                .iter()
+                .find(|(named, _)| *named == code)
+                .map(|(_, name)| name.to_lowercase())
+                .unwrap(),
+        };
+        let prefix = order
+            .iter()
+            .filter(|&&idx| [ctrl, alt, shift][idx])
+            .map(|&idx| ["c-", "a-", "s-"][idx])
+            .collect::<String>();
+        let (prefix, name) = match upper {
+            true if name.len() > 1 => (prefix.to_uppercase(), name.to_uppercase()),
+            true => (prefix.to_uppercase(), name),
+            false => (prefix, name),
+        };
+
+        match prefix.is_empty() && name.len() == 1 {
+            true => name,
+            false => format!("<{prefix}{name}>"),
+        }
+    }
+
+    fn modifiers([ctrl, alt, shift]: [bool; 3]) -> KeyModifiers {
+        let mut modifiers = KeyModifiers::NONE;
+
+        modifiers.set(KeyModifiers::CONTROL, ctrl);
+        modifiers.set(KeyModifiers::ALT, alt);
+        modifiers.set(KeyModifiers::SHIFT, shift);
+
+        modifiers
+    }
+
+    fn key(c: char) -> Key {
+        Key::new(&c.to_string()).unwrap()
+    }
+
+    fn event(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    // Bindings of up to three keys out of a few, so that they often share prefixes.
+    fn keymaps() -> impl Strategy<Value = Vec<String>> {
+        prop::collection::btree_set("[abcd]{1,3}", 1..8).prop_map(|set| set.into_iter().collect())
+    }
+
+    fn bound(bindings: &[String]) -> Keymaps {
+        let mut keymaps = Keymaps {
+            tables: HashMap::new(),
+            timeout: Duration::from_secs(1),
+        };
+
+        for (idx, keys) in bindings.iter().enumerate() {
+            keymaps
+                .bind(Context::List, keys, Order::Shell(idx))
+                .unwrap();
+        }
+
+        keymaps
+    }
+
+    // The bindings run by typing `keys` and then waiting out the timeout, by index.
+    fn typed(keymaps: &Keymaps, keys: &str) -> Vec<usize> {
+        let mut pool = Pool::default();
+        let mut orders = vec![];
+
+        for c in keys.chars() {
+            orders.extend(pool.push(keymaps, Context::List, key(c), event(c)));
+        }
+
+        orders.extend(pool.flush(keymaps, Context::List));
+
+        assert!(pool.is_empty());
+
+        orders
+            .into_iter()
+            .map(|order| match order {
+                Order::Shell(idx) => idx,
+                _ => unreachable!("only shell orders are bound"),
+            })
+            .collect()
+    }
+
+    proptest! {
+        #[test]
+        fn written_keys_bind_what_is_pressed(
+            code in code(),
+            mods in any::<[bool; 3]>(),
+            order in select(vec![[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]]),
+            upper in any::<bool>(),
+        ) {
+            // `>` closes the brackets, so it can only be bound bare.
+            prop_assume!(code != KeyCode::Char('>') || mods == [false; 3]);
+
+            let pressed = translate_to_key(KeyEvent::new(code, modifiers(mods)));
+            let keymap = canonical_keymap(&written(code, mods, order, upper));
+
+            prop_assert!(pressed.is_some(), "{code:?} has no key");
+            prop_assert_eq!(
+                Keymap::new(&keymap).ok().map(|keymap| keymap.as_vec()),
+                pressed.map(|key| vec![key])
+            );
+        }
+
+        #[test]
+        fn a_binding_typed_on_its_own_runs(
+            bindings in keymaps(),
+            which in any::<prop::sample::Index>(),
+        ) {
+            let keymaps = bound(&bindings);
+            let idx = which.index(bindings.len());
+
+            prop_assert_eq!(typed(&keymaps, &bindings[idx]), vec![idx]);
+        }
+
+        #[test]
+        fn stray_keys_never_swallow_a_binding(
+            bindings in keymaps(),
+            stray in "[abcd]{0,4}",
+            which in any::<prop::sample::Index>(),
+        ) {
+            let keymaps = bound(&bindings);
+            let idx = which.index(bindings.len());
+            let binding = &bindings[idx];
+            let stray = stray.chars().collect::<Vec<_>>();
+
+            // A binding that starts among the stray keys and runs into the typed one rightly wins.
+            for from in 0..stray.len() {
+                for to in 1..=binding.len() {
+                    let across = stray[from..].iter().collect::<String>() + &binding[..to];
+
+                    prop_assume!(!bindings.contains(&across));
+                }
+            }
+
+            let orders = typed(&keymaps, &(stray.iter().collect::<String>() + binding.as_str()));
+
+            prop_assert_eq!(orders.last(), Some(&idx));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{APP_DATA_PATH, Error, pipe_external};
+use std::io;
+use std::process::{Command, Stdio};
+
+const SERVICE: &str = "memoleak";
+
+// Secrets live in the platform's store through its own command line tool: secret-tool
+// (Secret Service) on Linux and BSD, security (Keychain) on macOS, and PowerShell's DPAPI
+// wrapping on Windows, which leaves only the encrypted blob in APP_DATA_PATH.
+pub fn get(account: &str) -> Result<Option<String>, Error> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        let mut cmd = Command::new("security");
+
+        cmd.args(["find-generic-password", "-s", SERVICE, "-a", account, "-w"]);
+        cmd
+    } else if cfg!(windows) {
+        let path = blob_path(account);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        powershell(&format!(
+            "$s = Get-Content -LiteralPath '{}' | ConvertTo-SecureString; \
+             [Runtime.InteropServices.Marshal]::PtrToStringBSTR(\
+             [Runtime.InteropServices.Marshal]::SecureStringToBSTR($s))",
+            path.to_string_lossy().replace('\'', "''")
+        ))
+    } else {
+        let mut cmd = Command::new("secret-tool");
+
+        cmd.args(["lookup", "service", SERVICE, "account", account]);
+        cmd
+    };
+    let program = cmd.get_program().to_string_lossy().to_string();
+
+    let out = cmd
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| failed(&program, e))?;
+
+    // The lookup tools exit non-zero when there is no such entry.
+    if !out.status.success() {
+        return Ok(None);
+    }
+
+    let secret = String::from_utf8_lossy(&out.stdout);
+    let secret = secret.strip_suffix('\n').unwrap_or(&secret);
+    let secret = secret.strip_suffix('\r').unwrap_or(secret);
+
+    Ok(Some(secret.to_string()).filter(|secret| !secret.is_empty()))
+}
+
+pub fn set(account: &str, secret: &str) -> Result<(), Error> {
+    if cfg!(target_os = "macos") {
+        // `security -i` reads the command from stdin, which keeps the secret out of argv.
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+
+        return pipe_external(
+            Command::new("security").arg("-i").stdout(Stdio::null()),
+            format!(
+                "add-generic-password -U -s {} -a {} -w {}\n",
+                quote(SERVICE),
+                quote(account),
+                quote(secret)
+            ),
+        );
+    }
+
+    if cfg!(windows) {
+        let path = blob_path(account);
+        let dir = path.parent().unwrap();
+
+        std::fs::create_dir_all(dir).map_err(|e| {
+            Error::with_cause(
+                format!("A dir '{}' creating failed", dir.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+
+        return pipe_external(
+            &mut powershell(&format!(
+                "[Console]::In.ReadLine() | ConvertTo-SecureString -AsPlainText -Force | \
+                 ConvertFrom-SecureString | Set-Content -LiteralPath '{}'",
+                path.to_string_lossy().replace('\'', "''")
+            )),
+            format!("{secret}\n"),
+        );
+    }
+
+    pipe_external(
+        Command::new("secret-tool")
+            .args(["store", "--label", &format!("{SERVICE} {account}")])
+            .args(["service", SERVICE, "account", account]),
+        secret,
+    )
+}
+
+fn powershell(script: &str) -> Command {
+    let mut cmd = Command::new("powershell");
+
+    cmd.args(["-NoProfile", "-NonInteractive", "-Command", script]);
+    cmd
+}
+
+fn blob_path(account: &str) -> std::path::PathBuf {
+    APP_DATA_PATH
+        .join("keyring")
+        .join(format!("{account}.dpapi"))
+}
+
+fn failed(program: &str, e: io::Error) -> Error {
+    if e.kind() == io::ErrorKind::NotFound {
+        Error::with_cause(
+            format!("The command '{program}' executing failed"),
+            "it is not installed or not in PATH",
+        )
+    } else {
+        Error::with_cause(
+            format!("The command '{program}' executing failed"),
+            e.kind(),
+        )
+    }
+}
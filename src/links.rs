@@ -0,0 +1,179 @@
+use crate::publish::href;
+use crate::{APP_DATA_PATH, Error, MEMO_EXTENSION, Stash, meta, private_file_options};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+// Broken links marked as fine, one `memo<TAB>target` per line, both lowercased. The memo is given
+// by its `id` when it has one, so what was ignored stays ignored after a rename.
+static IGNORED_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("ignored_links"));
+
+pub struct Broken {
+    pub from: PathBuf,
+    pub name: String,
+    pub id: Option<String>,
+    // 1-based, counting the front matter.
+    pub line: usize,
+    pub target: String,
+}
+
+// Wiki links whose target is neither a memo's name nor its id, leaving out the ignored ones. A
+// partly loaded memo is checked in its whole file, or in what is loaded if that cannot be read.
+pub fn broken(stash: &Stash) -> Vec<Broken> {
+    let targets = stash.link_targets();
+    let ignored = ignored();
+    let mut found = vec![];
+
+    for memo in &stash.stash {
+        let name = memo.name();
+        let whole = match memo.partial {
+            true => memo.read_latest_content().ok(),
+            false => None,
+        };
+        let content = whole.as_deref().unwrap_or(&memo.content_buffer);
+        let id = meta::split(content).0.id().map(String::from);
+        // Lines ignored before the memo had an id were kept by its name.
+        let keys = [id.as_deref(), Some(name.as_str())]
+            .into_iter()
+            .flatten()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+
+        for (i, text) in content.lines().enumerate() {
+            for target in meta::wiki_links(text) {
+                // `[[#heading]]` points into the memo itself.
+                let to = target.split('#').next().unwrap_or_default().trim();
+
+                if to.is_empty()
+                    || targets.contains_key(&to.to_lowercase())
+                    || keys
+                        .iter()
+                        .any(|key| ignored.contains(&(key.clone(), target.to_lowercase())))
+                {
+                    continue;
+                }
+
+                found.push(Broken {
+                    from: memo.original_path.clone(),
+                    name: name.clone(),
+                    id: id.clone(),
+                    line: i + 1,
+                    target,
+                });
+            }
+        }
+    }
+
+    found
+}
+
+pub fn ignore(link: &Broken) -> Result<(), Error> {
+    let line = format!(
+        "{}\t{}\n",
+        link.id.as_ref().unwrap_or(&link.name).to_lowercase(),
+        link.target.to_lowercase()
+    );
+
+    private_file_options()
+        .create(true)
+        .append(true)
+        .open(&*IGNORED_PATH)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' writing failed", IGNORED_PATH.to_string_lossy()),
+                e.kind(),
+            )
+        })
+}
+
+fn ignored() -> HashSet<(String, String)> {
+    fs::read_to_string(&*IGNORED_PATH)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, target)| (name.to_string(), target.to_string()))
+        .collect()
+}
+
+// A memo whose links lead to a renamed memo, and what it reads like with them following along.
+pub struct Relink {
+    pub path: PathBuf,
+    pub name: String,
+    pub content: String,
+    // 1-based line numbers with the line before and after.
+    pub lines: Vec<(usize, String, String)>,
+}
+
+// The memos with wiki links or relative markdown links to the memo `from`, rewritten to `to`. A
+// partly loaded memo is rewritten from its whole file, and left out if that cannot be read, so the
+// rewritten content never stands in for more than it was made from.
+pub fn relinks(stash: &Stash, from: &str, to: &str) -> Vec<Relink> {
+    let mut found = vec![];
+
+    for memo in &stash.stash {
+        let whole = match memo.partial {
+            true => match memo.read_latest_content() {
+                Ok(whole) => whole,
+                Err(_) => continue,
+            },
+            false => memo.content_buffer.clone(),
+        };
+        let content = rewrite(&whole, from, to);
+
+        if content == whole {
+            continue;
+        }
+
+        let lines = whole
+            .lines()
+            .zip(content.lines())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(i, (old, new))| (i + 1, old.to_string(), new.to_string()))
+            .collect();
+
+        found.push(Relink {
+            path: memo.original_path.clone(),
+            name: memo.name(),
+            content,
+            lines,
+        });
+    }
+
+    found
+}
+
+// Points `[[from]]`, `[[from#heading]]` and `[[from|label]]` at `to`, matching the name in any
+// case, and `[label](from.md)` as well whether written plain, percent-encoded or in `<>`.
+pub fn rewrite(text: &str, from: &str, to: &str) -> String {
+    let text = meta::replace_wiki_links(text, |link| {
+        let (target, rest) = match link.inner.find(['#', '|']) {
+            Some(at) => link.inner.split_at(at),
+            None => (link.inner, ""),
+        };
+
+        match target.trim().eq_ignore_ascii_case(from) {
+            true => format!("[[{to}{rest}]]"),
+            false => format!("[[{}]]", link.inner),
+        }
+    });
+    let file = |name: &str| format!("{name}.{MEMO_EXTENSION}");
+    let forms = [
+        (file(from), file(to)),
+        (href(&file(from)), href(&file(to))),
+        (file(from).replace(' ', "%20"), file(to).replace(' ', "%20")),
+        (format!("<{}>", file(from)), format!("<{}>", file(to))),
+    ];
+    let mut text = text;
+
+    for (old, new) in forms.iter().collect::<HashSet<_>>() {
+        for end in [")", "#"] {
+            text = text.replace(&format!("]({old}{end}"), &format!("]({new}{end}"));
+        }
+    }
+
+    text
+}
@@ -0,0 +1,158 @@
+use crate::{Error, meta, shell_command, shell_quote};
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::process::Stdio;
+
+pub struct Diagnostic {
+    // 1-based, counting the front matter.
+    pub line: usize,
+    pub message: String,
+}
+
+// The built-in rules: trailing whitespace, headings repeated within the memo, and wiki links or
+// relative links that lead nowhere. `is_memo` tells whether a wiki link target names a memo, and
+// relative links are looked up from `dir`.
+pub fn check<F: Fn(&str) -> bool>(content: &str, dir: &Path, is_memo: F) -> Vec<Diagnostic> {
+    let (_, body) = meta::split(content);
+    let body_start = content[..content.len() - body.len()].lines().count();
+    let mut headings = HashMap::new();
+    let mut fenced = false;
+    let mut found = vec![];
+    let mut flag = |line: usize, message: String| found.push(Diagnostic { line, message });
+
+    for (i, text) in content.lines().enumerate() {
+        let line = i + 1;
+        let trailing = text.len() - text.trim_end().len();
+
+        // Exactly two spaces are a hard line break.
+        if trailing > 0 && !(trailing == 2 && text.ends_with("  ") && !text.trim().is_empty()) {
+            flag(line, String::from("Trailing whitespace"));
+        }
+
+        if i < body_start {
+            continue;
+        }
+
+        let trimmed = text.trim_start();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fenced = !fenced;
+        }
+
+        if fenced {
+            continue;
+        }
+
+        if let Some(heading) = heading(text) {
+            match headings.get(&heading.to_lowercase()) {
+                Some(first) => flag(
+                    line,
+                    format!("The heading '{heading}' repeats line {first}"),
+                ),
+                None => {
+                    headings.insert(heading.to_lowercase(), line);
+                }
+            }
+        }
+
+        for target in meta::wiki_links(text) {
+            let name = target.split('#').next().unwrap_or_default().trim();
+
+            if !name.is_empty() && !is_memo(name) {
+                flag(line, format!("The link '[[{target}]]' leads to no memo"));
+            }
+        }
+
+        for target in relative_links(text) {
+            if !dir.join(target.replace("%20", " ")).exists() {
+                flag(line, format!("The link '{target}' leads to no file"));
+            }
+        }
+    }
+
+    found
+}
+
+// Runs a linter as `command <file>` and reads `file:line[:column] message` lines of its output.
+// Linters exit non-zero when they find something, so the exit status is not an error.
+pub fn external(command: &str, path: &Path) -> Result<Vec<Diagnostic>, Error> {
+    let line = format!("{command} {}", shell_quote(path.to_string_lossy()));
+    let out = shell_command(&line)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => Error::with_cause(
+                format!("The command '{command}' executing failed"),
+                "it is not installed or not in PATH",
+            ),
+            kind => Error::with_cause(format!("The command '{command}' executing failed"), kind),
+        })?;
+    let mut text = String::from_utf8_lossy(&out.stdout).to_string();
+
+    text.push_str(&String::from_utf8_lossy(&out.stderr));
+
+    Ok(text.lines().filter_map(parse_line).collect())
+}
+
+fn parse_line(text: &str) -> Option<Diagnostic> {
+    let is_digit = |c: char| c.is_ascii_digit();
+    let (_file, rest) = text.split_once(':')?;
+    let digits = rest.len() - rest.trim_start_matches(is_digit).len();
+    let line = rest[..digits].parse().ok()?;
+    let rest = &rest[digits..];
+    let rest = match rest.strip_prefix(':') {
+        Some(rest) => rest.trim_start_matches(is_digit),
+        None => rest,
+    };
+    let message = rest.trim_start_matches(':').trim();
+
+    Some(Diagnostic {
+        line,
+        message: message.to_string(),
+    })
+}
+
+// The text of an ATX heading, without the markers: `## Plans ##` is "Plans".
+fn heading(text: &str) -> Option<&str> {
+    let indent = text.len() - text.trim_start_matches(' ').len();
+    let text = text.trim_start_matches(' ');
+    let level = text.len() - text.trim_start_matches('#').len();
+
+    if indent > 3 || !(1..=6).contains(&level) {
+        return None;
+    }
+
+    let rest = &text[level..];
+
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    Some(rest.trim().trim_end_matches('#').trim_end()).filter(|text| !text.is_empty())
+}
+
+// Targets of `[text](target)` links that are neither URLs nor anchors within the memo.
+fn relative_links(text: &str) -> Vec<&str> {
+    let mut targets = vec![];
+    let mut rest = text;
+
+    while let Some(start) = rest.find("](") {
+        rest = &rest[start + 2..];
+
+        let Some(end) = rest.find(')') else {
+            break;
+        };
+        let target = rest[..end].split_whitespace().next().unwrap_or_default();
+        let target = target.trim_start_matches('<').trim_end_matches('>');
+        let target = target.split('#').next().unwrap_or_default();
+
+        if !target.is_empty() && !target.contains(':') {
+            targets.push(target);
+        }
+
+        rest = &rest[end..];
+    }
+
+    targets
+}
@@ -0,0 +1,34 @@
+use crate::Error;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Argon2, password_hash};
+
+// A PHC string (`$argon2id$v=19$...`) for `lock.passphrase_hash` in the config.
+pub fn hash(passphrase: &str) -> Result<String, Error> {
+    let mut salt = [0u8; 16];
+
+    getrandom::getrandom(&mut salt)
+        .map_err(|e| Error::with_cause("The salt generating failed", e))?;
+
+    let salt = SaltString::encode_b64(&salt)
+        .map_err(|e| Error::with_cause("The salt generating failed", e))?;
+
+    Argon2::default()
+        .hash_password(passphrase.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::with_cause("The passphrase hashing failed", e))
+}
+
+pub fn verify(passphrase: &str, hash: &str) -> Result<bool, Error> {
+    let hash = PasswordHash::new(hash).map_err(|e| {
+        Error::with_cause(
+            "The lock passphrase hash is invalid",
+            format!("{e}, make a new one with 'me lock'"),
+        )
+    })?;
+
+    match Argon2::default().verify_password(passphrase.as_bytes(), &hash) {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(Error::with_cause("The passphrase verifying failed", e)),
+    }
+}
@@ -0,0 +1,119 @@
+use crate::config::MailConfig;
+use crate::meta;
+use crate::{Error, TempFile, memo_name_of, pipe_external, read_secret, storage};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::Local;
+use std::path::Path;
+use std::process::Command;
+
+const DEFAULT_SENDMAIL: &str = "sendmail -t -i";
+
+// Sends a memo as a plain-text mail with its title as the subject.
+pub fn send(path: &Path, to: &[String], config: &MailConfig) -> Result<(), Error> {
+    let content = storage::read(path).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+    let name = memo_name_of(path);
+    let (front, body) = meta::split(&content);
+    let subject = front.title().unwrap_or(&name);
+
+    let mut message = String::new();
+
+    if let Some(from) = &config.from {
+        message.push_str(&format!("From: {from}\r\n"));
+    }
+
+    message.push_str(&format!("To: {}\r\n", to.join(", ")));
+    message.push_str(&format!("Subject: {}\r\n", encode_header(subject)));
+    message.push_str(&format!("Date: {}\r\n", Local::now().to_rfc2822()));
+    message.push_str("MIME-Version: 1.0\r\n");
+    message.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+    message.push_str("Content-Transfer-Encoding: 8bit\r\n\r\n");
+
+    for line in body.lines() {
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+
+    match &config.smtp.host {
+        Some(host) => smtp(host, to, &message, config),
+        None => sendmail(&message, config),
+    }
+}
+
+// RFC 2047 encoded-word for non-ASCII subjects.
+fn encode_header(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", STANDARD.encode(text))
+    }
+}
+
+fn sendmail(message: &str, config: &MailConfig) -> Result<(), Error> {
+    let line = config.sendmail.as_deref().unwrap_or(DEFAULT_SENDMAIL);
+    let mut words = line.split_whitespace();
+
+    let Some(program) = words.next() else {
+        return Err(Error::new("A mail.sendmail command is empty"));
+    };
+
+    pipe_external(Command::new(program).args(words), message)
+}
+
+// SMTP goes through curl, which already speaks TLS and AUTH.
+fn smtp(host: &str, to: &[String], message: &str, config: &MailConfig) -> Result<(), Error> {
+    let from = config
+        .from
+        .as_ref()
+        .ok_or_else(|| Error::new("A mail.from address is required for SMTP"))?;
+    let port = config.smtp.port.unwrap_or(587);
+    let scheme = if port == 465 { "smtps" } else { "smtp" };
+
+    let mut cmd = Command::new("curl");
+
+    cmd.args([
+        "--silent",
+        "--show-error",
+        "--ssl-reqd",
+        "--upload-file",
+        "-",
+    ])
+    .arg("--url")
+    .arg(format!("{scheme}://{host}:{port}"))
+    .arg("--mail-from")
+    .arg(from);
+
+    for rcpt in to {
+        cmd.arg("--mail-rcpt").arg(rcpt);
+    }
+
+    // Credentials go through a private curl config so they never show up in the process list.
+    let _credentials = match &config.smtp.username {
+        Some(username) => {
+            let password = match &config.smtp.password {
+                Some(password) => password.clone(),
+                None => read_secret(format!("SMTP password for {username}: "))?,
+            };
+            let file = TempFile::write(
+                "curl-smtp",
+                format!("user = \"{}:{}\"\n", quote(username), quote(&password)),
+            )?;
+
+            cmd.arg("--config").arg(file.path());
+
+            Some(file)
+        }
+        None => None,
+    };
+
+    pipe_external(&mut cmd, message)
+}
+
+fn quote(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
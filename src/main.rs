@@ -1,30 +1,274 @@
-use crossterm::cursor::{Hide, Show};
-use crossterm::event::{self, Event, KeyEvent};
-use crossterm::execute;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, DisableBracketedPaste, DisableFocusChange, EnableBracketedPaste, EnableFocusChange,
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{
+    Attribute, Color, Colors, Print, ResetColor, SetAttribute, SetColors, SetForegroundColor,
+};
 use crossterm::terminal::{
-    self, DisableLineWrap, EnableLineWrap, EnterAlternateScreen, LeaveAlternateScreen,
+    self, DisableLineWrap, EnableLineWrap, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
 };
-use std::collections::HashMap;
+use crossterm::{execute, queue};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::Display;
 use std::fs;
 use std::hash::{DefaultHasher, Hash, Hasher};
-use std::io;
+use std::io::{self, IsTerminal, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 use std::process::{self, Command, ExitStatus, Stdio};
-use std::sync::{Arc, LazyLock, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, OnceLock, RwLock};
 use std::thread;
-use viks::{Key, Keymap};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use config::{
+    ColumnKind, Config, FocusLog, OpenIn, ShellOutput, SourceConfig, Startup, TagStyle, Wrap,
+};
+use dialog::{Board, BoardEvent, Calendar, Overlay, Palette, Picked, Picker};
+use hooks::Hook;
+use journal::Journal;
+use keymap::{Context, Keymaps, Pool, canonical_keymap, translate_to_key};
+use periodic::Period;
+use plugin::{Outcome, Plugins};
+use prompt::{Completing, Prompt, PromptKind};
+use task::{Progress, Task};
+use undo::{Change, UndoLog};
+
+mod accessible;
+mod attachment;
+mod backup;
+mod bench;
+mod bookmark;
+mod cli;
+mod clipboard;
+mod config;
+mod dialog;
+mod export;
+mod focus;
+mod fuzzy;
+mod habit;
+mod history;
+mod hooks;
+mod importer;
+#[cfg(unix)]
+mod ipc;
+mod journal;
+mod keymap;
+mod keyring;
+mod links;
+mod lint;
+mod lock;
+mod mail;
+mod meta;
+mod onboard;
+mod periodic;
+mod plan;
+mod plugin;
+mod preview;
+mod prompt;
+mod publish;
+mod review;
+mod schema;
+mod snippet;
+mod spell;
+mod storage;
+mod sync;
+mod task;
+mod timesheet;
+mod undo;
 
 fn main() {
+    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    let mut accessible = false;
+
+    // Everything else, down to where the stash is, depends on the profile.
+    while let Some(option) = args.first().filter(|arg| arg.starts_with("--")) {
+        match option.as_str() {
+            "--profile" => {
+                let Some(name) = args.get(1).cloned() else {
+                    fatal_err(
+                        "The option '--profile' needs a value",
+                        Error::new("Usage: me [--profile <name>] [--accessible] [command]"),
+                    );
+                };
+
+                args.drain(..2);
+                config::select_profile(name.clone());
+
+                if let Err(e) = config::load() {
+                    fatal_err(format!("The profile '{name}' loading failed"), e);
+                }
+            }
+            "--accessible" => {
+                accessible = true;
+                args.remove(0);
+            }
+            _ => break,
+        }
+    }
+
+    let fresh = !MEMO_LIST_PATH.exists() && !config::CONFIG_PATH.exists();
+
     if let Err(e) = setup() {
         fatal_err("Local setup failed", e);
     }
 
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&args) {
+            fatal_err(format!("The command '{}' failed", args[0]), e);
+        }
+
+        return;
+    }
+
+    if fresh
+        && io::stdin().is_terminal()
+        && let Err(e) = onboard::run()
+    {
+        fatal_err("The first-run setup failed", e);
+    }
+
+    if accessible || config::load().is_ok_and(|config| config.accessible) {
+        if let Some(hash) = config::load()
+            .ok()
+            .and_then(|config| config.lock.passphrase_hash)
+            && let Err(e) = accessible::unlock(&hash)
+        {
+            fatal_err("The unlocking failed", e);
+        }
+
+        if let Err(e) = accessible::run() {
+            fatal_err("The accessible mode failed", e);
+        }
+
+        return;
+    }
+
     let mut stash = Stash::new();
 
     if let Err(e) = fill_stash_with_local(&mut stash) {
         fatal_err("The memo stash refilling failed", e);
     }
+
+    let (plugins, plugin_error) = plugin::load();
+    let (config, config_error) = match config::load() {
+        Ok(config) => (config, None),
+        Err(e) => (Config::default(), Some(e)),
+    };
+    let source_error = fill_stash_with_sources(&mut stash, &config.sources).err();
+    let purge_error = purge_trash(&config.delete).err();
+    let (recovered, unlocked) = leftover_journal(config.lock.passphrase_hash.as_deref());
+    let mut app = setup_tui(plugins, config);
+
+    if let Some(journal) = recovered {
+        app.recover(journal, &mut stash);
+    }
+
+    app.open_startup(&mut stash);
+
+    if app.config.lock.passphrase_hash.is_some() && !unlocked {
+        app.lock();
+    }
+
+    if let Some(e) = config_error
+        .or(plugin_error)
+        .or(source_error)
+        .or(purge_error)
+    {
+        app.status = e.to_string();
+    }
+
+    #[cfg(unix)]
+    let _listener = ipc::listen(app.orders.clone())
+        .map_err(|e| app.status = e.to_string())
+        .ok();
+
+    app.run(&mut stash);
+
+    disable_tui();
+
+    if app.config.scratch.clear_on_exit
+        && let Err(e) = clear_scratch()
+    {
+        eprintln!("[ERR] {e}");
+    }
+
+    if app.config.backup.on_exit {
+        let dir = app
+            .config
+            .backup
+            .dir()
+            .unwrap_or(backup::BACKUPS_PATH.clone());
+
+        if let Err(e) =
+            backup::create(&dir).and_then(|_| backup::rotate(&dir, app.config.backup.keep.get()))
+        {
+            eprintln!("[ERR] {e}");
+        }
+    }
+}
+
+// Asks what to do with changes a previous session could not write, unless that session is still
+// running and they are its own. Behind a lock, the passphrase is asked first, and whether it was is
+// returned so the TUI does not ask again.
+fn leftover_journal(lock_hash: Option<&str>) -> (Option<Journal>, bool) {
+    #[cfg(unix)]
+    if ipc::running() {
+        return (None, false);
+    }
+
+    // Going on would have the next autosave write over it.
+    let journal = match journal::read() {
+        Ok(Some(journal)) => journal,
+        Ok(None) => return (None, false),
+        Err(e) => fatal_err(
+            format!(
+                "The journal '{}' of the last session is left as it is",
+                journal::JOURNAL_PATH.to_string_lossy()
+            ),
+            e,
+        ),
+    };
+
+    eprintln!("The last session ended with changes it had not written.");
+
+    if let Some(hash) = lock_hash
+        && let Err(e) = accessible::unlock(hash)
+    {
+        fatal_err("The unlocking failed", e);
+    }
+
+    for line in journal.describe() {
+        eprintln!("  {line}");
+    }
+
+    let unlocked = lock_hash.is_some();
+
+    match onboard::confirm("Recover them? [Y/n]: ") {
+        Ok(true) => (Some(journal), unlocked),
+        Ok(false) => {
+            if let Err(e) = journal::clear() {
+                eprintln!("[ERR] {e}");
+            }
+
+            (None, unlocked)
+        }
+        Err(e) => fatal_err("The recovery question failed", e),
+    }
+}
+
+fn log_err(e: &Error) {
+    let _ = private_file_options()
+        .append(true)
+        .create(true)
+        .open(&*ERROR_LOG_PATH)
+        .and_then(|mut file| writeln!(file, "{}\t{e}", chrono::Local::now().to_rfc3339()));
 }
 
 fn fatal_err<S: AsRef<str>>(head: S, e: Error) -> ! {
@@ -34,6 +278,7 @@ fn fatal_err<S: AsRef<str>>(head: S, e: Error) -> ! {
     process::exit(1)
 }
 
+#[derive(Debug)]
 struct Error(String);
 
 impl Error {
@@ -53,6 +298,10 @@ impl Display for Error {
 }
 
 static APP_DATA_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+    if let Some(dir) = config::load().ok().and_then(|config| config.data_dir()) {
+        return dir;
+    }
+
     dirs::data_local_dir()
         .unwrap_or_else(|| {
             fatal_err(
@@ -65,20 +314,109 @@ static APP_DATA_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
 
 static MEMO_LIST_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("saved_files"));
 
+static HISTORY_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("history"));
+
+static TRASH_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("trash"));
+
+static ARCHIVE_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("archive"));
+
+// Attached files, each stored once under its content hash.
+static ATTACHMENT_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("attachments"));
+
+static SYNC_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("sync"));
+
+static PLUGIN_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("plugins"));
+
+static SNIPPET_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("snippets"));
+
+// The path of the memo last opened in the editor, for `startup = "last"`.
+static LAST_OPENED_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("last_opened"));
+
+// Errors that do not stop what caused them, kept here as the TUI leaves nowhere to print them.
+static ERROR_LOG_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("errors.log"));
+
+// The editor when `[editor] command` is not set.
+const EDITOR_FALLBACK: &str = match option_env!("EDITOR") {
+    Some(editor) => editor,
+    None => "vim",
+};
+
+static EDITOR_COMMAND: LazyLock<String> = LazyLock::new(|| {
+    config::load()
+        .ok()
+        .and_then(|config| config.editor.command)
+        .unwrap_or_else(|| EDITOR_FALLBACK.to_string())
+});
+
+static DISCARD_CODES: LazyLock<Vec<i32>> = LazyLock::new(|| {
+    config::load()
+        .map(|config| config.editor.discard_codes)
+        .unwrap_or_default()
+});
+
+static FORMAT_COMMAND: LazyLock<Option<String>> =
+    LazyLock::new(|| config::load().ok().and_then(|config| config.editor.format));
+
+// Lives outside MEMO_LIST_PATH so it never shows up in the list.
+static SCRATCH_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("scratch.md"));
+
 fn setup() -> Result<(), Error> {
     if !APP_DATA_PATH.exists() {
-        fs::create_dir_all(&*APP_DATA_PATH)
+        create_private_dir(&*APP_DATA_PATH)
             .map_err(|e| Error::with_cause("APP_DATA_PATH creating failed", e.kind()))?;
     }
 
     if !MEMO_LIST_PATH.exists() {
-        fs::create_dir_all(&*MEMO_LIST_PATH)
+        create_private_dir(&*MEMO_LIST_PATH)
             .map_err(|e| Error::with_cause("MEMO_LIST_PATH creating failed", e.kind()))?;
     }
 
+    if !HISTORY_PATH.exists() {
+        create_private_dir(&*HISTORY_PATH)
+            .map_err(|e| Error::with_cause("HISTORY_PATH creating failed", e.kind()))?;
+    }
+
+    if !TRASH_PATH.exists() {
+        create_private_dir(&*TRASH_PATH)
+            .map_err(|e| Error::with_cause("TRASH_PATH creating failed", e.kind()))?;
+    }
+
+    if !ARCHIVE_PATH.exists() {
+        create_private_dir(&*ARCHIVE_PATH)
+            .map_err(|e| Error::with_cause("ARCHIVE_PATH creating failed", e.kind()))?;
+    }
+
+    if !ATTACHMENT_PATH.exists() {
+        create_private_dir(&*ATTACHMENT_PATH)
+            .map_err(|e| Error::with_cause("ATTACHMENT_PATH creating failed", e.kind()))?;
+    }
+
     Ok(())
 }
 
+// Memos often hold credentials and personal notes, so on Unix the stash is made for its owner
+// only: 0700 for directories and 0600 for the memo files.
+fn create_private_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+
+    builder.recursive(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+    builder.create(path)
+}
+
+fn private_file_options() -> fs::OpenOptions {
+    #[allow(unused_mut)]
+    let mut options = fs::OpenOptions::new();
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+}
+
 struct Stash {
     stash: Vec<Memo>,
 }
@@ -96,322 +434,6493 @@ impl Stash {
         self.stash.remove(idx);
     }
 
-    fn edit(&self, idx: usize) -> Result<ExitStatus, Error> {
-        if idx >= self.stash.len() {
-            return Err(Error::new("Index out of bounds"));
-        }
+    fn get(&self, idx: usize) -> Option<&Memo> {
+        self.stash.get(idx)
+    }
 
-        let res = Command::new(option_env!("EDITOR").unwrap_or("vim"))
-            .arg(&self.stash[idx].original_path)
-            .stderr(Stdio::null())
-            .status();
+    fn len(&self) -> usize {
+        self.stash.len()
+    }
 
-        match res {
-            Ok(status) => Ok(status),
-            Err(e) => Err(Error::with_cause("$EDITOR executing failed", e.kind())),
-        }
+    fn sort(&mut self) {
+        self.stash.sort_by_key(|memo| memo.name());
     }
-}
 
-struct Memo {
-    original_path: PathBuf,
-    content_buffer: String,
-    content_hash: u64,
-}
+    // Either storage form of a memo's file finds it.
+    fn position<P: AsRef<Path>>(&self, path: P) -> Option<usize> {
+        let path = storage::plain_path(path);
 
-impl Memo {
-    fn new<P: AsRef<Path>>(original_path: P) -> Self {
-        Self {
-            original_path: original_path.as_ref().to_path_buf(),
-            content_buffer: String::new(),
-            // String::new hash
-            content_hash: 3476900567878811119,
+        self.stash
+            .iter()
+            .position(|memo| storage::plain_path(&memo.original_path) == path)
+    }
+
+    // The index of every memo by the path `position` takes, for looking many of them up at once.
+    fn positions(&self) -> HashMap<PathBuf, usize> {
+        self.stash
+            .iter()
+            .enumerate()
+            .map(|(idx, memo)| (storage::plain_path(&memo.original_path), idx))
+            .collect()
+    }
+
+    // What a wiki link target may name, lowercased: a memo's name, its id or one of its aliases.
+    // An alias never shadows another memo's name or id.
+    fn link_targets(&self) -> HashMap<String, usize> {
+        let mut by_name = self
+            .stash
+            .iter()
+            .enumerate()
+            .map(|(idx, memo)| (memo.name().to_lowercase(), idx))
+            .collect::<HashMap<_, _>>();
+
+        for (idx, memo) in self.stash.iter().enumerate() {
+            if let Some(id) = meta::split(&memo.content_buffer).0.id() {
+                by_name.insert(id.to_lowercase(), idx);
+            }
+        }
+
+        for (idx, memo) in self.stash.iter().enumerate() {
+            for alias in meta::split(&memo.content_buffer).0.aliases() {
+                by_name.entry(alias.to_lowercase()).or_insert(idx);
+            }
         }
+
+        by_name
     }
 
-    fn with_content<P: AsRef<Path>>(original_path: P) -> Result<Self, Error> {
-        let mut memo = Memo::new(original_path);
+    // Every tag used in a memo.
+    fn tag_index(&self) -> BTreeSet<String> {
+        self.stash
+            .iter()
+            .flat_map(|memo| {
+                meta::split(&memo.content_buffer)
+                    .0
+                    .tags()
+                    .into_iter()
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
 
-        memo.refresh()?;
+    // Outgoing wiki-links of every memo as stash indices; targets match names case-insensitively,
+    // or a memo's `id`.
+    fn links(&self) -> Vec<Vec<usize>> {
+        let by_name = self.link_targets();
 
-        Ok(memo)
+        self.stash
+            .iter()
+            .enumerate()
+            .map(|(idx, memo)| {
+                let mut targets = vec![];
+
+                for target in meta::wiki_links(&memo.content_buffer) {
+                    if let Some(&to) = by_name.get(&target.to_lowercase())
+                        && to != idx
+                        && !targets.contains(&to)
+                    {
+                        targets.push(to);
+                    }
+                }
+
+                targets
+            })
+            .collect()
     }
 
-    fn read_latest_content(&self) -> Result<String, Error> {
-        fs::read_to_string(&self.original_path).map_err(|e| {
-            Error::with_cause(
-                format!(
-                    "A file '{}' reading failed",
-                    self.original_path.to_string_lossy()
-                ),
-                e.kind(),
-            )
-        })
+    fn rewrite(&mut self, idx: usize, content: String) -> Result<(), Error> {
+        let Some(memo) = self.stash.get(idx) else {
+            return Err(Error::new("Index out of bounds"));
+        };
+
+        memo.ensure_whole()?;
+        storage::write(&memo.original_path, &content)?;
+
+        self.refresh(idx)
     }
 
-    fn create_latest_hash(&self) -> Result<u64, Error> {
-        let mut hasher = DefaultHasher::new();
+    // Changes the memo in memory only, until `save` writes it.
+    fn stage(&mut self, idx: usize, content: String) -> Result<(), Error> {
+        let Some(memo) = self.stash.get_mut(idx) else {
+            return Err(Error::new("Index out of bounds"));
+        };
 
-        self.read_latest_content()?.hash(&mut hasher);
+        memo.ensure_whole()?;
+        memo.set_buffer(content);
+        memo.dirty = true;
 
-        Ok(hasher.finish())
+        Ok(())
     }
 
-    fn eq_origin(&self) -> bool {
-        Some(self.content_hash) == self.create_latest_hash().ok()
+    fn is_dirty(&self) -> bool {
+        self.stash.iter().any(|memo| memo.dirty)
     }
 
-    fn refresh(&mut self) -> Result<(), Error> {
-        if !self.eq_origin() {
-            self.content_buffer = self.read_latest_content()?;
-            self.content_hash = self.create_latest_hash()?;
+    // Writes every staged change, returning the indices of the memos written and the errors of
+    // the ones that were not. A memo that fails stays dirty and does not hold back the others.
+    fn save(&mut self) -> (Vec<usize>, Vec<Error>) {
+        let mut saved = vec![];
+        let mut errors = vec![];
+
+        for idx in 0..self.stash.len() {
+            if !self.stash[idx].dirty {
+                continue;
+            }
+
+            match self.rewrite(idx, self.stash[idx].content_buffer.clone()) {
+                Ok(()) => {
+                    self.stash[idx].dirty = false;
+                    saved.push(idx);
+                }
+                Err(e) => errors.push(e),
+            }
         }
 
-        Ok(())
+        (saved, errors)
     }
-}
 
-fn create_new_memo<S: AsRef<str>>(memo_name: S) -> Result<Memo, Error> {
-    let memo_name = format!("{}.md", memo_name.as_ref());
-    let new_memo_path = MEMO_LIST_PATH.join(&memo_name);
+    fn refresh(&mut self, idx: usize) -> Result<(), Error> {
+        match self.stash.get_mut(idx) {
+            Some(memo) => memo.refresh(),
+            None => Err(Error::new("Index out of bounds")),
+        }
+    }
 
-    fs::write(&new_memo_path, b"").map_err(|_| {
-        Error::with_cause(
-            format!("A memo '{memo_name}' generating failed"),
-            "the broken name",
-        )
-    })?;
+    // Brings the list in line with the memo files on disk, given the ones it does not have yet. A
+    // memo whose file has vanished is paired with a found file that is the same file (by inode,
+    // where there are inodes) or else has the same content, and is taken to have been renamed
+    // rather than dropped, so it keeps its state. Returns the renames, or None if nothing changed.
+    fn rescan(
+        &mut self,
+        found: Vec<(PathBuf, Option<String>)>,
+    ) -> Result<Option<Vec<(PathBuf, PathBuf)>>, Error> {
+        let mut gone = (0..self.stash.len())
+            .filter(|&idx| !self.stash[idx].current_path().exists())
+            .collect::<Vec<_>>();
 
-    let memo = Memo::new(new_memo_path);
+        if gone.is_empty() && found.is_empty() {
+            return Ok(None);
+        }
 
-    Ok(memo)
-}
+        let mut found = found
+            .into_iter()
+            .map(|(path, source)| {
+                let mut memo = Memo::with_content(path)?;
 
-fn delete_memo(memo: Memo) -> Result<(), Error> {
-    let original_path = &memo.original_path;
+                memo.source = source;
 
-    fs::remove_file(original_path).map_err(|e| {
-        Error::with_cause(
-            format!(
-                "A file '{}' cleanup failed",
-                original_path.to_string_lossy()
-            ),
-            e.kind(),
-        )
-    })?;
+                Ok(memo)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut renames = vec![];
+        let mut failed = None;
+        let checks: [fn(&Memo, &Memo) -> bool; 2] = [
+            |old, new| old.file_id.is_some() && old.file_id == new.file_id,
+            |old, new| old.content_hash == new.content_hash,
+        ];
 
-    Ok(())
-}
+        for same in checks {
+            gone.retain(|&idx| {
+                let old = &mut self.stash[idx];
+                let Some(pos) = found.iter().position(|new| same(old, new)) else {
+                    return true;
+                };
+                let new = found.remove(pos);
 
-fn fill_stash_with_local(stash: &mut Stash) -> Result<(), Error> {
-    let memos = MEMO_LIST_PATH
-        .read_dir()
-        .map_err(|e| Error::with_cause("Memo files reading failed", e.kind()))?;
+                renames.push((old.original_path.clone(), new.original_path.clone()));
+
+                old.original_path = new.original_path;
+                old.source = new.source;
+
+                if let Err(e) = old.refresh() {
+                    failed = Some(e);
+                }
 
-    for entry in memos {
-        match entry {
-            Ok(entry) => stash.push(Memo::with_content(entry.path())?),
-            Err(e) => Err(Error::with_cause("A memo file reading failed", e.kind()))?,
+                false
+            });
+        }
+
+        for idx in gone.into_iter().rev() {
+            self.stash.remove(idx);
+        }
+
+        self.stash.extend(found);
+        self.sort();
+
+        match failed {
+            Some(e) => Err(e),
+            None => Ok(Some(renames)),
         }
     }
 
-    Ok(())
-}
+    // Every memo to read again for changes on disk, leaving unsaved changes alone, as jobs for
+    // `reread_in_background`.
+    fn reread_jobs(&self) -> Vec<RereadJob> {
+        self.stash
+            .iter()
+            .filter(|memo| !memo.dirty)
+            .map(|memo| RereadJob {
+                path: memo.current_path(),
+                load_full: memo.load_full,
+                hash: memo.content_hash,
+            })
+            .collect()
+    }
 
-fn enable_tui() {
-    let _ = terminal::enable_raw_mode()
-        .and_then(|_| execute!(io::stdout(), DisableLineWrap, EnterAlternateScreen, Hide));
-}
+    // Takes in what a refresh worker read, except for memos changed in the list since.
+    fn apply_reread(&mut self, changed: Vec<Reread>) {
+        let positions = self.positions();
 
-fn disable_tui() {
-    let _ = terminal::disable_raw_mode()
-        .and_then(|_| execute!(io::stdout(), EnableLineWrap, LeaveAlternateScreen, Show));
-}
+        for reread in changed {
+            let Some(memo) = positions
+                .get(&storage::plain_path(&reread.path))
+                .and_then(|&idx| self.stash.get_mut(idx))
+            else {
+                continue;
+            };
 
-fn setup_tui() -> AppContainer {
-    enable_tui();
+            if memo.dirty || memo.content_hash != reread.old_hash {
+                continue;
+            }
 
-    let orders: Arc<RwLock<Vec<Order>>> = Arc::new(RwLock::new(vec![]));
+            memo.original_path = reread.path;
+            memo.set_buffer(reread.content);
+            memo.partial = reread.partial;
+            memo.content_hash = reread.hash;
+        }
+    }
 
-    let oc = orders.clone();
+    fn load_full(&mut self, idx: usize) -> Result<(), Error> {
+        match self.stash.get_mut(idx) {
+            Some(memo) => {
+                memo.load_full = true;
+                memo.refresh()
+            }
+            None => Err(Error::new("Index out of bounds")),
+        }
+    }
 
-    thread::spawn(move || {
-        let orders = oc;
-        let mut pool: Vec<Key> = vec![];
-        let mut maps = HashMap::new();
+    // Runs `editor.format` over the memo as it is on disk.
+    fn format(&self, idx: usize) -> Result<(), Error> {
+        let (Some(line), Some(memo)) = (&*FORMAT_COMMAND, self.stash.get(idx)) else {
+            return Ok(());
+        };
+        let content = memo.read_latest_content()?;
+        let formatted = format_text(line, &content)?;
 
-        maps.insert(Keymap::new("ZZ").unwrap(), Order::Exit);
+        if formatted != content {
+            storage::write(memo.current_path(), &formatted)?;
+        }
 
-        let keys = maps.keys().map(|k| k.as_vec()).collect::<Vec<_>>();
+        Ok(())
+    }
 
-        'o: loop {
-            if let Ok(Event::Key(ev)) = event::read()
-                && let Some(key) = translate_to_key(ev)
-            {
-                pool.push(key);
-            }
+    // Opens the memos in one editor, at line `at` if given. The editor gets a compressed memo as a
+    // plain temp file, which is compressed back after.
+    fn edit(&self, idxs: &[usize], at: Option<usize>) -> Result<Edited, Error> {
+        let mut opened = vec![];
 
-            let keymap = Keymap::from(pool.clone());
+        for &idx in idxs {
+            let Some(memo) = self.stash.get(idx) else {
+                return Err(Error::new("Index out of bounds"));
+            };
+            let path = memo.current_path();
+            let original = memo.read_latest_content()?;
 
-            if let Some(matched) = maps.get(&keymap) {
-                orders.write().unwrap().push(*matched);
+            history::record(&path, &original)?;
 
-                pool.clear();
+            let file = match storage::is_compressed(&path) {
+                true => Some(TempFile::write(
+                    format!("{}.{MEMO_EXTENSION}", memo.name()),
+                    &original,
+                )?),
+                false => None,
+            };
 
-                continue;
-            }
+            opened.push((path, original, file));
+        }
+
+        let edited = Edited::from(edit_files(
+            &opened
+                .iter()
+                .map(|(path, _, file)| file.as_ref().map_or(path.as_path(), TempFile::path))
+                .collect::<Vec<_>>(),
+            at,
+        )?);
+        let read = |path: &Path| {
+            storage::read(path).map_err(|e| {
+                Error::with_cause(
+                    format!("A file '{}' reading failed", path.to_string_lossy()),
+                    e.kind(),
+                )
+            })
+        };
 
-            for key in keys.iter().filter(|k| k.len() > pool.len()) {
-                if key[..pool.len()] == pool {
-                    continue 'o;
+        for (path, original, file) in &opened {
+            match file {
+                Some(_) if edited == Edited::Discarded => {}
+                Some(file) => {
+                    let content = read(file.path())?;
+
+                    if content != *original {
+                        storage::write(path, &content)?;
+                    }
                 }
-            }
+                None => {
+                    if edited == Edited::Discarded && read(path)? != *original {
+                        storage::write(path, original)?;
+                    }
 
-            pool.clear();
+                    history::record_current(storage::settle(path)?)?;
+                }
+            }
         }
-    });
 
-    AppContainer::new(orders)
-}
-
-fn translate_to_key(key: KeyEvent) -> Option<Key> {
-    use crossterm::event::{KeyCode, KeyModifiers};
-
-    let mut key_str = match key.code {
-        KeyCode::Backspace => "BS",
-        KeyCode::Tab => "TAB",
-        KeyCode::Enter => "ENTER",
-        KeyCode::Esc => "ESC",
-        KeyCode::Char(' ') => "SPACE",
-        KeyCode::Char('!') => "!",
-        KeyCode::Char('"') => "\"",
-        KeyCode::Char('#') => "#",
-        KeyCode::Char('$') => "$",
-        KeyCode::Char('%') => "%",
-        KeyCode::Char('&') => "&",
-        KeyCode::Char('\'') => "'",
-        KeyCode::Char('(') => "(",
-        KeyCode::Char(')') => ")",
-        KeyCode::Char('*') => "*",
-        KeyCode::Char('+') => "+",
-        KeyCode::Char(',') => ",",
-        KeyCode::Char('-') => "-",
-        KeyCode::Char('.') => ".",
-        KeyCode::Char('/') => "/",
-        KeyCode::Char('0') => "0",
-        KeyCode::Char('1') => "1",
-        KeyCode::Char('2') => "2",
-        KeyCode::Char('3') => "3",
-        KeyCode::Char('4') => "4",
-        KeyCode::Char('5') => "5",
-        KeyCode::Char('6') => "6",
-        KeyCode::Char('7') => "7",
-        KeyCode::Char('8') => "8",
-        KeyCode::Char('9') => "9",
-        KeyCode::Char(':') => ":",
-        KeyCode::Char(';') => ";",
-        KeyCode::Char('<') => "lt",
-        KeyCode::Char('=') => "=",
-        KeyCode::Char('>') => ">",
-        KeyCode::Char('?') => "?",
-        KeyCode::Char('@') => "@",
-        KeyCode::Char('a') => "a",
-        KeyCode::Char('b') => "b",
-        KeyCode::Char('c') => "c",
-        KeyCode::Char('d') => "d",
-        KeyCode::Char('e') => "e",
-        KeyCode::Char('f') => "f",
-        KeyCode::Char('g') => "g",
-        KeyCode::Char('h') => "h",
-        KeyCode::Char('i') => "i",
-        KeyCode::Char('j') => "j",
-        KeyCode::Char('k') => "k",
-        KeyCode::Char('l') => "l",
-        KeyCode::Char('m') => "m",
-        KeyCode::Char('n') => "n",
-        KeyCode::Char('o') => "o",
-        KeyCode::Char('p') => "p",
-        KeyCode::Char('q') => "q",
-        KeyCode::Char('r') => "r",
-        KeyCode::Char('s') => "s",
-        KeyCode::Char('t') => "t",
-        KeyCode::Char('u') => "u",
-        KeyCode::Char('v') => "v",
-        KeyCode::Char('w') => "w",
-        KeyCode::Char('x') => "x",
-        KeyCode::Char('y') => "y",
-        KeyCode::Char('z') => "z",
-        KeyCode::Char('A') => "A",
-        KeyCode::Char('B') => "B",
-        KeyCode::Char('C') => "C",
-        KeyCode::Char('D') => "D",
-        KeyCode::Char('E') => "E",
-        KeyCode::Char('F') => "F",
-        KeyCode::Char('G') => "G",
-        KeyCode::Char('H') => "H",
-        KeyCode::Char('I') => "I",
-        KeyCode::Char('J') => "J",
-        KeyCode::Char('K') => "K",
-        KeyCode::Char('L') => "L",
-        KeyCode::Char('M') => "M",
-        KeyCode::Char('N') => "N",
-        KeyCode::Char('O') => "O",
-        KeyCode::Char('P') => "P",
-        KeyCode::Char('Q') => "Q",
-        KeyCode::Char('R') => "R",
-        KeyCode::Char('S') => "S",
-        KeyCode::Char('T') => "T",
-        KeyCode::Char('U') => "U",
-        KeyCode::Char('V') => "V",
-        KeyCode::Char('W') => "W",
-        KeyCode::Char('X') => "X",
-        KeyCode::Char('Y') => "Y",
-        KeyCode::Char('Z') => "Z",
-        KeyCode::Char('[') => "[",
-        KeyCode::Char('\\') => "\\",
-        KeyCode::Char(']') => "]",
-        KeyCode::Char('^') => "^",
-        KeyCode::Char('_') => "_",
-        KeyCode::Char('`') => "`",
-        KeyCode::Char('{') => "{",
-        KeyCode::Char('|') => "|",
-        KeyCode::Char('}') => "}",
-        KeyCode::Char('~') => "~",
-        KeyCode::Delete => "DEL",
-        _ => return None,
-    }
-    .to_string();
-
-    let is_big_alpha = key_str.len() == 1 && matches!(key_str.chars().next(), Some('A'..='Z'));
-
-    if !is_big_alpha && key.modifiers.contains(KeyModifiers::SHIFT) {
-        key_str = format!("s-{key_str}");
-    } else if key.modifiers.contains(KeyModifiers::ALT) {
-        key_str = format!("a-{key_str}");
-    } else if key.modifiers.contains(KeyModifiers::CONTROL) {
-        key_str = format!("c-{key_str}");
-    }
-
-    let key_str = if key_str.len() > 1 {
-        format!("<{key_str}>")
-    } else {
-        key_str
+        Ok(edited)
+    }
+}
+
+// Opens the editor in a new tmux or WezTerm pane and returns without waiting for it. The marker
+// file is removed by the pane once the editor exits, however the pane goes away.
+fn edit_file_in_pane(
+    path: &Path,
+    at: Option<usize>,
+    open_in: OpenIn,
+    marker: &Path,
+) -> Result<(), Error> {
+    let line = format!(
+        "trap 'rm -f {}' EXIT HUP TERM; {} {}{}",
+        shell_quote(marker.to_string_lossy()).replace('\'', "'\\''"),
+        *EDITOR_COMMAND,
+        at.map(|line| format!("+{line} ")).unwrap_or_default(),
+        shell_quote(path.to_string_lossy())
+    );
+    let mut cmd = match open_in {
+        OpenIn::TmuxSplit | OpenIn::TmuxWindow => Command::new("tmux"),
+        _ => Command::new("wezterm"),
+    };
+
+    match open_in {
+        OpenIn::TmuxSplit => cmd.args(["split-window", "-h"]),
+        OpenIn::TmuxWindow => cmd.args(["new-window"]),
+        OpenIn::WeztermSplit => cmd.args(["cli", "split-pane", "--right", "--"]),
+        OpenIn::WeztermTab => cmd.args(["cli", "spawn", "--"]),
+        OpenIn::Terminal => unreachable!(),
     };
 
-    Key::new(&key_str).ok()
+    run_external(cmd.args(["sh", "-c", &line]).stdout(Stdio::null()))
 }
 
-struct AppContainer {
-    orders: Arc<RwLock<Vec<Order>>>,
+// How an editor session ended, going by the exit status of the editor.
+#[derive(PartialEq, Eq)]
+enum Edited {
+    Saved,
+    // The exit code is one of `[editor] discard_codes`.
+    Discarded,
+    // "exited with code 2", "was killed by signal 9"
+    Failed(String),
 }
 
-impl AppContainer {
-    fn new(orders: Arc<RwLock<Vec<Order>>>) -> Self {
-        Self { orders }
+impl From<ExitStatus> for Edited {
+    fn from(status: ExitStatus) -> Self {
+        if status.success() {
+            return Edited::Saved;
+        }
+
+        match status.code() {
+            Some(code) if DISCARD_CODES.contains(&code) => Edited::Discarded,
+            Some(code) => Edited::Failed(format!("exited with code {code}")),
+            None => {
+                // Only unix has an editor end without a code, when a signal kills it.
+                #[cfg(unix)]
+                if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+                    return Edited::Failed(format!("was killed by signal {signal}"));
+                }
+
+                Edited::Failed("stopped unexpectedly".to_string())
+            }
+        }
     }
 }
 
-#[derive(Clone, Copy)]
-enum Order {
-    Exit,
+fn edit_file<P: AsRef<Path>>(path: P) -> Result<ExitStatus, Error> {
+    edit_files(&[path], None)
+}
+
+// All the files go to one editor, e.g. as vim buffers. A line to start at is passed as `+<line>`,
+// which vim, nano, emacs and most others understand.
+fn edit_files<P: AsRef<Path>>(paths: &[P], at: Option<usize>) -> Result<ExitStatus, Error> {
+    let mut words = EDITOR_COMMAND.split_whitespace();
+    let program = words.next().unwrap_or(EDITOR_FALLBACK);
+    let res = Command::new(program)
+        .args(words)
+        .args(at.map(|line| format!("+{line}")))
+        .args(paths.iter().map(AsRef::as_ref))
+        .stderr(Stdio::null())
+        .status();
+
+    match res {
+        Ok(status) => Ok(status),
+        Err(e) => Err(Error::with_cause(
+            format!("An editor '{program}' executing failed"),
+            e.kind(),
+        )),
+    }
+}
+
+struct Memo {
+    original_path: PathBuf,
+    content_buffer: String,
+    content_hash: u64,
+    // Whether content_buffer is only the head of a large memo.
+    partial: bool,
+    // Set by `:load` to read a large memo in full anyway.
+    load_full: bool,
+    // The badge of the source folder the memo is listed from, if not the stash directory.
+    source: Option<String>,
+    // Follows the file through renames made outside the TUI.
+    file_id: Option<(u64, u64)>,
+    // Whether content_buffer holds a change made in the TUI that is not written yet.
+    dirty: bool,
+    // `hidden: true` and `favorite: true` in the front matter, read along with content_buffer
+    // rather than each time the list is filtered.
+    hidden: bool,
+    favorite: bool,
+}
+
+impl Memo {
+    fn new<P: AsRef<Path>>(original_path: P) -> Self {
+        Self {
+            original_path: original_path.as_ref().to_path_buf(),
+            content_buffer: String::new(),
+            // String::new hash
+            content_hash: 3476900567878811119,
+            partial: false,
+            load_full: false,
+            source: None,
+            file_id: None,
+            dirty: false,
+            hidden: false,
+            favorite: false,
+        }
+    }
+
+    fn with_content<P: AsRef<Path>>(original_path: P) -> Result<Self, Error> {
+        let mut memo = Memo::new(original_path);
+
+        memo.refresh()?;
+
+        Ok(memo)
+    }
+
+    // A memo with only the head of its file read, which is enough for its front matter.
+    fn with_head<P: AsRef<Path>>(original_path: P) -> Result<Self, Error> {
+        let mut memo = Memo::new(original_path);
+        let head = storage::read_head(&memo.original_path, PREVIEW_HEAD).map_err(|e| {
+            Error::with_cause(
+                format!(
+                    "A file '{}' reading failed",
+                    memo.original_path.to_string_lossy()
+                ),
+                e.kind(),
+            )
+        })?;
+
+        memo.set_buffer(head);
+        memo.partial = true;
+
+        Ok(memo)
+    }
+
+    fn set_buffer(&mut self, content: String) {
+        let (front, _) = meta::split(&content);
+
+        self.hidden = front.get("hidden") == Some("true");
+        self.favorite = front.get("favorite") == Some("true");
+        self.content_buffer = content;
+    }
+
+    fn name(&self) -> String {
+        memo_name_of(&self.original_path)
+    }
+
+    // Where the memo is now, as a save may have moved it between the plain and compressed forms.
+    fn current_path(&self) -> PathBuf {
+        let other = storage::other_path(&self.original_path);
+
+        if !self.original_path.exists() && other.exists() {
+            other
+        } else {
+            self.original_path.clone()
+        }
+    }
+
+    // A content made from the head only would cut the rest off.
+    fn ensure_whole(&self) -> Result<(), Error> {
+        if self.partial {
+            return Err(Error::with_cause(
+                format!("A memo '{}' rewriting failed", self.name()),
+                "it is only partly loaded, run :load first",
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Left out of the list unless hidden memos are shown: dot-prefixed memos and those with
+    // `hidden: true` in the front matter, such as templates.
+    fn is_hidden(&self) -> bool {
+        self.hidden || self.name().starts_with('.')
+    }
+
+    // Set by `favorite: true` in the front matter.
+    fn is_favorite(&self) -> bool {
+        self.favorite
+    }
+
+    fn has_open_tasks(&self) -> bool {
+        meta::split(&self.content_buffer)
+            .1
+            .lines()
+            .map(str::trim_start)
+            .any(|line| line.starts_with("- [ ]") || line.starts_with("* [ ]"))
+    }
+
+    fn matches<S: AsRef<str>>(&self, query: S) -> bool {
+        matches_text(&self.name(), &self.content_buffer, query.as_ref())
+    }
+
+    fn matches_all(&self, query: &str) -> bool {
+        matches_query(&self.name(), &self.content_buffer, query)
+    }
+
+    fn read_latest_content(&self) -> Result<String, Error> {
+        storage::read(self.current_path()).map_err(|e| {
+            Error::with_cause(
+                format!(
+                    "A file '{}' reading failed",
+                    self.original_path.to_string_lossy()
+                ),
+                e.kind(),
+            )
+        })
+    }
+
+    fn read_buffer(&self) -> Result<(String, bool), Error> {
+        read_buffer(&self.current_path(), self.load_full)
+    }
+
+    fn create_latest_hash(&self) -> Result<u64, Error> {
+        let (content, partial) = self.read_buffer()?;
+
+        Ok(buffer_hash(&self.current_path(), &content, partial))
+    }
+
+    fn eq_origin(&self) -> bool {
+        Some(self.content_hash) == self.create_latest_hash().ok()
+    }
+
+    fn refresh(&mut self) -> Result<(), Error> {
+        self.original_path = self.current_path();
+        self.file_id = file_id(&self.original_path);
+
+        if !self.eq_origin() {
+            let (content, partial) = self.read_buffer()?;
+
+            self.set_buffer(content);
+            self.partial = partial;
+            self.content_hash = self.create_latest_hash()?;
+        }
+
+        Ok(())
+    }
+}
+
+// What is kept in memory of a memo: all of it, or only the head of a large one that has not been
+// loaded in full.
+fn read_buffer(path: &Path, load_full: bool) -> Result<(String, bool), Error> {
+    let failed = |e: io::Error| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    };
+
+    if !load_full && storage::size(path).map_err(failed)? > LARGE_MEMO_SIZE {
+        return storage::read_head(path, PREVIEW_HEAD)
+            .map(|head| (head, true))
+            .map_err(failed);
+    }
+
+    storage::read(path)
+        .map(|content| (content, false))
+        .map_err(failed)
+}
+
+fn buffer_hash(path: &Path, content: &str, partial: bool) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    content.hash(&mut hasher);
+
+    // The head alone misses changes further in.
+    if partial {
+        let meta = fs::metadata(path).ok();
+
+        meta.as_ref().map(|meta| meta.len()).hash(&mut hasher);
+        meta.and_then(|meta| meta.modified().ok()).hash(&mut hasher);
+    }
+
+    hasher.finish()
+}
+
+// A memo as the list holds it, to be compared with its file off the UI thread.
+struct RereadJob {
+    path: PathBuf,
+    load_full: bool,
+    hash: u64,
+}
+
+// A memo whose file changed, read again by a refresh worker.
+#[derive(Clone, PartialEq)]
+struct Reread {
+    path: PathBuf,
+    // The hash the job was given, which tells whether the memo changed in the list meanwhile.
+    old_hash: u64,
+    content: String,
+    partial: bool,
+    hash: u64,
+}
+
+// Hands what `reread_all` finds back as an Order::RereadDone, so thousands of memos never hold up
+// the UI.
+fn reread_in_background(jobs: Vec<RereadJob>, orders: Arc<RwLock<Vec<Order>>>) {
+    thread::spawn(move || {
+        let (changed, error) = reread_all(&jobs);
+
+        orders
+            .write()
+            .unwrap()
+            .push(Order::RereadDone { changed, error });
+    });
+}
+
+// Rereads the memos spread over a few worker threads, each file read and hashed once. Returns the
+// ones that changed and the first error met.
+fn reread_all(jobs: &[RereadJob]) -> (Vec<Reread>, Option<String>) {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get().min(8));
+    let chunk = jobs.len().div_ceil(workers).max(1);
+    let results = thread::scope(|scope| {
+        let handles = jobs
+            .chunks(chunk)
+            .map(|jobs| scope.spawn(move || jobs.iter().filter_map(reread).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect::<Vec<_>>()
+    });
+    let mut changed = vec![];
+    let mut error = None;
+
+    for result in results {
+        match result {
+            Ok(memo) => changed.push(memo),
+            Err(e) => {
+                error.get_or_insert(e.to_string());
+            }
+        }
+    }
+
+    (changed, error)
+}
+
+fn reread(job: &RereadJob) -> Option<Result<Reread, Error>> {
+    let (content, partial) = match read_buffer(&job.path, job.load_full) {
+        Ok(buffer) => buffer,
+        Err(e) => return Some(Err(e)),
+    };
+    let hash = buffer_hash(&job.path, &content, partial);
+
+    (hash != job.hash).then(|| {
+        Ok(Reread {
+            path: job.path.clone(),
+            old_hash: job.hash,
+            content,
+            partial,
+            hash,
+        })
+    })
+}
+
+// A file's device and inode numbers, which a rename keeps.
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(path).ok().map(|meta| (meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+const MEMO_EXTENSION: &str = "md";
+
+// Memos larger than this are only read up to PREVIEW_HEAD bytes until `:load` asks for all.
+const LARGE_MEMO_SIZE: u64 = 4 * 1024 * 1024;
+
+const PREVIEW_HEAD: usize = 64 * 1024;
+
+// How long changes made in the TUI are held back for more to come before they are written.
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(1);
+
+// The longest the autosave waits before trying again after failing to write.
+const MAX_AUTOSAVE_DELAY: Duration = Duration::from_secs(60);
+
+// How often the TUI looks for memo files changed by other programs while it is idle.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+// How long a cancelled helper program has to stop on its own before it is killed.
+const CANCEL_GRACE: Duration = Duration::from_secs(3);
+
+// How often the progress of a long operation is drawn again.
+const TASK_REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+// Most filesystems cap a file name at 255 bytes, and the name gets ".md" appended.
+const MAX_MEMO_NAME_LEN: usize = 255 - MEMO_EXTENSION.len() - 1;
+
+const RESERVED_CHARS: [char; 9] = ['/', '\\', '<', '>', ':', '"', '|', '?', '*'];
+
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name);
+
+    name == "." || name == ".." || RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem))
+}
+
+fn validate_memo_name<S: AsRef<str>>(memo_name: S) -> Result<(), Error> {
+    let memo_name = memo_name.as_ref();
+    let invalid = |cause: &str| {
+        Err(Error::with_cause(
+            format!("A memo name '{memo_name}' is invalid"),
+            cause,
+        ))
+    };
+
+    if memo_name.trim().is_empty() {
+        return Err(Error::new("A memo name is empty"));
+    }
+
+    if memo_name.contains(['/', '\\']) {
+        return invalid("contains a path separator");
+    }
+
+    if memo_name.contains(char::is_control) {
+        return invalid("contains a control character");
+    }
+
+    if let Some(c) = memo_name.chars().find(|c| RESERVED_CHARS.contains(c)) {
+        return invalid(&format!("contains a reserved character '{c}'"));
+    }
+
+    if is_reserved_name(memo_name) {
+        return invalid("is a reserved name");
+    }
+
+    if memo_name.ends_with(['.', ' ']) {
+        return invalid("ends with a dot or a space");
+    }
+
+    if memo_name.len() > MAX_MEMO_NAME_LEN {
+        return invalid(&format!("is longer than {MAX_MEMO_NAME_LEN} bytes"));
+    }
+
+    Ok(())
+}
+
+fn sanitize_memo_name<S: AsRef<str>>(memo_name: S) -> String {
+    let mut sanitized = String::new();
+
+    for c in memo_name.as_ref().trim().chars() {
+        if c.is_control() || c.is_whitespace() || RESERVED_CHARS.contains(&c) {
+            if !sanitized.ends_with('-') {
+                sanitized.push('-');
+            }
+        } else {
+            sanitized.push(c);
+        }
+    }
+
+    let mut sanitized = sanitized
+        .trim_matches(|c| c == '-' || c == '.' || c == ' ')
+        .to_string();
+
+    while sanitized.len() > MAX_MEMO_NAME_LEN {
+        sanitized.pop();
+    }
+
+    let mut sanitized = sanitized.trim_end_matches(['.', ' ']).to_string();
+
+    if sanitized.is_empty() {
+        sanitized = String::from("memo");
+    }
+
+    if is_reserved_name(&sanitized) {
+        sanitized.push('_');
+    }
+
+    sanitized
+}
+
+// The compressed file when the memo is stored that way, the plain one otherwise.
+fn memo_path<S: AsRef<str>>(memo_name: S) -> PathBuf {
+    let path = MEMO_LIST_PATH.join(format!("{}.{MEMO_EXTENSION}", memo_name.as_ref()));
+    let compressed = storage::compressed_path(&path);
+
+    if compressed.exists() {
+        compressed
+    } else {
+        path
+    }
+}
+
+fn matches_text(name: &str, content: &str, query: &str) -> bool {
+    let query = query.to_lowercase();
+
+    name.to_lowercase().contains(&query)
+        || meta::split(content)
+            .0
+            .aliases()
+            .iter()
+            .any(|alias| alias.to_lowercase().contains(&query))
+        || content.to_lowercase().contains(&query)
+}
+
+// A query of terms that all have to match: `tag:<name>` one of the memo's tags, anything else
+// as in `matches_text`.
+fn matches_query(name: &str, content: &str, query: &str) -> bool {
+    let (front, _) = meta::split(content);
+    let tags = front.tags();
+
+    query
+        .split_whitespace()
+        .all(|term| match term.strip_prefix("tag:") {
+            Some(tag) => tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            None => matches_text(name, content, term),
+        })
+}
+
+fn memo_name_of<P: AsRef<Path>>(path: P) -> String {
+    storage::plain_path(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+// `name` with the extensions of `like`, so a moved memo keeps its storage form.
+fn memo_file_name<P: AsRef<Path>>(name: &str, like: P) -> String {
+    match storage::is_compressed(like) {
+        true => format!("{name}.{MEMO_EXTENSION}.{}", storage::EXTENSION),
+        false => format!("{name}.{MEMO_EXTENSION}"),
+    }
+}
+
+fn numbered_memo_name<S: AsRef<str>>(memo_name: S) -> String {
+    let memo_name = memo_name.as_ref();
+
+    (2..)
+        .map(|n| {
+            let suffix = format!("-{n}");
+            let mut base = memo_name.to_string();
+
+            // A name already at the limit gives up its end to the number rather than overflow.
+            while base.len() + suffix.len() > MAX_MEMO_NAME_LEN {
+                base.pop();
+            }
+
+            format!("{base}{suffix}")
+        })
+        .find(|name| !memo_path(name).exists())
+        .unwrap()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OnCollision {
+    Refuse,
+    Overwrite,
+    Numbered,
+}
+
+fn create_new_memo<S: AsRef<str>>(memo_name: S, on_collision: OnCollision) -> Result<Memo, Error> {
+    validate_memo_name(&memo_name)?;
+
+    let memo_name = match on_collision {
+        OnCollision::Numbered if memo_path(&memo_name).exists() => numbered_memo_name(&memo_name),
+        _ => memo_name.as_ref().to_string(),
+    };
+    let new_memo_path = memo_path(&memo_name);
+    let content = meta::with_id("")?;
+
+    let res = private_file_options()
+        .write(true)
+        .truncate(true)
+        .create(true)
+        .create_new(on_collision != OnCollision::Overwrite)
+        .open(&new_memo_path)
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+
+    match res {
+        Ok(_) => Ok(Memo::new(new_memo_path)),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(Error::with_cause(
+            format!("A memo '{memo_name}' generating failed"),
+            "the memo already exists",
+        )),
+        Err(e) => Err(Error::with_cause(
+            format!("A memo '{memo_name}' generating failed"),
+            e.kind(),
+        )),
+    }
+}
+
+fn read_scratch() -> Result<String, Error> {
+    match fs::read_to_string(&*SCRATCH_PATH) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(Error::with_cause(
+            format!("A file '{}' reading failed", SCRATCH_PATH.to_string_lossy()),
+            e.kind(),
+        )),
+    }
+}
+
+fn clear_scratch() -> Result<(), Error> {
+    match fs::remove_file(&*SCRATCH_PATH) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::with_cause(
+            format!("A file '{}' cleanup failed", SCRATCH_PATH.to_string_lossy()),
+            e.kind(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn delete_memo(memo: Memo, secure: bool) -> Result<(), Error> {
+    let original_path = &memo.original_path;
+
+    if secure {
+        return erase_file(original_path);
+    }
+
+    fs::remove_file(original_path).map_err(|e| {
+        Error::with_cause(
+            format!(
+                "A file '{}' cleanup failed",
+                original_path.to_string_lossy()
+            ),
+            e.kind(),
+        )
+    })?;
+
+    Ok(())
+}
+
+// Overwrites the file with zeros and flushes it to the disk before unlinking, so the content
+// does not linger in the freed blocks of filesystems that write in place. A linked memo only loses
+// its link: what it leads to belongs elsewhere and is never erased through it. The versions kept of
+// an erased memo go with it, as does what the storage backend holds of it.
+fn erase_file<P: AsRef<Path>>(path: P) -> Result<(), Error> {
+    let path = path.as_ref();
+    // Read while the file is still there, for the versions kept by it.
+    let id = stored_id(path);
+    let failed = |e: io::Error| {
+        Error::with_cause(
+            format!("A file '{}' erasing failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    };
+    let is_link = |path: &Path| {
+        path.symlink_metadata()
+            .is_ok_and(|meta| meta.file_type().is_symlink())
+    };
+
+    if is_link(path) {
+        fs::remove_file(path).map_err(failed)?;
+
+        return history::forget(path, id.as_deref()).and_then(|_| storage::erased(path));
+    }
+
+    if path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(&*MEMO_LIST_PATH) && *dir != *MEMO_LIST_PATH)
+        .any(is_link)
+    {
+        return Err(Error::with_cause(
+            format!("A file '{}' erasing failed", path.to_string_lossy()),
+            "it lies in a linked directory, whose files are left alone",
+        ));
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(failed)?;
+    let mut left = file.metadata().map_err(failed)?.len();
+    let zeros = [0u8; 8192];
+
+    while left > 0 {
+        let n = left.min(zeros.len() as u64) as usize;
+
+        file.write_all(&zeros[..n]).map_err(failed)?;
+        left -= n as u64;
+    }
+
+    file.sync_all().map_err(failed)?;
+    file.set_len(0).map_err(failed)?;
+    drop(file);
+
+    fs::remove_file(path).map_err(failed)?;
+
+    history::forget(path, id.as_deref()).and_then(|_| storage::erased(path))
+}
+
+// Saves a quick note as a new memo, named after its first line unless a name is given.
+fn capture_memo(memo_name: Option<&str>, text: &str) -> Result<PathBuf, Error> {
+    let memo_name = match memo_name {
+        Some(memo_name) => memo_name.to_string(),
+        None => {
+            let first_line = text.lines().find(|line| !line.trim().is_empty());
+            let title = first_line
+                .unwrap_or_default()
+                .trim_start_matches('#')
+                .chars()
+                .take(40)
+                .collect::<String>();
+
+            sanitize_memo_name(title)
+        }
+    };
+
+    let memo = create_new_memo(memo_name, OnCollision::Numbered)?;
+    let path = memo.original_path;
+
+    let text = if text.ends_with('\n') || text.is_empty() {
+        text.to_string()
+    } else {
+        format!("{text}\n")
+    };
+
+    storage::write(&path, &meta::with_id(&text)?)
+}
+
+// The `id` in the front matter of the memo at `path`, if it can be read and has one.
+fn stored_id<P: AsRef<Path>>(path: P) -> Option<String> {
+    let content = storage::read(path).ok()?;
+
+    meta::split(&content).0.id().map(String::from)
+}
+
+// The memo's `id`, first giving it one in its front matter if it has none.
+fn memo_id(path: &Path) -> Result<String, Error> {
+    let content = storage::read(path).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+    let (mut front, body) = meta::split(&content);
+
+    if let Some(id) = front.id() {
+        return Ok(id.to_string());
+    }
+
+    let id = meta::new_id()?;
+
+    front.set("id", meta::Value::Text(id.clone()));
+    storage::write(path, &format!("{}{body}", front.render()))?;
+
+    Ok(id)
+}
+
+// Adds text to the end of a memo, a blank line apart from what is there; creates it if missing.
+fn append_memo(memo_name: &str, text: &str) -> Result<PathBuf, Error> {
+    validate_memo_name(memo_name)?;
+
+    let path = memo_path(memo_name);
+    let newline = if text.ends_with('\n') { "" } else { "\n" };
+    let existing = match storage::read(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return storage::write(&path, &meta::with_id(&format!("{text}{newline}"))?);
+        }
+        Err(e) => {
+            return Err(Error::with_cause(
+                format!("A file '{}' reading failed", path.to_string_lossy()),
+                e.kind(),
+            ));
+        }
+    };
+
+    let separator = match existing.as_str() {
+        "" => "",
+        _ if existing.ends_with("\n\n") => "",
+        _ if existing.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+
+    storage::append(&path, &format!("{separator}{text}{newline}"))
+}
+
+fn move_file<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<(), Error> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    let desc = || {
+        format!(
+            "A file '{}' moving to '{}' failed",
+            from.to_string_lossy(),
+            to.to_string_lossy()
+        )
+    };
+
+    if to.exists() {
+        return Err(Error::with_cause(desc(), "the destination already exists"));
+    }
+
+    fs::rename(from, to).map_err(|e| Error::with_cause(desc(), e.kind()))?;
+
+    // The memo is moved either way; a history left behind only loses its way back to it.
+    if let Err(e) = history::moved(from, to) {
+        log_err(&e);
+    }
+
+    storage::moved(from, to);
+
+    Ok(())
+}
+
+// Trashed memos are prefixed with the deletion time down to the nanosecond, which keeps
+// same-named memos apart even when they are deleted within the same second.
+fn trash_path<P: AsRef<Path>>(memo_path: P) -> PathBuf {
+    let deleted_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let name = format!(
+        "{}.{:09}_{}",
+        deleted_at.as_secs(),
+        deleted_at.subsec_nanos(),
+        memo_name_of(&memo_path)
+    );
+
+    TRASH_PATH.join(memo_file_name(&name, memo_path))
+}
+
+// When a file went to the trash, going by the time `trash_path` puts in front of its name. Files
+// trashed before the nanoseconds were added carry only the seconds.
+fn trashed_at<P: AsRef<Path>>(path: P) -> Option<SystemTime> {
+    let name = path.as_ref().file_name()?.to_string_lossy().to_string();
+    let (time, _) = name.split_once('_')?;
+    let (secs, nanos) = time.split_once('.').unwrap_or((time, "0"));
+
+    Some(UNIX_EPOCH + Duration::new(secs.parse().ok()?, nanos.parse().ok()?))
+}
+
+// Files in the trash for longer than `days`, or all of them without a limit.
+fn expired_trash(days: Option<u64>) -> Result<Vec<PathBuf>, Error> {
+    // A limit further back than the clock goes saturates, leaving nothing old enough.
+    let since = days.map(|days| {
+        let limit = Duration::from_secs(days.saturating_mul(24 * 60 * 60));
+
+        SystemTime::now().checked_sub(limit).unwrap_or(UNIX_EPOCH)
+    });
+    let mut expired = vec![];
+
+    for entry in TRASH_PATH
+        .read_dir()
+        .map_err(|e| Error::with_cause("Trash reading failed", e.kind()))?
+    {
+        let path = entry
+            .map_err(|e| Error::with_cause("Trash reading failed", e.kind()))?
+            .path();
+
+        if since.is_none_or(|since| trashed_at(&path).is_some_and(|at| at < since)) {
+            expired.push(path);
+        }
+    }
+
+    expired.sort();
+
+    Ok(expired)
+}
+
+// Deletes what has been in the trash for longer than `delete.purge_after_days`.
+fn purge_trash(config: &config::DeleteConfig) -> Result<(), Error> {
+    let Some(days) = config.purge_after_days else {
+        return Ok(());
+    };
+
+    for path in expired_trash(Some(days))? {
+        delete_memo(Memo::new(path), config.secure)?;
+    }
+
+    Ok(())
+}
+
+fn trash_memo(memo: &Memo) -> Result<PathBuf, Error> {
+    let trash_path = trash_path(&memo.original_path);
+
+    move_file(&memo.original_path, &trash_path)?;
+
+    Ok(trash_path)
+}
+
+fn archive_memo(memo: &Memo) -> Result<PathBuf, Error> {
+    let archive_path = ARCHIVE_PATH.join(memo_file_name(&memo.name(), &memo.original_path));
+
+    move_file(&memo.original_path, &archive_path)?;
+
+    Ok(archive_path)
+}
+
+fn rename_memo<S: AsRef<str>>(memo: &Memo, new_name: S) -> Result<PathBuf, Error> {
+    validate_memo_name(&new_name)?;
+
+    // The destination may exist in the other storage form, which move_file does not see.
+    if memo_path(&new_name).exists() {
+        return Err(Error::with_cause(
+            format!("A memo '{}' renaming failed", memo.name()),
+            format!("a memo '{}' already exists", new_name.as_ref()),
+        ));
+    }
+
+    // A memo in a linked directory is renamed where it is.
+    let new_path = memo
+        .original_path
+        .with_file_name(memo_file_name(new_name.as_ref(), &memo.original_path));
+
+    move_file(&memo.original_path, &new_path)?;
+
+    Ok(new_path)
+}
+
+fn sha256_hex<B: AsRef<[u8]>>(bytes: B) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(bytes.as_ref())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+// Reads a line from the terminal without echoing it, for passphrases.
+fn read_secret<S: AsRef<str>>(label: S) -> Result<String, Error> {
+    eprint!("{}", label.as_ref());
+
+    terminal::enable_raw_mode()
+        .map_err(|e| Error::with_cause("The terminal setup failed", e.kind()))?;
+
+    let mut secret = String::new();
+
+    let res = loop {
+        match event::read() {
+            // Windows reports releases too, which would type every character twice.
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Esc => break Err(Error::new("The passphrase input was cancelled")),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(Error::new("The passphrase input was cancelled"));
+                }
+                KeyCode::Backspace => {
+                    secret.pop();
+                }
+                KeyCode::Char(c) => secret.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(Error::with_cause("The passphrase reading failed", e.kind())),
+        }
+    };
+
+    let _ = terminal::disable_raw_mode();
+
+    eprintln!();
+
+    res.map(|_| secret)
+}
+
+// Runs a helper program to completion, turning a missing binary or a non-zero exit into an Error.
+fn run_external(cmd: &mut Command) -> Result<(), Error> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+
+    match cmd.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(Error::with_cause(
+            format!("The command '{program}' failed"),
+            status,
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(Error::with_cause(
+            format!("The command '{program}' executing failed"),
+            "it is not installed or not in PATH",
+        )),
+        Err(e) => Err(Error::with_cause(
+            format!("The command '{program}' executing failed"),
+            e.kind(),
+        )),
+    }
+}
+
+// A user-supplied command line, run through the platform shell.
+fn shell_command<S: AsRef<str>>(line: S) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut cmd = Command::new(shell);
+
+    cmd.args([flag, line.as_ref()]);
+
+    cmd
+}
+
+// Fills the memo placeholders of a `shell_command` line: `{file}`, `{name}` and `{stash}`. A
+// compressed memo is handed over as a plain copy, which the caller keeps until the command is done.
+fn expand_shell_line(
+    line: &str,
+    memo: Option<&Memo>,
+) -> Result<(String, Option<PlainCopy>), Error> {
+    let copy = match memo {
+        Some(memo) if line.contains("{file}") => PlainCopy::of(&memo.current_path())?,
+        _ => None,
+    };
+    let file = match (&copy, memo) {
+        (Some(copy), _) => copy.path().to_string_lossy().to_string(),
+        (None, Some(memo)) => memo.original_path.to_string_lossy().to_string(),
+        (None, None) => String::new(),
+    };
+    let line = line
+        .replace("{file}", &shell_quote(file))
+        .replace(
+            "{name}",
+            &shell_quote(memo.map(|memo| memo.name()).unwrap_or_default()),
+        )
+        .replace("{stash}", &shell_quote(MEMO_LIST_PATH.to_string_lossy()));
+
+    Ok((line, copy))
+}
+
+// Quotes a value for substitution into a `shell_command` line.
+fn shell_quote<S: AsRef<str>>(s: S) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", s.as_ref().replace('"', "\"\""))
+    } else {
+        format!("'{}'", s.as_ref().replace('\'', "'\\''"))
+    }
+}
+
+// Hands a URL or file to the desktop's default application without waiting for it.
+fn open_external(target: &str) -> Result<(), Error> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut cmd = Command::new("rundll32");
+
+        // Not `cmd /C start`: cmd would read `&`, `|` and `^` in a URL taken from a memo as its
+        // own syntax and run what follows them.
+        cmd.arg("url.dll,FileProtocolHandler");
+        cmd
+    } else {
+        Command::new("xdg-open")
+    };
+    let program = cmd.get_program().to_string_lossy().to_string();
+
+    let mut child = cmd
+        .arg(target)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                Error::with_cause(
+                    format!("The command '{program}' executing failed"),
+                    "it is not installed or not in PATH",
+                )
+            } else {
+                Error::with_cause(
+                    format!("The command '{program}' executing failed"),
+                    e.kind(),
+                )
+            }
+        })?;
+
+    // Reaped in the background so no zombie is left behind.
+    thread::spawn(move || child.wait());
+
+    Ok(())
+}
+
+// Feeds `input` to a helper program's stdin; errors are reported like `run_external`.
+fn pipe_external<B: AsRef<[u8]>>(cmd: &mut Command, input: B) -> Result<(), Error> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+
+    let mut child = cmd.stdin(Stdio::piped()).spawn().map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            Error::with_cause(
+                format!("The command '{program}' executing failed"),
+                "it is not installed or not in PATH",
+            )
+        } else {
+            Error::with_cause(
+                format!("The command '{program}' executing failed"),
+                e.kind(),
+            )
+        }
+    })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_ref()).map_err(|e| {
+            Error::with_cause(format!("The command '{program}' input failed"), e.kind())
+        })?;
+    }
+
+    match child.wait() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(Error::with_cause(
+            format!("The command '{program}' failed"),
+            status,
+        )),
+        Err(e) => Err(Error::with_cause(
+            format!("The command '{program}' executing failed"),
+            e.kind(),
+        )),
+    }
+}
+
+// Runs a helper program away from the terminal, for while the TUI is drawn on it: it reads
+// nothing, what it prints is dropped and the last line of its stderr tells why it failed. It is
+// killed once `progress` is cancelled, along with what it started itself.
+fn run_detached(cmd: &mut Command, progress: &Progress) -> Result<(), Error> {
+    let program = Path::new(cmd.get_program())
+        .file_name()
+        .unwrap_or(cmd.get_program())
+        .to_string_lossy()
+        .to_string();
+    let failed = |cause: String| {
+        Error::with_cause(format!("The command '{program}' executing failed"), cause)
+    };
+
+    // A group of its own, which also keeps it from reading keys off the terminal.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(cmd, 0);
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => failed(String::from("it is not installed or not in PATH")),
+            kind => failed(kind.to_string()),
+        })?;
+
+    // Read aside, so a chatty program never blocks on a full pipe.
+    let stderr = child.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut text = String::new();
+
+            let _ = io::Read::read_to_string(&mut stderr, &mut text);
+
+            text
+        })
+    });
+
+    let status = loop {
+        if progress.is_cancelled() {
+            // Asked to stop first, so git and rsync get to drop their lock and partial files; only
+            // a group still there after the grace period is killed outright.
+            #[cfg(unix)]
+            {
+                let group = format!("-{}", child.id());
+                let signal_group = |signal: &str| {
+                    Command::new("kill")
+                        .args([signal, "--", &group])
+                        .stderr(Stdio::null())
+                        .status()
+                };
+                let _ = signal_group("-TERM");
+                let asked = Instant::now();
+
+                while matches!(child.try_wait(), Ok(None)) && asked.elapsed() < CANCEL_GRACE {
+                    thread::sleep(Duration::from_millis(50));
+                }
+
+                if matches!(child.try_wait(), Ok(None)) {
+                    let _ = signal_group("-KILL");
+                }
+            }
+
+            let _ = child.kill();
+            let _ = child.wait();
+
+            return progress.check();
+        }
+
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(failed(e.kind().to_string())),
+        }
+    };
+
+    if status.success() {
+        return Ok(());
+    }
+
+    let stderr = stderr
+        .and_then(|reader| reader.join().ok())
+        .unwrap_or_default();
+
+    Err(Error::with_cause(
+        format!("The command '{program}' failed"),
+        stderr
+            .lines()
+            .rfind(|line| !line.trim().is_empty())
+            // A memoleak of its own marks its errors.
+            .map(|line| line.trim().trim_start_matches("[ERR] "))
+            .map_or_else(|| status.to_string(), String::from),
+    ))
+}
+
+// Pipes the text through a formatter's command line and reads back what it prints. A formatter
+// that fails, or prints nothing for text that has something in it, leaves the text as it was.
+fn format_text(line: &str, text: &str) -> Result<String, Error> {
+    let failed = |cause: String| {
+        Error::with_cause(format!("The formatter '{line}' executing failed"), cause)
+    };
+    let mut child = shell_command(line)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| failed(e.kind().to_string()))?;
+
+    // Written from another thread so a formatter that prints while reading cannot deadlock.
+    let mut stdin = child.stdin.take();
+    let input = text.to_string();
+    let writer = thread::spawn(move || {
+        stdin
+            .as_mut()
+            .map(|stdin| stdin.write_all(input.as_bytes()))
+    });
+    let out = child
+        .wait_with_output()
+        .map_err(|e| failed(e.kind().to_string()))?;
+    let _ = writer.join();
+
+    if !out.status.success() {
+        let stderr = String::from_utf8_lossy(&out.stderr);
+
+        let reason = match stderr.lines().find(|line| !line.trim().is_empty()) {
+            Some(reason) => reason.trim().to_string(),
+            None => out.status.to_string(),
+        };
+
+        return Err(Error::with_cause(
+            format!("The formatter '{line}' failed"),
+            reason,
+        ));
+    }
+
+    if out.stdout.is_empty() && !text.trim().is_empty() {
+        return Err(failed(String::from("it printed nothing")));
+    }
+
+    String::from_utf8(out.stdout).map_err(|_| failed(String::from("it printed no UTF-8")))
+}
+
+// A private file in the temp dir for handing data to helper programs; removed on drop.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    // The name gets a random part and the file is always a new one, so nothing planted in the
+    // shared temp dir under a guessable name is written through.
+    fn write<S: AsRef<str>, B: AsRef<[u8]>>(name: S, content: B) -> Result<Self, Error> {
+        let mut options = private_file_options();
+
+        options.write(true).create_new(true);
+
+        loop {
+            let mut bytes = [0u8; 8];
+
+            getrandom::getrandom(&mut bytes)
+                .map_err(|e| Error::with_cause("A temp file naming failed", e))?;
+
+            let random = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+            let path = std::env::temp_dir().join(format!(
+                "memoleak-{}-{random}-{}",
+                process::id(),
+                name.as_ref()
+            ));
+            let mut file = match options.open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(Error::with_cause(
+                        format!("A file '{}' writing failed", path.to_string_lossy()),
+                        e.kind(),
+                    ));
+                }
+            };
+            let temp = TempFile(path);
+
+            file.write_all(content.as_ref()).map_err(|e| {
+                Error::with_cause(
+                    format!("A file '{}' writing failed", temp.0.to_string_lossy()),
+                    e.kind(),
+                )
+            })?;
+
+            return Ok(temp);
+        }
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+// A compressed memo as a plain temp file for a command that takes a file, as the editor gets it in
+// `Stash::edit`. What the command changes in it is compressed back with `write_back`.
+struct PlainCopy {
+    memo: PathBuf,
+    original: String,
+    file: TempFile,
+}
+
+impl PlainCopy {
+    // None for a plain memo, which is handed over as it is.
+    fn of(path: &Path) -> Result<Option<Self>, Error> {
+        if !storage::is_compressed(path) {
+            return Ok(None);
+        }
+
+        let original = storage::read(path).map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' reading failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+        let file = TempFile::write(
+            format!("{}.{MEMO_EXTENSION}", memo_name_of(path)),
+            &original,
+        )?;
+
+        Ok(Some(Self {
+            memo: path.to_path_buf(),
+            original,
+            file,
+        }))
+    }
+
+    fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    fn write_back(self) -> Result<(), Error> {
+        let content = storage::read(self.path()).map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' reading failed", self.path().to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+
+        if content != self.original {
+            storage::write(&self.memo, &content)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn fill_stash_with_local(stash: &mut Stash) -> Result<(), Error> {
+    fill_stash_with(stash, memo_paths()?)
+}
+
+fn fill_stash_with(stash: &mut Stash, paths: Vec<PathBuf>) -> Result<(), Error> {
+    for path in paths {
+        stash.push(Memo::with_content(path)?);
+    }
+
+    stash.sort();
+
+    Ok(())
+}
+
+// Lists the markdown files of the configured source folders, skipping any already listed through
+// the stash directory. A source that cannot be read does not keep the others out.
+fn fill_stash_with_sources(stash: &mut Stash, sources: &[SourceConfig]) -> Result<(), Error> {
+    let mut listed = stash
+        .stash
+        .iter()
+        .filter_map(|memo| memo.original_path.canonicalize().ok())
+        .collect::<HashSet<_>>();
+    let mut errors = vec![];
+
+    for source in sources {
+        let dir = source.dir();
+
+        if !dir.is_dir() {
+            errors.push(format!(
+                "A source directory '{}' is not found",
+                dir.to_string_lossy()
+            ));
+            continue;
+        }
+
+        let paths = match markdown_paths(&dir, false) {
+            Ok(paths) => paths,
+            Err(e) => {
+                errors.push(e.to_string());
+                continue;
+            }
+        };
+
+        for path in paths {
+            if !path.canonicalize().is_ok_and(|real| listed.insert(real)) {
+                continue;
+            }
+
+            match Memo::with_content(&path) {
+                Ok(mut memo) => {
+                    memo.source = Some(source.badge());
+                    stash.push(memo);
+                }
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+    }
+
+    stash.sort();
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(Error::new(errors.join(", "))),
+    }
+}
+
+// Every memo file in MEMO_LIST_PATH, sorted. Symlinks are followed, so notes kept elsewhere can be
+// linked in; a linked directory adds the markdown files in it.
+fn memo_paths() -> Result<Vec<PathBuf>, Error> {
+    markdown_paths(&MEMO_LIST_PATH, true)
+}
+
+// The files under `root`, which at its top level are all taken to be memos when `any_top` is set.
+// Hidden files are left out, except for markdown ones, which are hidden memos. Dangling links are
+// skipped, and each directory is visited once however it is reached, which stops link cycles.
+fn markdown_paths(root: &Path, any_top: bool) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = vec![];
+    let mut visited = HashSet::new();
+    let mut pending = vec![(root.to_path_buf(), any_top)];
+
+    while let Some((dir, top)) = pending.pop() {
+        let Ok(real) = dir.canonicalize() else {
+            continue;
+        };
+
+        if !visited.insert(real) {
+            continue;
+        }
+
+        let entries = dir.read_dir().map_err(|e| {
+            Error::with_cause(
+                format!("A directory '{}' reading failed", dir.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+
+        for entry in entries {
+            let path = entry
+                .map_err(|e| Error::with_cause("A memo file reading failed", e.kind()))?
+                .path();
+
+            // Editor swap files and the like, which come and go while memos are open. Emacs lock
+            // files are dangling links named `.#memo.md`.
+            if path.file_name().is_some_and(|name| {
+                let name = name.to_string_lossy();
+
+                name.starts_with('.') && (name.starts_with(".#") || !is_markdown(&path))
+            }) {
+                continue;
+            }
+
+            if path.is_dir() {
+                pending.push((path, false));
+            } else if path.is_file() && (top || is_markdown(&path)) {
+                paths.push(path);
+            }
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+fn is_markdown(path: &Path) -> bool {
+    let path = storage::plain_path(path);
+
+    path.extension()
+        .is_some_and(|ext| ext == MEMO_EXTENSION || ext == "markdown")
+}
+
+// The terminal's own title is saved on its title stack (XTWINOPS) for disable_tui to bring back,
+// and the stash is reported as the working directory (OSC 7) so new tabs and panes open there.
+fn enable_tui() {
+    let _ = terminal::enable_raw_mode().and_then(|_| {
+        execute!(
+            io::stdout(),
+            Print("\x1b[22;0t"),
+            Print(working_dir_report()),
+            DisableLineWrap,
+            EnterAlternateScreen,
+            EnableBracketedPaste,
+            EnableFocusChange,
+            Hide
+        )
+    });
+
+    if keyboard_enhancement() {
+        let _ = execute!(
+            io::stdout(),
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        );
+    }
+}
+
+fn disable_tui() {
+    if keyboard_enhancement() {
+        let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+    }
+
+    let _ = terminal::disable_raw_mode().and_then(|_| {
+        execute!(
+            io::stdout(),
+            EnableLineWrap,
+            DisableBracketedPaste,
+            DisableFocusChange,
+            ResetColor,
+            LeaveAlternateScreen,
+            Show,
+            Print("\x1b[23;0t")
+        )
+    });
+}
+
+// Whether the terminal speaks the kitty keyboard protocol, which tells ctrl-i from TAB and reports
+// key repeats and releases. Asked once, on the first enable_tui before the input thread starts
+// reading, since the answer arrives as input.
+fn keyboard_enhancement() -> bool {
+    static SUPPORTED: OnceLock<bool> = OnceLock::new();
+
+    *SUPPORTED.get_or_init(|| terminal::supports_keyboard_enhancement().unwrap_or(false))
+}
+
+#[cfg(unix)]
+fn working_dir_report() -> String {
+    let path = MEMO_LIST_PATH
+        .as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .map(|&b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect::<String>();
+
+    format!("\x1b]7;file://{path}\x07")
+}
+
+#[cfg(not(unix))]
+fn working_dir_report() -> String {
+    String::new()
+}
+
+// The default keymaps with the user's and plugins' on top, and the user keymaps that are invalid.
+fn keymaps_of(plugins: &Plugins, config: &Config) -> (Keymaps, Vec<String>) {
+    let mut keymaps = Keymaps::default();
+    let mut invalid = vec![];
+
+    let user_maps = plugins
+        .bindings()
+        .map(|(idx, keys)| (Context::List, keys, Order::Plugin(idx)))
+        .chain(
+            config
+                .keys
+                .iter()
+                .enumerate()
+                .map(|(idx, (keys, key))| (key.context, keys.as_str(), Order::Shell(idx))),
+        );
+
+    for (context, keys, order) in user_maps {
+        if keymaps
+            .bind(context, &canonical_keymap(keys), order)
+            .is_err()
+        {
+            invalid.push(keys.to_string());
+        }
+    }
+
+    keymaps.timeout = Duration::from_millis(config.input.timeout);
+
+    (keymaps, invalid)
+}
+
+fn setup_tui(plugins: Plugins, config: Config) -> AppContainer {
+    enable_tui();
+
+    let orders: Arc<RwLock<Vec<Order>>> = Arc::new(RwLock::new(vec![]));
+    let input = Arc::new(InputControl::new());
+    let (keymaps, invalid) = keymaps_of(&plugins, &config);
+    let keymaps = Arc::new(RwLock::new(keymaps));
+
+    let oc = orders.clone();
+    let ic = input.clone();
+    let kc = keymaps.clone();
+
+    thread::spawn(move || {
+        let orders = oc;
+        let input = ic;
+        let keymaps = kc;
+        let mut pool = Pool::default();
+
+        loop {
+            let ev = input.read();
+            let context = input.context();
+            let Some(ev) = ev else {
+                // Nothing more came for a binding the pool is the start of: whatever the pool
+                // binds on its own runs, like vim's timeoutlen.
+                let timeout = keymaps.read().unwrap().timeout;
+
+                if !pool.is_empty() && pool.waited() >= timeout {
+                    let resolved = pool.flush(&keymaps.read().unwrap(), context);
+
+                    orders.write().unwrap().extend(resolved);
+                }
+
+                continue;
+            };
+
+            let resolved = match ev {
+                // Repeats count as presses, so holding j keeps moving.
+                Event::Key(ev) if ev.kind == KeyEventKind::Release => continue,
+                Event::Key(ev) if context == Context::Locked => vec![Order::Input(ev)],
+                Event::Key(ev) => {
+                    let keymaps = keymaps.read().unwrap();
+
+                    match translate_to_key(ev) {
+                        Some(key) => pool.push(&keymaps, context, key, ev),
+                        // A key with no notation ends the pool and goes on as it is.
+                        None => {
+                            let mut resolved = pool.flush(&keymaps, context);
+
+                            if context.takes_input() {
+                                resolved.push(Order::Input(ev));
+                            }
+
+                            resolved
+                        }
+                    }
+                }
+                // Pasted text reaches prompts in one piece instead of as keys run through the
+                // keymaps; outside of them it is dropped.
+                Event::Paste(text) if context.takes_input() => vec![Order::Paste(text)],
+                Event::Resize(_, _) => vec![Order::Redraw],
+                Event::FocusGained | Event::FocusLost => {
+                    vec![Order::Focus(matches!(ev, Event::FocusGained))]
+                }
+                _ => continue,
+            };
+
+            orders.write().unwrap().extend(resolved);
+        }
+    });
+
+    let mut app = AppContainer::new(orders, input, keymaps, plugins, config);
+
+    if !invalid.is_empty() {
+        app.status = format!("Keymaps are invalid: {}", invalid.join(", "));
+    }
+
+    app
+}
+
+// Shared between the UI thread and the input thread so the latter can be told to hand raw keys to a
+// prompt, or to stay away from stdin entirely while a child process like $EDITOR owns the terminal.
+struct InputControl {
+    context: Mutex<Context>,
+    suspended: AtomicBool,
+    reading: Mutex<()>,
+}
+
+impl InputControl {
+    fn new() -> Self {
+        Self {
+            context: Mutex::new(Context::List),
+            suspended: AtomicBool::new(false),
+            reading: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Option<Event> {
+        if self.suspended.load(Ordering::Acquire) {
+            thread::sleep(Duration::from_millis(20));
+
+            return None;
+        }
+
+        let _reading = self.reading.lock().unwrap();
+
+        if self.suspended.load(Ordering::Acquire) {
+            return None;
+        }
+
+        match event::poll(Duration::from_millis(50)) {
+            Ok(true) => event::read().ok(),
+            _ => None,
+        }
+    }
+
+    fn context(&self) -> Context {
+        *self.context.lock().unwrap()
+    }
+
+    fn set_context(&self, context: Context) {
+        *self.context.lock().unwrap() = context;
+    }
+
+    fn suspend<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        self.suspended.store(true, Ordering::Release);
+
+        let res = {
+            let _reading = self.reading.lock().unwrap();
+
+            f()
+        };
+
+        self.suspended.store(false, Ordering::Release);
+
+        res
+    }
+}
+
+// Columns moved per zh / zl.
+const HSCROLL_STEP: usize = 8;
+
+// Rows of the link and tag completion menu above a prompt.
+const COMPLETIONS: usize = 6;
+
+struct AppContainer {
+    orders: Arc<RwLock<Vec<Order>>>,
+    input: Arc<InputControl>,
+    keymaps: Arc<RwLock<Keymaps>>,
+    view: View,
+    // Every tab's view, the current one's as it was when another tab came to the front.
+    tabs: Vec<View>,
+    tab: usize,
+    // The tab shown in a pane beside the current one.
+    split: Option<usize>,
+    prompt: Option<Prompt>,
+    // What the open `[[` or `#` of the prompt completes from.
+    completion_words: Option<(Completing, Vec<String>)>,
+    picker: Option<Picker<Pick>>,
+    palette: Option<Palette<Order>>,
+    overlay: Option<Overlay>,
+    calendar: Option<Calendar>,
+    board: Option<Board>,
+    spell: bool,
+    wrap: Wrap,
+    spelling: spell::Cache,
+    // The content hash being spell checked on a worker thread; another check waits for it.
+    spell_pending: Option<u64>,
+    // The passphrase typed so far while the screen is locked.
+    locked: Option<String>,
+    histories: HashMap<PromptKind, Vec<String>>,
+    undo_log: UndoLog,
+    // When the stash directory and sources were last checked for changes made outside the TUI.
+    scanned: Instant,
+    // Whether a background refresh is still out; another one waits for it to come back.
+    rereading: bool,
+    // The long operation running on a worker thread; another one waits for it to finish.
+    task: Option<Task>,
+    // When the last change still to be written was made in the TUI.
+    unsaved_since: Option<Instant>,
+    // How long the autosave holds changes back; doubled each time writing them fails.
+    autosave_delay: Duration,
+    // Whether the terminal has focus, as far as it reports it.
+    focused: bool,
+    // The focus session running, counted down in the status line.
+    timer: Option<focus::Session>,
+    // Memos of the review under way still to go through.
+    review: Vec<PathBuf>,
+    // The config file's modification time when it was last loaded.
+    config_modified: Option<SystemTime>,
+    // Whether frame times and such are shown in the top right corner.
+    hud: bool,
+    // How long the last frame took to draw, and when it was drawn.
+    frame_time: Duration,
+    rendered: Instant,
+    // The orders handled in the last batch.
+    queued: usize,
+    // What the journal on disk holds.
+    journaled: Journal,
+    // Memos marked to be edited together, in the order they were marked.
+    marked: Vec<PathBuf>,
+    plugins: Plugins,
+    config: Config,
+    status: String,
+    redraw: bool,
+    exit: bool,
+}
+
+// What a tab shows of the stash: the memos it lists and how.
+#[derive(Default, Clone)]
+struct View {
+    cursor: usize,
+    filter: Option<String>,
+    // Whether the list shows only favorite memos.
+    favorites: bool,
+    show_hidden: bool,
+    // Whether memos are shown as a grid of cards instead of the list and preview.
+    grid: bool,
+    // Preview columns scrolled off to the left while wrapping is off.
+    hscroll: usize,
+}
+
+impl View {
+    fn visible(&self, stash: &Stash) -> Vec<usize> {
+        (0..stash.len())
+            .filter(|&idx| match &self.filter {
+                Some(query) => stash.get(idx).is_some_and(|memo| memo.matches(query)),
+                None => true,
+            })
+            .filter(|&idx| !self.favorites || stash.get(idx).is_some_and(Memo::is_favorite))
+            .filter(|&idx| self.show_hidden || stash.get(idx).is_some_and(|memo| !memo.is_hidden()))
+            .collect()
+    }
+}
+
+impl AppContainer {
+    fn new(
+        orders: Arc<RwLock<Vec<Order>>>,
+        input: Arc<InputControl>,
+        keymaps: Arc<RwLock<Keymaps>>,
+        plugins: Plugins,
+        config: Config,
+    ) -> Self {
+        Self {
+            orders,
+            input,
+            keymaps,
+            view: View::default(),
+            tabs: vec![View::default()],
+            tab: 0,
+            split: None,
+            prompt: None,
+            completion_words: None,
+            picker: None,
+            palette: None,
+            overlay: None,
+            calendar: None,
+            board: None,
+            spell: config.spell.enabled,
+            wrap: config.preview.wrap,
+            spelling: spell::Cache::default(),
+            spell_pending: None,
+            locked: None,
+            histories: [PromptKind::Search, PromptKind::Command]
+                .into_iter()
+                .map(|kind| (kind, prompt::load_history(kind)))
+                .collect(),
+            undo_log: UndoLog::default(),
+            scanned: Instant::now(),
+            rereading: false,
+            task: None,
+            unsaved_since: None,
+            autosave_delay: AUTOSAVE_DELAY,
+            focused: true,
+            timer: None,
+            review: vec![],
+            config_modified: config::modified(),
+            hud: false,
+            frame_time: Duration::ZERO,
+            rendered: Instant::now(),
+            queued: 0,
+            journaled: Journal::default(),
+            marked: vec![],
+            plugins,
+            config,
+            status: String::new(),
+            redraw: true,
+            exit: false,
+        }
+    }
+
+    fn run(&mut self, stash: &mut Stash) {
+        while !self.exit {
+            // A redraw that comes too soon after the last waits for the orders behind it.
+            if self.redraw && self.rendered.elapsed() >= self.config.display.frame_interval() {
+                let started = Instant::now();
+
+                self.check_spelling(stash);
+                self.render(stash);
+                self.frame_time = started.elapsed();
+                self.rendered = Instant::now();
+                self.redraw = false;
+            }
+
+            let orders = std::mem::take(&mut *self.orders.write().unwrap());
+
+            if orders.is_empty() {
+                match &self.timer {
+                    Some(timer) if timer.is_over() => {
+                        self.finish_timer(stash);
+                        self.redraw = true;
+                    }
+                    Some(_) if self.rendered.elapsed() >= Duration::from_secs(1) => {
+                        self.redraw = true;
+                    }
+                    _ => {}
+                }
+
+                if self.task.is_some() && self.rendered.elapsed() >= TASK_REDRAW_INTERVAL {
+                    self.redraw = true;
+                }
+
+                if self
+                    .unsaved_since
+                    .is_some_and(|since| since.elapsed() >= self.autosave_delay)
+                {
+                    self.save(stash);
+                    self.journal(stash);
+                    self.redraw = true;
+                }
+
+                // Nothing is polled while the terminal is in the background; it catches up on
+                // focus instead.
+                if !self.focused {
+                    thread::sleep(Duration::from_millis(100));
+
+                    continue;
+                }
+
+                if self.scanned.elapsed() >= RESCAN_INTERVAL {
+                    self.rescan(stash);
+
+                    if config::modified() != self.config_modified {
+                        self.reload_config();
+                        self.redraw = true;
+                    }
+                }
+
+                thread::sleep(Duration::from_millis(16));
+
+                continue;
+            }
+
+            self.queued = orders.len();
+
+            // Unsaved changes are written before anything else looks at the files, and when the
+            // selection moves on to another memo.
+            for order in orders {
+                if self.unsaved_since.is_some() && order.uses_files() {
+                    self.save(stash);
+                }
+
+                let focused = self.selected(stash);
+
+                self.handle(order, stash);
+
+                if self.unsaved_since.is_some() && self.selected(stash) != focused {
+                    self.save(stash);
+                }
+            }
+
+            self.gather_completions(stash);
+            self.journal(stash);
+
+            self.redraw = true;
+        }
+    }
+
+    // Spell checks the selected memo once per version of its content, on a worker thread that
+    // hands the words back as an Order::SpellDone so a slow checker never holds up the UI.
+    fn check_spelling(&mut self, stash: &Stash) {
+        if !self.spell || self.spell_pending.is_some() {
+            return;
+        }
+
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+
+        if self.spelling.touch(memo.content_hash) {
+            return;
+        }
+
+        let (hash, text) = (memo.content_hash, memo.content_buffer.clone());
+        let config = self.config.spell.clone();
+        let orders = self.orders.clone();
+
+        self.spell_pending = Some(hash);
+
+        thread::spawn(move || {
+            let words = spell::misspelled(&text, &config).map_err(|e| e.to_string());
+
+            orders
+                .write()
+                .unwrap()
+                .push(Order::SpellDone { hash, words });
+        });
+    }
+
+    fn toggle_spelling(&mut self) {
+        self.spell = !self.spell;
+        self.status = if self.spell {
+            format!("Spell checking on ({})", self.config.spell.language)
+        } else {
+            String::from("Spell checking off")
+        };
+    }
+
+    // Stash indices of the memos currently listed, narrowed by the search filter.
+    fn visible(&self, stash: &Stash) -> Vec<usize> {
+        self.view.visible(stash)
+    }
+
+    fn selected(&self, stash: &Stash) -> Option<usize> {
+        self.visible(stash).get(self.view.cursor).copied()
+    }
+
+    fn select<P: AsRef<Path>>(&mut self, path: P, stash: &Stash) {
+        let Some(idx) = stash.position(path) else {
+            return;
+        };
+
+        if !self.visible(stash).contains(&idx) {
+            self.view.filter = None;
+            self.view.favorites = false;
+        }
+
+        if !self.visible(stash).contains(&idx) {
+            self.view.show_hidden = true;
+        }
+
+        self.view.cursor = self
+            .visible(stash)
+            .iter()
+            .position(|&i| i == idx)
+            .unwrap_or(0);
+    }
+
+    fn handle(&mut self, order: Order, stash: &mut Stash) {
+        // Only what arrives from outside the keyboard gets through a locked screen.
+        if self.locked.is_some() {
+            match order {
+                Order::Input(key) => return self.input_lock(key),
+                // A passphrase from a password manager, without the newline it may be copied with.
+                Order::Paste(text) => {
+                    if let Some(typed) = self.locked.as_mut() {
+                        typed.push_str(&text.replace(char::is_control, ""));
+                    }
+
+                    return;
+                }
+                Order::Exit
+                | Order::Redraw
+                | Order::Insert(_)
+                | Order::ShellDone { .. }
+                | Order::EditDone(_)
+                | Order::TaskDone(_)
+                | Order::RereadDone { .. }
+                | Order::SpellDone { .. }
+                | Order::Focus(_) => {}
+                _ => return,
+            }
+        }
+
+        match order {
+            Order::Exit => self.exit = true,
+            Order::Redraw => {}
+            Order::Down => {
+                let step = self.grid_step();
+
+                if self.view.cursor + step < self.visible(stash).len() {
+                    self.view.cursor += step;
+                }
+            }
+            Order::Up => self.view.cursor = self.view.cursor.saturating_sub(self.grid_step()),
+            Order::Right if self.view.grid => {
+                if self.view.cursor + 1 < self.visible(stash).len() {
+                    self.view.cursor += 1;
+                }
+            }
+            Order::Left if self.view.grid => self.view.cursor = self.view.cursor.saturating_sub(1),
+            Order::Left | Order::Right => {}
+            Order::Edit => self.edit(stash),
+            Order::EditSelection => self.edit_selection(stash),
+            Order::ToggleMark => self.toggle_mark(stash),
+            Order::ToggleFavorite => self.toggle_favorite(stash),
+            Order::Favorites => self.toggle_favorites_view(),
+            Order::ToggleHidden => self.toggle_hidden(),
+            Order::ToggleGrid => self.toggle_grid(),
+            Order::NewTab => self.new_tab(stash),
+            Order::CloseTab => self.close_tab(stash),
+            Order::StepTab(step) => {
+                let count = self.tabs.len() as i32;
+
+                self.switch_tab((self.tab as i32 + step).rem_euclid(count) as usize, stash);
+            }
+            Order::Split => self.split(stash),
+            Order::OtherPane => match self.split {
+                Some(other) => self.switch_tab(other, stash),
+                None => self.status = String::from("There is no other pane"),
+            },
+            Order::Unsplit => self.split = None,
+            Order::Create => {
+                self.status.clear();
+                self.new_prompt(PromptKind::Create);
+            }
+            Order::Delete => self.delete(stash),
+            Order::Rename => {
+                if let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) {
+                    let mut prompt = Prompt::new(PromptKind::Rename, vec![]);
+
+                    prompt.editor.set_buffer(memo.name());
+
+                    self.open_prompt(prompt);
+                }
+            }
+            Order::Archive => self.archive(stash),
+            Order::Undo => self.undo(stash),
+            Order::Insert(path) => self.insert(&path, stash),
+            Order::Redo => self.redo(stash),
+            Order::Search => self.new_prompt(PromptKind::Search),
+            Order::Command => self.new_prompt(PromptKind::Command),
+            Order::Qr => self.qr(stash),
+            Order::Graph => self.graph(stash),
+            Order::Calendar => self.calendar(stash),
+            Order::Board => self.board(stash),
+            Order::OpenLink => self.open_link(stash),
+            Order::ToggleSpell => self.toggle_spelling(),
+            Order::Lint => self.lint(stash),
+            Order::BrokenLinks => self.broken_links(stash),
+            Order::Lock => self.lock(),
+            Order::CycleWrap => {
+                self.wrap = match self.wrap {
+                    Wrap::Off => Wrap::Window,
+                    Wrap::Window => Wrap::Column,
+                    Wrap::Column => Wrap::Off,
+                };
+                self.view.hscroll = 0;
+                self.status = match self.wrap {
+                    Wrap::Off => String::from("Wrap off (zh/zl: scroll)"),
+                    Wrap::Window => String::from("Wrap at the window edge"),
+                    Wrap::Column => format!("Wrap at column {}", self.config.preview.wrap_column),
+                };
+            }
+            Order::ScrollLeft => self.view.hscroll = self.view.hscroll.saturating_sub(HSCROLL_STEP),
+            Order::ScrollRight if self.wrap == Wrap::Off => self.view.hscroll += HSCROLL_STEP,
+            Order::ScrollRight => {}
+            Order::HalfPage(sign) => {
+                let height = terminal::size().map_or(20, |(_, height)| height as i32);
+
+                if let Some(overlay) = self.overlay.as_mut() {
+                    overlay.scroll_by(sign * height / 2);
+                }
+            }
+            Order::Scratch => self.scratch(),
+            Order::LogEntry => self.new_prompt(PromptKind::Log),
+            Order::QuickEdit if self.selected(stash).is_some() => {
+                self.new_prompt(PromptKind::QuickEdit)
+            }
+            Order::QuickEdit => {}
+            Order::Timer => self.toggle_timer(stash),
+            Order::ClockIn => self.clock_in(stash),
+            Order::ClockOut => self.clock_out(),
+            Order::Habits => self.habits(stash),
+            Order::CheckHabit => self.check_habit(stash),
+            Order::CopyField => self.copy_field(stash, None),
+            Order::Random => self.random(stash),
+            Order::Review => self.review(review::COUNT, stash),
+            Order::Stale => self.stale(stash),
+            Order::Periodic(period) => {
+                let start = period.start(chrono::Local::now().date_naive());
+
+                self.open_period(period, start, stash);
+            }
+            Order::StepPeriod(step) => {
+                let recognized = self
+                    .selected(stash)
+                    .and_then(|idx| stash.get(idx))
+                    .and_then(|memo| periodic::recognize(&memo.name(), &self.config.periodic));
+
+                match recognized {
+                    Some((period, start)) => {
+                        self.open_period(period, period.step(start, step), stash)
+                    }
+                    None => self.status = String::from("The memo is not a periodic note"),
+                }
+            }
+            Order::Shell(idx) => self.run_shell(idx, stash),
+            // Files may have been changed from another window meanwhile.
+            Order::Focus(true) => {
+                self.focused = true;
+                self.rescan(stash);
+                self.reread(stash);
+            }
+            Order::Focus(false) => self.focused = false,
+            Order::EditDone(path) => {
+                if let Some(idx) = stash.position(&path) {
+                    self.status.clear();
+                    self.edited(idx, stash);
+                }
+            }
+            Order::Cancel => {
+                if let Some(task) = &self.task {
+                    task.progress.cancel();
+                }
+            }
+            Order::TaskDone(done) => self.finish_task(done, stash),
+            Order::SpellDone { hash, words } => {
+                self.spell_pending = None;
+
+                match words {
+                    Ok(words) => self.spelling.insert(hash, words),
+                    Err(e) => {
+                        self.spell = false;
+                        self.status = e;
+                    }
+                }
+            }
+            Order::RereadDone { changed, error } => {
+                self.rereading = false;
+                stash.apply_reread(changed);
+
+                if let Some(e) = error {
+                    self.status = e;
+                }
+            }
+            Order::ShellDone {
+                line,
+                output,
+                scratch,
+            } => {
+                if scratch {
+                    self.open_overlay(Overlay::text(format!("$ {line}  (q: close)"), &output));
+                } else {
+                    self.status = output;
+                }
+
+                if let Some(idx) = self.selected(stash)
+                    && let Err(e) = stash.refresh(idx)
+                {
+                    self.status = e.to_string();
+                }
+            }
+            Order::Plugin(idx) => self.run_plugin(stash, |plugins, path, content| {
+                plugins.run_binding(idx, path, content)
+            }),
+            Order::Palette => self.palette(),
+            Order::ReloadConfig => self.reload_config(),
+            Order::DebugHud => self.hud = !self.hud,
+            Order::Run(line) => self.run_command(&line, stash),
+            Order::Input(key)
+                if self.overlay.is_some()
+                    || self.picker.is_some()
+                    || self.palette.is_some()
+                    || self.calendar.is_some()
+                    || self.board.is_some() =>
+            {
+                self.input_dialog(key, stash)
+            }
+            Order::Input(key) => self.input_prompt(key, stash),
+            Order::Paste(text) => {
+                if let Some(palette) = self.palette.as_mut() {
+                    palette.paste(&text);
+                } else if let Some(prompt) = self.prompt.as_mut() {
+                    prompt.editor.paste(&text);
+                }
+            }
+        }
+    }
+
+    fn edit(&mut self, stash: &mut Stash) {
+        self.edit_at(stash, None);
+    }
+
+    fn edit_at(&mut self, stash: &mut Stash, at: Option<usize>) {
+        let Some(idx) = self.selected(stash) else {
+            return;
+        };
+        let open_in = self.config.editor.open_in;
+
+        if let Some(memo) = stash.get(idx)
+            && let Err(e) = private_file_options()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&*LAST_OPENED_PATH)
+                .and_then(|mut file| {
+                    file.write_all(memo.original_path.to_string_lossy().as_bytes())
+                })
+        {
+            self.status =
+                Error::with_cause("The last opened memo saving failed", e.kind()).to_string();
+        }
+
+        if open_in != OpenIn::Terminal
+            && let Some(memo) = stash.get(idx)
+            && !storage::is_compressed(memo.current_path())
+        {
+            if let Err(e) = self.edit_in_pane(memo.current_path(), at, memo.name(), open_in) {
+                self.status = e.to_string();
+            }
+
+            return;
+        }
+
+        let res = self.input.suspend(|| {
+            disable_tui();
+
+            let res = stash.edit(&[idx], at);
+
+            enable_tui();
+
+            res
+        });
+
+        let name = stash.get(idx).map(|memo| memo.name()).unwrap_or_default();
+
+        match res {
+            Ok(Edited::Saved) => self.edited(idx, stash),
+            Ok(Edited::Discarded) => self.status = format!("Discarded the changes to '{name}'"),
+            Ok(Edited::Failed(how)) => {
+                self.status = format!("The editor {how}, '{name}' was not reloaded")
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    // Opens the marked memos together, or the selected one when none is marked.
+    fn edit_selection(&mut self, stash: &mut Stash) {
+        let marked = self
+            .marked
+            .iter()
+            .filter(|path| stash.position(path).is_some())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if marked.is_empty() {
+            return self.edit(stash);
+        }
+
+        let idxs = marked
+            .iter()
+            .filter_map(|path| stash.position(path))
+            .collect::<Vec<_>>();
+        let res = self.input.suspend(|| {
+            disable_tui();
+
+            let res = stash.edit(&idxs, None);
+
+            enable_tui();
+
+            res
+        });
+
+        match res {
+            Ok(Edited::Saved) => {
+                self.status = format!("Edited {} memos", marked.len());
+
+                // Going by path, as a memo transformed on edit may move in the list.
+                for path in &marked {
+                    if let Some(idx) = stash.position(path) {
+                        self.edited(idx, stash);
+                    }
+                }
+            }
+            Ok(Edited::Discarded) => {
+                self.status = format!("Discarded the changes to {} memos", marked.len())
+            }
+            Ok(Edited::Failed(how)) => {
+                self.status = format!("The editor {how}, the memos were not reloaded");
+
+                return;
+            }
+            Err(e) => {
+                self.status = e.to_string();
+
+                return;
+            }
+        }
+
+        self.marked.clear();
+    }
+
+    fn toggle_favorite(&mut self, stash: &mut Stash) {
+        let Some(idx) = self.selected(stash) else {
+            return;
+        };
+        let memo = &stash.stash[idx];
+        let name = memo.name();
+        let favorite = !memo.is_favorite();
+        let (mut front, body) = meta::split(&memo.content_buffer);
+
+        match favorite {
+            true => front.set("favorite", meta::Value::Text(String::from("true"))),
+            false => front.remove("favorite"),
+        }
+
+        let content = format!("{}{body}", front.render());
+
+        self.status = match self.stage(idx, content, stash) {
+            Ok(()) if favorite => format!("Added '{name}' to the favorites"),
+            Ok(()) => format!("Removed '{name}' from the favorites"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn toggle_favorites_view(&mut self) {
+        self.view.favorites = !self.view.favorites;
+        self.view.cursor = 0;
+    }
+
+    fn switch_tab(&mut self, to: usize, stash: &Stash) {
+        if to == self.tab {
+            return;
+        }
+
+        // The pane beside trades places with the one that came to the front.
+        if self.split == Some(to) {
+            self.split = Some(self.tab);
+        }
+
+        self.tabs[self.tab] = self.view.clone();
+        self.tab = to;
+        self.view = self.tabs[to].clone();
+        self.view.cursor = self
+            .view
+            .cursor
+            .min(self.visible(stash).len().saturating_sub(1));
+    }
+
+    // A tab showing what the current one does, brought to the front.
+    fn new_tab(&mut self, stash: &Stash) {
+        self.tabs.push(self.view.clone());
+        self.switch_tab(self.tabs.len() - 1, stash);
+    }
+
+    fn close_tab(&mut self, stash: &Stash) {
+        if self.tabs.len() == 1 {
+            self.status = String::from("The last tab cannot be closed");
+
+            return;
+        }
+
+        let closed = self.tab;
+        let to = match self.split.take() {
+            Some(other) => other,
+            None if closed + 1 < self.tabs.len() => closed + 1,
+            None => closed - 1,
+        };
+
+        self.switch_tab(to, stash);
+        self.tabs.remove(closed);
+
+        if self.tab > closed {
+            self.tab -= 1;
+        }
+    }
+
+    // Shows the current tab in a pane beside a new one, or beside the next tab when already split.
+    fn split(&mut self, stash: &Stash) {
+        let beside = self.tab;
+
+        match self.split {
+            Some(_) => {
+                let next = (self.tab + 1) % self.tabs.len();
+
+                self.split = None;
+                self.switch_tab(next, stash);
+            }
+            None => self.new_tab(stash),
+        }
+
+        if self.tab != beside {
+            self.split = Some(beside);
+        }
+    }
+
+    fn toggle_grid(&mut self) {
+        self.view.grid = !self.view.grid;
+    }
+
+    // How far j and k move: a memo in the list, a row of cards in the grid.
+    fn grid_step(&self) -> usize {
+        match self.view.grid {
+            true => dialog::grid_columns(terminal::size().map_or(80, |(width, _)| width as usize)),
+            false => 1,
+        }
+    }
+
+    fn toggle_hidden(&mut self) {
+        self.view.show_hidden = !self.view.show_hidden;
+        self.view.cursor = 0;
+        self.status = match self.view.show_hidden {
+            true => String::from("Showing hidden memos"),
+            false => String::from("Hiding hidden memos"),
+        };
+    }
+
+    fn toggle_mark(&mut self, stash: &Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+
+        match self
+            .marked
+            .iter()
+            .position(|path| *path == memo.original_path)
+        {
+            Some(at) => {
+                self.marked.remove(at);
+            }
+            None => self.marked.push(memo.original_path.clone()),
+        }
+
+        self.status = format!("{} memos marked", self.marked.len());
+    }
+
+    // The list stays usable meanwhile; an order picks the memo up again when the editor is done.
+    fn edit_in_pane(
+        &mut self,
+        path: PathBuf,
+        at: Option<usize>,
+        name: String,
+        open_in: OpenIn,
+    ) -> Result<(), Error> {
+        let marker = TempFile::write(format!("editing-{name}"), "")?;
+
+        history::record_current(&path)?;
+        edit_file_in_pane(&path, at, open_in, marker.path())?;
+
+        let orders = self.orders.clone();
+
+        thread::spawn(move || {
+            while marker.path().exists() {
+                thread::sleep(Duration::from_millis(250));
+            }
+
+            orders.write().unwrap().push(Order::EditDone(path));
+        });
+
+        self.status = format!("Editing '{name}' in another pane");
+
+        Ok(())
+    }
+
+    fn edited(&mut self, idx: usize, stash: &mut Stash) {
+        let changed = stash.get(idx).is_some_and(|memo| !memo.eq_origin());
+
+        if changed && let Err(e) = stash.format(idx) {
+            self.status = e.to_string();
+        }
+
+        if let Err(e) = stash.refresh(idx) {
+            self.status = e.to_string();
+
+            return;
+        }
+
+        if !changed {
+            return;
+        }
+
+        if let Err(e) = self.transform(idx, stash) {
+            self.status = e.to_string();
+        }
+
+        if let Some(memo) = stash.get(idx) {
+            if let Err(e) = history::record(memo.current_path(), &memo.content_buffer) {
+                self.status = e.to_string();
+            }
+
+            self.fire(Hook::Edit, &memo.original_path, &memo.content_buffer);
+        }
+    }
+
+    fn scratch(&mut self) {
+        let res = self.input.suspend(|| {
+            disable_tui();
+
+            let res = edit_file(&*SCRATCH_PATH);
+
+            enable_tui();
+
+            res
+        });
+
+        self.status = match res.and_then(|_| read_scratch()) {
+            Ok(content) if content.trim().is_empty() => String::new(),
+            Ok(_) => {
+                String::from("Kept in the scratch memo, ':promote <name>' turns it into a memo")
+            }
+            Err(e) => e.to_string(),
+        };
+    }
+
+    // Moves the scratch memo's content into a new memo and empties the scratch memo.
+    fn promote(&mut self, memo_name: &str, stash: &mut Stash) {
+        let res = read_scratch().and_then(|content| {
+            if content.trim().is_empty() {
+                return Err(Error::new("The scratch memo is empty"));
+            }
+
+            let mut memo = create_new_memo(memo_name, OnCollision::Refuse)?;
+
+            storage::write(&memo.original_path, &meta::with_id(&content)?)?;
+            memo.refresh()?;
+            clear_scratch()?;
+
+            Ok(memo)
+        });
+
+        match res {
+            Ok(memo) => {
+                let path = memo.original_path.clone();
+                let content = memo.content_buffer.clone();
+
+                stash.push(memo);
+                stash.sort();
+
+                self.select(&path, stash);
+                self.status = format!("Promoted the scratch memo to '{memo_name}'");
+                self.fire(Hook::Create, &path, &content);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    // Selects the period's memo, creating it from its template first if needed.
+    fn open_period(&mut self, period: Period, start: chrono::NaiveDate, stash: &mut Stash) {
+        let res =
+            periodic::ensure(period, start, &self.config.periodic).and_then(|(path, created)| {
+                if stash.position(&path).is_none() {
+                    stash.push(Memo::with_content(&path)?);
+                    stash.sort();
+                }
+
+                Ok((path, created))
+            });
+
+        match res {
+            Ok((path, created)) => {
+                self.select(&path, stash);
+
+                if created && let Some(memo) = stash.position(&path).and_then(|idx| stash.get(idx))
+                {
+                    self.status = format!("Created '{}'", memo.name());
+                    self.fire(Hook::Create, &path, &memo.content_buffer);
+                }
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    // Appends `- HH:MM text` under today's heading in the log memo, creating either as needed.
+    fn toggle_timer(&mut self, stash: &Stash) {
+        if let Some(timer) = self.timer.take() {
+            self.status = format!("Stopped the focus session on '{}'", timer.name);
+
+            return;
+        }
+
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+        let minutes = self.config.focus.minutes.max(1);
+
+        self.timer = Some(focus::Session::new(
+            memo.original_path.clone(),
+            memo.name(),
+            minutes,
+        ));
+        self.status = format!("Focusing on '{}' for {minutes} min", memo.name());
+    }
+
+    fn habits(&mut self, stash: &Stash) {
+        let today = chrono::Local::now().date_naive();
+        let basic = self.config.display.basic();
+        let mut lines = vec![];
+
+        for memo in &stash.stash {
+            let Some(habit) = habit::Habit::parse(&memo.content_buffer) else {
+                continue;
+            };
+
+            lines.push(format!(
+                "{} ({})  streak {}, best {}",
+                habit.name,
+                memo.name(),
+                habit.streak(today),
+                habit.best(today)
+            ));
+            lines.extend(
+                habit
+                    .grid(today, basic)
+                    .iter()
+                    .map(|row| format!("  {row}")),
+            );
+            lines.push(String::new());
+        }
+
+        if lines.is_empty() {
+            self.status = String::from("No memo has 'habit: <name>' in its front matter");
+
+            return;
+        }
+
+        self.overlay = Some(Overlay::text("Habits", &lines.join("\n")));
+    }
+
+    fn check_habit(&mut self, stash: &mut Stash) {
+        let Some(idx) = self.selected(stash) else {
+            return;
+        };
+        let memo = &stash.stash[idx];
+        let today = chrono::Local::now().date_naive();
+
+        let Some(habit) = habit::Habit::parse(&memo.content_buffer) else {
+            self.status = format!("'{}' is no habit", memo.name());
+
+            return;
+        };
+
+        let checked = !habit.is_done(today);
+        let content = habit::toggle(&memo.content_buffer, today);
+        let streak = habit::Habit::parse(&content).map_or(0, |habit| habit.streak(today));
+
+        self.status = match self.stage(idx, content, stash) {
+            Ok(()) if checked => format!("Checked off '{}', a streak of {streak}", habit.name),
+            Ok(()) => format!("Took back today's check of '{}'", habit.name),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn clock_in(&mut self, stash: &Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+        let name = memo.name();
+
+        if timesheet::clocked().is_some_and(|(_, memo)| memo == name) {
+            self.status = format!("The clock already runs for '{name}'");
+
+            return;
+        }
+
+        self.status = match timesheet::clock_in(&name) {
+            Ok(Some(previous)) => format!(
+                "Clocked out of '{}' after {} and in to '{name}'",
+                previous.memo,
+                timesheet::format_duration(previous.duration())
+            ),
+            Ok(None) => format!("Clocked in to '{name}'"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn clock_out(&mut self) {
+        self.status = match timesheet::clock_out() {
+            Ok(Some(interval)) => format!(
+                "Clocked out of '{}' after {}",
+                interval.memo,
+                timesheet::format_duration(interval.duration())
+            ),
+            Ok(None) => String::from("The clock is not running"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn finish_timer(&mut self, stash: &mut Stash) {
+        let Some(timer) = self.timer.take() else {
+            return;
+        };
+        let idx = stash.position(&timer.path);
+
+        let res = match (self.config.focus.log, idx) {
+            (FocusLog::Memo, Some(idx)) => {
+                let content = meta::append_under_heading(
+                    &stash.stash[idx].content_buffer,
+                    "## Focus",
+                    &timer.entry(),
+                );
+
+                self.stage(idx, content, stash)
+            }
+            _ => focus::log(&timer),
+        };
+
+        // The bell, in case the terminal is out of sight.
+        let _ = execute!(io::stdout(), Print('\x07'));
+
+        self.status = match res {
+            Ok(()) => format!(
+                "Finished {} min of focus on '{}'",
+                timer.minutes(),
+                timer.name
+            ),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn log_entry(&mut self, text: &str, stash: &mut Stash) {
+        let text = text.trim();
+
+        if text.is_empty() {
+            return;
+        }
+
+        let memo_name = self.config.log.memo.clone();
+        let path = memo_path(&memo_name);
+        let now = chrono::Local::now();
+
+        let res = validate_memo_name(&memo_name)
+            .and_then(|_| match storage::read(&path) {
+                Ok(content) => Ok(Some(content)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(Error::with_cause(
+                    format!("A file '{}' reading failed", path.to_string_lossy()),
+                    e.kind(),
+                )),
+            })
+            .and_then(|content| {
+                let created = content.is_none();
+                let content = meta::append_under_heading(
+                    &content.unwrap_or_default(),
+                    &format!("## {}", now.format("%Y-%m-%d")),
+                    &format!("- {} {text}", now.format("%H:%M")),
+                );
+                let content = match created {
+                    true => meta::with_id(&content)?,
+                    false => content,
+                };
+
+                let path = storage::write(&path, &content)?;
+
+                match stash.position(&path) {
+                    Some(idx) => stash.refresh(idx)?,
+                    None => {
+                        stash.push(Memo::with_content(&path)?);
+                        stash.sort();
+                    }
+                }
+
+                Ok((created, path, content))
+            });
+
+        match res {
+            Ok((created, path, content)) => {
+                self.status = format!("Logged to '{memo_name}'");
+                self.fire(
+                    if created { Hook::Create } else { Hook::Edit },
+                    &path,
+                    &content,
+                );
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn stage(&mut self, idx: usize, content: String, stash: &mut Stash) -> Result<(), Error> {
+        stash.stage(idx, content)?;
+        self.unsaved_since = Some(Instant::now());
+
+        Ok(())
+    }
+
+    // Keeps the journal in step with what is not on disk yet.
+    fn journal(&mut self, stash: &Stash) {
+        let journal = Journal {
+            staged: stash
+                .stash
+                .iter()
+                .filter(|memo| memo.dirty)
+                .map(|memo| (memo.original_path.clone(), memo.content_buffer.clone()))
+                .collect(),
+            log_entry: self
+                .prompt
+                .as_ref()
+                .filter(|prompt| prompt.kind == PromptKind::Log && !prompt.text().is_empty())
+                .map(|prompt| prompt.text().to_string()),
+        };
+
+        if journal == self.journaled {
+            return;
+        }
+
+        match journal::write(&journal) {
+            Ok(()) => self.journaled = journal,
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    // Stages the changes of a journal again, for the autosave to write. A memo gone since comes
+    // back under a new name rather than losing them.
+    fn recover(&mut self, journal: Journal, stash: &mut Stash) {
+        let mut errors = vec![];
+
+        for (path, content) in &journal.staged {
+            let res = match stash.position(path) {
+                Some(idx) => self.stage(idx, content.clone(), stash),
+                None => create_new_memo(
+                    format!("{} (recovered)", memo_name_of(path)),
+                    OnCollision::Numbered,
+                )
+                .and_then(|memo| storage::write(&memo.original_path, &meta::with_id(content)?))
+                .and_then(|path| {
+                    stash.push(Memo::with_content(path)?);
+                    stash.sort();
+
+                    Ok(())
+                }),
+            };
+
+            if let Err(e) = res {
+                errors.push(e.to_string());
+            }
+        }
+
+        if let Some(entry) = &journal.log_entry {
+            let mut prompt = Prompt::new(PromptKind::Log, vec![]);
+
+            prompt.editor.set_buffer(entry.clone());
+            self.open_prompt(prompt);
+        }
+
+        // The file still holds the journal until the recovered changes are written.
+        self.journaled = journal;
+        self.status = match errors.is_empty() {
+            true => String::from("Recovered the unsaved changes"),
+            false => errors.join(", "),
+        };
+    }
+
+    // Writes the staged changes. What fails to be written stays staged and is tried again later,
+    // waiting longer after each failure.
+    fn save(&mut self, stash: &mut Stash) {
+        let (saved, errors) = stash.save();
+
+        for idx in saved {
+            let memo = &stash.stash[idx];
+
+            self.fire(Hook::Edit, &memo.original_path, &memo.content_buffer);
+        }
+
+        if errors.is_empty() {
+            self.unsaved_since = None;
+            self.autosave_delay = AUTOSAVE_DELAY;
+
+            return;
+        }
+
+        self.unsaved_since = Some(Instant::now());
+        self.autosave_delay = (self.autosave_delay * 2).min(MAX_AUTOSAVE_DELAY);
+        self.status = errors
+            .iter()
+            .map(Error::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+
+    // Runs the plugins' on_save transforms over a freshly edited memo.
+    fn transform(&self, idx: usize, stash: &mut Stash) -> Result<(), Error> {
+        let Some(memo) = stash.get(idx) else {
+            return Ok(());
+        };
+        let content = self
+            .plugins
+            .transform(&memo.original_path, &memo.content_buffer)?;
+
+        if content == memo.content_buffer {
+            return Ok(());
+        }
+
+        stash.rewrite(idx, content)
+    }
+
+    fn delete(&mut self, stash: &mut Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+        let from = memo.original_path.clone();
+        let content = memo.content_buffer.clone();
+
+        match trash_memo(memo) {
+            Ok(to) => {
+                self.forget(&from, stash);
+                self.record(Change::Trash {
+                    from: from.clone(),
+                    to,
+                });
+                self.fire(Hook::Delete, &from, &content);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn delete_permanently(&mut self, stash: &mut Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+
+        let name = memo.name();
+        let path = memo.original_path.clone();
+        let content = memo.content_buffer.clone();
+
+        match delete_memo(Memo::new(&path), self.config.delete.secure) {
+            Ok(_) => {
+                self.forget(&path, stash);
+                self.status = format!("Deleted '{name}' permanently");
+                self.fire(Hook::Delete, &path, &content);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn archive(&mut self, stash: &mut Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+        let from = memo.original_path.clone();
+
+        match archive_memo(memo) {
+            Ok(to) => {
+                self.forget(&from, stash);
+                self.record(Change::Archive { from, to });
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn rename(&mut self, new_name: &str, stash: &mut Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+        let from = memo.original_path.clone();
+
+        if memo.name() == new_name {
+            return;
+        }
+
+        match rename_memo(memo, new_name) {
+            Ok(to) => {
+                self.forget(&from, stash);
+
+                if let Err(e) = self.adopt(&to, stash) {
+                    self.status = e.to_string();
+
+                    return;
+                }
+
+                let (old, new) = (memo_name_of(&from), memo_name_of(&to));
+
+                self.record(Change::Rename { from, to });
+                self.offer_relinks(&old, &new, stash);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    // Shows the lines a rename leaves pointing at the old name, to have them rewritten.
+    fn offer_relinks(&mut self, from: &str, to: &str, stash: &Stash) {
+        let relinks = links::relinks(stash, from, to);
+        let items = relinks
+            .iter()
+            .flat_map(|relink| {
+                relink.lines.iter().map(|(line, old, new)| {
+                    (
+                        format!("{}:{line}  {} -> {}", relink.name, old.trim(), new.trim()),
+                        Pick::Relink(from.to_string(), to.to_string()),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            return;
+        }
+
+        let title = format!(
+            "Rewrite the links to '{from}' in {} memos? (y/Enter: rewrite, n: leave them)",
+            relinks.len()
+        );
+
+        self.open_picker(Picker::new(title, items).with_actions(&['y', 'n']));
+    }
+
+    fn relink(&mut self, from: &str, to: &str, stash: &mut Stash) {
+        let relinks = links::relinks(stash, from, to);
+
+        for relink in &relinks {
+            let Some(idx) = stash.position(&relink.path) else {
+                continue;
+            };
+
+            // The rewrite was made from the whole file, which staging takes only over a whole memo.
+            let loaded = match stash.get(idx).is_some_and(|memo| memo.partial) {
+                true => stash.load_full(idx),
+                false => Ok(()),
+            };
+
+            if let Err(e) = loaded.and_then(|_| self.stage(idx, relink.content.clone(), stash)) {
+                self.status = e.to_string();
+
+                return;
+            }
+        }
+
+        self.status = format!("Rewrote the links to '{from}' in {} memos", relinks.len());
+    }
+
+    fn run_shell(&mut self, idx: usize, stash: &mut Stash) {
+        let Some(key) = self.config.keys.values().nth(idx) else {
+            return;
+        };
+        let memo = self.selected(stash).and_then(|idx| stash.get(idx));
+
+        match expand_shell_line(&key.shell, memo) {
+            Ok((line, copy)) => self.shell(line, copy, key.output, stash),
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    // `:!cmd {file}` runs a one-off command over the selected memo and shows what it prints.
+    fn bang(&mut self, line: &str, stash: &mut Stash) {
+        if line.trim().is_empty() {
+            self.status = String::from("Usage: :!<command>, with {file} and {name} for the memo");
+
+            return;
+        }
+
+        let memo = self.selected(stash).and_then(|idx| stash.get(idx));
+
+        match expand_shell_line(line, memo) {
+            Ok((line, copy)) => self.shell(line, copy, ShellOutput::Scratch, stash),
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    // A plain copy of a compressed memo is written back once the command has succeeded.
+    fn shell(
+        &mut self,
+        line: String,
+        copy: Option<PlainCopy>,
+        kind: ShellOutput,
+        stash: &mut Stash,
+    ) {
+        if kind == ShellOutput::Terminal {
+            let res = self.input.suspend(|| {
+                disable_tui();
+
+                let res = run_external(&mut shell_command(&line));
+
+                enable_tui();
+
+                res
+            });
+            let res = match copy {
+                Some(copy) => res.and_then(|_| copy.write_back()),
+                None => res,
+            };
+
+            if let Err(e) = res {
+                self.status = e.to_string();
+            }
+
+            if let Some(idx) = self.selected(stash)
+                && let Err(e) = stash.refresh(idx)
+            {
+                self.status = e.to_string();
+            }
+
+            return;
+        }
+
+        // Everything else runs in the background and reports back through an order.
+        let orders = self.orders.clone();
+
+        thread::spawn(move || {
+            let res = shell_command(&line).stdin(Stdio::null()).output();
+
+            let text = match res {
+                Ok(out) => {
+                    let mut text = String::from_utf8_lossy(&out.stdout).to_string();
+
+                    text.push_str(&String::from_utf8_lossy(&out.stderr));
+
+                    let written = match copy {
+                        Some(copy) if out.status.success() => copy.write_back(),
+                        _ => Ok(()),
+                    };
+
+                    if let Err(e) = written {
+                        e.to_string()
+                    } else if !out.status.success() && kind != ShellOutput::Scratch {
+                        format!("'{line}' failed: {}", out.status)
+                    } else {
+                        text
+                    }
+                }
+                Err(e) => format!("'{line}' executing failed: {}", e.kind()),
+            };
+
+            let output = match kind {
+                ShellOutput::None => return,
+                ShellOutput::Scratch => text,
+                _ => text
+                    .lines()
+                    .rfind(|line| !line.trim().is_empty())
+                    .unwrap_or_default()
+                    .to_string(),
+            };
+
+            orders.write().unwrap().push(Order::ShellDone {
+                line,
+                output,
+                scratch: kind == ShellOutput::Scratch,
+            });
+        });
+    }
+
+    fn run_plugin<F>(&mut self, stash: &mut Stash, f: F)
+    where
+        F: FnOnce(&Plugins, &Path, &str) -> Result<Outcome, Error>,
+    {
+        let Some(idx) = self.selected(stash) else {
+            return;
+        };
+        let memo = &stash.stash[idx];
+
+        let res = match f(&self.plugins, &memo.original_path, &memo.content_buffer) {
+            Ok(Outcome::Nothing) => Ok(()),
+            Ok(Outcome::Status(status)) => {
+                self.status = status;
+
+                Ok(())
+            }
+            Ok(Outcome::Rewrite(content)) => self.stage(idx, content, stash),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = res {
+            self.status = e.to_string();
+        }
+    }
+
+    // Hook failures only surface in the status line; the operation itself already succeeded.
+    fn fire(&mut self, hook: Hook, path: &Path, content: &str) {
+        if let Err(e) = hooks::fire(&self.config.hooks, hook, path, content) {
+            self.status = e.to_string();
+        }
+    }
+
+    fn record(&mut self, change: Change) {
+        self.status = format!("{} (u: undo)", change.summary());
+        self.undo_log.record(change);
+    }
+
+    fn undo(&mut self, stash: &mut Stash) {
+        let Some(change) = self.undo_log.pop_undo() else {
+            self.status = String::from("Nothing to undo");
+
+            return;
+        };
+
+        let result = match &change {
+            Change::Trash { from, to }
+            | Change::Rename { from, to }
+            | Change::Archive { from, to } => self.move_memo(to, from, stash),
+            Change::Tag { path, added } => self.retag(path, stash, |tags| {
+                tags.retain(|tag| !added.contains(tag));
+            }),
+        };
+
+        match result {
+            Ok(_) => {
+                self.status = format!("Undid {}", change.describe());
+                self.undo_log.push_undone(change);
+            }
+            Err(e) => {
+                self.status = e.to_string();
+                self.undo_log.push_done(change);
+            }
+        }
+    }
+
+    fn redo(&mut self, stash: &mut Stash) {
+        let Some(change) = self.undo_log.pop_redo() else {
+            self.status = String::from("Nothing to redo");
+
+            return;
+        };
+
+        let result = match &change {
+            Change::Trash { from, to }
+            | Change::Rename { from, to }
+            | Change::Archive { from, to } => self.move_memo(from, to, stash),
+            Change::Tag { path, added } => self.retag(path, stash, |tags| {
+                for tag in added {
+                    if !tags.contains(tag) {
+                        tags.push(tag.clone());
+                    }
+                }
+            }),
+        };
+
+        match result {
+            Ok(_) => {
+                self.status = format!("Redid {}", change.describe());
+                self.undo_log.push_done(change);
+            }
+            Err(e) => {
+                self.status = e.to_string();
+                self.undo_log.push_undone(change);
+            }
+        }
+    }
+
+    fn move_memo(&mut self, from: &Path, to: &Path, stash: &mut Stash) -> Result<(), Error> {
+        move_file(from, to)?;
+
+        self.forget(from, stash);
+
+        if to.starts_with(&*MEMO_LIST_PATH) || self.source_of(to).is_some() {
+            self.adopt(to, stash)?;
+        }
+
+        Ok(())
+    }
+
+    // Drops a memo that has left the stash directory from the list.
+    fn forget(&mut self, path: &Path, stash: &mut Stash) {
+        if let Some(idx) = stash.position(path) {
+            stash.remove(idx);
+        }
+
+        self.view.cursor = self
+            .view
+            .cursor
+            .min(self.visible(stash).len().saturating_sub(1));
+    }
+
+    // Lists a memo created behind the UI's back (e.g. by `me add`) without moving the selection.
+    fn insert(&mut self, path: &Path, stash: &mut Stash) {
+        // Appended to a listed memo.
+        if let Some(idx) = stash.position(path) {
+            self.status = match stash.refresh(idx) {
+                Ok(_) => format!("Captured into '{}'", stash.get(idx).unwrap().name()),
+                Err(e) => e.to_string(),
+            };
+
+            return;
+        }
+
+        let selected = self
+            .selected(stash)
+            .and_then(|idx| stash.get(idx))
+            .map(|memo| memo.original_path.clone());
+
+        match Memo::with_content(path) {
+            Ok(memo) => {
+                self.status = format!("Captured '{}'", memo.name());
+
+                stash.push(memo);
+                stash.sort();
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+
+        if let Some(selected) = selected {
+            self.select(selected, stash);
+        }
+    }
+
+    fn open_startup(&mut self, stash: &mut Stash) {
+        match self.config.startup.clone() {
+            Startup::List => {}
+            // Quietly stays at the top if the memo is gone.
+            Startup::Last => {
+                if let Ok(path) = fs::read_to_string(&*LAST_OPENED_PATH) {
+                    self.select(path.trim_end(), stash);
+                }
+            }
+            Startup::Today => {
+                let today = chrono::Local::now().date_naive();
+
+                self.open_period(Period::Day, today, stash);
+            }
+            Startup::Search(name) => self.saved_search(&name),
+        }
+    }
+
+    fn saved_search(&mut self, name: &str) {
+        match self.config.searches.get(name) {
+            Some(query) => {
+                self.view.filter = Some(query.clone()).filter(|query| !query.is_empty());
+                self.view.cursor = 0;
+            }
+            None => self.status = format!("A saved search '{name}' is not found"),
+        }
+    }
+
+    // Runs `work` on a worker thread, its progress in the status line until it reports back.
+    fn start_task<F>(&mut self, label: &str, work: F)
+    where
+        F: FnOnce(&Progress) -> Result<Done, Error> + Send + 'static,
+    {
+        if let Some(task) = &self.task {
+            self.status = format!("{} is still running", task.label);
+
+            return;
+        }
+
+        let task = Task::new(label);
+        let progress = task.progress.clone();
+        let orders = self.orders.clone();
+
+        thread::spawn(move || {
+            let done = work(&progress).map_err(|e| e.to_string());
+
+            orders.write().unwrap().push(Order::TaskDone(done));
+        });
+
+        self.task = Some(task);
+        self.status.clear();
+    }
+
+    fn finish_task(&mut self, done: Result<Done, String>, stash: &mut Stash) {
+        let Some(task) = self.task.take() else {
+            return;
+        };
+
+        // What it wrote before it finished, failed or was cancelled is on disk all the same.
+        self.rescan(stash);
+        self.reread(stash);
+
+        match done {
+            Ok(Done::Status(status)) => self.status = status,
+            Ok(Done::Found(query, paths)) => self.found(&query, paths, stash),
+            Err(_) if task.progress.is_cancelled() => {
+                self.status = format!("{} cancelled", task.label);
+            }
+            Err(e) => self.status = e,
+        }
+    }
+
+    // Searches the full text of the memos through the storage backend, like `me search`.
+    fn find(&mut self, query: &str) {
+        if query.is_empty() {
+            self.status = String::from("Usage: :find <query>");
+
+            return;
+        }
+
+        let query = query.to_string();
+
+        self.start_task("Searching", move |progress| {
+            let paths = storage::search(&memo_paths()?, &query, progress)?;
+
+            Ok(Done::Found(query, paths))
+        });
+    }
+
+    // Hidden memos are only offered while the list shows them.
+    fn found(&mut self, query: &str, paths: Vec<PathBuf>, stash: &Stash) {
+        let positions = stash.positions();
+        let items = paths
+            .into_iter()
+            .filter_map(|path| {
+                let memo = stash.get(*positions.get(&storage::plain_path(path))?)?;
+
+                (self.view.show_hidden || !memo.is_hidden())
+                    .then(|| (memo.name(), Pick::Select(memo.original_path.clone())))
+            })
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            self.status = format!("No memo matches '{query}'");
+
+            return;
+        }
+
+        self.open_picker(Picker::new(
+            format!("{} memos match '{query}':", items.len()),
+            items,
+        ));
+    }
+
+    // Imports files, or another app's export with `--from <app>`, like `me import`.
+    fn import(&mut self, arg: &str) {
+        let (format, sources) = match arg.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["--from", format, sources @ ..] => (Some(format.to_string()), sources.to_vec()),
+            sources => (None, sources.to_vec()),
+        };
+
+        if sources.is_empty() {
+            self.status =
+                String::from("Usage: :import [--from simplenote|jrnl|standardnotes] <file|dir>...");
+
+            return;
+        }
+
+        let sources = sources
+            .iter()
+            .map(|source| config::expand_home(Path::new(source)))
+            .collect::<Vec<_>>();
+
+        self.start_task("Importing", move |progress| {
+            let plan = importer::plan(&sources, format.as_deref())?;
+
+            plan.apply(progress)?;
+
+            Ok(Done::Status(match plan.len() {
+                0 => String::from("Nothing to import"),
+                count => format!("{count} memos imported"),
+            }))
+        });
+    }
+
+    // Exports the memos matching a query, or the ones listed without one, into a directory, one
+    // file each, like `me export --query`.
+    fn export(&mut self, arg: &str, stash: &Stash) {
+        let words = arg.split_whitespace().collect::<Vec<_>>();
+        let [format, out, query @ ..] = words.as_slice() else {
+            self.status = String::from("Usage: :export pdf|docx|md <dir> [query]");
+
+            return;
+        };
+        let format = match export::Format::parse(format) {
+            Ok(format) => format,
+            Err(e) => {
+                self.status = e.to_string();
+
+                return;
+            }
+        };
+        let query = query.join(" ");
+        let paths = match query.is_empty() {
+            true => self
+                .visible(stash)
+                .into_iter()
+                .filter_map(|idx| stash.get(idx))
+                .map(|memo| memo.original_path.clone())
+                .collect::<Vec<_>>(),
+            false => stash
+                .stash
+                .iter()
+                .filter(|memo| memo.matches_all(&query))
+                .map(|memo| memo.original_path.clone())
+                .collect(),
+        };
+
+        if paths.is_empty() {
+            self.status = match query.is_empty() {
+                true => String::from("No memo is listed"),
+                false => format!("No memo matches '{query}'"),
+            };
+
+            return;
+        }
+
+        let out = config::expand_home(Path::new(out));
+
+        self.start_task("Exporting", move |progress| {
+            export::export_all(
+                &paths,
+                format,
+                &out,
+                &config::load()?.export,
+                Some(progress),
+            )?;
+
+            Ok(Done::Status(format!(
+                "{} memos exported to '{}'",
+                paths.len(),
+                out.to_string_lossy()
+            )))
+        });
+    }
+
+    // Pushes the stash to the configured sync backend, or pulls it with `pull`, through a `me sync`
+    // of its own so nothing the tools behind it print lands on the screen.
+    fn sync(&mut self, arg: &str, stash: &mut Stash) {
+        let pull = match arg {
+            "" => false,
+            "pull" => true,
+            _ => {
+                self.status = String::from("Usage: :sync [pull]");
+
+                return;
+            }
+        };
+        let Some(backend) = self.config.sync.backend.clone() else {
+            self.status = Error::with_cause(
+                "The sync starting failed",
+                "no backend is configured in sync.backend",
+            )
+            .to_string();
+
+            return;
+        };
+
+        // A push takes the files as they are, so what is still held back goes first. A pull would
+        // have the next autosave write an unsaved buffer over what it brings.
+        self.save(stash);
+
+        if pull && stash.is_dirty() {
+            self.status = Error::with_cause(
+                "The sync starting failed",
+                "some changes could not be written and a pull would lose them",
+            )
+            .to_string();
+
+            return;
+        }
+
+        self.start_task("Syncing", move |progress| {
+            let failed = |cause: String| Error::with_cause("The sync starting failed", cause);
+
+            if sync::prompts(&config::load()?.sync.encryption)? {
+                return Err(failed(String::from(
+                    "it would ask for the passphrase; with sync.encryption.keyring set, one 'me sync' in a shell keeps it",
+                )));
+            }
+
+            let exe = std::env::current_exe().map_err(|e| failed(e.kind().to_string()))?;
+            let mut cmd = Command::new(exe);
+
+            if let Some(profile) = config::profile() {
+                cmd.args(["--profile", profile]);
+            }
+
+            cmd.args(["sync", "--backend", &backend]);
+
+            if pull {
+                cmd.arg("--pull");
+            }
+
+            run_detached(&mut cmd, progress)?;
+
+            Ok(Done::Status(match pull {
+                true => format!("Pulled from {backend}"),
+                false => format!("Pushed to {backend}"),
+            }))
+        });
+    }
+
+    // Reads the memos again on worker threads, applying what changed once they are done.
+    fn reread(&mut self, stash: &Stash) {
+        if self.rereading {
+            return;
+        }
+
+        self.rereading = true;
+        reread_in_background(stash.reread_jobs(), self.orders.clone());
+    }
+
+    // Catches up with memo files added, removed or renamed outside the TUI. A renamed memo stays
+    // selected, and undoing a rename of it still finds it.
+    fn rescan(&mut self, stash: &mut Stash) {
+        self.scanned = Instant::now();
+
+        let selected = self
+            .selected(stash)
+            .and_then(|idx| stash.get(idx))
+            .map(|memo| memo.original_path.clone());
+
+        let renames = match self.unlisted(stash).and_then(|found| stash.rescan(found)) {
+            Ok(Some(renames)) => renames,
+            Ok(None) => return,
+            Err(e) => {
+                self.status = e.to_string();
+                self.redraw = true;
+
+                return;
+            }
+        };
+
+        let mut selected = selected;
+
+        for (from, to) in &renames {
+            self.undo_log.relocate(from, to);
+
+            if selected.as_ref() == Some(from) {
+                selected = Some(to.clone());
+            }
+        }
+
+        if let Some((from, to)) = renames.last() {
+            self.status = format!(
+                "Noticed '{}' renamed to '{}'",
+                memo_name_of(from),
+                memo_name_of(to)
+            );
+        }
+
+        self.view.cursor = self
+            .view
+            .cursor
+            .min(self.visible(stash).len().saturating_sub(1));
+
+        if let Some(selected) = selected {
+            self.select(selected, stash);
+        }
+
+        self.redraw = true;
+    }
+
+    // Memo files in the stash directory and the sources that are not listed yet, with the badge
+    // of the source they are in.
+    fn unlisted(&self, stash: &Stash) -> Result<Vec<(PathBuf, Option<String>)>, Error> {
+        let mut found = memo_paths()?
+            .into_iter()
+            .map(|path| (path, None))
+            .collect::<Vec<_>>();
+
+        for source in &self.config.sources {
+            // A missing source was reported at startup.
+            if let Ok(paths) = markdown_paths(&source.dir(), false) {
+                found.extend(paths.into_iter().map(|path| (path, Some(source.badge()))));
+            }
+        }
+
+        let positions = stash.positions();
+
+        found.retain(|(path, _)| !positions.contains_key(&storage::plain_path(path)));
+
+        if found.is_empty() {
+            return Ok(found);
+        }
+
+        let mut listed = stash
+            .stash
+            .iter()
+            .filter_map(|memo| memo.original_path.canonicalize().ok())
+            .collect::<HashSet<_>>();
+
+        found.retain(|(path, _)| path.canonicalize().is_ok_and(|real| listed.insert(real)));
+
+        Ok(found)
+    }
+
+    // Lists a memo that has (re)appeared in the stash directory and selects it.
+    fn adopt(&mut self, path: &Path, stash: &mut Stash) -> Result<(), Error> {
+        let mut memo = Memo::with_content(path)?;
+
+        memo.source = self.source_of(path);
+
+        stash.push(memo);
+        stash.sort();
+
+        self.select(path, stash);
+
+        Ok(())
+    }
+
+    fn source_of(&self, path: &Path) -> Option<String> {
+        self.config
+            .sources
+            .iter()
+            .find(|source| path.starts_with(source.dir()))
+            .map(SourceConfig::badge)
+    }
+
+    fn new_prompt(&mut self, kind: PromptKind) {
+        let history = self.histories.get(&kind).cloned().unwrap_or_default();
+
+        self.open_prompt(Prompt::new(kind, history));
+    }
+
+    fn open_prompt(&mut self, prompt: Prompt) {
+        self.prompt = Some(prompt);
+        self.input.set_context(Context::Prompt);
+    }
+
+    fn close_prompt(&mut self) {
+        self.prompt = None;
+        self.input.set_context(Context::List);
+    }
+
+    // Takes up edits to the config file without a restart. The editor command and the compression
+    // threshold are read once per process and still need one.
+    fn reload_config(&mut self) {
+        self.config_modified = config::modified();
+
+        let config = match config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                self.status = e.to_string();
+
+                return;
+            }
+        };
+        let (keymaps, invalid) = keymaps_of(&self.plugins, &config);
+
+        *self.keymaps.write().unwrap() = keymaps;
+        self.config = config;
+        self.status = match invalid.is_empty() {
+            true => String::from("Reloaded the config"),
+            false => format!("Keymaps are invalid: {}", invalid.join(", ")),
+        };
+    }
+
+    fn palette(&mut self) {
+        let mut actions = [
+            ("Edit the memo", Order::Edit),
+            ("New memo", Order::Create),
+            ("Rename the memo", Order::Rename),
+            ("Delete the memo", Order::Delete),
+            (
+                "Delete the memo permanently",
+                Order::Run(String::from("rm!")),
+            ),
+            ("Archive the memo", Order::Archive),
+            ("Load the memo in full", Order::Run(String::from("load"))),
+            ("Mark the memo", Order::ToggleMark),
+            ("Edit the marked memos", Order::EditSelection),
+            ("Toggle the memo as a favorite", Order::ToggleFavorite),
+            ("Switch between all memos and favorites", Order::Favorites),
+            ("Show or hide hidden memos", Order::ToggleHidden),
+            (
+                "Switch between the list and a grid of cards",
+                Order::ToggleGrid,
+            ),
+            ("Open a new tab", Order::NewTab),
+            ("Close the tab", Order::CloseTab),
+            ("Go to the next tab", Order::StepTab(1)),
+            ("Go to the previous tab", Order::StepTab(-1)),
+            ("Split the screen into two panes", Order::Split),
+            ("Go to the other pane", Order::OtherPane),
+            ("Close the other pane", Order::Unsplit),
+            ("Undo", Order::Undo),
+            ("Redo", Order::Redo),
+            ("Select the next memo", Order::Down),
+            ("Select the previous memo", Order::Up),
+            ("Search memos", Order::Search),
+            ("Enter a command", Order::Command),
+            ("Open a link of the memo", Order::OpenLink),
+            ("Show the memo as a QR code", Order::Qr),
+            ("Show the links of the memo", Order::Graph),
+            ("Edit the scratch memo", Order::Scratch),
+            ("Add a log entry", Order::LogEntry),
+            ("Add a line to the memo", Order::QuickEdit),
+            ("Start or stop a focus session", Order::Timer),
+            ("Clock in to the memo", Order::ClockIn),
+            ("Clock out", Order::ClockOut),
+            ("Show the habits", Order::Habits),
+            ("Check the habit off for today", Order::CheckHabit),
+            ("Copy a field of the memo", Order::CopyField),
+            ("Select a memo long gone unseen", Order::Random),
+            ("Review memos long gone unseen", Order::Review),
+            ("Go through the stale memos", Order::Stale),
+            ("Open today's note", Order::Periodic(Period::Day)),
+            ("Open this week's note", Order::Periodic(Period::Week)),
+            ("Open this month's note", Order::Periodic(Period::Month)),
+            ("Open the previous periodic note", Order::StepPeriod(-1)),
+            ("Open the next periodic note", Order::StepPeriod(1)),
+            ("Show the calendar", Order::Calendar),
+            ("Show the board", Order::Board),
+            ("Toggle spell checking", Order::ToggleSpell),
+            ("Check the memo for issues", Order::Lint),
+            ("List broken links", Order::BrokenLinks),
+            ("Cycle preview wrapping", Order::CycleWrap),
+            ("Scroll the preview left", Order::ScrollLeft),
+            ("Scroll the preview right", Order::ScrollRight),
+            ("Lock the screen", Order::Lock),
+            ("Reload the config", Order::ReloadConfig),
+            ("Toggle the debug HUD", Order::DebugHud),
+            ("Quit", Order::Exit),
+        ]
+        .map(|(name, order)| (name.to_string(), order))
+        .to_vec();
+
+        actions.extend(
+            self.config
+                .keys
+                .values()
+                .enumerate()
+                .map(|(idx, key)| (format!("Run '{}'", key.shell), Order::Shell(idx))),
+        );
+        actions.extend(
+            self.plugins
+                .bindings()
+                .filter_map(|(idx, _)| Some((idx, self.plugins.binding_name(idx)?)))
+                .map(|(idx, name)| (format!("Plugin: {name}"), Order::Plugin(idx))),
+        );
+        actions.extend(
+            self.plugins
+                .commands()
+                .map(|name| (format!("Plugin: {name}"), Order::Run(name.to_string()))),
+        );
+        actions.extend(self.config.searches.keys().map(|name| {
+            (
+                format!("Search '{name}'"),
+                Order::Run(format!("search {name}")),
+            )
+        }));
+
+        let keymaps = self.keymaps.read().unwrap();
+        let items = actions
+            .into_iter()
+            .map(|(name, order)| {
+                let keys = match &order {
+                    Order::Run(line) => format!(":{line}"),
+                    _ => keymaps.keys_for(Context::List, &order).join(", "),
+                };
+
+                (name, keys, order)
+            })
+            .collect();
+
+        self.palette = Some(Palette::new(items));
+        self.input.set_context(Context::Prompt);
+    }
+
+    fn open_picker(&mut self, picker: Picker<Pick>) {
+        self.picker = Some(picker);
+        self.input.set_context(Context::Dialog);
+    }
+
+    fn load(&mut self, stash: &mut Stash) {
+        let Some(idx) = self.selected(stash) else {
+            return;
+        };
+
+        if stash.get(idx).is_some_and(|memo| !memo.partial) {
+            self.status = String::from("The memo is already loaded in full");
+
+            return;
+        }
+
+        self.status = match stash.load_full(idx) {
+            Ok(_) => String::from("Loaded the memo in full"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn lock(&mut self) {
+        if self.config.lock.passphrase_hash.is_none() {
+            self.status = String::from("No lock passphrase is set, make one with 'me lock'");
+
+            return;
+        }
+
+        self.prompt = None;
+        self.close_dialog();
+        self.status.clear();
+        self.locked = Some(String::new());
+        self.input.set_context(Context::Locked);
+    }
+
+    fn input_lock(&mut self, key: KeyEvent) {
+        let Some(typed) = self.locked.as_mut() else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Enter => {
+                let typed = std::mem::take(typed);
+                let res = match &self.config.lock.passphrase_hash {
+                    Some(hash) => lock::verify(&typed, hash),
+                    None => Ok(true),
+                };
+
+                match res {
+                    Ok(true) => {
+                        self.locked = None;
+                        self.status.clear();
+                        self.input.set_context(Context::List);
+                    }
+                    Ok(false) => self.status = String::from("The passphrase is wrong"),
+                    Err(e) => self.status = e.to_string(),
+                }
+            }
+            KeyCode::Backspace => {
+                typed.pop();
+            }
+            KeyCode::Esc => typed.clear(),
+            KeyCode::Char(_) if key.modifiers.contains(KeyModifiers::CONTROL) => {}
+            KeyCode::Char(c) => typed.push(c),
+            _ => {}
+        }
+    }
+
+    fn open_overlay(&mut self, overlay: Overlay) {
+        self.input.set_context(if overlay.fixed {
+            Context::Dialog
+        } else {
+            Context::Pager
+        });
+        self.overlay = Some(overlay);
+    }
+
+    fn open_calendar(&mut self, calendar: Calendar) {
+        self.calendar = Some(calendar);
+        self.input.set_context(Context::Dialog);
+    }
+
+    fn open_board(&mut self, board: Board) {
+        self.board = Some(board);
+        self.input.set_context(Context::Dialog);
+    }
+
+    fn close_dialog(&mut self) {
+        self.picker = None;
+        self.palette = None;
+        self.overlay = None;
+        self.calendar = None;
+        self.board = None;
+        self.input.set_context(Context::List);
+    }
+
+    fn input_dialog(&mut self, key: KeyEvent, stash: &mut Stash) {
+        if let Some(palette) = self.palette.as_mut() {
+            match palette.handle(key) {
+                Picked::Pending | Picked::Action(..) => {}
+                Picked::Cancelled => self.close_dialog(),
+                Picked::Chosen(order) => {
+                    self.close_dialog();
+                    self.handle(order, stash);
+                }
+            }
+
+            return;
+        }
+
+        if let Some(overlay) = self.overlay.as_mut() {
+            if !overlay.handle(key) {
+                self.close_dialog();
+            }
+
+            return;
+        }
+
+        if let Some(board) = self.board.as_mut() {
+            match board.handle(key) {
+                BoardEvent::Pending => {}
+                BoardEvent::Closed => self.close_dialog(),
+                BoardEvent::Open(path) => {
+                    self.close_dialog();
+                    self.select(&path, stash);
+                }
+                BoardEvent::Moved(path, column) => {
+                    if let Err(e) = self.move_card(&path, &column, stash) {
+                        self.status = e.to_string();
+                        // Redraw from the files so the board does not show a move that failed.
+                        self.board(stash);
+                    }
+                }
+            }
+
+            return;
+        }
+
+        if let Some(calendar) = self.calendar.as_mut() {
+            match calendar.handle(key) {
+                Picked::Pending | Picked::Action(..) => {}
+                Picked::Cancelled => self.close_dialog(),
+                Picked::Chosen(date) => {
+                    self.close_dialog();
+                    self.open_period(Period::Day, date, stash);
+
+                    if self
+                        .selected(stash)
+                        .and_then(|idx| stash.get(idx))
+                        .is_some_and(|memo| {
+                            periodic::recognize(&memo.name(), &self.config.periodic)
+                                == Some((Period::Day, date))
+                        })
+                    {
+                        self.edit(stash);
+                    }
+                }
+            }
+
+            return;
+        }
+
+        let Some(picker) = self.picker.as_mut() else {
+            return;
+        };
+
+        match picker.handle(key) {
+            Picked::Pending => {}
+            Picked::Cancelled => self.close_dialog(),
+            Picked::Chosen(pick) => {
+                self.close_dialog();
+
+                match pick {
+                    Pick::Qr(text) => self.show_qr(&text),
+                    Pick::Open(url) => self.open_url(&url),
+                    Pick::Follow(path) => {
+                        self.select(&path, stash);
+                        self.graph(stash);
+                    }
+                    Pick::Select(path) => self.select(&path, stash),
+                    Pick::Jump(path, line) => {
+                        self.select(&path, stash);
+                        self.edit_at(stash, Some(line));
+                    }
+                    Pick::Broken(link) => {
+                        self.select(&link.from, stash);
+                        self.edit_at(stash, Some(link.line));
+                    }
+                    Pick::Copy(key, value) => self.copy(&key, &value),
+                    Pick::Review(path) => {
+                        self.review.clear();
+                        self.select(&path, stash);
+                    }
+                    Pick::Relink(from, to) => self.relink(&from, &to, stash),
+                }
+            }
+            Picked::Action(key, Pick::Broken(link)) => {
+                self.close_dialog();
+
+                match key {
+                    'c' => self.create(&link.target, OnCollision::Refuse, stash),
+                    'e' => {
+                        self.select(&link.from, stash);
+                        self.edit_at(stash, Some(link.line));
+                    }
+                    _ => match links::ignore(&link) {
+                        Ok(()) => self.status = format!("Ignoring '[[{}]]'", link.target),
+                        Err(e) => self.status = e.to_string(),
+                    },
+                }
+
+                // Back to the rest of them, unless a new prompt or picker took over.
+                if self.prompt.is_none()
+                    && self.picker.is_none()
+                    && key != 'e'
+                    && !links::broken(stash).is_empty()
+                {
+                    self.broken_links(stash);
+                }
+            }
+            Picked::Action(key, Pick::Review(path)) => {
+                self.close_dialog();
+                self.review.retain(|p| *p != path);
+                self.select(&path, stash);
+
+                match key {
+                    'a' => self.archive(stash),
+                    'd' => self.delete(stash),
+                    't' => self.new_prompt(PromptKind::Tag),
+                    'm' => self.new_prompt(PromptKind::Merge),
+                    // Kept, which holds it back from the stale ones for a while.
+                    _ => {
+                        let memo = self.selected(stash).and_then(|idx| stash.get(idx));
+
+                        if let Some(Err(e)) = memo.map(|memo| review::mark(&[memo.name()])) {
+                            self.status = e.to_string();
+                        }
+                    }
+                }
+
+                // The tag and merge prompts go back to the review once they are done with.
+                if self.prompt.is_none() {
+                    self.open_review(stash);
+                }
+            }
+            Picked::Action(key, Pick::Relink(from, to)) => {
+                self.close_dialog();
+
+                if key == 'y' {
+                    self.relink(&from, &to, stash);
+                }
+            }
+            Picked::Action(..) => {}
+        }
+    }
+
+    fn board_column(&self, content: &str) -> Option<usize> {
+        let (front, _) = meta::split(content);
+        let columns = &self.config.board.columns;
+        let find = |name: &str| {
+            columns
+                .iter()
+                .position(|column| column.eq_ignore_ascii_case(name))
+        };
+
+        match front.get("status") {
+            Some(status) => find(status),
+            None => front.tags().into_iter().find_map(find),
+        }
+    }
+
+    fn board(&mut self, stash: &Stash) {
+        let mut columns = self
+            .config
+            .board
+            .columns
+            .iter()
+            .map(|column| (column.clone(), vec![]))
+            .collect::<Vec<_>>();
+
+        for memo in (0..stash.len()).filter_map(|idx| stash.get(idx)) {
+            if let Some(column) = self.board_column(&memo.content_buffer) {
+                columns[column]
+                    .1
+                    .push((memo.name(), memo.original_path.clone()));
+            }
+        }
+
+        if columns.iter().all(|(_, cards)| cards.is_empty()) {
+            self.status = format!(
+                "No memo has a status of {}",
+                self.config.board.columns.join(", ")
+            );
+
+            return;
+        }
+
+        self.open_board(Board::new(columns));
+    }
+
+    // Sets the front matter status, dropping any tags that named a column.
+    fn move_card(&mut self, path: &Path, column: &str, stash: &mut Stash) -> Result<(), Error> {
+        let Some(idx) = stash.position(path) else {
+            return Err(Error::new("The memo is no longer in the stash"));
+        };
+        let content = &stash.get(idx).unwrap().content_buffer;
+        let (mut front, body) = meta::split(content);
+
+        let columns = &self.config.board.columns;
+        let tags = front
+            .tags()
+            .into_iter()
+            .filter(|tag| {
+                !columns
+                    .iter()
+                    .any(|column| column.eq_ignore_ascii_case(tag))
+            })
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        if tags.len() != front.tags().len() {
+            if tags.is_empty() {
+                front.remove("tags");
+            } else {
+                front.set("tags", meta::Value::List(tags));
+            }
+        }
+
+        front.set("status", meta::Value::Text(column.to_string()));
+
+        let content = format!("{}{body}", front.render());
+
+        self.stage(idx, content, stash)
+    }
+
+    // Days are marked by their daily note and by the memos created on them, going by front
+    // matter first and the file's creation time otherwise.
+    fn calendar(&mut self, stash: &Stash) {
+        let mut days = BTreeMap::<chrono::NaiveDate, Vec<String>>::new();
+
+        for memo in (0..stash.len()).filter_map(|idx| stash.get(idx)) {
+            let name = memo.name();
+            let (front, _) = meta::split(&memo.content_buffer);
+
+            let daily = match periodic::recognize(&name, &self.config.periodic) {
+                Some((Period::Day, date)) => Some(date),
+                _ => None,
+            };
+            let created = front
+                .get("created")
+                .and_then(|created| {
+                    chrono::DateTime::parse_from_rfc3339(created)
+                        .map(|time| time.with_timezone(&chrono::Local).date_naive())
+                        .or_else(|_| {
+                            chrono::NaiveDate::parse_from_str(
+                                created.get(..10).unwrap_or(created),
+                                "%Y-%m-%d",
+                            )
+                        })
+                        .ok()
+                })
+                .or_else(|| {
+                    fs::metadata(&memo.original_path)
+                        .and_then(|meta| meta.created())
+                        .ok()
+                        .map(|time| chrono::DateTime::<chrono::Local>::from(time).date_naive())
+                });
+
+            for date in [daily, created].into_iter().flatten() {
+                let names = days.entry(date).or_default();
+
+                if !names.contains(&name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+
+        self.open_calendar(Calendar::new(chrono::Local::now().date_naive(), days));
+    }
+
+    // Names and modification times of every memo in the stash, for `review::pick`.
+    fn review_candidates(stash: &Stash) -> Vec<(String, Option<SystemTime>)> {
+        stash
+            .stash
+            .iter()
+            .map(|memo| {
+                let modified = fs::metadata(memo.current_path()).and_then(|meta| meta.modified());
+
+                (memo.name(), modified.ok())
+            })
+            .collect()
+    }
+
+    fn random(&mut self, stash: &Stash) {
+        let candidates = Self::review_candidates(stash);
+        let Some(&idx) = review::pick(&candidates, 1).first() else {
+            return;
+        };
+        let name = &candidates[idx].0;
+
+        self.select(&stash.stash[idx].original_path, stash);
+        self.status = match review::mark(std::slice::from_ref(name)) {
+            Ok(()) => format!("Resurfaced '{name}'"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    // Draws `count` memos gone longest without a look, and goes through them in a picker.
+    fn review(&mut self, count: usize, stash: &Stash) {
+        let candidates = Self::review_candidates(stash);
+        let picked = review::pick(&candidates, count);
+        let names = picked
+            .iter()
+            .map(|&idx| candidates[idx].0.clone())
+            .collect::<Vec<_>>();
+
+        if picked.is_empty() {
+            self.status = String::from("No memos to review");
+
+            return;
+        }
+
+        if let Err(e) = review::mark(&names) {
+            self.status = e.to_string();
+
+            return;
+        }
+
+        self.review = picked
+            .into_iter()
+            .map(|idx| stash.stash[idx].original_path.clone())
+            .collect();
+        self.open_review(stash);
+    }
+
+    // The rest of the review under way, if anything is left of it.
+    fn open_review(&mut self, stash: &Stash) {
+        self.review.retain(|path| stash.position(path).is_some());
+
+        let items = self
+            .review
+            .iter()
+            .filter_map(|path| stash.get(stash.position(path)?))
+            .map(|memo| {
+                let (front, body) = meta::split(&memo.content_buffer);
+                let first = body.lines().map(str::trim).find(|line| !line.is_empty());
+                // Days since the memo last changed.
+                let age = fs::metadata(memo.current_path())
+                    .and_then(|meta| meta.modified())
+                    .ok()
+                    .and_then(|time| time.elapsed().ok())
+                    .map(|age| format!("{}d", age.as_secs() / (24 * 60 * 60)))
+                    .unwrap_or_default();
+                let label = match front.title().or(first) {
+                    Some(line) => format!("{}  {age}  {line}", memo.name()),
+                    None => format!("{}  {age}", memo.name()),
+                };
+
+                (label, Pick::Review(memo.original_path.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            return;
+        }
+
+        let title = format!(
+            "Review, {} left (Enter: show, n: keep, a: archive, t: tag, m: merge, d: delete)",
+            items.len()
+        );
+
+        self.open_picker(Picker::new(title, items).with_actions(&['n', 'a', 't', 'm', 'd']));
+    }
+
+    // Memos untouched for `review.stale_days`, to go through in the review picker.
+    fn stale(&mut self, stash: &Stash) {
+        let days = self.config.review.stale_days;
+        let stale = review::stale(&Self::review_candidates(stash), days);
+
+        if stale.is_empty() {
+            self.status = format!("No memo has gone {days} days untouched");
+
+            return;
+        }
+
+        self.review = stale
+            .into_iter()
+            .map(|idx| stash.stash[idx].original_path.clone())
+            .collect();
+        self.open_review(stash);
+    }
+
+    // Appends the selected memo under a heading of its name to the memo `into` names, then moves
+    // it to the trash.
+    fn merge(&mut self, into: &str, stash: &mut Stash) {
+        let Some(idx) = self.selected(stash) else {
+            return;
+        };
+        let into = into.trim().trim_start_matches("[[").trim_end_matches("]]");
+
+        if into.is_empty() {
+            return;
+        }
+
+        let Some(&to) = stash.link_targets().get(&into.to_lowercase()) else {
+            self.status = format!("No memo '{into}'");
+
+            return;
+        };
+
+        if to == idx {
+            self.status = String::from("A memo cannot be merged into itself");
+
+            return;
+        }
+
+        let name = stash.stash[idx].name();
+        let into = stash.stash[to].name();
+        let (_, body) = meta::split(&stash.stash[idx].content_buffer);
+        let target = stash.stash[to].content_buffer.trim_end();
+        let content = match target.is_empty() {
+            true => format!("## {name}\n\n{}\n", body.trim()),
+            false => format!("{target}\n\n## {name}\n\n{}\n", body.trim()),
+        };
+
+        if let Err(e) = self.stage(to, content, stash) {
+            self.status = e.to_string();
+
+            return;
+        }
+
+        self.delete(stash);
+        self.status = format!("Merged '{name}' into '{into}'");
+    }
+
+    // Appends the line typed in the quick-edit prompt to the selected memo, staged for the
+    // autosave like the other in-TUI changes.
+    fn quick_edit(&mut self, text: &str, stash: &mut Stash) {
+        let Some(idx) = self.selected(stash).filter(|_| !text.trim().is_empty()) else {
+            return;
+        };
+        let memo = &stash.stash[idx];
+        let name = memo.name();
+        let mut content = memo.content_buffer.clone();
+
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+
+        content.push_str(text);
+        content.push('\n');
+
+        self.status = match self.stage(idx, content, stash) {
+            Ok(()) => format!("Added a line to '{name}'"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    // Adds tags, separated by spaces or commas and with or without a `#`, to the selected memo.
+    fn tag(&mut self, text: &str, stash: &mut Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+        let path = memo.original_path.clone();
+        let (front, _) = meta::split(&memo.content_buffer);
+        let tags = front.tags();
+        let mut added = Vec::<String>::new();
+
+        for tag in text
+            .split([' ', ','])
+            .map(|tag| tag.trim_start_matches('#'))
+        {
+            if !tag.is_empty() && !tags.contains(&tag) && !added.iter().any(|t| t == tag) {
+                added.push(tag.to_string());
+            }
+        }
+
+        if added.is_empty() {
+            return;
+        }
+
+        match self.retag(&path, stash, |tags| tags.extend(added.iter().cloned())) {
+            Ok(()) => self.record(Change::Tag { path, added }),
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    // Stages the memo with its front matter tags changed by `f`.
+    fn retag<F: FnOnce(&mut Vec<String>)>(
+        &mut self,
+        path: &Path,
+        stash: &mut Stash,
+        f: F,
+    ) -> Result<(), Error> {
+        let Some(idx) = stash.position(path) else {
+            return Err(Error::new(format!(
+                "A memo '{}' is not listed",
+                memo_name_of(path)
+            )));
+        };
+        let (mut front, body) = meta::split(&stash.stash[idx].content_buffer);
+        let mut tags = front
+            .tags()
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+
+        f(&mut tags);
+
+        match tags.is_empty() {
+            true => front.remove("tags"),
+            false => front.set("tags", meta::Value::List(tags)),
+        }
+
+        let content = format!("{}{body}", front.render());
+
+        self.stage(idx, content, stash)
+    }
+
+    // Wiki links throughout the stash that lead to no memo. Each can be fixed by creating the memo,
+    // by editing the link or by ignoring it.
+    fn broken_links(&mut self, stash: &Stash) {
+        let items = links::broken(stash)
+            .into_iter()
+            .map(|link| {
+                (
+                    format!("{}:{}  [[{}]]", link.name, link.line, link.target),
+                    Pick::Broken(link),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if items.is_empty() {
+            self.status = String::from("No broken links");
+
+            return;
+        }
+
+        self.open_picker(
+            Picker::new(
+                "Broken links (c: create the memo, e/Enter: edit the link, i: ignore)",
+                items,
+            )
+            .with_actions(&['c', 'e', 'i']),
+        );
+    }
+
+    // Lists the issues of the selected memo; choosing one opens the editor at its line.
+    fn lint(&mut self, stash: &Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+
+        let found = match &self.config.lint.command {
+            Some(command) => match storage::is_compressed(memo.current_path()) {
+                true => memo
+                    .read_latest_content()
+                    .and_then(|content| {
+                        TempFile::write(format!("{}.{MEMO_EXTENSION}", memo.name()), content)
+                    })
+                    .and_then(|file| lint::external(command, file.path())),
+                false => lint::external(command, &memo.current_path()),
+            },
+            None => {
+                let names = stash.link_targets();
+                let dir = memo.original_path.parent().unwrap_or(Path::new(""));
+                let content = match memo.partial {
+                    true => memo.read_latest_content(),
+                    false => Ok(memo.content_buffer.clone()),
+                };
+
+                content.map(|content| {
+                    lint::check(&content, dir, |name| {
+                        names.contains_key(&name.to_lowercase())
+                    })
+                })
+            }
+        };
+
+        let found = match found {
+            Ok(found) => found,
+            Err(e) => {
+                self.status = e.to_string();
+
+                return;
+            }
+        };
+
+        if found.is_empty() {
+            self.status = format!("No issues in '{}'", memo.name());
+
+            return;
+        }
+
+        let items = found
+            .into_iter()
+            .map(|found| {
+                (
+                    format!("{:>5}  {}", found.line, found.message),
+                    Pick::Jump(memo.original_path.clone(), found.line),
+                )
+            })
+            .collect();
+
+        self.open_picker(Picker::new(
+            format!("Issues in '{}' (Enter: edit at the line)", memo.name()),
+            items,
+        ));
+    }
+
+    // Neighbors of the selected memo; following one re-centers the view on it.
+    fn graph(&mut self, stash: &Stash) {
+        let Some(idx) = self.selected(stash) else {
+            return;
+        };
+        let links = stash.links();
+        let name = |idx: usize| stash.get(idx).map(|memo| memo.name()).unwrap_or_default();
+        let follow = |idx: usize| {
+            Pick::Follow(
+                stash
+                    .get(idx)
+                    .map(|memo| memo.original_path.clone())
+                    .unwrap_or_default(),
+            )
+        };
+
+        let outgoing = links[idx].clone();
+        let incoming = (0..links.len())
+            .filter(|&from| links[from].contains(&idx))
+            .collect::<Vec<_>>();
+        let mut items = vec![];
+
+        for &to in &outgoing {
+            items.push((format!("-> {}", name(to)), follow(to)));
+        }
+
+        for &from in &incoming {
+            items.push((format!("<- {}", name(from)), follow(from)));
+        }
+
+        let mut seen = outgoing.clone();
+
+        seen.extend(&incoming);
+        seen.push(idx);
+
+        for &near in outgoing.iter().chain(&incoming) {
+            let neighbors = links[near]
+                .iter()
+                .copied()
+                .chain((0..links.len()).filter(|&from| links[from].contains(&near)));
+
+            for far in neighbors {
+                if !seen.contains(&far) {
+                    seen.push(far);
+                    items.push((
+                        format!("~> {} (via {})", name(far), name(near)),
+                        follow(far),
+                    ));
+                }
+            }
+        }
+
+        if items.is_empty() {
+            self.status = format!("'{}' has no links", name(idx));
+
+            return;
+        }
+
+        self.open_picker(Picker::new(
+            format!(
+                "Links of '{}' (->: links to, <-: linked from, ~>: 2nd degree)",
+                name(idx)
+            ),
+            items,
+        ));
+    }
+
+    // Offers the URLs found in the memo before falling back to the whole content.
+    fn qr(&mut self, stash: &Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+        let content = memo.content_buffer.trim();
+        let urls = meta::urls(content);
+
+        if urls.is_empty() {
+            self.show_qr(content);
+
+            return;
+        }
+
+        let mut items = vec![(String::from("Whole memo"), Pick::Qr(content.to_string()))];
+
+        items.extend(
+            urls.into_iter()
+                .map(|url| (url.to_string(), Pick::Qr(url.to_string()))),
+        );
+
+        self.open_picker(Picker::new(
+            format!("QR code for '{}':", memo.name()),
+            items,
+        ));
+    }
+
+    // Offers the memo's front matter fields to copy, or copies the named one straight away.
+    fn copy_field(&mut self, stash: &Stash, name: Option<&str>) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+        let (front, _) = meta::split(&memo.content_buffer);
+        let fields = front.entries();
+
+        if let Some(name) = name {
+            match fields
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            {
+                Some((key, value)) => self.copy(key, value),
+                None => self.status = format!("'{}' has no field '{name}'", memo.name()),
+            }
+
+            return;
+        }
+
+        match fields.as_slice() {
+            [] => self.status = format!("'{}' has no fields", memo.name()),
+            [(key, value)] => self.copy(key, value),
+            _ => self.open_picker(Picker::new(
+                format!("Copy a field of '{}':", memo.name()),
+                fields
+                    .iter()
+                    .map(|(key, value)| {
+                        let pick = Pick::Copy(key.to_string(), value.clone());
+
+                        (format!("{key}: {value}"), pick)
+                    })
+                    .collect(),
+            )),
+        }
+    }
+
+    fn copy(&mut self, key: &str, value: &str) {
+        self.status = match clipboard::write(value, self.config.clip.copy_command.as_deref()) {
+            Ok(()) => format!("Copied the {key}"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn open_link(&mut self, stash: &Stash) {
+        let Some(memo) = self.selected(stash).and_then(|idx| stash.get(idx)) else {
+            return;
+        };
+        let urls = meta::urls(&memo.content_buffer);
+
+        match urls.as_slice() {
+            [] => self.status = format!("'{}' has no links", memo.name()),
+            [url] => self.open_url(url),
+            _ => self.open_picker(Picker::new(
+                format!("Open a link of '{}':", memo.name()),
+                urls.into_iter()
+                    .map(|url| (url.to_string(), Pick::Open(url.to_string())))
+                    .collect(),
+            )),
+        }
+    }
+
+    fn open_url(&mut self, url: &str) {
+        self.status = match open_external(url) {
+            Ok(_) => format!("Opened {url}"),
+            Err(e) => e.to_string(),
+        };
+    }
+
+    fn show_qr(&mut self, text: &str) {
+        use qrcode::QrCode;
+        use qrcode::render::unicode::Dense1x2;
+
+        let code = match QrCode::new(text.as_bytes()) {
+            Ok(code) => code,
+            Err(e) => {
+                self.status = Error::with_cause("The QR code encoding failed", e).to_string();
+
+                return;
+            }
+        };
+
+        // Inverted so the code reads as dark-on-light on the usual dark terminal background.
+        let image = code
+            .render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build();
+
+        self.open_overlay(Overlay::fixed(
+            "Scan, then press any key",
+            image.lines().map(String::from).collect(),
+        ));
+    }
+
+    // Gathers what an open `[[` or `#` completes from when it opens, rather than on every key and
+    // frame, and drops it once it closes.
+    fn gather_completions(&mut self, stash: &Stash) {
+        let completing = self
+            .prompt
+            .as_ref()
+            .filter(|prompt| !matches!(prompt.kind, PromptKind::Create | PromptKind::Rename))
+            .and_then(|prompt| prompt.editor.completing())
+            .map(|(completing, _)| completing);
+
+        match completing {
+            None => self.completion_words = None,
+            Some(completing)
+                if self
+                    .completion_words
+                    .as_ref()
+                    .is_some_and(|(gathered, _)| *gathered == completing) => {}
+            Some(Completing::Link) => {
+                let words = stash
+                    .link_targets()
+                    .into_iter()
+                    .map(|(target, idx)| {
+                        // Names keep their case; ids and aliases are only kept lowercased.
+                        let name = stash.stash[idx].name();
+
+                        match name.to_lowercase() == target {
+                            true => name,
+                            false => target,
+                        }
+                    })
+                    .collect();
+
+                self.completion_words = Some((Completing::Link, words));
+            }
+            Some(Completing::Tag) => {
+                self.completion_words =
+                    Some((Completing::Tag, stash.tag_index().into_iter().collect()));
+            }
+        }
+    }
+
+    // Memo names, ids and aliases for an open `[[`, or tags for a `#`, best matches first.
+    fn completions(&self) -> Vec<String> {
+        let Some(prompt) = &self.prompt else {
+            return vec![];
+        };
+        let (Some((_, typed)), Some((_, words))) =
+            (prompt.editor.completing(), &self.completion_words)
+        else {
+            return vec![];
+        };
+
+        let mut ranked = words
+            .iter()
+            .filter_map(|word| Some((fuzzy::score(typed, word)?, word.clone())))
+            .collect::<Vec<_>>();
+
+        ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        ranked
+            .into_iter()
+            .map(|(_, word)| word)
+            .take(COMPLETIONS)
+            .collect()
+    }
+
+    fn input_prompt(&mut self, key: KeyEvent, stash: &mut Stash) {
+        let completions = self.completions();
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let picked = completions.get(prompt.completion);
+
+        match key.code {
+            KeyCode::Char('n') if ctrl && !completions.is_empty() => {
+                prompt.completion = (prompt.completion + 1) % completions.len();
+
+                return;
+            }
+            KeyCode::Char('p') if ctrl && !completions.is_empty() => {
+                prompt.completion = (prompt.completion + completions.len() - 1) % completions.len();
+
+                return;
+            }
+            _ => prompt.completion = 0,
+        }
+
+        match key.code {
+            KeyCode::Esc => {
+                let kind = prompt.kind;
+
+                self.close_prompt();
+
+                if matches!(kind, PromptKind::Tag | PromptKind::Merge) {
+                    self.open_review(stash);
+                }
+            }
+            KeyCode::Enter => {
+                let prompt = self.prompt.take().unwrap();
+
+                self.close_prompt();
+                self.submit_prompt(prompt, stash);
+            }
+            KeyCode::Tab => {
+                let expansion = prompt
+                    .editor
+                    .trigger_word(snippet::TRIGGER)
+                    .and_then(|name| snippet::expand(name, &self.config.snippets));
+
+                if let Some(text) = expansion {
+                    prompt.editor.replace_trigger(snippet::TRIGGER, &text);
+                } else if let Some(word) = picked {
+                    prompt.editor.complete(word);
+                } else if let Some(suggestion) = prompt.suggestion() {
+                    prompt.editor.set_buffer(suggestion);
+                }
+            }
+            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let mut prompt = self.prompt.take().unwrap();
+
+                prompt.force = true;
+
+                self.close_prompt();
+                self.submit_prompt(prompt, stash);
+            }
+            _ => {
+                prompt.editor.handle(key);
+            }
+        }
+    }
+
+    fn submit_prompt(&mut self, prompt: Prompt, stash: &mut Stash) {
+        if self.unsaved_since.is_some() {
+            self.save(stash);
+        }
+
+        let history = self.histories.entry(prompt.kind).or_default();
+
+        if !prompt.text().is_empty() && history.last().map(String::as_str) != Some(prompt.text()) {
+            history.push(prompt.text().to_string());
+
+            if let Err(e) = prompt::save_history(prompt.kind, history) {
+                self.status = e.to_string();
+            }
+        }
+
+        match prompt.kind {
+            PromptKind::Create => {
+                let on_collision = if prompt.force {
+                    OnCollision::Overwrite
+                } else {
+                    OnCollision::Refuse
+                };
+
+                self.create(prompt.text(), on_collision, stash);
+            }
+            PromptKind::Search => {
+                self.view.filter =
+                    Some(prompt.text().to_string()).filter(|query| !query.is_empty());
+                self.view.cursor = 0;
+            }
+            PromptKind::Command => self.run_command(prompt.text(), stash),
+            PromptKind::Rename => self.rename(prompt.text(), stash),
+            PromptKind::Log => self.log_entry(prompt.text(), stash),
+            PromptKind::Tag => {
+                self.tag(prompt.text(), stash);
+                self.open_review(stash);
+            }
+            PromptKind::Merge => {
+                self.merge(prompt.text(), stash);
+                self.open_review(stash);
+            }
+            PromptKind::QuickEdit => self.quick_edit(prompt.text(), stash),
+        }
+    }
+
+    fn run_command(&mut self, line: &str, stash: &mut Stash) {
+        if let Some(line) = line.trim_start().strip_prefix('!') {
+            return self.bang(line, stash);
+        }
+
+        let (cmd, arg) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let arg = arg.trim();
+
+        match cmd {
+            "" => {}
+            "q" | "quit" => self.exit = true,
+            "new" => self.create(arg, OnCollision::Refuse, stash),
+            "new!" => self.create(arg, OnCollision::Overwrite, stash),
+            "rm" | "delete" => self.delete(stash),
+            "rm!" | "delete!" => self.delete_permanently(stash),
+            "rename" => self.rename(arg, stash),
+            "archive" => self.archive(stash),
+            "undo" => self.undo(stash),
+            "redo" => self.redo(stash),
+            "qr" => self.qr(stash),
+            "links" => self.graph(stash),
+            "scratch" => self.scratch(),
+            "promote" => self.promote(arg, stash),
+            "log" => self.log_entry(arg, stash),
+            "board" => self.board(stash),
+            "open" => self.open_link(stash),
+            "spell" => self.toggle_spelling(),
+            "lint" => self.lint(stash),
+            "broken" => self.broken_links(stash),
+            "lock" => self.lock(),
+            "load" => self.load(stash),
+            "search" => self.saved_search(arg),
+            "find" => self.find(arg),
+            "import" => self.import(arg),
+            "export" => self.export(arg, stash),
+            "sync" => self.sync(arg, stash),
+            "palette" => self.palette(),
+            "reload" => self.reload_config(),
+            "hud" => self.hud = !self.hud,
+            "focus" => self.toggle_timer(stash),
+            "clockin" => self.clock_in(stash),
+            "clockout" => self.clock_out(),
+            "habits" => self.habits(stash),
+            "check" => self.check_habit(stash),
+            "copy" => self.copy_field(stash, Some(arg).filter(|arg| !arg.is_empty())),
+            "random" => self.random(stash),
+            "review" => match arg {
+                "" => self.review(review::COUNT, stash),
+                count => match count.parse() {
+                    Ok(count) => self.review(count, stash),
+                    Err(_) => self.status = format!("'{count}' is no count of memos"),
+                },
+            },
+            "stale" => self.stale(stash),
+            "tag" => self.tag(arg, stash),
+            "merge" => self.merge(arg, stash),
+            "favorite" => self.toggle_favorite(stash),
+            "favorites" => self.toggle_favorites_view(),
+            "hidden" => self.toggle_hidden(),
+            "grid" => self.toggle_grid(),
+            "tabnew" => self.new_tab(stash),
+            "tabclose" => self.close_tab(stash),
+            "tabnext" => self.handle(Order::StepTab(1), stash),
+            "tabprevious" => self.handle(Order::StepTab(-1), stash),
+            "split" | "vsplit" => self.split(stash),
+            "only" => self.split = None,
+            _ => match self.plugins.command(cmd) {
+                Some(idx) => self.run_plugin(stash, |plugins, path, content| {
+                    plugins.run_command(idx, path, content)
+                }),
+                None => self.status = format!("Unknown command '{cmd}'"),
+            },
+        }
+    }
+
+    fn create(&mut self, memo_name: &str, on_collision: OnCollision, stash: &mut Stash) {
+        match create_new_memo(memo_name, on_collision) {
+            Ok(memo) => {
+                let path = memo.original_path.clone();
+
+                if stash.position(&path).is_none() {
+                    stash.push(memo);
+                    stash.sort();
+                }
+
+                self.select(&path, stash);
+                self.status = format!("Created '{memo_name}'");
+
+                // Read back with the id it was given, which later changes must keep.
+                if let Some(Err(e)) = stash.position(&path).map(|idx| stash.refresh(idx)) {
+                    self.status = e.to_string();
+                }
+                self.fire(Hook::Create, &path, "");
+            }
+            Err(e) => {
+                self.status = e.to_string();
+
+                let mut prompt = Prompt::new(PromptKind::Create, vec![]);
+
+                prompt.editor.set_buffer(memo_name.to_string());
+
+                self.open_prompt(prompt);
+            }
+        }
+    }
+
+    fn render(&self, stash: &Stash) {
+        let (width, height) = terminal::size().unwrap_or((80, 24));
+
+        self.draw(&mut io::stdout(), stash, width as usize, height as usize);
+    }
+
+    fn is_marked(&self, memo: &Memo) -> bool {
+        self.marked.contains(&memo.original_path)
+    }
+
+    // A tab's view at column `x`: its list and the selected memo's preview, or its grid of cards.
+    fn draw_pane<W: io::Write>(
+        &self,
+        out: &mut W,
+        stash: &Stash,
+        tab: usize,
+        (x, width, height): (usize, usize, usize),
+        basic: bool,
+    ) {
+        let view = match tab == self.tab {
+            true => &self.view,
+            false => &self.tabs[tab],
+        };
+        let visible = view.visible(stash);
+
+        if view.grid {
+            let card = |at: usize| {
+                let memo = &stash.stash[visible[at]];
+                let title = match self.is_marked(memo) {
+                    true => format!("*{}", memo.name()),
+                    false => memo.name(),
+                };
+                let lines = meta::split(&memo.content_buffer)
+                    .1
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(|line| line.replace('\t', "    "))
+                    .collect();
+
+                dialog::Card { title, lines }
+            };
+            let lines = dialog::grid(visible.len(), view.cursor, card, (width, height), basic);
+
+            for row in 0..height {
+                let line = lines.get(row).map(String::as_str).unwrap_or("");
+
+                let _ = queue!(
+                    out,
+                    MoveTo(x as u16, row as u16),
+                    Print(fit_width(line, width))
+                );
+            }
+
+            return;
+        }
+
+        let list_width = match self.config.display.list_width {
+            Some(percent) => (width * percent.min(100) / 100).max(12.min(width)),
+            None => (width / 3).clamp(12.min(width), 40.min(width)),
+        };
+        let preview_width = width.saturating_sub(list_width + 1);
+        let scroll = view.cursor.saturating_sub(height.saturating_sub(1));
+
+        let preview = visible
+            .get(view.cursor)
+            .and_then(|&idx| stash.get(idx))
+            .map(|memo| {
+                let misspelled = self.spelling.get(memo.content_hash).filter(|_| self.spell);
+
+                let mut lines = fields_preview(&memo.content_buffer, basic)
+                    .lines()
+                    .map(|line| {
+                        let text = line.replace('\t', "    ");
+                        let marks = misspelled
+                            .map(|words| spell::marks(&text, words))
+                            .unwrap_or_default();
+
+                        preview::Line { text, marks }
+                    })
+                    .collect::<Vec<_>>();
+
+                if memo.partial {
+                    let notice = format!(
+                        "-- only the first {} KiB of this large memo, :load reads it all --",
+                        PREVIEW_HEAD / 1024
+                    );
+
+                    lines.insert(0, preview::Line::plain(notice));
+                }
+
+                lines
+            })
+            .unwrap_or_default();
+        let wrap = match self.wrap {
+            Wrap::Off => None,
+            Wrap::Window => Some(preview_width),
+            Wrap::Column => Some(self.config.preview.wrap_column.min(preview_width)),
+        };
+        let preview = preview::layout(
+            preview::tables(preview, preview_width, basic),
+            wrap,
+            view.hscroll,
+        );
+
+        let shown = visible
+            .iter()
+            .enumerate()
+            .skip(scroll)
+            .take(height)
+            .filter_map(|(at, &idx)| Some((at, stash.get(idx)?)))
+            .collect::<Vec<_>>();
+        // In the pane beside the current one the cursor row is only dimly marked.
+        let cursor = (view.cursor, tab == self.tab);
+        let rows = self.list_rows(&shown, cursor, list_width, basic);
+
+        for row in 0..height {
+            let (name, styled) = rows.get(row).cloned().unwrap_or_default();
+            let fitted = fit_width(&name, list_width);
+            // Past the fitted part of the name there are only padding spaces.
+            let (head, body, tail) = match &styled {
+                Some((range, _)) => {
+                    let start = range.start.min(fitted.len());
+                    let end = range.end.min(fitted.len());
+
+                    (&fitted[..start], &fitted[start..end], &fitted[end..])
+                }
+                None => (fitted.as_str(), "", ""),
+            };
+            let color = styled.map_or(Color::Reset, |(_, color)| color);
+
+            // Without colors to lean on, the cursor row stands out reversed as well.
+            let reverse = match basic && scroll + row == view.cursor && !name.is_empty() {
+                true => Attribute::Reverse,
+                false => Attribute::NoReverse,
+            };
+
+            let _ = queue!(
+                out,
+                MoveTo(x as u16, row as u16),
+                SetAttribute(reverse),
+                Print(head),
+                SetForegroundColor(color),
+                Print(body),
+                SetForegroundColor(Color::Reset),
+                Print(tail),
+                SetAttribute(Attribute::NoReverse),
+                Print("|")
+            );
+
+            match preview.get(row) {
+                Some(line) => preview::print(out, line, preview_width, basic),
+                None => preview::print(out, &preview::Line::plain(""), preview_width, basic),
+            }
+        }
+    }
+
+    // The list rows of memos at the given positions, laid out in `display.columns`, each with where
+    // the name is and its color when a styled tag colors it. `cursor` is the cursor position and
+    // whether the list has focus.
+    fn list_rows(
+        &self,
+        memos: &[(usize, &Memo)],
+        (cursor, focused): (usize, bool),
+        width: usize,
+        basic: bool,
+    ) -> Vec<ListRow> {
+        let display = &self.config.display;
+        let nerd_font = display.nerd_font && !basic;
+        let layout = &display.columns;
+        let icons = match layout.is_empty() {
+            true => display.icons,
+            false => layout.iter().any(|column| column.show == ColumnKind::Icon),
+        };
+        let mut rows = vec![];
+
+        for &(at, memo) in memos {
+            let marker = match (at == cursor, self.is_marked(memo)) {
+                (true, true) if !focused => "-*",
+                (true, false) if !focused => "- ",
+                (true, true) => ">*",
+                (true, false) => "> ",
+                (false, true) => " *",
+                (false, false) => "  ",
+            };
+
+            let mut badges = self
+                .plugins
+                .columns(&memo.original_path, &memo.content_buffer);
+
+            if memo.partial {
+                badges.insert(0, String::from("[large]"));
+            }
+
+            if memo.is_hidden() {
+                badges.insert(0, String::from("[hidden]"));
+            }
+
+            if memo.is_favorite() && !icons {
+                badges.insert(0, String::from(if basic { "(fav)" } else { "★" }));
+            }
+
+            if let Some(source) = &memo.source {
+                badges.insert(0, format!("[{source}]"));
+            }
+
+            let style = self.tag_style(memo).filter(|_| !basic);
+            let name = match style.and_then(|style| style.icon.as_deref()) {
+                Some(icon) => format!("{icon} {}", memo.name()),
+                None => memo.name(),
+            };
+            let color = style.and_then(|style| style.color());
+            let title = match badges.is_empty() {
+                true => name.clone(),
+                false => format!("{name} {}", badges.join(" ")),
+            };
+            let cells = match layout.is_empty() {
+                true => icons
+                    .then(|| list_icons(memo, nerd_font))
+                    .into_iter()
+                    .chain([title])
+                    .collect::<Vec<_>>(),
+                false => layout
+                    .iter()
+                    .map(|column| match column.show {
+                        ColumnKind::Title => title.clone(),
+                        kind => list_cell(memo, kind, nerd_font),
+                    })
+                    .collect(),
+            };
+
+            rows.push((marker, cells, name.len(), color));
+        }
+
+        let widths = match layout.is_empty() {
+            true => None,
+            false => {
+                let mut widths = layout
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| {
+                        column.width.or_else(|| {
+                            (column.show != ColumnKind::Title).then(|| {
+                                rows.iter()
+                                    .map(|(_, cells, _, _)| cells[i].width())
+                                    .max()
+                                    .unwrap_or(0)
+                            })
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let taken = widths.iter().flatten().sum::<usize>() + layout.len() + 1;
+                let left = width.saturating_sub(taken);
+
+                for width in &mut widths {
+                    width.get_or_insert(left);
+                }
+
+                Some(widths.into_iter().flatten().collect::<Vec<_>>())
+            }
+        };
+        let title_at = match layout.is_empty() {
+            true => usize::from(icons),
+            false => layout
+                .iter()
+                .position(|column| column.show == ColumnKind::Title)
+                .unwrap_or(usize::MAX),
+        };
+
+        rows.into_iter()
+            .map(|(marker, cells, name_len, color)| {
+                let mut text = String::from(marker);
+                let mut styled = None;
+
+                for (i, cell) in cells.iter().enumerate() {
+                    if i > 0 {
+                        text.push(' ');
+                    }
+
+                    let cell = match &widths {
+                        Some(widths) if layout[i].show == ColumnKind::Words => {
+                            fit_width(&format!("{cell:>0$}", widths[i]), widths[i])
+                        }
+                        Some(widths) => fit_width(cell, widths[i]),
+                        None => cell.clone(),
+                    };
+
+                    if i == title_at {
+                        styled = color.map(|color| {
+                            (text.len()..text.len() + name_len.min(cell.len()), color)
+                        });
+                    }
+
+                    text.push_str(&cell);
+                }
+
+                (text, styled)
+            })
+            .collect()
+    }
+
+    // The style of the memo's first tag that has one in `[tags]`.
+    fn tag_style(&self, memo: &Memo) -> Option<&TagStyle> {
+        meta::split(&memo.content_buffer)
+            .0
+            .tags()
+            .into_iter()
+            .find_map(|tag| {
+                self.config
+                    .tags
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(tag))
+                    .map(|(_, style)| style)
+            })
+    }
+
+    // Composes a whole frame of the given size into `out`.
+    fn draw<W: io::Write>(&self, out: &mut W, stash: &Stash, width: usize, height: usize) {
+        let basic = self.config.display.basic();
+
+        let _ = match basic {
+            true => queue!(out, SetColors(Colors::new(Color::White, Color::Black))),
+            false => queue!(out, ResetColor),
+        };
+
+        if let Some(typed) = &self.locked {
+            return self.draw_locked(out, typed, width, height);
+        }
+
+        let status_height = if self.prompt.is_some() && !self.status.is_empty() {
+            2
+        } else {
+            1
+        };
+        let body_height = height.saturating_sub(status_height);
+
+        let visible = self.visible(stash);
+        let title = match visible
+            .get(self.view.cursor)
+            .and_then(|&idx| stash.get(idx))
+        {
+            Some(memo) if basic => format!("memoleak - {}", memo.name()),
+            Some(memo) => format!("memoleak — {}", memo.name()),
+            None => String::from("memoleak"),
+        };
+
+        let _ = queue!(out, SetTitle(title));
+
+        let dialog = if let Some(board) = &self.board {
+            Some(board.lines(width, body_height))
+        } else if let Some(calendar) = &self.calendar {
+            Some(calendar.lines())
+        } else if let Some(overlay) = &self.overlay {
+            let fits = overlay.lines.iter().all(|line| line.width() <= width)
+                && overlay.lines.len() < body_height;
+
+            if fits || !overlay.fixed {
+                Some(overlay.visible(body_height))
+            } else {
+                Some(vec![String::from(
+                    "The terminal is too small to show this, press any key",
+                )])
+            }
+        } else if let Some(palette) = &self.palette {
+            Some(palette.lines(width, body_height))
+        } else {
+            self.picker.as_ref().map(|picker| picker.lines(body_height))
+        };
+
+        match (&dialog, self.split) {
+            (Some(lines), _) => {
+                for row in 0..body_height {
+                    let line = lines.get(row).map(String::as_str).unwrap_or("");
+
+                    let _ = queue!(out, MoveTo(0, row as u16), Print(fit_width(line, width)));
+                }
+            }
+            // The panes keep the order of their tabs, whichever of them is the current one.
+            (None, Some(other)) => {
+                let left = width.saturating_sub(1) / 2;
+                let right = width.saturating_sub(left + 1);
+                let (first, second) = (other.min(self.tab), other.max(self.tab));
+
+                self.draw_pane(out, stash, first, (0, left, body_height), basic);
+
+                for row in 0..body_height {
+                    let _ = queue!(
+                        out,
+                        MoveTo(left as u16, row as u16),
+                        Print(if basic { "#" } else { "║" })
+                    );
+                }
+
+                self.draw_pane(out, stash, second, (left + 1, right, body_height), basic);
+            }
+            (None, None) => self.draw_pane(out, stash, self.tab, (0, width, body_height), basic),
+        }
+
+        let status = match &self.view.filter {
+            Some(query) if self.status.is_empty() => {
+                format!("/{query} ({} matches)", visible.len())
+            }
+            None if self.status.is_empty() && self.view.favorites => {
+                format!("Favorites ({})", visible.len())
+            }
+            _ => self.status.clone(),
+        };
+        let status = match &self.task {
+            Some(task) => task.render(),
+            None => status,
+        };
+        let status = match &self.timer {
+            Some(timer) => format!("[{} {}] {status}", timer.countdown(), timer.name),
+            None => status,
+        };
+        let status = match self.tabs.len() {
+            1 => status,
+            count => format!("[{}/{count}] {status}", self.tab + 1),
+        };
+        let status = match stash.is_dirty() {
+            true => format!("[+] {status}"),
+            false => status,
+        };
+
+        let bottom = match &self.prompt {
+            Some(prompt) if status_height == 2 => vec![status, prompt.render()],
+            Some(prompt) => vec![prompt.render()],
+            None => vec![status],
+        };
+
+        for (i, line) in bottom.iter().enumerate() {
+            let _ = queue!(
+                out,
+                MoveTo(0, (body_height + i) as u16),
+                Print(fit_width(line, width))
+            );
+        }
+
+        let completions = self.completions();
+        let menu_width = completions
+            .iter()
+            .map(|word| word.width() + 2)
+            .max()
+            .unwrap_or(0)
+            .min(width);
+
+        // Bottom up from the prompt, so the best match is nearest to it.
+        for (i, word) in completions.iter().enumerate().take(body_height) {
+            let highlighted = self
+                .prompt
+                .as_ref()
+                .is_some_and(|prompt| prompt.completion == i);
+            let attribute = match highlighted {
+                true => Attribute::Reverse,
+                false => Attribute::NoReverse,
+            };
+
+            let _ = queue!(
+                out,
+                MoveTo(0, (body_height - 1 - i) as u16),
+                SetAttribute(attribute),
+                Print(fit_width(&format!(" {word} "), menu_width)),
+                SetAttribute(Attribute::NoReverse)
+            );
+        }
+
+        if self.hud {
+            let buffered = (0..stash.len())
+                .filter_map(|idx| stash.get(idx))
+                .map(|memo| memo.content_buffer.len())
+                .sum::<usize>();
+            let hud = format!(
+                " {:.1}ms | {} queued | {:.1} MiB | {} memos ",
+                self.frame_time.as_secs_f64() * 1000.0,
+                self.queued,
+                buffered as f64 / (1024.0 * 1024.0),
+                stash.len()
+            );
+            let hud = fit_width(&hud, hud.width().min(width));
+
+            let _ = queue!(
+                out,
+                MoveTo(width.saturating_sub(hud.width()) as u16, 0),
+                SetAttribute(Attribute::Reverse),
+                Print(hud),
+                SetAttribute(Attribute::NoReverse)
+            );
+        }
+
+        match (&self.palette, &self.prompt) {
+            (Some(palette), _) => {
+                let column = palette.cursor_column().min(width.saturating_sub(1));
+
+                let _ = queue!(out, MoveTo(column as u16, 0), Show);
+            }
+            (None, Some(prompt)) => {
+                let column = prompt.cursor_column().min(width.saturating_sub(1));
+
+                let _ = queue!(out, MoveTo(column as u16, (height - 1) as u16), Show);
+            }
+            (None, None) => {
+                let _ = queue!(out, Hide);
+            }
+        }
+
+        let _ = out.flush();
+    }
+
+    // Nothing of the stash is drawn, not even the memo count or the title.
+    fn draw_locked<W: io::Write>(&self, out: &mut W, typed: &str, width: usize, height: usize) {
+        let middle = height / 2;
+
+        let _ = queue!(out, SetTitle("memoleak"));
+
+        for row in 0..height {
+            let line = match row {
+                _ if row + 1 == height => self.status.clone(),
+                _ if row + 1 == middle => String::from("memoleak is locked"),
+                _ if row == middle => format!("Passphrase: {}", "*".repeat(typed.chars().count())),
+                _ => String::new(),
+            };
+
+            let _ = queue!(out, MoveTo(0, row as u16), Print(fit_width(&line, width)));
+        }
+
+        let _ = queue!(out, Hide);
+        let _ = out.flush();
+    }
+}
+
+// Truncates or pads by terminal columns rather than chars, so wide CJK/emoji graphemes and
+// combining marks keep the columns aligned. A wide grapheme that would straddle the edge is
+// replaced by padding.
+fn fit_width(s: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut used = 0;
+
+    for grapheme in s.graphemes(true) {
+        let w = grapheme.width();
+
+        if used + w > width {
+            break;
+        }
+
+        fitted.push_str(grapheme);
+        used += w;
+    }
+
+    fitted.extend(std::iter::repeat_n(' ', width - used));
+
+    fitted
+}
+
+// One cell for each of the file type, favorite, attachments and open tasks, blank when it does not
+// apply, so that the names stay aligned.
+fn list_icons(memo: &Memo, nerd_font: bool) -> String {
+    let icons = match nerd_font {
+        true => ['\u{e609}', '\u{f1c6}', '\u{f08d}', '\u{f0c6}', '\u{f046}'],
+        false => ['m', 'z', '^', '@', '+'],
+    };
+    let file_type = match storage::is_compressed(memo.current_path()) {
+        true => icons[1],
+        false => icons[0],
+    };
+    let flag = |on: bool, icon: char| if on { icon } else { ' ' };
+
+    [
+        file_type,
+        flag(memo.is_favorite(), icons[2]),
+        flag(attachment::is_linked(&memo.content_buffer), icons[3]),
+        flag(memo.has_open_tasks(), icons[4]),
+    ]
+    .iter()
+    .collect()
+}
+
+// The content with its front matter laid out as aligned fields over a rule.
+fn fields_preview(content: &str, basic: bool) -> String {
+    let (front, body) = meta::split(content);
+    let fields = front.entries();
+
+    if fields.is_empty() {
+        return content.to_string();
+    }
+
+    let key_width = fields.iter().map(|(key, _)| key.width()).max().unwrap_or(0);
+    let mut out = String::new();
+    let mut widest = 0;
+
+    for (key, value) in fields {
+        let line = format!("{key}{}  {value}", " ".repeat(key_width - key.width()));
+
+        widest = widest.max(line.width());
+        out.push_str(&line);
+        out.push('\n');
+    }
+
+    out.push_str(&(if basic { "-" } else { "─" }).repeat(widest));
+    out.push('\n');
+    out.push_str(body);
+
+    out
+}
+
+// A list row's text, and where the memo name is in it with its color.
+type ListRow = (String, Option<(Range<usize>, Color)>);
+
+// A list column other than the title.
+fn list_cell(memo: &Memo, kind: ColumnKind, nerd_font: bool) -> String {
+    match kind {
+        ColumnKind::Icon => list_icons(memo, nerd_font),
+        ColumnKind::Title => memo.name(),
+        ColumnKind::Tags => meta::split(&memo.content_buffer).0.tags().join(" "),
+        ColumnKind::Modified => fs::metadata(memo.current_path())
+            .and_then(|meta| meta.modified())
+            .map(|time| {
+                chrono::DateTime::<chrono::Local>::from(time)
+                    .format("%Y-%m-%d")
+                    .to_string()
+            })
+            .unwrap_or_default(),
+        ColumnKind::Words => meta::split(&memo.content_buffer)
+            .1
+            .split_whitespace()
+            .count()
+            .to_string(),
+        ColumnKind::Folder => match memo.original_path.parent() {
+            Some(dir) if dir != *MEMO_LIST_PATH => dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        },
+    }
+}
+
+// What a long operation hands back to the UI.
+#[derive(Clone, PartialEq)]
+enum Done {
+    Status(String),
+    // The memos a full-text search found for a query, the best match first.
+    Found(String, Vec<PathBuf>),
+}
+
+// What a picker row stands for once chosen.
+enum Pick {
+    Qr(String),
+    Open(String),
+    Follow(PathBuf),
+    // A memo and a line in it to open the editor at.
+    Jump(PathBuf, usize),
+    Broken(links::Broken),
+    // A memo to select in the list.
+    Select(PathBuf),
+    Copy(String, String),
+    Review(PathBuf),
+    // Links to a renamed memo, by its old name and its new one.
+    Relink(String, String),
+}
+
+#[derive(Clone, PartialEq)]
+enum Order {
+    Exit,
+    Redraw,
+    Down,
+    Up,
+    // Move between cards in a row of the grid.
+    Left,
+    Right,
+    Edit,
+    // Edits the marked memos in one editor.
+    EditSelection,
+    ToggleMark,
+    ToggleFavorite,
+    // Switches between all memos and the favorite ones.
+    Favorites,
+    ToggleHidden,
+    ToggleGrid,
+    NewTab,
+    CloseTab,
+    // Brings the next or previous tab to the front, by sign.
+    StepTab(i32),
+    // Shows two tabs side by side.
+    Split,
+    // Brings the tab in the pane beside to the front.
+    OtherPane,
+    Unsplit,
+    Create,
+    Delete,
+    Rename,
+    Archive,
+    Undo,
+    Redo,
+    Search,
+    Command,
+    Qr,
+    Graph,
+    Scratch,
+    LogEntry,
+    // Adds a line to the selected memo from a prompt, without the editor.
+    QuickEdit,
+    // Starts a focus session on the selected memo, or stops the one running.
+    Timer,
+    // Starts the clock for the selected memo in the timesheet, stopping it for any other.
+    ClockIn,
+    ClockOut,
+    Habits,
+    // Checks the selected habit off for today, or takes the check back.
+    CheckHabit,
+    // Copies one of the selected memo's front matter fields to the clipboard.
+    CopyField,
+    // Selects a memo drawn from the ones longest without a look.
+    Random,
+    // Goes through a few such memos, to keep, archive, tag, merge or delete each.
+    Review,
+    // Goes through the memos untouched for a while the same way.
+    Stale,
+    Periodic(Period),
+    StepPeriod(i32),
+    Calendar,
+    Board,
+    OpenLink,
+    ToggleSpell,
+    Lint,
+    BrokenLinks,
+    Lock,
+    CycleWrap,
+    ScrollLeft,
+    ScrollRight,
+    // Scrolls the pager by half a screen, down or up by sign.
+    HalfPage(i32),
+    Palette,
+    ReloadConfig,
+    DebugHud,
+    // A `:` command line.
+    Run(String),
+    Plugin(usize),
+    Shell(usize),
+    ShellDone {
+        line: String,
+        output: String,
+        scratch: bool,
+    },
+    EditDone(PathBuf),
+    // Stops the long operation running, if any.
+    Cancel,
+    // What the long operation running came to, or why it failed.
+    TaskDone(Result<Done, String>),
+    // The memos a background refresh found changed, and the first one it failed to read.
+    RereadDone {
+        changed: Vec<Reread>,
+        error: Option<String>,
+    },
+    // The misspelled words a background check found in the memo content with that hash.
+    SpellDone {
+        hash: u64,
+        words: Result<HashSet<String>, String>,
+    },
+    // Whether the terminal gained or lost focus.
+    Focus(bool),
+    Insert(PathBuf),
+    Input(KeyEvent),
+    Paste(String),
+}
+
+impl Order {
+    // Whether the order may hand memo files to something that reads them from disk.
+    fn uses_files(&self) -> bool {
+        !matches!(
+            self,
+            Order::Down
+                | Order::Up
+                | Order::Left
+                | Order::Right
+                | Order::ToggleGrid
+                | Order::NewTab
+                | Order::CloseTab
+                | Order::StepTab(_)
+                | Order::Split
+                | Order::OtherPane
+                | Order::Unsplit
+                | Order::Redraw
+                | Order::ToggleSpell
+                | Order::CycleWrap
+                | Order::DebugHud
+                | Order::ToggleMark
+                | Order::Favorites
+                | Order::ToggleHidden
+                | Order::ScrollLeft
+                | Order::ScrollRight
+                | Order::HalfPage(_)
+                | Order::Palette
+                | Order::Input(_)
+                | Order::Paste(_)
+                | Order::Cancel
+                | Order::RereadDone { .. }
+                | Order::SpellDone { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_width::UnicodeWidthChar;
+
+    // Plays a frame back on a blank terminal of its size, understanding the few sequences a frame
+    // is made of: cursor moves, showing and hiding the cursor, and the title. Colors and attributes
+    // are left out.
+    fn frame(app: &AppContainer, stash: &Stash, width: usize, height: usize) -> String {
+        let mut out = vec![];
+
+        app.draw(&mut out, stash, width, height);
+
+        let out = String::from_utf8(out).unwrap();
+        let mut rows = vec![vec![String::from(" "); width]; height];
+        let (mut x, mut y) = (0, 0);
+        let mut title = String::new();
+        let mut cursor = None;
+        let mut chars = out.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => match chars.next() {
+                    Some('[') => {
+                        let mut params = String::new();
+                        let end = chars.by_ref().find(|c| match c {
+                            '@'..='~' => true,
+                            c => {
+                                params.push(*c);
+                                false
+                            }
+                        });
+
+                        match (end, params.as_str()) {
+                            (Some('H'), params) => {
+                                let (row, column) = params.split_once(';').unwrap();
+
+                                y = row.parse::<usize>().unwrap() - 1;
+                                x = column.parse::<usize>().unwrap() - 1;
+                            }
+                            (Some('h'), "?25") => cursor = Some((x, y)),
+                            (Some('l'), "?25") => cursor = None,
+                            _ => {}
+                        }
+                    }
+                    Some(']') => {
+                        let osc = chars.by_ref().take_while(|&c| c != '\x07');
+
+                        title = osc.collect::<String>().trim_start_matches("0;").to_string();
+                    }
+                    _ => {}
+                },
+                c => {
+                    let w = c.width().unwrap_or(0);
+
+                    if y < height && x + w <= width {
+                        rows[y][x] = c.to_string();
+
+                        if w == 2 {
+                            rows[y][x + 1] = String::new();
+                        }
+                    }
+
+                    x += w;
+                }
+            }
+        }
+
+        let mut shown = format!("title: {title}\n");
+
+        for row in rows {
+            shown.push_str(row.concat().trim_end());
+            shown.push('\n');
+        }
+
+        if let Some((x, y)) = cursor {
+            shown.push_str(&format!("cursor: {x},{y}\n"));
+        }
+
+        shown
+    }
+
+    fn app() -> AppContainer {
+        let mut config = Config::default();
+
+        // Not left to the TERM the tests run in.
+        config.display.basic = Some(false);
+
+        AppContainer::new(
+            Arc::new(RwLock::new(vec![])),
+            Arc::new(InputControl::new()),
+            Arc::new(RwLock::new(Keymaps::default())),
+            Plugins::default(),
+            config,
+        )
+    }
+
+    fn stash() -> Stash {
+        let mut stash = Stash::new();
+        let memos = [
+            ("groceries", "# Groceries\n\n- milk\n- eggs\n- bread\n"),
+            (
+                "budget",
+                "# Budget\n\n| item | cost |\n|------|-----:|\n| rent | 900 |\n| food | 250 |\n",
+            ),
+            ("日本語", "# 日本語のメモ\n\n幅の広い文字\n"),
+        ];
+
+        for (name, content) in memos {
+            let mut memo = Memo::new(format!("{name}.{MEMO_EXTENSION}"));
+
+            memo.set_buffer(content.to_string());
+            stash.push(memo);
+        }
+
+        stash.sort();
+
+        stash
+    }
+
+    #[test]
+    fn list_and_preview() {
+        let mut app = app();
+        let stash = stash();
+
+        app.view.cursor = 1;
+
+        insta::assert_snapshot!(frame(&app, &stash, 60, 10));
+    }
+
+    #[test]
+    fn table_preview() {
+        let app = app();
+
+        insta::assert_snapshot!(frame(&app, &stash(), 60, 10));
+    }
+
+    #[test]
+    fn basic_table_preview() {
+        let mut app = app();
+
+        app.config.display.basic = Some(true);
+
+        insta::assert_snapshot!(frame(&app, &stash(), 60, 10));
+    }
+
+    #[test]
+    fn list_columns() {
+        let mut app = app();
+        let column = |show, width| config::ListColumn { show, width };
+
+        app.config.display.columns = vec![
+            column(ColumnKind::Title, None),
+            column(ColumnKind::Words, None),
+            column(ColumnKind::Tags, Some(6)),
+        ];
+        app.config.display.list_width = Some(50);
+
+        let mut stash = stash();
+        let groceries = stash
+            .stash
+            .iter_mut()
+            .find(|memo| memo.name() == "groceries");
+
+        groceries.unwrap().content_buffer =
+            String::from("---\ntags: [errands, home]\n---\n- milk\n");
+
+        insta::assert_snapshot!(frame(&app, &stash, 60, 6));
+    }
+
+    #[test]
+    fn grid() {
+        let mut app = app();
+
+        app.view.grid = true;
+        app.view.cursor = 1;
+
+        insta::assert_snapshot!(frame(&app, &stash(), 64, 16));
+    }
+
+    #[test]
+    fn narrow() {
+        let mut app = app();
+
+        app.view.cursor = 2;
+
+        insta::assert_snapshot!(frame(&app, &stash(), 24, 6));
+    }
+
+    #[test]
+    fn search_prompt_with_status() {
+        let mut app = app();
+        let mut prompt = Prompt::new(PromptKind::Search, vec![]);
+
+        prompt.editor.set_buffer(String::from("bud"));
+        app.open_prompt(prompt);
+        app.status = String::from("Reloaded the config");
+
+        insta::assert_snapshot!(frame(&app, &stash(), 60, 8));
+    }
+
+    #[test]
+    fn palette() {
+        let mut app = app();
+
+        app.palette();
+
+        insta::assert_snapshot!(frame(&app, &stash(), 60, 10));
+    }
+
+    #[test]
+    fn overlay() {
+        let mut app = app();
+        let lines = ["Links of 'budget'", "", "  -> groceries", "  <- 日本語"];
+
+        app.overlay = Some(Overlay::fixed("Links", lines.map(String::from).to_vec()));
+
+        insta::assert_snapshot!(frame(&app, &stash(), 60, 8));
+    }
+
+    #[test]
+    fn locked() {
+        let mut app = app();
+
+        app.locked = Some(String::from("hunter"));
+
+        insta::assert_snapshot!(frame(&app, &stash(), 40, 6));
+    }
 }
@@ -0,0 +1,336 @@
+use crate::Error;
+use std::collections::BTreeMap;
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Value {
+    Text(String),
+    List(Vec<String>),
+}
+
+#[derive(Default)]
+pub struct FrontMatter {
+    fields: BTreeMap<String, Value>,
+}
+
+impl FrontMatter {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match self.fields.get(key)? {
+            Value::Text(text) => Some(text),
+            Value::List(_) => None,
+        }
+    }
+
+    // A scalar is treated as a one-item list so `tags: work` and `tags: [work]` agree.
+    pub fn list(&self, key: &str) -> Vec<&str> {
+        match self.fields.get(key) {
+            Some(Value::Text(text)) if !text.is_empty() => vec![text.as_str()],
+            Some(Value::List(items)) => items.iter().map(String::as_str).collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.get("title").filter(|title| !title.is_empty())
+    }
+
+    pub fn tags(&self) -> Vec<&str> {
+        self.list("tags")
+    }
+
+    // Other names the memo goes by, e.g. its name before a rename or an abbreviation.
+    pub fn aliases(&self) -> Vec<&str> {
+        self.list("aliases")
+    }
+
+    // The memo's stable identity, which renames and moves keep since it travels in the file.
+    pub fn id(&self) -> Option<&str> {
+        self.get("id").filter(|id| !id.is_empty())
+    }
+
+    pub fn set<S: Into<String>>(&mut self, key: S, value: Value) {
+        self.fields.insert(key.into(), value);
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.fields.remove(key);
+    }
+
+    // Every field in key order, with list items joined by commas.
+    pub fn entries(&self) -> Vec<(&str, String)> {
+        self.fields
+            .iter()
+            .map(|(key, value)| match value {
+                Value::Text(text) => (key.as_str(), text.clone()),
+                Value::List(items) => (key.as_str(), items.join(", ")),
+            })
+            .collect()
+    }
+
+    // The `---` block as `split` reads it back, or nothing when there are no fields.
+    pub fn render(&self) -> String {
+        if self.fields.is_empty() {
+            return String::new();
+        }
+
+        let mut out = String::from("---\n");
+
+        for (key, value) in &self.fields {
+            match value {
+                Value::Text(text) => out.push_str(&format!("{key}: {}\n", quote(text))),
+                // Inline lists split on commas, so items containing one use the block form.
+                Value::List(items) if items.iter().any(|item| item.contains(',')) => {
+                    out.push_str(&format!("{key}:\n"));
+
+                    for item in items {
+                        out.push_str(&format!("  - {}\n", quote(item)));
+                    }
+                }
+                Value::List(items) => {
+                    let items = items.iter().map(|item| quote(item)).collect::<Vec<_>>();
+
+                    out.push_str(&format!("{key}: [{}]\n", items.join(", ")));
+                }
+            }
+        }
+
+        out.push_str("---\n");
+
+        out
+    }
+}
+
+// Splits a `---` delimited front matter block off the content.
+// Only flat `key: value`, `key: [a, b]` and `- item` lists are understood.
+pub fn split(content: &str) -> (FrontMatter, &str) {
+    let mut front = FrontMatter::default();
+
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (front, content);
+    };
+
+    let mut offset = 0;
+    let mut body = None;
+    let mut current = None::<String>;
+
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+
+        if line == "---" {
+            body = Some(&rest[offset..]);
+            break;
+        }
+
+        if let Some(item) = line.trim_start().strip_prefix("- ")
+            && line.starts_with(char::is_whitespace)
+            && let Some(key) = &current
+        {
+            if !matches!(front.fields.get(key), Some(Value::List(_))) {
+                front.fields.insert(key.clone(), Value::List(vec![]));
+            }
+
+            if let Some(Value::List(items)) = front.fields.get_mut(key) {
+                items.push(unquote(item).to_string());
+            }
+
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+
+        let value = match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            Some(items) => Value::List(
+                items
+                    .split(',')
+                    .map(|item| unquote(item.trim()).to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect(),
+            ),
+            None => Value::Text(unquote(value).to_string()),
+        };
+
+        front.fields.insert(key.clone(), value);
+        current = Some(key);
+    }
+
+    match body {
+        Some(body) => (front, body),
+        None => (FrontMatter::default(), content),
+    }
+}
+
+// A random (version 4) UUID for a memo's `id`.
+pub fn new_id() -> Result<String, Error> {
+    let mut bytes = [0u8; 16];
+
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| Error::with_cause("The memo ID generating failed", e))?;
+
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = bytes.iter().map(|b| format!("{b:02x}")).collect::<String>();
+
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+// The content of a memo being created, given an `id` unless its front matter has one already.
+pub fn with_id(content: &str) -> Result<String, Error> {
+    let (mut front, body) = split(content);
+
+    if front.id().is_some() {
+        return Ok(content.to_string());
+    }
+
+    front.set("id", Value::Text(new_id()?));
+
+    Ok(format!("{}{body}", front.render()))
+}
+
+fn quote(s: &str) -> String {
+    let plain = s.trim() == s && !s.starts_with(['"', '\'', '[']) && !s.ends_with(']');
+
+    if plain {
+        s.to_string()
+    } else {
+        format!("\"{s}\"")
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .or_else(|| s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+        .unwrap_or(s)
+}
+
+pub struct WikiLink<'a> {
+    pub target: &'a str,
+    pub label: &'a str,
+    // Everything between the brackets, as written.
+    pub inner: &'a str,
+}
+
+// Rewrites every `[[target]]` / `[[target|label]]` with the result of `f`.
+pub fn replace_wiki_links<F: FnMut(WikiLink) -> String>(text: &str, mut f: F) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("[[") {
+        let Some(len) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + len];
+
+        out.push_str(&rest[..start]);
+
+        if inner.is_empty() || inner.contains('\n') {
+            out.push_str("[[");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        let (target, label) = inner.split_once('|').unwrap_or((inner, inner));
+
+        out.push_str(&f(WikiLink {
+            target: target.trim(),
+            label: label.trim(),
+            inner,
+        }));
+        rest = &rest[start + 2 + len + 2..];
+    }
+
+    out.push_str(rest);
+
+    out
+}
+
+pub fn wiki_links(text: &str) -> Vec<String> {
+    let mut targets = vec![];
+
+    replace_wiki_links(text, |link| {
+        targets.push(link.target.to_string());
+        String::new()
+    });
+
+    targets
+}
+
+// Bare http(s) URLs, in order of appearance and without duplicates.
+pub fn urls(text: &str) -> Vec<&str> {
+    let mut found = vec![];
+
+    for word in text.split(|c: char| c.is_whitespace() || "<>\"()[]".contains(c)) {
+        if !(word.starts_with("http://") || word.starts_with("https://")) {
+            continue;
+        }
+
+        let url = word.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'']);
+
+        if !found.contains(&url) {
+            found.push(url);
+        }
+    }
+
+    found
+}
+
+// Adds `line` at the end of the section under `heading`, appending the heading first if it is
+// missing. A section ends at the next heading of the same or a higher level.
+pub fn append_under_heading(content: &str, heading: &str, line: &str) -> String {
+    let level = heading.chars().take_while(|&c| c == '#').count();
+    let ends_section = |l: &str| {
+        let hashes = l.chars().take_while(|&c| c == '#').count();
+
+        hashes > 0 && hashes <= level && l[hashes..].starts_with(' ')
+    };
+
+    let lines = content.lines().collect::<Vec<_>>();
+
+    let Some(start) = lines.iter().position(|l| l.trim_end() == heading) else {
+        let separator = match content {
+            "" => "",
+            _ if content.ends_with("\n\n") => "",
+            _ if content.ends_with('\n') => "\n",
+            _ => "\n\n",
+        };
+
+        return format!("{content}{separator}{heading}\n{line}\n");
+    };
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| ends_section(l))
+        .map_or(lines.len(), |pos| start + 1 + pos);
+    let last = lines[start..end]
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(start, |pos| start + pos);
+
+    let mut out = lines[..=last].join("\n");
+
+    out.push('\n');
+    out.push_str(line);
+    out.push('\n');
+
+    if last + 1 < lines.len() {
+        out.push_str(&lines[last + 1..].join("\n"));
+        out.push('\n');
+    }
+
+    out
+}
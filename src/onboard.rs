@@ -0,0 +1,134 @@
+use crate::config::CONFIG_PATH;
+use crate::{EDITOR_FALLBACK, Error, OnCollision, create_new_memo, meta, storage};
+use std::fs;
+use std::io::{self, Write};
+
+const EXAMPLE_MEMO: &str = "welcome";
+
+const EXAMPLE: &str = "# Welcome to memoleak
+
+Memos are markdown files kept in one folder. In the list:
+
+- `a` makes a new memo and Enter edits the selected one
+- `/` searches and `:` runs a command
+- `dd` deletes a memo and `u` brings it back
+- `<c-p>` lists everything else there is to do
+- `ZZ` quits
+
+Delete this memo once you are done with it.
+";
+
+// Asked once, before the TUI first starts on an empty stash with no config file.
+pub fn run() -> Result<(), Error> {
+    eprintln!("Welcome to memoleak! A few questions to set things up; Enter takes the default.");
+    eprintln!();
+
+    let default_editor = std::env::var("EDITOR").unwrap_or_else(|_| EDITOR_FALLBACK.to_string());
+    let editor = ask(&format!("Editor to write memos with [{default_editor}]: "))?;
+    let editor = if editor.is_empty() {
+        default_editor
+    } else {
+        editor
+    };
+    let basic = loop {
+        match ask("Theme: auto, color, or basic for limited terminals [auto]: ")?
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "auto" => break None,
+            "color" => break Some(false),
+            "basic" => break Some(true),
+            _ => eprintln!("Type auto, color or basic."),
+        }
+    };
+
+    if confirm("Create an example memo? [Y/n]: ")? {
+        let memo = create_new_memo(EXAMPLE_MEMO, OnCollision::Numbered)?;
+
+        storage::write(&memo.original_path, &meta::with_id(EXAMPLE)?)?;
+    }
+
+    let question = format!(
+        "Write a starter config to '{}'? [Y/n]: ",
+        CONFIG_PATH.to_string_lossy()
+    );
+
+    // The answers above are kept either way; the starter config only adds the commented settings.
+    let starter = confirm(&question)?;
+
+    write_config(&editor, basic, starter)
+}
+
+fn ask(question: &str) -> Result<String, Error> {
+    eprint!("{question}");
+
+    let _ = io::stderr().flush();
+    let mut answer = String::new();
+
+    io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| Error::with_cause("The answer reading failed", e.kind()))?;
+
+    Ok(answer.trim().to_string())
+}
+
+pub fn confirm(question: &str) -> Result<bool, Error> {
+    let answer = ask(question)?.to_lowercase();
+
+    Ok(answer.is_empty() || answer == "y" || answer == "yes")
+}
+
+fn write_config(editor: &str, basic: Option<bool>, starter: bool) -> Result<(), Error> {
+    let editor = toml::Value::String(editor.to_string());
+    let display = match basic {
+        Some(basic) => format!("basic = {basic}"),
+        None => String::from("# basic = false"),
+    };
+    let content = match (starter, basic) {
+        (true, _) => format!(
+            r#"# memoleak settings. All of them are optional and have defaults.
+
+# What the TUI opens on: "list", "last", "today" or {{ search = "<name>" }}.
+# startup = "list"
+
+[editor]
+command = {editor}
+# Where it opens: "terminal", "tmux-split", "tmux-window", "wezterm-split" or "wezterm-tab".
+# open_in = "terminal"
+
+[display]
+# ASCII borders and white on black for limited terminals; guessed from TERM when unset.
+{display}
+
+[input]
+# Milliseconds to wait for the rest of a longer keymap.
+# timeout = 1000
+
+[keys]
+# Shell command lines bound to keymaps, e.g.
+# "gp" = {{ shell = "git -C {{stash}} push" }}
+"#
+        ),
+        (false, None) => format!("[editor]\ncommand = {editor}\n"),
+        (false, Some(_)) => format!("[editor]\ncommand = {editor}\n\n[display]\n{display}\n"),
+    };
+
+    if let Some(dir) = CONFIG_PATH.parent() {
+        fs::create_dir_all(dir).map_err(|e| {
+            Error::with_cause(
+                format!("A config dir '{}' creating failed", dir.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+    }
+
+    fs::write(&*CONFIG_PATH, content).map_err(|e| {
+        Error::with_cause(
+            format!(
+                "A config '{}' writing failed",
+                CONFIG_PATH.to_string_lossy()
+            ),
+            e.kind(),
+        )
+    })
+}
@@ -0,0 +1,163 @@
+use crate::config::{PeriodConfig, PeriodicConfig};
+use crate::{Error, memo_path, meta, private_file_options, validate_memo_name};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    pub fn parse(name: &str) -> Result<Self, Error> {
+        match name {
+            "day" => Ok(Period::Day),
+            "week" => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            _ => Err(Error::with_cause(
+                format!("A period '{name}' is not supported"),
+                "expected day, week or month",
+            )),
+        }
+    }
+
+    fn config(self, config: &PeriodicConfig) -> &PeriodConfig {
+        match self {
+            Period::Day => &config.day,
+            Period::Week => &config.week,
+            Period::Month => &config.month,
+        }
+    }
+
+    fn format(self, config: &PeriodicConfig) -> &str {
+        self.config(config).format.as_deref().unwrap_or(match self {
+            Period::Day => "%Y-%m-%d",
+            Period::Week => "%G-W%V",
+            Period::Month => "%Y-%m",
+        })
+    }
+
+    // Weeks start on Monday, as in ISO 8601.
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+            Period::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    fn last(self, start: NaiveDate) -> NaiveDate {
+        self.step(start, 1).pred_opt().unwrap_or(start)
+    }
+
+    pub fn step(self, start: NaiveDate, forward: i32) -> NaiveDate {
+        let res = match (self, forward >= 0) {
+            (Period::Day, true) => start.checked_add_days(Days::new(forward as u64)),
+            (Period::Day, false) => {
+                start.checked_sub_days(Days::new(forward.unsigned_abs() as u64))
+            }
+            (Period::Week, true) => start.checked_add_days(Days::new(7 * forward as u64)),
+            (Period::Week, false) => {
+                start.checked_sub_days(Days::new(7 * forward.unsigned_abs() as u64))
+            }
+            (Period::Month, true) => start.checked_add_months(Months::new(forward as u32)),
+            (Period::Month, false) => start.checked_sub_months(Months::new(forward.unsigned_abs())),
+        };
+
+        res.unwrap_or(start)
+    }
+
+    pub fn name(self, start: NaiveDate, config: &PeriodicConfig) -> Result<String, Error> {
+        let format = self.format(config);
+        let mut name = String::new();
+
+        write!(name, "{}", start.format(format)).map_err(|_| {
+            Error::with_cause(
+                format!("A name format '{format}' is invalid"),
+                "see chrono's strftime documentation",
+            )
+        })?;
+
+        Ok(name)
+    }
+}
+
+// Finds which period a memo name belongs to by formatting the period it would start and
+// checking that it round-trips.
+pub fn recognize(name: &str, config: &PeriodicConfig) -> Option<(Period, NaiveDate)> {
+    [Period::Day, Period::Week, Period::Month]
+        .into_iter()
+        .find_map(|period| {
+            let format = period.format(config);
+            // The formats name a period, not a day, so the day is added for chrono to resolve one.
+            let date = match period {
+                Period::Day => NaiveDate::parse_from_str(name, format),
+                Period::Week => {
+                    NaiveDate::parse_from_str(&format!("{name} 1"), &format!("{format} %u"))
+                }
+                Period::Month => {
+                    NaiveDate::parse_from_str(&format!("{name} 01"), &format!("{format} %d"))
+                }
+            }
+            .ok()?;
+            let start = period.start(date);
+
+            (period.name(start, config).ok()? == name).then_some((period, start))
+        })
+}
+
+// Creates the period's memo from its template unless it exists. Returns the path and whether
+// it was created. `{{name}}`, `{{start}}` and `{{end}}` in the template are filled in.
+pub fn ensure(
+    period: Period,
+    start: NaiveDate,
+    config: &PeriodicConfig,
+) -> Result<(PathBuf, bool), Error> {
+    let name = period.name(start, config)?;
+
+    validate_memo_name(&name)?;
+
+    let path = memo_path(&name);
+
+    if path.exists() {
+        return Ok((path, false));
+    }
+
+    let content = match &period.config(config).template {
+        Some(template) => fs::read_to_string(template)
+            .map_err(|e| {
+                Error::with_cause(
+                    format!("A template '{}' reading failed", template.to_string_lossy()),
+                    e.kind(),
+                )
+            })?
+            .replace("{{name}}", &name)
+            .replace("{{start}}", &start.format("%Y-%m-%d").to_string())
+            .replace(
+                "{{end}}",
+                &period.last(start).format("%Y-%m-%d").to_string(),
+            ),
+        None => String::new(),
+    };
+    let content = meta::with_id(&content)?;
+
+    let res = private_file_options()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+
+    match res {
+        Ok(_) => Ok((path, true)),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok((path, false)),
+        Err(e) => Err(Error::with_cause(
+            format!("A memo '{name}' generating failed"),
+            e.kind(),
+        )),
+    }
+}
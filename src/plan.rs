@@ -0,0 +1,187 @@
+use crate::task::Progress;
+use crate::{Error, erase_file, move_file, private_file_options, storage};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+// A change to the stash on disk, planned up front so destructive commands can either apply it or
+// just report what they would do.
+pub enum Action {
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Delete {
+        path: PathBuf,
+    },
+    // A delete that overwrites the content first.
+    Erase {
+        path: PathBuf,
+    },
+    Create {
+        path: PathBuf,
+        content: String,
+        // Carried over from the source so imported memos keep their age.
+        modified: Option<SystemTime>,
+    },
+    Modify {
+        path: PathBuf,
+        content: String,
+        lines: usize,
+    },
+}
+
+impl Action {
+    fn apply(&self) -> Result<(), Error> {
+        match self {
+            Action::Move { from, to } => move_file(from, to),
+            Action::Copy { from, to } => {
+                if to.exists() {
+                    return Err(Error::with_cause(
+                        format!("A file '{}' copying failed", from.to_string_lossy()),
+                        "the destination already exists",
+                    ));
+                }
+
+                fs::copy(from, to).map(|_| ()).map_err(|e| {
+                    Error::with_cause(
+                        format!("A file '{}' copying failed", from.to_string_lossy()),
+                        e.kind(),
+                    )
+                })
+            }
+            Action::Delete { path } => fs::remove_file(path).map_err(|e| {
+                Error::with_cause(
+                    format!("A file '{}' cleanup failed", path.to_string_lossy()),
+                    e.kind(),
+                )
+            }),
+            Action::Erase { path } => erase_file(path),
+            Action::Create {
+                path,
+                content,
+                modified,
+            } => {
+                let failed = |e: std::io::Error| {
+                    Error::with_cause(
+                        format!("A file '{}' creating failed", path.to_string_lossy()),
+                        e.kind(),
+                    )
+                };
+                let mut file = private_file_options()
+                    .write(true)
+                    .create_new(true)
+                    .open(path)
+                    .map_err(failed)?;
+
+                file.write_all(content.as_bytes()).map_err(failed)?;
+
+                match modified {
+                    Some(time) => file.set_modified(*time).map_err(failed),
+                    None => Ok(()),
+                }
+            }
+            Action::Modify { path, content, .. } => storage::write(path, content).map(|_| ()),
+        }
+    }
+
+    fn describe(&self, dry_run: bool) -> String {
+        let verb = |done: &str, planned: &str| if dry_run { planned } else { done }.to_string();
+
+        match self {
+            Action::Move { from, to } => format!(
+                "{} {} -> {}",
+                verb("moved", "would move"),
+                from.to_string_lossy(),
+                to.to_string_lossy()
+            ),
+            Action::Copy { from, to } => format!(
+                "{} {} -> {}",
+                verb("copied", "would copy"),
+                from.to_string_lossy(),
+                to.to_string_lossy()
+            ),
+            Action::Delete { path } => {
+                format!(
+                    "{} {}",
+                    verb("deleted", "would delete"),
+                    path.to_string_lossy()
+                )
+            }
+            Action::Erase { path } => {
+                format!(
+                    "{} {}",
+                    verb("erased", "would erase"),
+                    path.to_string_lossy()
+                )
+            }
+            Action::Create { path, .. } => format!(
+                "{} {}",
+                verb("created", "would create"),
+                path.to_string_lossy()
+            ),
+            Action::Modify { path, lines, .. } => format!(
+                "{} {} ({lines} lines)",
+                verb("modified", "would modify"),
+                path.to_string_lossy()
+            ),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Plan {
+    actions: Vec<Action>,
+}
+
+impl Plan {
+    pub fn push(&mut self, action: Action) {
+        self.actions.push(action);
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    // Applies every action in order without a word, stopping at the first failure or once
+    // `progress` is cancelled.
+    pub fn apply(&self, progress: &Progress) -> Result<(), Error> {
+        progress.start(self.actions.len());
+
+        for action in &self.actions {
+            progress.step()?;
+            action.apply()?;
+        }
+
+        Ok(())
+    }
+
+    // Applies (or with dry_run only prints) every action in order, stopping at the first failure.
+    pub fn execute(&self, dry_run: bool) -> Result<(), Error> {
+        for action in &self.actions {
+            if !dry_run {
+                action.apply()?;
+            }
+
+            println!("{}", action.describe(dry_run));
+        }
+
+        if dry_run {
+            println!(
+                "{} changes planned, nothing written (dry run)",
+                self.actions.len()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{Error, PLUGIN_PATH};
+use std::path::Path;
+
+// A script function hooked up by one of the registration calls below.
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+struct Hooked {
+    script: usize,
+    func: String,
+}
+
+// What a binding or command asked for once it returned.
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+pub enum Outcome {
+    Nothing,
+    Status(String),
+    Rewrite(String),
+}
+
+// User scripts from PLUGIN_PATH. At load time a script registers itself through:
+//   bind(keys, fn)       run `fn(memo)` on a keymap in the list view
+//   command(name, fn)    run `fn(memo)` as `:name`
+//   on_save(fn)          run `fn(memo)` after an edit; `#{ content: ... }` replaces the content
+//   column(fn)           show `fn(memo)` next to each name in the list
+// `memo` is a map of name, path, content, title and tags. Bindings and commands may return a
+// string for the status line or `#{ content: ... }` to rewrite the memo. Anything else an on_save
+// function returns is ignored, so only a script that means to change the memo does.
+#[derive(Default)]
+pub struct Plugins {
+    #[cfg(feature = "plugins")]
+    engine: rhai::Engine,
+    #[cfg(feature = "plugins")]
+    scripts: Vec<rhai::AST>,
+    bindings: Vec<(String, Hooked)>,
+    commands: Vec<(String, Hooked)>,
+    transforms: Vec<Hooked>,
+    columns: Vec<Hooked>,
+}
+
+impl Plugins {
+    pub fn bindings(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.bindings
+            .iter()
+            .enumerate()
+            .map(|(idx, (keys, _))| (idx, keys.as_str()))
+    }
+
+    // The script function a binding runs.
+    pub fn binding_name(&self, idx: usize) -> Option<&str> {
+        self.bindings
+            .get(idx)
+            .map(|(_, hooked)| hooked.func.as_str())
+    }
+
+    pub fn commands(&self) -> impl Iterator<Item = &str> {
+        self.commands.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn command(&self, name: &str) -> Option<usize> {
+        self.commands
+            .iter()
+            .position(|(command, _)| command == name)
+    }
+
+    pub fn run_binding(&self, idx: usize, path: &Path, content: &str) -> Result<Outcome, Error> {
+        match self.bindings.get(idx) {
+            Some((_, hooked)) => self.run(hooked, path, content),
+            None => Ok(Outcome::Nothing),
+        }
+    }
+
+    pub fn run_command(&self, idx: usize, path: &Path, content: &str) -> Result<Outcome, Error> {
+        match self.commands.get(idx) {
+            Some((_, hooked)) => self.run(hooked, path, content),
+            None => Ok(Outcome::Nothing),
+        }
+    }
+
+    // Applies every on_save transform in load order.
+    pub fn transform(&self, path: &Path, content: &str) -> Result<String, Error> {
+        let mut content = content.to_string();
+
+        for hooked in &self.transforms {
+            if let Outcome::Rewrite(new) = self.run(hooked, path, &content)? {
+                content = new;
+            }
+        }
+
+        Ok(content)
+    }
+
+    // One cell per registered column; a failing script shows `!` rather than breaking the list.
+    pub fn columns(&self, path: &Path, content: &str) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|hooked| match self.run(hooked, path, content) {
+                Ok(Outcome::Status(cell)) => cell,
+                Ok(_) => String::new(),
+                Err(_) => String::from("!"),
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "plugins")]
+    fn run(&self, hooked: &Hooked, path: &Path, content: &str) -> Result<Outcome, Error> {
+        use crate::{memo_name_of, meta};
+        use rhai::{Array, Dynamic, Map, Scope};
+
+        let (front, _) = meta::split(content);
+        let name = memo_name_of(path);
+
+        let mut memo = Map::new();
+
+        memo.insert(
+            "title".into(),
+            front.title().unwrap_or(&name).to_string().into(),
+        );
+        memo.insert(
+            "tags".into(),
+            Dynamic::from_array(
+                front
+                    .tags()
+                    .into_iter()
+                    .map(|tag| Dynamic::from(tag.to_string()))
+                    .collect::<Array>(),
+            ),
+        );
+        memo.insert("name".into(), name.into());
+        memo.insert("path".into(), path.to_string_lossy().to_string().into());
+        memo.insert("content".into(), content.into());
+
+        let res = self
+            .engine
+            .call_fn::<Dynamic>(
+                &mut Scope::new(),
+                &self.scripts[hooked.script],
+                &hooked.func,
+                (memo,),
+            )
+            .map_err(|e| Error::with_cause(format!("A plugin '{}' failed", hooked.func), e))?;
+
+        if let Some(map) = res.clone().try_cast::<Map>() {
+            return Ok(match map.get("content") {
+                Some(content) => Outcome::Rewrite(content.to_string()),
+                None => Outcome::Nothing,
+            });
+        }
+
+        Ok(if res.is_unit() {
+            Outcome::Nothing
+        } else {
+            Outcome::Status(res.to_string())
+        })
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    fn run(&self, _: &Hooked, _: &Path, _: &str) -> Result<Outcome, Error> {
+        Ok(Outcome::Nothing)
+    }
+}
+
+fn script_paths() -> Vec<std::path::PathBuf> {
+    let mut paths = PLUGIN_PATH
+        .read_dir()
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    paths.sort();
+
+    paths
+}
+
+// Loads every script it can; the first failure is returned next to whatever did load.
+#[cfg(feature = "plugins")]
+pub fn load() -> (Plugins, Option<Error>) {
+    use rhai::{Engine, FnPtr};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct Registry {
+        script: usize,
+        bindings: Vec<(String, Hooked)>,
+        commands: Vec<(String, Hooked)>,
+        transforms: Vec<Hooked>,
+        columns: Vec<Hooked>,
+    }
+
+    impl Registry {
+        // Drops whatever a script registered before it failed.
+        fn forget(&mut self, script: usize) {
+            self.bindings.retain(|(_, hooked)| hooked.script != script);
+            self.commands.retain(|(_, hooked)| hooked.script != script);
+            self.transforms.retain(|hooked| hooked.script != script);
+            self.columns.retain(|hooked| hooked.script != script);
+        }
+    }
+
+    let registry = Rc::new(RefCell::new(Registry::default()));
+    let mut engine = Engine::new();
+
+    // Scripts run on the UI thread, columns on every draw, so a runaway one is stopped with an
+    // error rather than hanging the TUI. Strings may still hold a memo well past LARGE_MEMO_SIZE.
+    engine
+        .set_max_operations(1_000_000)
+        .set_max_call_levels(64)
+        .set_max_string_size(64 * 1024 * 1024);
+
+    // Functions may be given by name or as a `Fn("name")` pointer.
+    fn func_name(func: rhai::Dynamic) -> String {
+        match func.clone().try_cast::<FnPtr>() {
+            Some(ptr) => ptr.fn_name().to_string(),
+            None => func.to_string(),
+        }
+    }
+
+    let r = registry.clone();
+    engine.register_fn("bind", move |keys: &str, func: rhai::Dynamic| {
+        let mut r = r.borrow_mut();
+        let script = r.script;
+
+        r.bindings.push((
+            keys.to_string(),
+            Hooked {
+                script,
+                func: func_name(func),
+            },
+        ));
+    });
+
+    let r = registry.clone();
+    engine.register_fn("command", move |name: &str, func: rhai::Dynamic| {
+        let mut r = r.borrow_mut();
+        let script = r.script;
+
+        r.commands.push((
+            name.to_string(),
+            Hooked {
+                script,
+                func: func_name(func),
+            },
+        ));
+    });
+
+    let r = registry.clone();
+    engine.register_fn("on_save", move |func: rhai::Dynamic| {
+        let mut r = r.borrow_mut();
+        let script = r.script;
+
+        r.transforms.push(Hooked {
+            script,
+            func: func_name(func),
+        });
+    });
+
+    let r = registry.clone();
+    engine.register_fn("column", move |func: rhai::Dynamic| {
+        let mut r = r.borrow_mut();
+        let script = r.script;
+
+        r.columns.push(Hooked {
+            script,
+            func: func_name(func),
+        });
+    });
+
+    let mut scripts = vec![];
+    let mut error = None;
+
+    for path in script_paths() {
+        registry.borrow_mut().script = scripts.len();
+
+        let res = engine
+            .compile_file(path.clone())
+            .map_err(|e| e.to_string())
+            .and_then(|ast| engine.run_ast(&ast).map(|_| ast).map_err(|e| e.to_string()));
+
+        match res {
+            Ok(ast) => scripts.push(ast),
+            Err(e) => {
+                registry.borrow_mut().forget(scripts.len());
+                error.get_or_insert(Error::with_cause(
+                    format!("A plugin '{}' loading failed", path.to_string_lossy()),
+                    e,
+                ));
+            }
+        }
+    }
+
+    let registry = std::mem::take(&mut *registry.borrow_mut());
+
+    (
+        Plugins {
+            engine,
+            scripts,
+            bindings: registry.bindings,
+            commands: registry.commands,
+            transforms: registry.transforms,
+            columns: registry.columns,
+        },
+        error,
+    )
+}
+
+#[cfg(not(feature = "plugins"))]
+pub fn load() -> (Plugins, Option<Error>) {
+    let error = (!script_paths().is_empty())
+        .then(|| Error::new("Plugins are present but this build has no plugin support"));
+
+    (Plugins::default(), error)
+}
@@ -0,0 +1,297 @@
+use crate::fit_width;
+use crossterm::queue;
+use crossterm::style::{Attribute, Print, SetAttribute};
+use std::io;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+// A preview row and the byte ranges of it to underline.
+#[derive(Clone)]
+pub struct Line {
+    pub text: String,
+    pub marks: Vec<Range<usize>>,
+}
+
+impl Line {
+    pub fn plain<S: Into<String>>(text: S) -> Self {
+        Self {
+            text: text.into(),
+            marks: vec![],
+        }
+    }
+}
+
+impl Line {
+    // The part of the line in `range`, with its marks moved along.
+    fn slice(&self, range: Range<usize>) -> Line {
+        Line {
+            text: self.text[range.clone()].to_string(),
+            marks: self
+                .marks
+                .iter()
+                .filter(|mark| mark.start < range.end && mark.end > range.start)
+                .map(|mark| {
+                    mark.start.max(range.start) - range.start..mark.end.min(range.end) - range.start
+                })
+                .collect(),
+        }
+    }
+}
+
+// Soft-wraps at `width` columns when given, preferring to break after a space; otherwise cuts
+// `hscroll` columns off the left of every line.
+pub fn layout(lines: Vec<Line>, wrap: Option<usize>, hscroll: usize) -> Vec<Line> {
+    match wrap {
+        Some(width) => lines
+            .iter()
+            .flat_map(|line| {
+                breaks(&line.text, width.max(1))
+                    .into_iter()
+                    .map(|range| line.slice(range))
+            })
+            .collect(),
+        None if hscroll == 0 => lines,
+        None => lines
+            .iter()
+            .map(|line| {
+                let mut skipped = 0;
+                let start = line
+                    .text
+                    .grapheme_indices(true)
+                    .find(|(_, g)| {
+                        let done = skipped >= hscroll;
+
+                        skipped += g.width();
+
+                        done
+                    })
+                    .map_or(line.text.len(), |(i, _)| i);
+
+                line.slice(start..line.text.len())
+            })
+            .collect(),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// Draws GFM tables with box-drawing characters. A table wider than `width` stays as written.
+pub fn tables(lines: Vec<Line>, width: usize, ascii: bool) -> Vec<Line> {
+    let mut out = Vec::with_capacity(lines.len());
+    let mut i = 0;
+
+    while i < lines.len() {
+        let aligns = lines.get(i + 1).and_then(|line| delimiter(&line.text));
+
+        let Some(aligns) = aligns.filter(|_| lines[i].text.contains('|')) else {
+            out.push(lines[i].clone());
+            i += 1;
+            continue;
+        };
+
+        let end = (i + 2..lines.len())
+            .find(|&j| !lines[j].text.contains('|') || lines[j].text.trim().is_empty())
+            .unwrap_or(lines.len());
+        let rows = std::iter::once(i)
+            .chain(i + 2..end)
+            .map(|j| cells(&lines[j].text, aligns.len()))
+            .collect::<Vec<_>>();
+
+        match draw(&rows, &aligns, ascii)
+            .filter(|drawn| drawn.iter().all(|row| row.width() <= width))
+        {
+            Some(drawn) => out.extend(drawn.into_iter().map(Line::plain)),
+            None => out.extend_from_slice(&lines[i..end]),
+        }
+
+        i = end;
+    }
+
+    out
+}
+
+// The alignments of a `| --- | :-: |` row, or None if it is not one.
+fn delimiter(text: &str) -> Option<Vec<Align>> {
+    let text = text.trim();
+
+    // Without a pipe this could as well be a rule or a heading underline.
+    if !text.contains('|') || !text.contains('-') {
+        return None;
+    }
+
+    split_row(text)
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Align::Center,
+                (false, true) => Align::Right,
+                _ => Align::Left,
+            })
+        })
+        .collect()
+}
+
+fn split_row(text: &str) -> Vec<String> {
+    let text = text.trim();
+    let text = text.strip_prefix('|').unwrap_or(text);
+    let text = if text.ends_with('|') && !text.ends_with("\\|") {
+        &text[..text.len() - 1]
+    } else {
+        text
+    };
+
+    let mut cells = vec![String::new()];
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => {
+                cells.last_mut().unwrap().push('|');
+                chars.next();
+            }
+            '|' => cells.push(String::new()),
+            c => cells.last_mut().unwrap().push(c),
+        }
+    }
+
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+// Rows have exactly as many cells as the table has columns, as GFM pads and cuts them.
+fn cells(text: &str, columns: usize) -> Vec<String> {
+    let mut cells = split_row(text);
+
+    cells.resize(columns, String::new());
+
+    cells
+}
+
+fn draw(rows: &[Vec<String>], aligns: &[Align], ascii: bool) -> Option<Vec<String>> {
+    let widths = (0..aligns.len())
+        .map(|c| rows.iter().map(|row| row[c].width()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    let rule = |left: &str, mid: &str, right: &str| {
+        let line = if ascii { "-" } else { "─" };
+        let parts = widths
+            .iter()
+            .map(|w| line.repeat(w + 2))
+            .collect::<Vec<_>>();
+
+        format!("{left}{}{right}", parts.join(mid))
+    };
+    let row = |cells: &[String]| {
+        let parts = cells
+            .iter()
+            .zip(&widths)
+            .zip(aligns)
+            .map(|((cell, &w), align)| {
+                let pad = w - cell.width();
+                let (before, after) = match align {
+                    Align::Left => (0, pad),
+                    Align::Center => (pad / 2, pad - pad / 2),
+                    Align::Right => (pad, 0),
+                };
+
+                format!(" {}{cell}{} ", " ".repeat(before), " ".repeat(after))
+            })
+            .collect::<Vec<_>>();
+
+        let bar = if ascii { "|" } else { "│" };
+
+        format!("{bar}{}{bar}", parts.join(bar))
+    };
+
+    let (header, body) = rows.split_first()?;
+    let mut drawn = match ascii {
+        true => vec![rule("+", "+", "+"), row(header), rule("+", "+", "+")],
+        false => vec![rule("┌", "┬", "┐"), row(header), rule("├", "┼", "┤")],
+    };
+
+    drawn.extend(body.iter().map(|cells| row(cells)));
+    drawn.push(match ascii {
+        true => rule("+", "+", "+"),
+        false => rule("└", "┴", "┘"),
+    });
+
+    Some(drawn)
+}
+
+fn breaks(text: &str, width: usize) -> Vec<Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = 0;
+    let mut used = 0;
+    let mut after_space = None;
+
+    for (i, g) in text.grapheme_indices(true) {
+        let w = g.width();
+
+        if used + w > width && i > start {
+            let cut = after_space.filter(|&cut| cut > start).unwrap_or(i);
+
+            ranges.push(start..cut);
+            start = cut;
+            used = text[start..i].width();
+            after_space = None;
+        }
+
+        used += w;
+
+        if g.chars().all(char::is_whitespace) {
+            after_space = Some(i + g.len());
+        }
+    }
+
+    ranges.push(start..text.len());
+
+    ranges
+}
+
+// Prints a row fitted to `width`; marks past the cut are dropped with the text they cover. Marks
+// are underlined, or reversed on `basic` terminals that may not underline.
+pub fn print<W: io::Write>(out: &mut W, line: &Line, width: usize, basic: bool) {
+    let (on, off) = match basic {
+        true => (Attribute::Reverse, Attribute::NoReverse),
+        false => (Attribute::Underlined, Attribute::NoUnderline),
+    };
+    let fitted = fit_width(&line.text, width);
+    // The fitted row starts with whatever of the text fit.
+    let kept = fitted
+        .bytes()
+        .zip(line.text.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut at = 0;
+
+    for mark in &line.marks {
+        let (from, to) = (mark.start.min(kept), mark.end.min(kept));
+
+        if from >= to || from < at {
+            continue;
+        }
+
+        let _ = queue!(
+            out,
+            Print(&fitted[at..from]),
+            SetAttribute(on),
+            Print(&fitted[from..to]),
+            SetAttribute(off)
+        );
+        at = to;
+    }
+
+    let _ = queue!(out, Print(&fitted[at..]));
+}
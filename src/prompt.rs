@@ -0,0 +1,384 @@
+use crate::{
+    Error, HISTORY_PATH, memo_path, numbered_memo_name, private_file_options, sanitize_memo_name,
+    validate_memo_name,
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const HISTORY_LIMIT: usize = 500;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PromptKind {
+    Create,
+    Rename,
+    Search,
+    Command,
+    Log,
+    Tag,
+    Merge,
+    QuickEdit,
+}
+
+impl PromptKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PromptKind::Create => "New memo",
+            PromptKind::Rename => "Rename to",
+            PromptKind::Search => "/",
+            PromptKind::Command => ":",
+            PromptKind::Log => "Log",
+            PromptKind::Tag => "Tags",
+            PromptKind::Merge => "Merge into",
+            PromptKind::QuickEdit => "Add line",
+        }
+    }
+
+    fn history_path(&self) -> Option<PathBuf> {
+        match self {
+            PromptKind::Search => Some(HISTORY_PATH.join("search")),
+            PromptKind::Command => Some(HISTORY_PATH.join("command")),
+            PromptKind::Create
+            | PromptKind::Rename
+            | PromptKind::Log
+            | PromptKind::Tag
+            | PromptKind::Merge
+            | PromptKind::QuickEdit => None,
+        }
+    }
+}
+
+pub fn load_history(kind: PromptKind) -> Vec<String> {
+    kind.history_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|content| content.lines().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+pub fn save_history(kind: PromptKind, history: &[String]) -> Result<(), Error> {
+    let Some(path) = kind.history_path() else {
+        return Ok(());
+    };
+
+    let kept = &history[history.len().saturating_sub(HISTORY_LIMIT)..];
+    let content = kept
+        .iter()
+        .map(|entry| format!("{entry}\n"))
+        .collect::<String>();
+
+    private_file_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A history '{}' saving failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })
+}
+
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub editor: LineEditor,
+    pub force: bool,
+    // The highlighted row of the completion menu.
+    pub completion: usize,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, history: Vec<String>) -> Self {
+        Self {
+            kind,
+            editor: LineEditor::new(history),
+            force: false,
+            completion: 0,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.editor.buffer
+    }
+
+    pub fn suggestion(&self) -> Option<String> {
+        let text = self.text();
+
+        match self.kind {
+            PromptKind::Create | PromptKind::Rename if text.is_empty() => None,
+            PromptKind::Create | PromptKind::Rename if validate_memo_name(text).is_err() => {
+                Some(sanitize_memo_name(text))
+            }
+            PromptKind::Create if memo_path(text).exists() => Some(numbered_memo_name(text)),
+            _ => None,
+        }
+    }
+
+    fn prefix(&self) -> String {
+        match self.kind {
+            PromptKind::Search | PromptKind::Command => self.kind.label().to_string(),
+            PromptKind::Create
+            | PromptKind::Rename
+            | PromptKind::Log
+            | PromptKind::Tag
+            | PromptKind::Merge
+            | PromptKind::QuickEdit => {
+                format!("{}: ", self.kind.label())
+            }
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let line = format!("{}{}", self.prefix(), self.text());
+
+        match (self.kind, self.suggestion()) {
+            (PromptKind::Create, Some(suggestion)) if memo_path(self.text()).exists() => {
+                format!("{line}  (exists, TAB: '{suggestion}', C-f: overwrite)")
+            }
+            (_, Some(suggestion)) => format!("{line}  (invalid name, TAB: '{suggestion}')"),
+            (_, None) => line,
+        }
+    }
+
+    // Terminal column of the cursor within the rendered prompt line.
+    pub fn cursor_column(&self) -> usize {
+        self.prefix().width() + self.editor.cursor_width()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Completing {
+    Link,
+    Tag,
+}
+
+pub struct LineEditor {
+    buffer: String,
+    // Byte offset into buffer, always on a grapheme boundary.
+    cursor: usize,
+    history: Vec<String>,
+    history_pos: Option<usize>,
+    draft: String,
+}
+
+impl LineEditor {
+    pub fn new(history: Vec<String>) -> Self {
+        Self {
+            buffer: String::new(),
+            cursor: 0,
+            history,
+            history_pos: None,
+            draft: String::new(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.buffer
+    }
+
+    pub fn set_buffer(&mut self, buffer: String) {
+        self.cursor = buffer.len();
+        self.buffer = buffer;
+    }
+
+    pub fn cursor_width(&self) -> usize {
+        self.buffer[..self.cursor].width()
+    }
+
+    // Returns false for keys the editor does not own, leaving them to the caller.
+    pub fn handle(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+
+        match key.code {
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.buffer.len(),
+            KeyCode::Char('w') if ctrl => self.delete_word(),
+            KeyCode::Char('u') if ctrl => self.delete_range(0, self.cursor),
+            KeyCode::Char('k') if ctrl => self.delete_range(self.cursor, self.buffer.len()),
+            KeyCode::Char('b') if alt => self.cursor = self.prev_word(),
+            KeyCode::Char('f') if alt => self.cursor = self.next_word(),
+            KeyCode::Char(_) if ctrl || alt => return false,
+            KeyCode::Char(c) => self.insert(&c.to_string()),
+            KeyCode::Backspace => self.delete_range(self.prev_boundary(), self.cursor),
+            KeyCode::Delete => self.delete_range(self.cursor, self.next_boundary()),
+            KeyCode::Left => self.cursor = self.prev_boundary(),
+            KeyCode::Right => self.cursor = self.next_boundary(),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.buffer.len(),
+            KeyCode::Up => self.history_prev(),
+            KeyCode::Down => self.history_next(),
+            _ => return false,
+        }
+
+        true
+    }
+
+    // What follows the last `prefix` before the cursor, unless a space comes in between; so
+    // `notes-;date` has the trigger word `date`.
+    pub fn trigger_word(&self, prefix: char) -> Option<&str> {
+        let before = &self.buffer[..self.cursor];
+        let word = &before[before.rfind(prefix)? + prefix.len_utf8()..];
+
+        (!word.is_empty() && !word.contains(char::is_whitespace)).then_some(word)
+    }
+
+    // Replaces the trigger word before the cursor; line breaks are flattened like pasted text.
+    pub fn replace_trigger(&mut self, prefix: char, text: &str) {
+        let Some(len) = self
+            .trigger_word(prefix)
+            .map(|word| word.len() + prefix.len_utf8())
+        else {
+            return;
+        };
+
+        self.delete_range(self.cursor - len, self.cursor);
+        self.paste(text);
+    }
+
+    // A wiki link left open, as in `see [[pro`, or a tag being typed, as in `#wo`, before the
+    // cursor; with what is typed of it so far.
+    pub fn completing(&self) -> Option<(Completing, &str)> {
+        let before = &self.buffer[..self.cursor];
+
+        if let Some(start) = before.rfind("[[") {
+            let typed = &before[start + 2..];
+
+            if !typed.contains("]]") && !typed.contains('|') {
+                return Some((Completing::Link, typed));
+            }
+        }
+
+        let start = before.rfind('#')?;
+        let typed = &before[start + 1..];
+        let on_its_own = before[..start]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+
+        (on_its_own && !typed.contains(char::is_whitespace)).then_some((Completing::Tag, typed))
+    }
+
+    // Replaces what is typed of a link or tag with `text`, closing the link.
+    pub fn complete(&mut self, text: &str) {
+        let Some((completing, typed)) = self.completing() else {
+            return;
+        };
+
+        self.delete_range(self.cursor - typed.len(), self.cursor);
+
+        match completing {
+            Completing::Link => self.insert(&format!("{text}]]")),
+            Completing::Tag => self.insert(text),
+        }
+    }
+
+    // Pasted text arrives in one piece; line breaks are flattened since prompts are single-line.
+    pub fn paste(&mut self, text: &str) {
+        let text = text
+            .split(['\r', '\n'])
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        self.insert(&text.replace(char::is_control, ""));
+    }
+
+    fn insert(&mut self, text: &str) {
+        self.buffer.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    fn delete_range(&mut self, from: usize, to: usize) {
+        self.buffer.replace_range(from..to, "");
+        self.cursor = from;
+    }
+
+    fn delete_word(&mut self) {
+        self.delete_range(self.prev_word(), self.cursor);
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.buffer[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(i, _)| i)
+            .unwrap_or(0)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.buffer[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map(|g| self.cursor + g.len())
+            .unwrap_or(self.cursor)
+    }
+
+    fn prev_word(&self) -> usize {
+        let mut pos = self.cursor;
+        let mut seen_word = false;
+
+        for (i, g) in self.buffer[..self.cursor].grapheme_indices(true).rev() {
+            let blank = g.chars().all(char::is_whitespace);
+
+            if blank && seen_word {
+                break;
+            }
+
+            seen_word |= !blank;
+            pos = i;
+        }
+
+        pos
+    }
+
+    fn next_word(&self) -> usize {
+        let mut seen_word = false;
+
+        for (i, g) in self.buffer[self.cursor..].grapheme_indices(true) {
+            let blank = g.chars().all(char::is_whitespace);
+
+            if blank && seen_word {
+                return self.cursor + i;
+            }
+
+            seen_word |= !blank;
+        }
+
+        self.buffer.len()
+    }
+
+    fn history_prev(&mut self) {
+        let pos = match self.history_pos {
+            None if self.history.is_empty() => return,
+            None => {
+                self.draft = self.buffer.clone();
+                self.history.len() - 1
+            }
+            Some(pos) => pos.saturating_sub(1),
+        };
+
+        self.history_pos = Some(pos);
+        self.set_buffer(self.history[pos].clone());
+    }
+
+    fn history_next(&mut self) {
+        let Some(pos) = self.history_pos else {
+            return;
+        };
+
+        if pos + 1 < self.history.len() {
+            self.history_pos = Some(pos + 1);
+            self.set_buffer(self.history[pos + 1].clone());
+        } else {
+            let draft = std::mem::take(&mut self.draft);
+
+            self.history_pos = None;
+            self.set_buffer(draft);
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use crate::config::PublishConfig;
+use crate::meta::{self, WikiLink};
+use crate::{Error, memo_name_of, storage};
+use chrono::{DateTime, SecondsFormat, Utc};
+use pulldown_cmark::{Options, Parser, html};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const STYLE: &str = "body { max-width: 46rem; margin: 2rem auto; padding: 0 1rem; font-family: sans-serif; line-height: 1.6; }
+nav { margin-bottom: 2rem; }
+a.missing { color: #b00; text-decoration: line-through; }
+ul.tags { list-style: none; padding: 0; }
+ul.tags li { display: inline; margin-right: 0.5rem; }
+pre { overflow-x: auto; padding: 0.5rem; background: #f4f4f4; }
+footer { margin-top: 3rem; font-size: 0.9em; }
+";
+
+struct Page {
+    name: String,
+    id: Option<String>,
+    aliases: Vec<String>,
+    title: String,
+    tags: Vec<String>,
+    body: String,
+    modified: SystemTime,
+}
+
+impl Page {
+    fn read(path: &Path) -> Result<Self, Error> {
+        let content = storage::read(path).map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' reading failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+        let name = memo_name_of(path);
+        let modified = fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let (front, body) = meta::split(&content);
+
+        Ok(Self {
+            id: front.id().map(String::from),
+            aliases: front.aliases().into_iter().map(String::from).collect(),
+            title: front.title().unwrap_or(&name).to_string(),
+            tags: front.tags().into_iter().map(String::from).collect(),
+            body: body.to_string(),
+            modified,
+            name,
+        })
+    }
+}
+
+// Renders the memos at `paths` into a static site under `out`, returning how many pages were
+// written.
+pub fn publish(
+    paths: &[PathBuf],
+    out: &Path,
+    tag: Option<&str>,
+    config: &PublishConfig,
+) -> Result<usize, Error> {
+    let mut pages = vec![];
+
+    for path in paths {
+        let page = Page::read(path)?;
+
+        if tag.is_none_or(|tag| page.tags.iter().any(|t| t == tag)) {
+            pages.push(page);
+        }
+    }
+
+    let mut by_name = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| (page.name.to_lowercase(), i))
+        .collect::<HashMap<_, _>>();
+
+    for (i, page) in pages.iter().enumerate() {
+        if let Some(id) = &page.id {
+            by_name.insert(id.to_lowercase(), i);
+        }
+    }
+
+    for (i, page) in pages.iter().enumerate() {
+        for alias in &page.aliases {
+            by_name.entry(alias.to_lowercase()).or_insert(i);
+        }
+    }
+
+    let mut backlinks = vec![vec![]; pages.len()];
+    let mut tags = BTreeMap::<&str, Vec<usize>>::new();
+
+    for (i, page) in pages.iter().enumerate() {
+        for target in meta::wiki_links(&page.body) {
+            if let Some(&j) = by_name.get(&target.to_lowercase())
+                && j != i
+                && !backlinks[j].contains(&i)
+            {
+                backlinks[j].push(i);
+            }
+        }
+
+        for tag in &page.tags {
+            tags.entry(tag.as_str()).or_default().push(i);
+        }
+    }
+
+    create_dir(&out.join("tags"))?;
+    write(&out.join("style.css"), STYLE)?;
+
+    let site_title = config.title.as_deref().unwrap_or("Memos");
+    let rendered = pages
+        .iter()
+        .map(|page| {
+            markdown(&meta::replace_wiki_links(&page.body, |link| {
+                wiki_link(&link, &pages, &by_name)
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    for (i, page) in pages.iter().enumerate() {
+        let mut content = format!("<h1>{}</h1>\n{}", escape(&page.title), rendered[i]);
+
+        if !page.tags.is_empty() {
+            content.push_str(&tag_list(&page.tags, "tags/"));
+        }
+
+        if !backlinks[i].is_empty() {
+            content.push_str("<footer>\n<h2>Linked from</h2>\n");
+            content.push_str(&page_list(backlinks[i].iter().map(|&j| &pages[j]), ""));
+            content.push_str("</footer>\n");
+        }
+
+        write(
+            &out.join(page_file(&page.name)),
+            &layout(&page.title, "", &content),
+        )?;
+    }
+
+    for (tag, members) in &tags {
+        let title = format!("#{tag}");
+        let content = format!(
+            "<h1>{}</h1>\n{}",
+            escape(&title),
+            page_list(members.iter().map(|&i| &pages[i]), "../")
+        );
+
+        write(
+            &out.join("tags").join(tag_file(tag)),
+            &layout(&title, "../", &content),
+        )?;
+    }
+
+    let mut index = format!(
+        "<h1>{}</h1>\n{}",
+        escape(site_title),
+        page_list(pages.iter(), "")
+    );
+
+    if !tags.is_empty() {
+        index.push_str("<h2>Tags</h2>\n");
+        index.push_str(&tag_list(tags.keys(), "tags/"));
+    }
+
+    write(&out.join("index.html"), &layout(site_title, "", &index))?;
+
+    if config.feed.enabled {
+        let mut recent = pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| {
+                config
+                    .feed
+                    .tag
+                    .as_ref()
+                    .is_none_or(|tag| page.tags.contains(tag))
+            })
+            .collect::<Vec<_>>();
+
+        recent.sort_by_key(|(_, page)| std::cmp::Reverse(page.modified));
+        recent.truncate(config.feed.limit);
+
+        let entries = recent
+            .into_iter()
+            .map(|(i, page)| (page, rendered[i].as_str()))
+            .collect::<Vec<_>>();
+
+        write(&out.join("feed.xml"), &feed(site_title, config, &entries))?;
+    }
+
+    Ok(pages.len())
+}
+
+fn wiki_link(link: &WikiLink, pages: &[Page], by_name: &HashMap<String, usize>) -> String {
+    let label = link.label.replace(['[', ']'], "");
+
+    match by_name.get(&link.target.to_lowercase()) {
+        // An unlabelled link by ID reads better as the page's title.
+        Some(&i) if link.label == link.target && pages[i].id.as_deref() == Some(link.target) => {
+            format!(
+                "[{}]({})",
+                pages[i].title.replace(['[', ']'], ""),
+                href(&page_file(&pages[i].name))
+            )
+        }
+        Some(&i) => format!("[{label}]({})", href(&page_file(&pages[i].name))),
+        None => format!(
+            "<a class=\"missing\" title=\"Missing memo\">{}</a>",
+            escape(&label)
+        ),
+    }
+}
+
+// An Atom feed; entries link relative to `base_url` when it is configured.
+fn feed(title: &str, config: &PublishConfig, entries: &[(&Page, &str)]) -> String {
+    let base = config.base_url.as_deref().unwrap_or("");
+    let base_attr = if base.is_empty() {
+        String::new()
+    } else {
+        format!(" xml:base=\"{}\"", escape(base))
+    };
+    let updated = entries
+        .iter()
+        .map(|(page, _)| page.modified)
+        .max()
+        .unwrap_or_else(SystemTime::now);
+
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<feed xmlns=\"http://www.w3.org/2005/Atom\"{base_attr}>
+<title>{}</title>
+<id>{}</id>
+<updated>{}</updated>
+<link rel=\"self\" href=\"feed.xml\"/>
+<link href=\"index.html\"/>
+",
+        escape(title),
+        escape(&feed_id(base, "index.html")),
+        timestamp(updated)
+    );
+
+    for (page, content) in entries {
+        let file = href(&page_file(&page.name));
+
+        xml.push_str(&format!(
+            "<entry>
+<title>{}</title>
+<id>{}</id>
+<updated>{}</updated>
+<link href=\"{file}\"/>
+<author><name>memoleak</name></author>
+<content type=\"html\">{}</content>
+</entry>
+",
+            escape(&page.title),
+            escape(&feed_id(base, &file)),
+            timestamp(page.modified),
+            escape(content)
+        ));
+    }
+
+    xml.push_str("</feed>\n");
+
+    xml
+}
+
+// Atom ids must be absolute IRIs; without a base URL a stable URN stands in.
+fn feed_id(base: &str, file: &str) -> String {
+    if base.is_empty() {
+        format!("urn:memoleak:{file}")
+    } else {
+        format!("{}/{file}", base.trim_end_matches('/'))
+    }
+}
+
+fn timestamp(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn markdown(text: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let mut out = String::new();
+
+    html::push_html(&mut out, Parser::new_ext(text, options));
+
+    out
+}
+
+fn layout(title: &str, root: &str, content: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{}</title>
+<link rel=\"stylesheet\" href=\"{root}style.css\">
+<link rel=\"alternate\" type=\"application/atom+xml\" href=\"{root}feed.xml\">
+</head>
+<body>
+<nav><a href=\"{root}index.html\">Index</a></nav>
+<main>
+{content}</main>
+</body>
+</html>
+",
+        escape(title)
+    )
+}
+
+fn page_list<'a, I: Iterator<Item = &'a Page>>(pages: I, root: &str) -> String {
+    let items = pages
+        .map(|page| {
+            format!(
+                "<li><a href=\"{root}{}\">{}</a></li>\n",
+                href(&page_file(&page.name)),
+                escape(&page.title)
+            )
+        })
+        .collect::<String>();
+
+    format!("<ul>\n{items}</ul>\n")
+}
+
+fn tag_list<S: AsRef<str>, I: IntoIterator<Item = S>>(tags: I, root: &str) -> String {
+    let items = tags
+        .into_iter()
+        .map(|tag| {
+            let tag = tag.as_ref();
+
+            format!(
+                "<li><a href=\"{root}{}\">#{}</a></li>\n",
+                href(&tag_file(tag)),
+                escape(tag)
+            )
+        })
+        .collect::<String>();
+
+    format!("<ul class=\"tags\">\n{items}</ul>\n")
+}
+
+fn page_file(name: &str) -> String {
+    format!("{name}.html")
+}
+
+// Percent-encoded rather than sanitized, so tags like `a/b` and `a_b` keep pages of their own.
+// Dots are encoded too, which keeps a tag from being taken for `.`, `..` or a hidden file.
+fn tag_file(tag: &str) -> String {
+    let name = tag
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'~' => (b as char).to_string(),
+            b => format!("%{b:02X}"),
+        })
+        .collect::<String>();
+
+    format!("{name}.html")
+}
+
+// Percent-encodes everything outside the URL unreserved set.
+pub fn href(file: &str) -> String {
+    file.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn create_dir(path: &Path) -> Result<(), Error> {
+    fs::create_dir_all(path).map_err(|e| {
+        Error::with_cause(
+            format!("A directory '{}' creating failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })
+}
+
+fn write(path: &Path, content: &str) -> Result<(), Error> {
+    fs::write(path, content).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' writing failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })
+}
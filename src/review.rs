@@ -0,0 +1,116 @@
+use crate::{APP_DATA_PATH, Error, private_file_options};
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// When each memo last came up for review, one `memo<TAB>time` per line with the time in RFC 3339.
+static REVIEWED_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("reviewed"));
+
+pub const COUNT: usize = 5;
+
+pub fn reviewed() -> HashMap<String, DateTime<Local>> {
+    fs::read_to_string(&*REVIEWED_PATH)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (memo, time) = line.split_once('\t')?;
+            let time = DateTime::parse_from_rfc3339(time).ok()?;
+
+            Some((memo.to_string(), time.with_timezone(&Local)))
+        })
+        .collect()
+}
+
+pub fn mark(memos: &[String]) -> Result<(), Error> {
+    let mut reviewed = reviewed();
+    let now = Local::now();
+
+    for memo in memos {
+        reviewed.insert(memo.clone(), now);
+    }
+
+    let mut lines = reviewed
+        .into_iter()
+        .map(|(memo, time)| format!("{memo}\t{}\n", time.to_rfc3339()))
+        .collect::<Vec<_>>();
+
+    lines.sort();
+
+    private_file_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&*REVIEWED_PATH)
+        .and_then(|mut file| file.write_all(lines.concat().as_bytes()))
+        .map_err(|e| {
+            Error::with_cause(
+                format!(
+                    "A file '{}' writing failed",
+                    REVIEWED_PATH.to_string_lossy()
+                ),
+                e.kind(),
+            )
+        })
+}
+
+// Up to `count` memos drawn at random from the ones gone longest without a look. Takes the memo
+// names with their modification times and hands back positions among them.
+pub fn pick(memos: &[(String, Option<SystemTime>)], count: usize) -> Vec<usize> {
+    let looked = looked(memos);
+    let mut order = (0..memos.len()).collect::<Vec<_>>();
+
+    order.sort_by_key(|&idx| looked[idx]);
+    order.truncate(count * 3);
+    shuffle(&mut order);
+    order.truncate(count);
+
+    order
+}
+
+// The memos neither changed nor kept in a review for `days`, the longest untouched first.
+pub fn stale(memos: &[(String, Option<SystemTime>)], days: u64) -> Vec<usize> {
+    let looked = looked(memos);
+    let limit = Duration::from_secs(days.saturating_mul(24 * 60 * 60));
+    let since = SystemTime::now().checked_sub(limit).unwrap_or(UNIX_EPOCH);
+    let mut order = (0..memos.len())
+        .filter(|&idx| looked[idx].is_none_or(|time| time < since))
+        .collect::<Vec<_>>();
+
+    order.sort_by_key(|&idx| looked[idx]);
+
+    order
+}
+
+// When each memo was last changed or reviewed, whichever came later.
+fn looked(memos: &[(String, Option<SystemTime>)]) -> Vec<Option<SystemTime>> {
+    let reviewed = reviewed();
+
+    memos
+        .iter()
+        .map(|(memo, modified)| {
+            let review = reviewed.get(memo).map(|&time| SystemTime::from(time));
+
+            (*modified).max(review)
+        })
+        .collect()
+}
+
+fn shuffle<T>(items: &mut [T]) {
+    let mut bytes = vec![0u8; items.len() * 4];
+
+    // Falls back to the order as is, which still holds the oldest memos.
+    if getrandom::getrandom(&mut bytes).is_err() {
+        return;
+    }
+
+    for i in (1..items.len()).rev() {
+        let chunk = &bytes[i * 4..i * 4 + 4];
+        let j = u32::from_le_bytes(chunk.try_into().unwrap()) as usize % (i + 1);
+
+        items.swap(i, j);
+    }
+}
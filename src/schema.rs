@@ -0,0 +1,95 @@
+use crate::Error;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value as Json;
+
+pub type Step<T> = fn(T) -> Result<T, Error>;
+
+// A file format and the steps that bring its older versions up to date, `steps[n]` turning
+// version `n` into `n + 1`. Files written before formats were versioned count as version 0, so
+// the current version is the number of steps.
+pub struct Schema<T: 'static> {
+    pub name: &'static str,
+    pub steps: &'static [Step<T>],
+}
+
+impl<T> Schema<T> {
+    pub fn version(&self) -> u32 {
+        self.steps.len() as u32
+    }
+
+    pub fn upgrade(&self, from: u32, data: T) -> Result<T, Error> {
+        let steps = self.steps.get(from as usize..).ok_or_else(|| {
+            Error::with_cause(
+                format!("The {} upgrading failed", self.name),
+                format!(
+                    "it is version {from}, written by a newer memoleak that knows up to version {}",
+                    self.version()
+                ),
+            )
+        })?;
+
+        steps.iter().try_fold(data, |data, step| step(data))
+    }
+}
+
+// Text formats carry a `version <n>` first line; it holds no tab, so the line-based formats
+// written before it skip the line when reading it back.
+pub fn read_text(schema: &Schema<String>, content: &str) -> Result<String, Error> {
+    let (version, body) = match content.split_once('\n') {
+        Some((first, body)) if first.starts_with("version ") => {
+            let version = first["version ".len()..].trim().parse().map_err(|_| {
+                Error::with_cause(
+                    format!("The {} reading failed", schema.name),
+                    format!("'{first}' is not a version"),
+                )
+            })?;
+
+            (version, body)
+        }
+        _ => (0, content),
+    };
+
+    schema.upgrade(version, body.to_string())
+}
+
+pub fn write_text(schema: &Schema<String>, body: &str) -> String {
+    format!("version {}\n{body}", schema.version())
+}
+
+// JSON formats carry a top-level `version` field.
+pub fn read_json<T: DeserializeOwned>(schema: &Schema<Json>, content: &str) -> Result<T, Error> {
+    let failed =
+        |cause: String| Error::with_cause(format!("The {} reading failed", schema.name), cause);
+    let mut json = serde_json::from_str::<Json>(content).map_err(|e| failed(e.to_string()))?;
+    let version = match json
+        .as_object_mut()
+        .and_then(|object| object.remove("version"))
+    {
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| failed(format!("'{version}' is not a version")))?,
+        None => 0,
+    };
+    let json = schema.upgrade(version.try_into().unwrap_or(u32::MAX), json)?;
+
+    serde_json::from_value(json).map_err(|e| failed(e.to_string()))
+}
+
+pub fn write_json<T: Serialize>(schema: &Schema<Json>, value: &T) -> Result<String, Error> {
+    let failed = |e: serde_json::Error| {
+        Error::with_cause(format!("The {} serializing failed", schema.name), e)
+    };
+    let mut json = serde_json::to_value(value).map_err(failed)?;
+
+    if let Some(object) = json.as_object_mut() {
+        object.insert("version".to_string(), schema.version().into());
+    }
+
+    serde_json::to_string(&json).map_err(failed)
+}
+
+// For a version that only started marking the format, with the data itself unchanged.
+pub fn unchanged<T>(data: T) -> Result<T, Error> {
+    Ok(data)
+}
@@ -0,0 +1,37 @@
+use crate::SNIPPET_PATH;
+use chrono::Local;
+use std::collections::BTreeMap;
+use std::fs;
+
+pub const TRIGGER: char = ';';
+
+// Looks a snippet up by name: files in SNIPPET_PATH (named after their stem) win over the
+// config's `[snippets]`, which win over the built-in `date`, `time` and `now`. `{{date}}` and
+// `{{time}}` inside a snippet are filled in as well.
+pub fn expand(name: &str, configured: &BTreeMap<String, String>) -> Option<String> {
+    let now = Local::now();
+    let date = now.format("%Y-%m-%d").to_string();
+    let time = now.format("%H:%M").to_string();
+
+    let text = from_dir(name)
+        .or_else(|| configured.get(name).cloned())
+        .or_else(|| match name {
+            "date" => Some(date.clone()),
+            "time" => Some(time.clone()),
+            "now" => Some(format!("{date} {time}")),
+            _ => None,
+        })?;
+
+    Some(text.replace("{{date}}", &date).replace("{{time}}", &time))
+}
+
+fn from_dir(name: &str) -> Option<String> {
+    SNIPPET_PATH
+        .read_dir()
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| path.file_stem().is_some_and(|stem| stem == name) && path.is_file())
+        .and_then(|path| fs::read_to_string(path).ok())
+        // Files almost always end in a newline that is not part of the snippet.
+        .map(|text| text.strip_suffix('\n').unwrap_or(&text).to_string())
+}
@@ -0,0 +1,135 @@
+use crate::config::SpellConfig;
+use crate::{Error, shell_command};
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+
+// The words a checker rejects. By default that is `hunspell -l -d <language>`; a configured
+// command gets the same contract: text on stdin, one misspelled word per line on stdout.
+pub fn misspelled(text: &str, config: &SpellConfig) -> Result<HashSet<String>, Error> {
+    let (mut cmd, program) = match &config.command {
+        Some(line) => (shell_command(line), line.clone()),
+        None => {
+            let mut cmd = Command::new("hunspell");
+
+            cmd.args(["-l", "-d", &config.language]);
+
+            (cmd, String::from("hunspell"))
+        }
+    };
+
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| {
+            if e.kind() == io::ErrorKind::NotFound {
+                Error::with_cause(
+                    format!("The command '{program}' executing failed"),
+                    "install hunspell and a dictionary or set spell.command",
+                )
+            } else {
+                Error::with_cause(
+                    format!("The command '{program}' executing failed"),
+                    e.kind(),
+                )
+            }
+        })?;
+
+    // Written from another thread so a checker that answers while reading cannot deadlock.
+    let mut stdin = child.stdin.take();
+    let text = text.to_string();
+    let writer =
+        thread::spawn(move || stdin.as_mut().map(|stdin| stdin.write_all(text.as_bytes())));
+
+    let out = child.wait_with_output().map_err(|e| {
+        Error::with_cause(
+            format!("The command '{program}' executing failed"),
+            e.kind(),
+        )
+    })?;
+    let _ = writer.join();
+
+    if !out.status.success() {
+        return Err(Error::with_cause(
+            format!("The command '{program}' failed"),
+            out.status,
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|word| !word.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+// How many checked versions the cache keeps, enough for going back and forth over a list.
+const CACHE_SIZE: usize = 64;
+
+// Misspelled words by content hash for the versions checked last, so a long session does not keep
+// a set for every version of every memo it showed.
+#[derive(Default)]
+pub struct Cache {
+    // The least recently used first.
+    entries: VecDeque<(u64, HashSet<String>)>,
+}
+
+impl Cache {
+    pub fn get(&self, hash: u64) -> Option<&HashSet<String>> {
+        self.entries
+            .iter()
+            .find(|(found, _)| *found == hash)
+            .map(|(_, words)| words)
+    }
+
+    // Moves the version to the recently used end, or returns false if it is not cached.
+    pub fn touch(&mut self, hash: u64) -> bool {
+        let Some(at) = self.entries.iter().position(|(found, _)| *found == hash) else {
+            return false;
+        };
+
+        if let Some(entry) = self.entries.remove(at) {
+            self.entries.push_back(entry);
+        }
+
+        true
+    }
+
+    pub fn insert(&mut self, hash: u64, words: HashSet<String>) {
+        self.entries.retain(|(found, _)| *found != hash);
+        self.entries.push_back((hash, words));
+
+        while self.entries.len() > CACHE_SIZE {
+            self.entries.pop_front();
+        }
+    }
+}
+
+// Byte ranges of the words in `line` that are in `words`. Words are letters with inner
+// apostrophes, as spell checkers split them.
+pub fn marks(line: &str, words: &HashSet<String>) -> Vec<std::ops::Range<usize>> {
+    let mut marks = vec![];
+    let mut start = None;
+
+    for (i, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        let inner = c == '\'' && start.is_some() && line[i + 1..].starts_with(char::is_alphabetic);
+
+        match (c.is_alphabetic() || inner, start) {
+            (true, None) => start = Some(i),
+            (false, Some(from)) => {
+                if words.contains(&line[from..i]) {
+                    marks.push(from..i);
+                }
+
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    marks
+}
@@ -0,0 +1,662 @@
+use crate::config;
+use crate::task::Progress;
+use crate::{Error, history, log_err, matches_query, memo_name_of, private_file_options};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+// Appended to the memo extension of memos stored gzip-compressed, as in `name.md.gz`.
+pub const EXTENSION: &str = "gz";
+
+static COMPRESS_ABOVE: LazyLock<Option<u64>> = LazyLock::new(|| {
+    config::load()
+        .ok()
+        .and_then(|config| config.storage.compress_above)
+});
+
+// What keeps track of the memos besides the files themselves, which are always there to edit.
+pub trait StorageBackend: Send + Sync {
+    // Told about every memo written through `write`, once the file is in place.
+    fn written(&self, path: &Path, content: &str) -> Result<(), Error>;
+
+    // Told about a memo file moved, by a rename or to the trash.
+    fn moved(&self, from: &Path, to: &Path) -> Result<(), Error>;
+
+    // Told about a memo erased, which is to leave nothing of it behind.
+    fn erased(&self, path: &Path) -> Result<(), Error>;
+
+    // The memos among `paths` matching a query of terms that all have to match, `tag:<name>` for
+    // one of the memo's tags; the best match first where the backend can rank them. A term matches
+    // anywhere in the text of the files, but only at the start of a word in the SQLite index. Stops
+    // at the next file once `progress` is cancelled.
+    fn search(
+        &self,
+        paths: &[PathBuf],
+        query: &str,
+        progress: &Progress,
+    ) -> Result<Vec<PathBuf>, Error>;
+}
+
+static BACKEND: LazyLock<Box<dyn StorageBackend>> = LazyLock::new(|| {
+    let config = config::load()
+        .map(|config| config.storage)
+        .unwrap_or_default();
+
+    match config.backend {
+        #[cfg(feature = "sqlite")]
+        config::Backend::Sqlite => Box::new(sqlite::Sqlite::new(config.store_content)),
+        _ => Box::new(Files),
+    }
+});
+
+pub fn search(paths: &[PathBuf], query: &str, progress: &Progress) -> Result<Vec<PathBuf>, Error> {
+    BACKEND.search(paths, query, progress)
+}
+
+// The backend catches up with a missed move by the next search, so a failure is only logged.
+pub fn moved<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) {
+    if let Err(e) = BACKEND.moved(from.as_ref(), to.as_ref()) {
+        log_err(&e);
+    }
+}
+
+pub fn erased<P: AsRef<Path>>(path: P) -> Result<(), Error> {
+    BACKEND.erased(path.as_ref())
+}
+
+// The memo files alone; a search reads through every one of them.
+struct Files;
+
+impl StorageBackend for Files {
+    fn written(&self, _: &Path, _: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn moved(&self, _: &Path, _: &Path) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn erased(&self, _: &Path) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn search(
+        &self,
+        paths: &[PathBuf],
+        query: &str,
+        progress: &Progress,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut found = vec![];
+
+        progress.start(paths.len());
+
+        for path in paths {
+            progress.step()?;
+
+            if read(path).is_ok_and(|content| matches_query(&memo_name_of(path), &content, query)) {
+                found.push(path.clone());
+            }
+        }
+
+        Ok(found)
+    }
+}
+
+pub fn is_compressed<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|ext| ext == EXTENSION)
+}
+
+pub fn plain_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+
+    match is_compressed(path) {
+        true => path.with_extension(""),
+        false => path.to_path_buf(),
+    }
+}
+
+pub fn compressed_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = plain_path(path);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+
+    name.push(format!(".{EXTENSION}"));
+
+    path.with_file_name(name)
+}
+
+// The same memo in the other storage form.
+pub fn other_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    match is_compressed(&path) {
+        true => plain_path(path),
+        false => compressed_path(path),
+    }
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let path = path.as_ref();
+
+    if !is_compressed(path) {
+        return fs::read_to_string(path);
+    }
+
+    let bytes = fs::read(path)?;
+    let mut content = String::new();
+
+    // A compressed memo truncated by `:new!` has no gzip header left.
+    if !bytes.is_empty() {
+        GzDecoder::new(bytes.as_slice()).read_to_string(&mut content)?;
+    }
+
+    Ok(content)
+}
+
+// The size of the memo's text, which for a compressed one takes decompressing it.
+pub fn size<P: AsRef<Path>>(path: P) -> io::Result<u64> {
+    let path = path.as_ref();
+
+    if !is_compressed(path) {
+        return fs::metadata(path).map(|meta| meta.len());
+    }
+
+    match fs::File::open(path)? {
+        file if file.metadata()?.len() == 0 => Ok(0),
+        file => io::copy(&mut GzDecoder::new(file), &mut io::sink()),
+    }
+}
+
+// Up to `limit` bytes from the start of the memo, cut back to a whole character.
+pub fn read_head<P: AsRef<Path>>(path: P, limit: usize) -> io::Result<String> {
+    let path = path.as_ref();
+    let file = fs::File::open(path)?;
+    let mut bytes = vec![];
+
+    match is_compressed(path) {
+        true => GzDecoder::new(file)
+            .take(limit as u64)
+            .read_to_end(&mut bytes)?,
+        false => file.take(limit as u64).read_to_end(&mut bytes)?,
+    };
+
+    if let Err(e) = std::str::from_utf8(&bytes)
+        && e.error_len().is_none()
+    {
+        bytes.truncate(e.valid_up_to());
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).to_string())
+}
+
+// Saves a memo compressed when it is larger than `storage.compress_above` and plain otherwise,
+// removing it in the other form. Without a threshold the memo keeps the form it has. Returns
+// where the memo is now.
+pub fn write<P: AsRef<Path>>(path: P, content: &str) -> Result<PathBuf, Error> {
+    let path = path.as_ref();
+    let compress = match *COMPRESS_ABOVE {
+        // A linked memo is written through its link, which would be left dangling by a new form.
+        _ if path.is_symlink() => is_compressed(path),
+        Some(limit) => content.len() as u64 > limit,
+        None => is_compressed(path),
+    };
+    let (target, stale) = match compress {
+        true => (compressed_path(path), plain_path(path)),
+        false => (plain_path(path), compressed_path(path)),
+    };
+    let failed = |path: &Path, e: io::Error| {
+        Error::with_cause(
+            format!("A file '{}' writing failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    };
+
+    // Picks up changes made outside memoleak before they are written over. The history is kept
+    // alongside and never holds up the write itself.
+    if let Err(e) = history::record_current(path) {
+        log_err(&e);
+    }
+
+    let bytes = match compress {
+        true => {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+
+            encoder
+                .write_all(content.as_bytes())
+                .and_then(|_| encoder.finish())
+                .map_err(|e| failed(&target, e))?
+        }
+        false => content.as_bytes().to_vec(),
+    };
+
+    private_file_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&target)
+        .and_then(|mut file| file.write_all(&bytes))
+        .map_err(|e| failed(&target, e))?;
+
+    // Left behind, the old file would be listed as a second memo of the same name.
+    match fs::remove_file(&stale) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::with_cause(
+            format!("A file '{}' cleanup failed", stale.to_string_lossy()),
+            e.kind(),
+        )),
+        _ => Ok(()),
+    }?;
+
+    if let Err(e) = history::record(&target, content) {
+        log_err(&e);
+    }
+
+    // A missed update is caught up with by the next search, as for a memo edited elsewhere.
+    if let Err(e) = BACKEND.written(&target, content) {
+        log_err(&e);
+    }
+
+    Ok(target)
+}
+
+// Adds `text` to the end of a memo. A plain memo is appended to in place, so that appends from two
+// processes at once both land; a compressed one can only be rewritten whole. Returns where the
+// memo is now.
+pub fn append<P: AsRef<Path>>(path: P, text: &str) -> Result<PathBuf, Error> {
+    let path = path.as_ref();
+    let reading_failed = |e: io::Error| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    };
+
+    if is_compressed(path) {
+        let existing = read(path).map_err(reading_failed)?;
+
+        return write(path, &format!("{existing}{text}"));
+    }
+
+    if let Err(e) = history::record_current(path) {
+        log_err(&e);
+    }
+
+    private_file_options()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' writing failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+
+    let content = read(path).map_err(reading_failed)?;
+
+    // Grown past `storage.compress_above`, it moves to the compressed form, which records it.
+    if COMPRESS_ABOVE.is_some_and(|limit| content.len() as u64 > limit) {
+        return write(path, &content);
+    }
+
+    if let Err(e) = history::record(path, &content) {
+        log_err(&e);
+    }
+
+    if let Err(e) = BACKEND.written(path, &content) {
+        log_err(&e);
+    }
+
+    Ok(path.to_path_buf())
+}
+
+// Rewrites a memo whose size no longer matches its form, e.g. after an editor saved it.
+pub fn settle<P: AsRef<Path>>(path: P) -> Result<PathBuf, Error> {
+    let path = path.as_ref();
+
+    let Some(limit) = *COMPRESS_ABOVE else {
+        return Ok(path.to_path_buf());
+    };
+
+    let content = read(path).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    match (content.len() as u64 > limit) == is_compressed(path) {
+        true => Ok(path.to_path_buf()),
+        false => write(path, &content),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::{StorageBackend, read};
+    use crate::task::Progress;
+    use crate::{APP_DATA_PATH, Error, memo_name_of, meta, private_file_options};
+    use rusqlite::{Connection, OptionalExtension, Transaction, params};
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::{LazyLock, Mutex};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    static DATABASE_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("memos.sqlite"));
+
+    const MEMOS_TABLE: &str = "CREATE TABLE IF NOT EXISTS memos (
+        id INTEGER PRIMARY KEY,
+        path TEXT NOT NULL UNIQUE,
+        name TEXT NOT NULL,
+        title TEXT,
+        tags TEXT NOT NULL,
+        modified INTEGER NOT NULL,
+        size INTEGER NOT NULL
+    )";
+
+    // A row of metadata per memo and an FTS5 index over the names and the text, in one file. Memos
+    // edited outside memoleak are caught up with by their modification time before each search.
+    pub struct Sqlite {
+        store_content: bool,
+        // Opened on first use, so runs that never write or search leave the file alone.
+        connection: Mutex<Option<Connection>>,
+    }
+
+    impl Sqlite {
+        pub fn new(store_content: bool) -> Self {
+            Self {
+                store_content,
+                connection: Mutex::new(None),
+            }
+        }
+
+        fn open(&self) -> Result<Connection, Error> {
+            let failed = |cause: String| {
+                Error::with_cause(
+                    format!(
+                        "A database '{}' opening failed",
+                        DATABASE_PATH.to_string_lossy()
+                    ),
+                    cause,
+                )
+            };
+
+            // Created up front, as SQLite would make it readable to everyone.
+            private_file_options()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&*DATABASE_PATH)
+                .map_err(|e| failed(e.kind().to_string()))?;
+
+            let connection =
+                Connection::open(&*DATABASE_PATH).map_err(|e| failed(e.to_string()))?;
+
+            // Another memoleak may be writing to it, e.g. `me add` while the TUI runs.
+            connection
+                .busy_timeout(Duration::from_secs(5))
+                .map_err(|e| failed(e.to_string()))?;
+
+            connection
+                .execute_batch("PRAGMA secure_delete = ON")
+                .and_then(|_| self.prepare(&connection))
+                .map_err(|e| failed(e.to_string()))?;
+
+            Ok(connection)
+        }
+
+        fn prepare(&self, connection: &Connection) -> rusqlite::Result<()> {
+            let text_table = match self.store_content {
+                true => "CREATE VIRTUAL TABLE memo_text USING fts5(name, body)",
+                false => {
+                    "CREATE VIRTUAL TABLE memo_text USING fts5(name, body, content='', contentless_delete=1)"
+                }
+            };
+            let existing = connection
+                .query_row(
+                    "SELECT sql FROM sqlite_master WHERE name = 'memo_text'",
+                    [],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional()?;
+
+            // A changed `store_content` takes a fresh index, which the next search fills again.
+            if existing.as_deref() != Some(text_table) {
+                connection.execute_batch(&format!(
+                    "BEGIN;
+                    DROP TABLE IF EXISTS memo_text;
+                    DROP TABLE IF EXISTS memos;
+                    {MEMOS_TABLE};
+                    {text_table};
+                    COMMIT;"
+                ))?;
+            }
+
+            Ok(())
+        }
+
+        fn with_connection<T>(
+            &self,
+            doing: &str,
+            f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+        ) -> Result<T, Error> {
+            let failed = |e: rusqlite::Error| {
+                Error::with_cause(
+                    format!(
+                        "A database '{}' {doing} failed",
+                        DATABASE_PATH.to_string_lossy()
+                    ),
+                    e,
+                )
+            };
+            let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+
+            if connection.is_none() {
+                *connection = Some(self.open()?);
+            }
+
+            f(connection.as_mut().unwrap()).map_err(failed)
+        }
+    }
+
+    impl StorageBackend for Sqlite {
+        fn written(&self, path: &Path, content: &str) -> Result<(), Error> {
+            self.with_connection("updating", |connection| {
+                let tx = connection.transaction()?;
+
+                put(&tx, path, content)?;
+                tx.commit()
+            })
+        }
+
+        fn moved(&self, from: &Path, to: &Path) -> Result<(), Error> {
+            self.with_connection("updating", |connection| {
+                connection.execute(
+                    "UPDATE memos SET path = ?2 WHERE path = ?1",
+                    params![from.to_string_lossy(), to.to_string_lossy()],
+                )?;
+
+                Ok(())
+            })
+        }
+
+        // Freed pages are zeroed, and the index is merged even when the row went earlier, as a
+        // deleted row's words linger in the index until then.
+        fn erased(&self, path: &Path) -> Result<(), Error> {
+            self.with_connection("erasing", |connection| {
+                let tx = connection.transaction()?;
+                let id = tx
+                    .query_row(
+                        "SELECT id FROM memos WHERE path = ?1",
+                        [path.to_string_lossy()],
+                        |row| row.get::<_, i64>(0),
+                    )
+                    .optional()?;
+
+                if let Some(id) = id {
+                    tx.execute("DELETE FROM memo_text WHERE rowid = ?1", [id])?;
+                    tx.execute("DELETE FROM memos WHERE id = ?1", [id])?;
+                }
+
+                tx.execute("INSERT INTO memo_text (memo_text) VALUES ('optimize')", [])?;
+                tx.commit()
+            })
+        }
+
+        fn search(
+            &self,
+            paths: &[PathBuf],
+            query: &str,
+            progress: &Progress,
+        ) -> Result<Vec<PathBuf>, Error> {
+            let (tags, terms): (Vec<_>, Vec<_>) = query
+                .split_whitespace()
+                .partition(|term| term.starts_with("tag:"));
+            let tags = tags
+                .iter()
+                .map(|tag| &tag["tag:".len()..])
+                .collect::<Vec<_>>();
+            // Each term is looked up as a prefix of a word, quoted so nothing in it is taken as
+            // FTS5 syntax.
+            let terms = terms
+                .iter()
+                .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            self.with_connection("indexing", |connection| {
+                catch_up(connection, paths, progress)
+            })?;
+            progress.check()?;
+
+            self.with_connection("searching", |connection| {
+                let mut statement = match terms.is_empty() {
+                    true => connection.prepare("SELECT path, tags FROM memos ORDER BY name")?,
+                    false => connection.prepare(
+                        "SELECT memos.path, memos.tags FROM memo_text
+                        JOIN memos ON memos.id = memo_text.rowid
+                        WHERE memo_text MATCH ?1 ORDER BY bm25(memo_text)",
+                    )?,
+                };
+                let rows = match terms.is_empty() {
+                    true => statement.query([])?,
+                    false => statement.query([&terms])?,
+                };
+
+                rows.mapped(|row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+                    .filter(|row| {
+                        row.as_ref().map_or(true, |(_, memo_tags)| {
+                            tags.iter().all(|tag| {
+                                memo_tags
+                                    .split(' ')
+                                    .any(|memo_tag| memo_tag.eq_ignore_ascii_case(tag))
+                            })
+                        })
+                    })
+                    .map(|row| row.map(|(path, _)| PathBuf::from(path)))
+                    .collect()
+            })
+        }
+    }
+
+    // Brings the rows in line with the memo files: new and changed ones are indexed again, gone
+    // ones dropped. Cancelled, it keeps what it indexed so far for the next search.
+    fn catch_up(
+        connection: &mut Connection,
+        paths: &[PathBuf],
+        progress: &Progress,
+    ) -> rusqlite::Result<()> {
+        let tx = connection.transaction()?;
+        let known = tx
+            .prepare("SELECT path, id, modified FROM memos")?
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    (row.get::<_, i64>(1)?, row.get::<_, i64>(2)?),
+                ))
+            })?
+            .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+        let current = paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect::<HashSet<_>>();
+
+        progress.start(paths.len());
+
+        for path in paths {
+            if progress.step().is_err() {
+                return tx.commit();
+            }
+
+            let indexed = known.get(path.to_string_lossy().as_ref());
+
+            if indexed.is_some_and(|&(_, modified)| modified == modified_of(path)) {
+                continue;
+            }
+
+            // One that cannot be read now is tried again by the next search.
+            if let Ok(content) = read(path) {
+                put(&tx, path, &content)?;
+            }
+        }
+
+        for (path, (id, _)) in &known {
+            if !current.contains(path) {
+                tx.execute("DELETE FROM memo_text WHERE rowid = ?1", [id])?;
+                tx.execute("DELETE FROM memos WHERE id = ?1", [id])?;
+            }
+        }
+
+        tx.commit()
+    }
+
+    fn put(tx: &Transaction, path: &Path, content: &str) -> rusqlite::Result<()> {
+        let name = memo_name_of(path);
+        let (front, _) = meta::split(content);
+        let names = [name.as_str()]
+            .into_iter()
+            .chain(front.aliases())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let id = tx.query_row(
+            "INSERT INTO memos (path, name, title, tags, modified, size)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (path) DO UPDATE SET
+                name = excluded.name,
+                title = excluded.title,
+                tags = excluded.tags,
+                modified = excluded.modified,
+                size = excluded.size
+            RETURNING id",
+            params![
+                path.to_string_lossy(),
+                name,
+                front.title(),
+                front.tags().join(" "),
+                modified_of(path),
+                content.len() as i64,
+            ],
+            |row| row.get::<_, i64>(0),
+        )?;
+
+        tx.execute("DELETE FROM memo_text WHERE rowid = ?1", [id])?;
+        tx.execute(
+            "INSERT INTO memo_text (rowid, name, body) VALUES (?1, ?2, ?3)",
+            params![id, names, content],
+        )?;
+
+        Ok(())
+    }
+
+    fn modified_of(path: &Path) -> i64 {
+        fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos() as i64)
+    }
+}
@@ -0,0 +1,699 @@
+use crate::config::{Config, EncryptionConfig, S3Config, SshConfig};
+use crate::schema::{self, Schema};
+use crate::{
+    Error, SYNC_PATH, create_private_dir, keyring, meta, move_file, private_file_options,
+    read_secret, run_external, sha256_hex, stored_id,
+};
+use age::secrecy::{ExposeSecret, SecretString};
+use age::x25519;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+pub trait SyncBackend {
+    // Uploads the local stash so the remote mirrors it.
+    fn push(&self, local: &Path, dry_run: bool) -> Result<(), Error>;
+
+    // Downloads the remote copy into the local stash.
+    fn pull(&self, local: &Path, dry_run: bool) -> Result<(), Error>;
+
+    // Downloads the one remote file `name` into `local`, or returns false if the remote has none.
+    fn fetch(&self, name: &str, local: &Path) -> Result<bool, Error>;
+}
+
+// `target` is the remote location given on the command line, overriding the configured one.
+pub fn backend(
+    name: &str,
+    target: Option<&str>,
+    config: &Config,
+) -> Result<Box<dyn SyncBackend>, Error> {
+    match name {
+        "s3" => Ok(Box::new(S3::new(target, &config.sync.s3)?)),
+        "ssh" => Ok(Box::new(Ssh::new(target, &config.sync.ssh)?)),
+        _ => Err(Error::new(format!("Unknown sync backend '{name}'"))),
+    }
+}
+
+// Drives the `aws` CLI, which also covers MinIO and other S3-compatible stores via an endpoint URL.
+struct S3 {
+    url: String,
+    endpoint: Option<String>,
+    region: Option<String>,
+    access_key_id: Option<String>,
+    secret_access_key: Option<String>,
+}
+
+impl S3 {
+    // A target is taken as `bucket[/prefix]`.
+    fn new(target: Option<&str>, config: &S3Config) -> Result<Self, Error> {
+        let setting =
+            |value: &Option<String>, var: &str| value.clone().or_else(|| env::var(var).ok());
+
+        let url = match target {
+            Some(target) => format!("s3://{}", target.trim_start_matches("s3://")),
+            None => {
+                let bucket = setting(&config.bucket, "MEMOLEAK_S3_BUCKET").ok_or_else(|| {
+                    Error::with_cause(
+                        "The S3 backend setup failed",
+                        "sync.s3.bucket is not configured",
+                    )
+                })?;
+                let prefix = setting(&config.prefix, "MEMOLEAK_S3_PREFIX").unwrap_or_default();
+
+                format!("s3://{bucket}/{}", prefix.trim_matches('/'))
+            }
+        };
+
+        Ok(Self {
+            url,
+            endpoint: setting(&config.endpoint, "MEMOLEAK_S3_ENDPOINT"),
+            region: config.region.clone(),
+            access_key_id: config.access_key_id.clone(),
+            secret_access_key: config.secret_access_key.clone(),
+        })
+    }
+
+    // `delete` removes what `from` does not have, which a push wants so memos deleted here go from
+    // the remote too. A pull leaves it out: a memo made here since the last push is not on the
+    // remote yet and must not be lost.
+    fn sync(&self, from: &str, to: &str, delete: bool, dry_run: bool) -> Result<(), Error> {
+        let mut cmd = self.aws(&["s3", "sync", from, to]);
+
+        if delete {
+            cmd.arg("--delete");
+        }
+
+        if dry_run {
+            cmd.arg("--dryrun");
+        }
+
+        run_external(&mut cmd)
+    }
+
+    fn aws(&self, args: &[&str]) -> Command {
+        let mut cmd = Command::new("aws");
+
+        cmd.args(args);
+
+        if let Some(endpoint) = &self.endpoint {
+            cmd.args(["--endpoint-url", endpoint]);
+        }
+
+        if let Some(region) = &self.region {
+            cmd.args(["--region", region]);
+        }
+
+        // Credentials from the config win; otherwise aws falls back to its own env/profile lookup.
+        if let Some(key) = &self.access_key_id {
+            cmd.env("AWS_ACCESS_KEY_ID", key);
+        }
+
+        if let Some(secret) = &self.secret_access_key {
+            cmd.env("AWS_SECRET_ACCESS_KEY", secret);
+        }
+
+        cmd
+    }
+}
+
+impl SyncBackend for S3 {
+    fn push(&self, local: &Path, dry_run: bool) -> Result<(), Error> {
+        self.sync(&local.to_string_lossy(), &self.url, true, dry_run)
+    }
+
+    fn pull(&self, local: &Path, dry_run: bool) -> Result<(), Error> {
+        self.sync(&self.url, &local.to_string_lossy(), false, dry_run)
+    }
+
+    fn fetch(&self, name: &str, local: &Path) -> Result<bool, Error> {
+        let url = format!("{}/{name}", self.url.trim_end_matches('/'));
+        // `ls` lists every key starting with the name and exits with 1 when there is none.
+        let listed = self
+            .aws(&["s3", "ls", &url])
+            .output()
+            .map_err(|e| Error::with_cause("The command 'aws' executing failed", e.kind()))?;
+
+        match listed.status.code() {
+            Some(0) => {}
+            Some(1) => return Ok(false),
+            _ => {
+                return Err(Error::with_cause(
+                    format!("A remote file '{url}' looking up failed"),
+                    String::from_utf8_lossy(&listed.stderr).trim(),
+                ));
+            }
+        }
+
+        let suffix = format!(" {name}");
+
+        if !String::from_utf8_lossy(&listed.stdout)
+            .lines()
+            .any(|line| line.ends_with(&suffix))
+        {
+            return Ok(false);
+        }
+
+        run_external(&mut self.aws(&["s3", "cp", &url, &local.join(name).to_string_lossy()]))?;
+
+        Ok(true)
+    }
+}
+
+// Mirrors over ssh with rsync; --checksum makes it compare content hashes rather than mtimes, and
+// rsync's own delta transfer keeps the traffic small.
+struct Ssh {
+    target: String,
+    port: Option<u16>,
+    identity: Option<String>,
+}
+
+impl Ssh {
+    // A target is taken as `[user@]host:path`.
+    fn new(target: Option<&str>, config: &SshConfig) -> Result<Self, Error> {
+        let target = target
+            .map(String::from)
+            .or_else(|| config.target.clone())
+            .ok_or_else(|| {
+                Error::with_cause(
+                    "The ssh backend setup failed",
+                    "no user@host:path target is given or configured in sync.ssh.target",
+                )
+            })?;
+
+        if !target.contains(':') {
+            return Err(Error::with_cause(
+                format!("A target '{target}' is invalid"),
+                "expected [user@]host:path",
+            ));
+        }
+
+        Ok(Self {
+            target: format!("{}/", target.trim_end_matches('/')),
+            port: config.port,
+            identity: config
+                .identity
+                .as_ref()
+                .map(|path| path.to_string_lossy().to_string()),
+        })
+    }
+
+    fn ssh_args(&self) -> Vec<String> {
+        let mut args = vec![];
+
+        if let Some(port) = self.port {
+            args.extend([String::from("-p"), port.to_string()]);
+        }
+
+        if let Some(identity) = &self.identity {
+            args.extend([String::from("-i"), identity.clone()]);
+        }
+
+        args
+    }
+
+    // `delete` as for S3: a push removes from the remote what the stash no longer has, and a pull
+    // keeps what was made here since the last push.
+    fn rsync(&self, from: &str, to: &str, delete: bool, dry_run: bool) -> Result<(), Error> {
+        // rsync splits the command on spaces itself; within quotes, a doubled quote is a literal.
+        let mut ssh = String::from("ssh");
+
+        for arg in self.ssh_args() {
+            ssh.push_str(&format!(" '{}'", arg.replace('\'', "''")));
+        }
+
+        let mut cmd = Command::new("rsync");
+
+        cmd.args(["--archive", "--checksum", "--compress", "--itemize-changes"])
+            .args(["--rsh", &ssh]);
+
+        if delete {
+            cmd.arg("--delete");
+        }
+
+        if dry_run {
+            cmd.arg("--dry-run");
+        }
+
+        run_external(cmd.args([from, to]))
+    }
+}
+
+impl SyncBackend for Ssh {
+    fn push(&self, local: &Path, dry_run: bool) -> Result<(), Error> {
+        self.rsync(
+            &format!("{}/", local.to_string_lossy()),
+            &self.target,
+            true,
+            dry_run,
+        )
+    }
+
+    fn pull(&self, local: &Path, dry_run: bool) -> Result<(), Error> {
+        self.rsync(
+            &self.target,
+            &format!("{}/", local.to_string_lossy()),
+            false,
+            dry_run,
+        )
+    }
+
+    fn fetch(&self, name: &str, local: &Path) -> Result<bool, Error> {
+        let (host, dir) = self.target.split_once(':').unwrap_or_default();
+        let path = format!("{dir}{name}");
+        // Quoted for the remote shell, except a leading `~/` that it is to expand.
+        let quoted = match path.strip_prefix("~/") {
+            Some(rest) => format!("~/{}", shell_quote(rest)),
+            None => shell_quote(&path),
+        };
+        // `test` exits with 1 for a missing file, and ssh itself with 255 when it cannot connect.
+        let status = Command::new("ssh")
+            .args(self.ssh_args())
+            .args([host, &format!("test -e {quoted}")])
+            .status()
+            .map_err(|e| Error::with_cause("The command 'ssh' executing failed", e.kind()))?;
+
+        match status.code() {
+            Some(0) => {}
+            Some(1) => return Ok(false),
+            _ => {
+                return Err(Error::with_cause(
+                    format!("A remote file '{host}:{path}' looking up failed"),
+                    status,
+                ));
+            }
+        }
+
+        self.rsync(
+            &format!("{host}:{path}"),
+            &format!("{}/", local.to_string_lossy()),
+            false,
+            false,
+        )?;
+
+        Ok(true)
+    }
+}
+
+// Single-quotes `text` for a POSIX shell.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+const KEY_FILE: &str = "key.age";
+const MANIFEST_FILE: &str = "manifest.age";
+
+enum KeySource {
+    Identity(PathBuf),
+    Passphrase(Option<String>),
+    // Prompted for once and then kept in the OS keyring; `prompt` skips the stored one.
+    Keyring { prompt: bool },
+}
+
+const KEYRING_ACCOUNT: &str = "sync";
+
+// Whether syncing would stop to ask for the passphrase, which takes a terminal of its own.
+pub fn prompts(config: &EncryptionConfig) -> Result<bool, Error> {
+    match (&config.identity, &config.passphrase) {
+        _ if !config.enabled => Ok(false),
+        (None, None) if config.keyring => Ok(keyring::get(KEYRING_ACCOUNT)?.is_none()),
+        (None, None) => Ok(true),
+        _ => Ok(false),
+    }
+}
+
+// Wraps another backend so the remote only ever holds age ciphertext under opaque names. Memos are
+// encrypted into a staging dir that the inner backend mirrors; a manifest mapping the opaque names
+// back to real ones is kept locally and also uploaded encrypted, so a fresh machine can pull. The
+// opaque name comes from a memo's `id` when it has one, so a renamed memo keeps its remote copy
+// and a pull renames it on the other side as well.
+pub struct Encrypted {
+    inner: Box<dyn SyncBackend>,
+    key: KeySource,
+    staging: PathBuf,
+}
+
+impl Encrypted {
+    pub fn new(inner: Box<dyn SyncBackend>, config: &EncryptionConfig, prompt: bool) -> Self {
+        let key = match (&config.identity, &config.passphrase) {
+            (Some(path), _) => KeySource::Identity(path.clone()),
+            (None, None) if config.keyring => KeySource::Keyring { prompt },
+            (None, passphrase) => KeySource::Passphrase(passphrase.clone()),
+        };
+
+        Self {
+            inner,
+            key,
+            staging: SYNC_PATH.join("encrypted"),
+        }
+    }
+
+    // With a passphrase, the files are encrypted to a generated X25519 key stored
+    // passphrase-wrapped next to them, so the slow scrypt work happens once per sync rather than
+    // once per memo. A machine without the key takes the remote's, and only makes one when the
+    // remote has none.
+    fn identity(&self, create: bool) -> Result<x25519::Identity, Error> {
+        let key_path = self.staging.join(KEY_FILE);
+
+        if !key_path.exists() && !matches!(self.key, KeySource::Identity(_)) {
+            self.inner.fetch(KEY_FILE, &self.staging)?;
+        }
+
+        // `remember` is set for a freshly prompted passphrase that belongs in the keyring.
+        let (passphrase, stored, remember) = match &self.key {
+            KeySource::Identity(path) => return read_identity_file(path),
+            KeySource::Passphrase(Some(passphrase)) => (passphrase.clone(), false, false),
+            KeySource::Passphrase(None) => (read_secret("Sync passphrase: ")?, false, false),
+            KeySource::Keyring { prompt } => {
+                let found = match prompt {
+                    true => None,
+                    false => keyring::get(KEYRING_ACCOUNT)?,
+                };
+
+                match found {
+                    Some(passphrase) => (passphrase, true, false),
+                    None => (read_secret("Sync passphrase: ")?, false, true),
+                }
+            }
+        };
+        let remembered = remember.then(|| passphrase.clone());
+        let passphrase = SecretString::from(passphrase);
+
+        if key_path.exists() {
+            let wrapped = read_file(&key_path)?;
+            let unwrapped = age::decrypt(&age::scrypt::Identity::new(passphrase), &wrapped)
+                .map_err(|e| match stored {
+                    true => Error::with_cause(
+                        "The sync key unlocking failed",
+                        format!("{e}; the keyring has another passphrase, run with --prompt"),
+                    ),
+                    false => Error::with_cause("The sync key unlocking failed", e),
+                })?;
+
+            if let Some(passphrase) = remembered {
+                keyring::set(KEYRING_ACCOUNT, &passphrase)?;
+            }
+
+            return x25519::Identity::from_str(&String::from_utf8_lossy(&unwrapped))
+                .map_err(|e| Error::with_cause("The sync key parsing failed", e));
+        }
+
+        let identity = x25519::Identity::generate();
+
+        if create {
+            let wrapped = age::encrypt(
+                &age::scrypt::Recipient::new(passphrase),
+                identity.to_string().expose_secret().as_bytes(),
+            )
+            .map_err(|e| Error::with_cause("The sync key wrapping failed", e))?;
+
+            write_file(&key_path, &wrapped)?;
+
+            if let Some(passphrase) = remembered {
+                keyring::set(KEYRING_ACCOUNT, &passphrase)?;
+            }
+        }
+
+        Ok(identity)
+    }
+}
+
+impl SyncBackend for Encrypted {
+    fn push(&self, local: &Path, dry_run: bool) -> Result<(), Error> {
+        create_private_dir(&self.staging)
+            .map_err(|e| Error::with_cause("The sync staging dir creating failed", e.kind()))?;
+
+        let identity = self.identity(!dry_run)?;
+        let recipient = identity.to_public();
+        let old = Manifest::load()?;
+        let mut new = Manifest::default();
+        let mut ids = HashSet::new();
+
+        for path in list_files(local)? {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let content = read_file(&path)?;
+            let hash = sha256_hex(&content);
+            // A copied memo shares its id with the original, and only the first goes by it.
+            let opaque = match stored_id(&path).map(|id| id.to_lowercase()) {
+                Some(id) if ids.insert(id.clone()) => opaque_name(&identity, &format!("id:{id}")),
+                _ => opaque_name(&identity, &name),
+            };
+            let staged = self.staging.join(&opaque);
+
+            if old.hash(&opaque) != Some(&hash) || !staged.exists() {
+                if dry_run {
+                    println!("would encrypt {name}");
+                } else {
+                    let ciphertext = age::encrypt(&recipient, &content).map_err(|e| {
+                        Error::with_cause(format!("A memo '{name}' encrypting failed"), e)
+                    })?;
+
+                    write_file(&staged, &ciphertext)?;
+                }
+            }
+
+            new.insert(opaque, hash, name);
+        }
+
+        for path in list_files(&self.staging)? {
+            let file = path.file_name().unwrap().to_string_lossy().to_string();
+
+            if file != KEY_FILE && file != MANIFEST_FILE && new.hash(&file).is_none() {
+                if dry_run {
+                    println!("would drop {file}");
+                } else {
+                    remove_file(&path)?;
+                }
+            }
+        }
+
+        if !dry_run && (new != old || !self.staging.join(MANIFEST_FILE).exists()) {
+            let ciphertext = age::encrypt(&recipient, new.serialize().as_bytes())
+                .map_err(|e| Error::with_cause("The sync manifest encrypting failed", e))?;
+
+            write_file(&self.staging.join(MANIFEST_FILE), &ciphertext)?;
+            new.save()?;
+        }
+
+        self.inner.push(&self.staging, dry_run)
+    }
+
+    fn pull(&self, local: &Path, dry_run: bool) -> Result<(), Error> {
+        create_private_dir(&self.staging)
+            .map_err(|e| Error::with_cause("The sync staging dir creating failed", e.kind()))?;
+
+        self.inner.pull(&self.staging, dry_run)?;
+
+        if dry_run {
+            return Ok(());
+        }
+
+        let identity = self.identity(false)?;
+        let manifest = read_file(&self.staging.join(MANIFEST_FILE))?;
+        let manifest = age::decrypt(&identity, &manifest)
+            .map_err(|e| Error::with_cause("The sync manifest decrypting failed", e))?;
+        let manifest = Manifest::parse(&String::from_utf8_lossy(&manifest))?;
+        let names = manifest
+            .entries
+            .values()
+            .map(|(_, name)| name.as_str())
+            .collect::<HashSet<_>>();
+        // The memos here under a name the remote no longer has, by id: renamed on the other side.
+        let mut renamed = list_files(local)?
+            .into_iter()
+            .filter(|path| !names.contains(path.file_name().unwrap().to_string_lossy().as_ref()))
+            .filter_map(|path| Some((stored_id(&path)?.to_lowercase(), path)))
+            .collect::<HashMap<_, _>>();
+
+        for (opaque, (hash, name)) in &manifest.entries {
+            // The manifest came from the remote; never let it write outside the stash.
+            if Path::new(name).file_name().map(|n| n.to_string_lossy()) != Some(name.into()) {
+                return Err(Error::with_cause(
+                    format!("A synced name '{name}' is invalid"),
+                    "it is not a plain file name",
+                ));
+            }
+
+            let target = local.join(name);
+
+            if target.exists() && sha256_hex(read_file(&target)?) == *hash {
+                continue;
+            }
+
+            let ciphertext = read_file(&self.staging.join(opaque))?;
+            let plaintext = age::decrypt(&identity, &ciphertext)
+                .map_err(|e| Error::with_cause(format!("A memo '{name}' decrypting failed"), e))?;
+            let id = meta::split(&String::from_utf8_lossy(&plaintext))
+                .0
+                .id()
+                .map(str::to_lowercase);
+
+            if let Some(old) = id
+                .and_then(|id| renamed.remove(&id))
+                .filter(|_| !target.exists())
+            {
+                move_file(&old, &target)?;
+                println!(
+                    "renamed {} to {name}",
+                    old.file_name().unwrap().to_string_lossy()
+                );
+
+                if sha256_hex(read_file(&target)?) == *hash {
+                    continue;
+                }
+            }
+
+            write_file(&target, &plaintext)?;
+            println!("decrypted {name}");
+        }
+
+        manifest.save()
+    }
+
+    // Only ever asked of the inner backend, for the staged key.
+    fn fetch(&self, name: &str, local: &Path) -> Result<bool, Error> {
+        self.inner.fetch(name, local)
+    }
+}
+
+fn opaque_name(identity: &x25519::Identity, name: &str) -> String {
+    let keyed = format!("{}\0{name}", identity.to_string().expose_secret());
+
+    format!("{}.age", &sha256_hex(keyed)[..32])
+}
+
+fn read_identity_file(path: &Path) -> Result<x25519::Identity, Error> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        Error::with_cause(
+            format!("An identity '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    content
+        .lines()
+        .find(|line| line.starts_with("AGE-SECRET-KEY-"))
+        .and_then(|line| x25519::Identity::from_str(line.trim()).ok())
+        .ok_or_else(|| {
+            Error::with_cause(
+                format!("An identity '{}' parsing failed", path.to_string_lossy()),
+                "no AGE-SECRET-KEY line is found",
+            )
+        })
+}
+
+static MANIFEST_SCHEMA: Schema<String> = Schema {
+    name: "sync manifest",
+    steps: &[schema::unchanged],
+};
+
+// opaque name -> (content hash, real file name)
+#[derive(Default, PartialEq)]
+struct Manifest {
+    entries: BTreeMap<String, (String, String)>,
+}
+
+impl Manifest {
+    fn path() -> PathBuf {
+        SYNC_PATH.join("manifest")
+    }
+
+    fn load() -> Result<Self, Error> {
+        match fs::read_to_string(Self::path()) {
+            Ok(content) => Self::parse(&content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::with_cause(
+                "The sync manifest reading failed",
+                e.kind(),
+            )),
+        }
+    }
+
+    fn parse(content: &str) -> Result<Self, Error> {
+        let entries = schema::read_text(&MANIFEST_SCHEMA, content)?
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+
+                Some((
+                    fields.next()?.to_string(),
+                    (fields.next()?.to_string(), fields.next()?.to_string()),
+                ))
+            })
+            .collect();
+
+        Ok(Self { entries })
+    }
+
+    fn serialize(&self) -> String {
+        let lines = self
+            .entries
+            .iter()
+            .map(|(opaque, (hash, name))| format!("{opaque}\t{hash}\t{name}\n"))
+            .collect::<String>();
+
+        schema::write_text(&MANIFEST_SCHEMA, &lines)
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        write_file(&Self::path(), self.serialize().as_bytes())
+    }
+
+    fn hash(&self, opaque: &str) -> Option<&String> {
+        self.entries.get(opaque).map(|(hash, _)| hash)
+    }
+
+    fn insert(&mut self, opaque: String, hash: String, name: String) {
+        self.entries.insert(opaque, (hash, name));
+    }
+}
+
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let entries = dir.read_dir().map_err(|e| {
+        Error::with_cause(
+            format!("A directory '{}' reading failed", dir.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    Ok(entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' reading failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<(), Error> {
+    private_file_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(content))
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' writing failed", path.to_string_lossy()),
+                e.kind(),
+            )
+        })
+}
+
+fn remove_file(path: &Path) -> Result<(), Error> {
+    fs::remove_file(path).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' cleanup failed", path.to_string_lossy()),
+            e.kind(),
+        )
+    })
+}
@@ -0,0 +1,85 @@
+use crate::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
+
+// How far a long operation on a worker thread got, and whether it is to stop. Clones share the
+// counts: the worker steps through its share and the UI shows it and cancels through its own.
+#[derive(Clone, Default)]
+pub struct Progress(Arc<Counts>);
+
+#[derive(Default)]
+struct Counts {
+    done: AtomicUsize,
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    // Starts counting toward `total` steps.
+    pub fn start(&self, total: usize) {
+        self.0.done.store(0, Ordering::Relaxed);
+        self.0.total.store(total, Ordering::Relaxed);
+    }
+
+    // Counts a step about to be taken, failing instead once the operation is cancelled.
+    pub fn step(&self) -> Result<(), Error> {
+        self.check()?;
+        self.0.done.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    pub fn check(&self) -> Result<(), Error> {
+        match self.is_cancelled() {
+            true => Err(Error::new("The operation was cancelled")),
+            false => Ok(()),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    // `done/total`, or nothing for an operation that cannot tell how long it takes.
+    fn counts(&self) -> Option<String> {
+        match self.0.total.load(Ordering::Relaxed) {
+            0 => None,
+            total => Some(format!(
+                "{}/{total}",
+                self.0.done.load(Ordering::Relaxed).min(total)
+            )),
+        }
+    }
+}
+
+// A long operation under way, shown in the status line until it reports back.
+pub struct Task {
+    pub label: String,
+    pub progress: Progress,
+    started: Instant,
+}
+
+impl Task {
+    pub fn new<S: Into<String>>(label: S) -> Self {
+        Self {
+            label: label.into(),
+            progress: Progress::default(),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let seconds = self.started.elapsed().as_secs();
+
+        match (self.progress.is_cancelled(), self.progress.counts()) {
+            (true, _) => format!("{}: cancelling", self.label),
+            (false, Some(counts)) => format!("{} {counts} ({seconds}s, Esc: cancel)", self.label),
+            (false, None) => format!("{} ({seconds}s, Esc: cancel)", self.label),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{APP_DATA_PATH, Error, private_file_options};
+use chrono::{DateTime, Local, TimeDelta};
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+// The memo clocked in to, as `start<TAB>memo`, while there is one.
+static CLOCK_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("clock"));
+// Finished intervals, one `start<TAB>end<TAB>memo` per line with the times in RFC 3339.
+static TIMESHEET_PATH: LazyLock<PathBuf> = LazyLock::new(|| APP_DATA_PATH.join("timesheet"));
+
+pub struct Interval {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub memo: String,
+}
+
+impl Interval {
+    pub fn duration(&self) -> TimeDelta {
+        self.end - self.start
+    }
+}
+
+// When the clock started and for which memo.
+pub fn clocked() -> Option<(DateTime<Local>, String)> {
+    let line = fs::read_to_string(&*CLOCK_PATH).ok()?;
+    let (start, memo) = line.trim_end_matches('\n').split_once('\t')?;
+
+    Some((parse_time(start)?, memo.to_string()))
+}
+
+// Clocks out of whatever memo the clock runs for first, handing back that interval.
+pub fn clock_in(memo: &str) -> Result<Option<Interval>, Error> {
+    let previous = clock_out()?;
+
+    private_file_options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&*CLOCK_PATH)
+        .and_then(|mut file| writeln!(file, "{}\t{memo}", Local::now().to_rfc3339()))
+        .map_err(|e| {
+            Error::with_cause(
+                format!("A file '{}' writing failed", CLOCK_PATH.to_string_lossy()),
+                e.kind(),
+            )
+        })?;
+
+    Ok(previous)
+}
+
+pub fn clock_out() -> Result<Option<Interval>, Error> {
+    let Some((start, memo)) = clocked() else {
+        return Ok(None);
+    };
+    let interval = Interval {
+        start,
+        end: Local::now(),
+        memo,
+    };
+    let line = format!(
+        "{}\t{}\t{}\n",
+        interval.start.to_rfc3339(),
+        interval.end.to_rfc3339(),
+        interval.memo
+    );
+
+    private_file_options()
+        .create(true)
+        .append(true)
+        .open(&*TIMESHEET_PATH)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| {
+            Error::with_cause(
+                format!(
+                    "A file '{}' writing failed",
+                    TIMESHEET_PATH.to_string_lossy()
+                ),
+                e.kind(),
+            )
+        })?;
+
+    fs::remove_file(&*CLOCK_PATH).map_err(|e| {
+        Error::with_cause(
+            format!("A file '{}' removing failed", CLOCK_PATH.to_string_lossy()),
+            e.kind(),
+        )
+    })?;
+
+    Ok(Some(interval))
+}
+
+// The finished intervals, and the running one up to now.
+pub fn intervals() -> Result<Vec<Interval>, Error> {
+    let content = match fs::read_to_string(&*TIMESHEET_PATH) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(Error::with_cause(
+                format!(
+                    "A file '{}' reading failed",
+                    TIMESHEET_PATH.to_string_lossy()
+                ),
+                e.kind(),
+            ));
+        }
+    };
+    let mut intervals = content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+
+            Some(Interval {
+                start: parse_time(fields.next()?)?,
+                end: parse_time(fields.next()?)?,
+                memo: fields.next()?.to_string(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if let Some((start, memo)) = clocked() {
+        intervals.push(Interval {
+            start,
+            end: Local::now(),
+            memo,
+        });
+    }
+
+    Ok(intervals)
+}
+
+// `1h 05m`, or just the minutes under an hour.
+pub fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes().max(0);
+
+    match minutes / 60 {
+        0 => format!("{minutes}m"),
+        hours => format!("{hours}h {:02}m", minutes % 60),
+    }
+}
+
+fn parse_time(text: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .map(|time| time.with_timezone(&Local))
+}
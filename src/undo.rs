@@ -0,0 +1,98 @@
+use crate::memo_name_of;
+use std::path::{Path, PathBuf};
+
+// Most reversible stash operations move a memo file somewhere else, so undoing is moving it back
+// and redoing is moving it again. Tagging keeps only the tags it added, to take just those out
+// again without losing what was edited since.
+pub enum Change {
+    Trash { from: PathBuf, to: PathBuf },
+    Rename { from: PathBuf, to: PathBuf },
+    Archive { from: PathBuf, to: PathBuf },
+    Tag { path: PathBuf, added: Vec<String> },
+}
+
+impl Change {
+    // "deleting 'memo'", to be prefixed with "Undid" / "Redid".
+    pub fn describe(&self) -> String {
+        match self {
+            Change::Trash { from, .. } => format!("deleting '{}'", memo_name_of(from)),
+            Change::Rename { from, to } => format!(
+                "renaming '{}' to '{}'",
+                memo_name_of(from),
+                memo_name_of(to)
+            ),
+            Change::Archive { from, .. } => format!("archiving '{}'", memo_name_of(from)),
+            Change::Tag { path, added } => {
+                format!("tagging '{}' with {}", memo_name_of(path), added.join(", "))
+            }
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self {
+            Change::Trash { from, .. } => format!("Deleted '{}'", memo_name_of(from)),
+            Change::Rename { from, to } => {
+                format!("Renamed '{}' to '{}'", memo_name_of(from), memo_name_of(to))
+            }
+            Change::Archive { from, .. } => format!("Archived '{}'", memo_name_of(from)),
+            Change::Tag { path, added } => {
+                format!("Tagged '{}' with {}", memo_name_of(path), added.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct UndoLog {
+    done: Vec<Change>,
+    undone: Vec<Change>,
+}
+
+impl UndoLog {
+    pub fn record(&mut self, change: Change) {
+        self.done.push(change);
+        self.undone.clear();
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Change> {
+        self.done.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Change> {
+        self.undone.pop()
+    }
+
+    pub fn push_undone(&mut self, change: Change) {
+        self.undone.push(change);
+    }
+
+    pub fn push_done(&mut self, change: Change) {
+        self.done.push(change);
+    }
+
+    // Follows a memo renamed outside the TUI: done changes left it at their `to`, and undone ones
+    // at their `from`.
+    pub fn relocate(&mut self, from: &Path, to: &Path) {
+        for change in &mut self.done {
+            if let Change::Trash { to: at, .. }
+            | Change::Rename { to: at, .. }
+            | Change::Archive { to: at, .. }
+            | Change::Tag { path: at, .. } = change
+                && at == from
+            {
+                *at = to.to_path_buf();
+            }
+        }
+
+        for change in &mut self.undone {
+            if let Change::Trash { from: at, .. }
+            | Change::Rename { from: at, .. }
+            | Change::Archive { from: at, .. }
+            | Change::Tag { path: at, .. } = change
+                && at == from
+            {
+                *at = to.to_path_buf();
+            }
+        }
+    }
+}